@@ -0,0 +1,409 @@
+use peggy::grammar::{
+    node_at, parse_peg_nocheck, validate, Diagnostic, DiagnosticSeverity, ParserLoc, PegSyntaxTree,
+    RulePatternValue,
+};
+use peggy::peggygen::{gen_peggy_rule, PeggyGenOptions};
+use peggy::rustgen::{rule_type_shapes, RustGenOptions, TypeShape};
+use tower_lsp::lsp_types::{self, Position, Range, TextEdit};
+
+/// Width above which formatted rules made of an union are split with one member per line
+const MAX_UNION_WIDTH: usize = 100;
+
+/// Get the diagnostics of a grammar: its syntax error if it can't be parsed, or its validation errors and warnings
+///
+/// As the parser stops at the first syntax error, only this one is reported, the grammar's following lines being
+/// checked once it is fixed.
+pub fn diagnostics(text: &str) -> Vec<lsp_types::Diagnostic> {
+    let diagnostics = match parse_peg_nocheck(text) {
+        Ok(pst) => validate(&pst).to_diagnostics(text),
+        Err(err) => vec![err.to_diagnostic(text)],
+    };
+
+    diagnostics
+        .into_iter()
+        .map(|diagnostic| to_lsp_diagnostic(text, diagnostic))
+        .collect()
+}
+
+/// Get the range of the name of the rule declared or referenced at a position
+pub fn definition(text: &str, position: Position) -> Option<Range> {
+    let pst = parse_peg_nocheck(text).ok()?;
+    let name = rule_at(&pst, text, position)?;
+    let rule = &pst.rules()[name];
+
+    Some(name_range(text, rule.decl_loc(), name))
+}
+
+/// Get the ranges of all the references to the rule declared or referenced at a position, including its declaration's
+/// name with `include_declaration`
+pub fn references(text: &str, position: Position, include_declaration: bool) -> Vec<Range> {
+    let pst = match parse_peg_nocheck(text) {
+        Ok(pst) => pst,
+        Err(_) => return vec![],
+    };
+
+    let name = match rule_at(&pst, text, position) {
+        Some(name) => name,
+        None => return vec![],
+    };
+
+    let mut locs = vec![];
+
+    if include_declaration {
+        locs.push(pst.rules()[name].decl_loc());
+    }
+
+    pst.walk_rules(&mut |_, pattern| {
+        if matches!(pattern.value(), RulePatternValue::Rule(referenced) if *referenced == name)
+            && is_written_at(text, pattern.loc(), name)
+        {
+            locs.push(pattern.loc());
+        }
+    });
+
+    // The content of parametric rules is visited once per call
+    locs.sort();
+    locs.dedup();
+
+    locs.into_iter()
+        .map(|loc| name_range(text, loc, name))
+        .collect()
+}
+
+/// Describe the rule declared or referenced at a position, with its declaration and the data it captures in generated
+/// parsers (as Markdown)
+pub fn hover(text: &str, position: Position) -> Option<String> {
+    let pst = parse_peg_nocheck(text).ok()?;
+    let name = rule_at(&pst, text, position)?;
+    let declaration = rule_text(text, &pst, name)?;
+
+    let mut hover = format!("```peggy\n{}\n```", declaration);
+
+    // Rules' data can only be computed for valid grammars
+    if validate(&pst).is_valid() {
+        if let Ok(mut shapes) = rule_type_shapes(&pst, &RustGenOptions::new()) {
+            match shapes.remove(name).flatten() {
+                Some(shape) => {
+                    hover.push_str(&format!("\n\nCaptured data: `{}`", format_shape(&shape)))
+                }
+                None => hover.push_str("\n\nDoesn't capture any data"),
+            }
+        }
+    }
+
+    Some(hover)
+}
+
+/// Format the rules of a grammar
+///
+/// Rules are regenerated from the syntax tree, which doesn't keep comments nor the calls to parametric rules, and
+/// contains the whitespaces inserted by the `@whitespace` directive. So only the rules whose regenerated code only
+/// differs in whitespaces are formatted, the other ones being left untouched.
+pub fn format(text: &str) -> Vec<TextEdit> {
+    let pst = match parse_peg_nocheck(text) {
+        Ok(pst) => pst,
+        Err(_) => return vec![],
+    };
+
+    let options = PeggyGenOptions {
+        max_union_width: Some(MAX_UNION_WIDTH),
+    };
+
+    let mut edits = vec![];
+
+    for rule in pst.ordered_rules() {
+        let current = match rule_text(text, &pst, rule.name()) {
+            Some(current) => current,
+            None => continue,
+        };
+
+        let formatted = gen_peggy_rule(rule.name(), rule.pattern(), &options);
+
+        if formatted != current
+            && strip_whitespaces(&formatted, rule.name()) == strip_whitespaces(current, rule.name())
+        {
+            let span = rule.span();
+
+            edits.push(TextEdit {
+                range: Range {
+                    start: to_position(text, span.start()),
+                    end: to_position(text, span.end()),
+                },
+                new_text: formatted,
+            });
+        }
+    }
+
+    edits
+}
+
+/// Get the name of the declared rule at a position, which is either on the rule's name or on a reference to it
+fn rule_at<'a>(pst: &PegSyntaxTree<'a>, text: &str, position: Position) -> Option<&'a str> {
+    let (line, col) = to_line_col(text, position);
+    let path = node_at(pst, line, col)?;
+
+    match path.innermost() {
+        None => Some(path.rule().name()),
+        Some(pattern) => match pattern.value() {
+            RulePatternValue::Rule(name) if pst.rules().contains_key(name) => Some(name),
+            _ => None,
+        },
+    }
+}
+
+/// Get the declaration of a rule, as written in the grammar
+fn rule_text<'t>(text: &'t str, pst: &PegSyntaxTree, name: &str) -> Option<&'t str> {
+    let span = pst.rules().get(name)?.span();
+    text.get(span.start().byte_offset(text)?..span.end().byte_offset(text)?)
+}
+
+/// Check if a rule's name is written at a location of the grammar
+///
+/// Patterns which are not written in the grammar (e.g. the whitespaces inserted by the `@whitespace` directive)
+/// use the location of the pattern they precede.
+fn is_written_at(text: &str, loc: ParserLoc, name: &str) -> bool {
+    loc.byte_offset(text)
+        .and_then(|offset| text.get(offset..))
+        .is_some_and(|rest| rest.starts_with(name))
+}
+
+/// Remove the whitespaces of a rule's declaration, as well as the vertical bar which may start its union
+fn strip_whitespaces(declaration: &str, name: &str) -> String {
+    let stripped: String = declaration.chars().filter(|c| !c.is_whitespace()).collect();
+    let content = &stripped[name.len()..];
+
+    match content.strip_prefix("=|") {
+        Some(members) => format!("{}={}", name, members),
+        None => stripped,
+    }
+}
+
+/// Format a rule's data shape like the generated type (e.g. `Vec<(expr, "+")>`), constant strings being shown as is
+fn format_shape(shape: &TypeShape) -> String {
+    match shape {
+        TypeShape::Str => "&str".to_string(),
+        TypeShape::StrMarker(string) => format!("{:?}", string),
+        TypeShape::BuiltinChar(name) => name.clone(),
+        TypeShape::RuleRef { name, .. } => name.clone(),
+        TypeShape::Count => "usize".to_string(),
+        TypeShape::Flag => "bool".to_string(),
+        TypeShape::Vec(data) => format!("Vec<{}>", format_shape(data)),
+        TypeShape::Option(data) => format!("Option<{}>", format_shape(data)),
+        TypeShape::Tuple(pieces) => format!(
+            "({})",
+            pieces
+                .iter()
+                .map(format_shape)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        TypeShape::Union(members) => format!(
+            "({})",
+            members
+                .iter()
+                .map(|member| member
+                    .as_ref()
+                    .map_or_else(|| "()".to_string(), format_shape))
+                .collect::<Vec<_>>()
+                .join(" | ")
+        ),
+    }
+}
+
+/// Get the range of a rule's name written at a location
+fn name_range(text: &str, loc: ParserLoc, name: &str) -> Range {
+    let start = to_position(text, loc);
+
+    Range {
+        start,
+        end: Position {
+            line: start.line,
+            character: start.character + name.encode_utf16().count() as u32,
+        },
+    }
+}
+
+/// Convert a diagnostic, whose columns are counted in characters
+fn to_lsp_diagnostic(text: &str, diagnostic: Diagnostic) -> lsp_types::Diagnostic {
+    let position = |(line, col): (usize, usize)| Position {
+        line: line as u32,
+        character: line_text(text, line)
+            .chars()
+            .take(col)
+            .map(char::len_utf16)
+            .sum::<usize>() as u32,
+    };
+
+    lsp_types::Diagnostic {
+        range: Range {
+            start: position(diagnostic.start),
+            end: position(diagnostic.end),
+        },
+        severity: Some(match diagnostic.severity {
+            DiagnosticSeverity::Error => lsp_types::DiagnosticSeverity::ERROR,
+            DiagnosticSeverity::Warning => lsp_types::DiagnosticSeverity::WARNING,
+        }),
+        source: Some("peggy".to_string()),
+        message: diagnostic.message,
+        ..lsp_types::Diagnostic::default()
+    }
+}
+
+/// Convert a location, whose column is counted in bytes, to a position, whose column is counted in UTF-16 code units
+fn to_position(text: &str, loc: ParserLoc) -> Position {
+    let line = line_text(text, loc.line());
+
+    Position {
+        line: loc.line() as u32,
+        character: line.get(..loc.col()).unwrap_or(line).encode_utf16().count() as u32,
+    }
+}
+
+/// Convert a position to a line and a column counted in bytes (see [`to_position`])
+fn to_line_col(text: &str, position: Position) -> (usize, usize) {
+    let line = line_text(text, position.line as usize);
+    let mut units = 0;
+
+    let col = line
+        .char_indices()
+        .find(|(_, c)| {
+            units += c.len_utf16();
+            units > position.character as usize
+        })
+        .map_or(line.len(), |(col, _)| col);
+
+    (position.line as usize, col)
+}
+
+/// Get a line of the grammar, without its line terminator (empty if it doesn't exist)
+fn line_text(text: &str, line: usize) -> &str {
+    text.lines().nth(line).unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Grammar with non-ASCII characters before the references, '𝄞' taking 4 bytes and 2 UTF-16 code units
+    const GRAMMAR: &str = "main = \"日本𝄞\" value \"é\" value\nvalue = \"a\" | \"b\"\n";
+
+    fn pos(line: u32, character: u32) -> Position {
+        Position { line, character }
+    }
+
+    fn range(line: u32, start: u32, end: u32) -> Range {
+        Range {
+            start: pos(line, start),
+            end: pos(line, end),
+        }
+    }
+
+    #[test]
+    fn positions_to_line_col() {
+        let line = "main = \"日本𝄞\" value \"é\" value";
+
+        assert_eq!(to_line_col(GRAMMAR, pos(0, 14)), (0, 20));
+        assert_eq!(to_line_col(GRAMMAR, pos(0, 24)), (0, 31));
+
+        // Positions in the middle of a surrogate pair are on the character
+        assert_eq!(to_line_col(GRAMMAR, pos(0, 11)), (0, 14));
+
+        // Positions after the end of a line are at its end
+        assert_eq!(to_line_col(GRAMMAR, pos(0, 100)), (0, line.len()));
+    }
+
+    #[test]
+    fn diagnostics_positions() {
+        // Validation errors
+        let found = diagnostics("main = \"𝄞\" missing\n");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].range, range(0, 12, 19));
+        assert_eq!(
+            found[0].severity,
+            Some(lsp_types::DiagnosticSeverity::ERROR)
+        );
+
+        // Only the first syntax error is reported
+        let found = diagnostics("main = \"日本\" )\nvalue = (\n");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].range.start.line, 0);
+
+        assert!(diagnostics(GRAMMAR).is_empty());
+    }
+
+    #[test]
+    fn rules_definition() {
+        assert_eq!(definition(GRAMMAR, pos(0, 16)), Some(range(1, 0, 5)));
+        assert_eq!(definition(GRAMMAR, pos(0, 26)), Some(range(1, 0, 5)));
+        assert_eq!(definition(GRAMMAR, pos(1, 2)), Some(range(1, 0, 5)));
+        assert_eq!(definition(GRAMMAR, pos(0, 2)), Some(range(0, 0, 4)));
+
+        // Not on a rule
+        assert_eq!(definition(GRAMMAR, pos(0, 10)), None);
+    }
+
+    #[test]
+    fn rules_references() {
+        assert_eq!(
+            references(GRAMMAR, pos(1, 2), true),
+            [range(0, 14, 19), range(0, 24, 29), range(1, 0, 5)]
+        );
+
+        assert_eq!(
+            references(GRAMMAR, pos(0, 16), false),
+            [range(0, 14, 19), range(0, 24, 29)]
+        );
+
+        // The content of parametric rules is only reported once, whatever the number of calls
+        let grammar = "main = list(\"日\") list(\"b\")\nlist(x) = x value\nvalue = \"c\"\n";
+
+        assert_eq!(references(grammar, pos(2, 0), false), [range(1, 12, 17)]);
+    }
+
+    #[test]
+    fn rules_hover() {
+        assert_eq!(
+            hover(GRAMMAR, pos(0, 16)).unwrap(),
+            "```peggy\nvalue = \"a\" | \"b\"\n```\n\nCaptured data: `(\"a\" | \"b\")`"
+        );
+
+        let grammar = "main = value\nvalue = °\"é\"\n";
+
+        assert_eq!(
+            hover(grammar, pos(0, 8)).unwrap(),
+            "```peggy\nvalue = °\"é\"\n```\n\nDoesn't capture any data"
+        );
+
+        // The data isn't computed for invalid grammars
+        let grammar = "main = value missing\nvalue = \"é\"\n";
+
+        assert_eq!(
+            hover(grammar, pos(0, 8)).unwrap(),
+            "```peggy\nvalue = \"é\"\n```"
+        );
+
+        assert_eq!(hover(GRAMMAR, pos(0, 10)), None);
+    }
+
+    #[test]
+    fn rules_format() {
+        // Comments following a rule are kept, as they are not part of its declaration
+        let grammar = "main  =  \"日本𝄞\"   value\nvalue  =  \"a\" # comment\nother = \"é\"\n";
+
+        assert_eq!(
+            format(grammar),
+            [
+                TextEdit {
+                    range: range(0, 0, 23),
+                    new_text: "main = \"日本𝄞\" value".to_string(),
+                },
+                TextEdit {
+                    range: range(1, 0, 13),
+                    new_text: "value = \"a\"".to_string(),
+                },
+            ]
+        );
+
+        assert!(format(GRAMMAR).is_empty());
+    }
+}
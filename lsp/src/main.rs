@@ -0,0 +1,149 @@
+#![forbid(unsafe_code)]
+#![forbid(unused_must_use)]
+
+mod analysis;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types::*;
+use tower_lsp::{Client, LanguageServer, LspService, Server};
+
+/// Language server for Peggy grammars
+struct Backend {
+    client: Client,
+
+    /// Content of the opened grammars
+    documents: Mutex<HashMap<Url, String>>,
+}
+
+impl Backend {
+    /// Get the content of an opened grammar
+    fn document(&self, uri: &Url) -> Option<String> {
+        self.documents.lock().unwrap().get(uri).cloned()
+    }
+
+    /// Store the new content of a grammar and publish its diagnostics
+    async fn update(&self, uri: Url, text: String, version: i32) {
+        let diagnostics = analysis::diagnostics(&text);
+
+        self.documents.lock().unwrap().insert(uri.clone(), text);
+
+        self.client
+            .publish_diagnostics(uri, diagnostics, Some(version))
+            .await;
+    }
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for Backend {
+    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+        Ok(InitializeResult {
+            capabilities: ServerCapabilities {
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                    TextDocumentSyncKind::FULL,
+                )),
+                definition_provider: Some(OneOf::Left(true)),
+                references_provider: Some(OneOf::Left(true)),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                document_formatting_provider: Some(OneOf::Left(true)),
+                ..ServerCapabilities::default()
+            },
+            server_info: Some(ServerInfo {
+                name: env!("CARGO_PKG_NAME").to_string(),
+                version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            }),
+        })
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        let document = params.text_document;
+        self.update(document.uri, document.text, document.version)
+            .await;
+    }
+
+    async fn did_change(&self, mut params: DidChangeTextDocumentParams) {
+        // Documents are fully synchronized, so the last change holds the whole content
+        if let Some(change) = params.content_changes.pop() {
+            let document = params.text_document;
+            self.update(document.uri, change.text, document.version)
+                .await;
+        }
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+
+        self.documents.lock().unwrap().remove(&uri);
+        self.client.publish_diagnostics(uri, vec![], None).await;
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+        let uri = text_document.uri;
+
+        Ok(self
+            .document(&uri)
+            .and_then(|text| analysis::definition(&text, position))
+            .map(|range| GotoDefinitionResponse::Scalar(Location::new(uri, range))))
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position;
+        let uri = text_document.uri;
+        let include_declaration = params.context.include_declaration;
+
+        Ok(self.document(&uri).map(|text| {
+            analysis::references(&text, position, include_declaration)
+                .into_iter()
+                .map(|range| Location::new(uri.clone(), range))
+                .collect()
+        }))
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let position = params.text_document_position_params;
+
+        Ok(self
+            .document(&position.text_document.uri)
+            .and_then(|text| analysis::hover(&text, position.position))
+            .map(|value| Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value,
+                }),
+                range: None,
+            }))
+    }
+
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        Ok(self
+            .document(&params.text_document.uri)
+            .map(|text| analysis::format(&text)))
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let (service, socket) = LspService::new(|client| Backend {
+        client,
+        documents: Mutex::new(HashMap::new()),
+    });
+
+    Server::new(tokio::io::stdin(), tokio::io::stdout(), socket)
+        .serve(service)
+        .await;
+}
@@ -0,0 +1,76 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../examples/indentation.peggy", indentation = true)]
+mod indentation_grammar {}
+
+use indentation_grammar::unions::Sw2;
+use indentation_grammar::{matched, preprocess_indentation, IndentationError};
+
+static TEST_INPUT: &str = "\
+setup
+loop:
+    read
+
+    if:
+        print
+        flush
+    wait
+exit
+";
+
+// Blocks indented with tabs, closed at the end of the input (which has no trailing newline)
+static TABS_INPUT: &str = "loop:\n\tif:\n\t\tbreak";
+
+// The second level mixes tabs and spaces differently than the first one
+static INCONSISTENT_INPUT: &str = "loop:\n\tif:\n    \tbreak\n";
+
+fn main() {
+    for input in [TEST_INPUT, TABS_INPUT] {
+        // Insert the indentation markers
+        let indented = preprocess_indentation(input).unwrap_or_else(|err| {
+            panic!("Failed to preprocess input: {}", err);
+        });
+
+        let success = indentation_grammar::exec(indented.text()).unwrap_or_else(|err| {
+            panic!(
+                "Failed to match input against indentation grammar:\n{}",
+                err
+            );
+        });
+
+        println!("Input:\n{}\n", input);
+        println!("Statements:");
+
+        for stmt in &success.matched {
+            print_stmt(stmt, 1);
+        }
+
+        println!();
+    }
+
+    assert_eq!(
+        preprocess_indentation(INCONSISTENT_INPUT),
+        Err(IndentationError::InconsistentIndentation(11))
+    );
+}
+
+fn print_stmt(stmt: &matched::stmt, depth: usize) {
+    let padding = "  ".repeat(depth);
+
+    match &stmt.matched {
+        Sw2::A(compound) => {
+            let (name, block) = &compound.matched;
+            println!(
+                "{}{} (block of {})",
+                padding,
+                name.matched,
+                block.matched.len()
+            );
+
+            for stmt in &block.matched {
+                print_stmt(stmt, depth + 1);
+            }
+        }
+        Sw2::B(simple) => println!("{}{}", padding, simple.matched.matched),
+    }
+}
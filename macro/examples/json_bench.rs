@@ -0,0 +1,92 @@
+use peggy_macro::peggy_gen;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+#[peggy_gen(filename = "../examples/json.peggy")]
+pub mod json_grammar {}
+
+#[peggy_gen(filename = "../examples/json.peggy", preallocate_repetitions = true)]
+pub mod json_grammar_preallocated {}
+
+static ARRAY_LEN: usize = 100_000;
+static ITERATIONS: usize = 20;
+
+/// Allocator counting the reallocations, to show the effect of preallocating repetitions
+struct CountingAllocator;
+
+static REALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        REALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn main() {
+    // Build a large array literal
+    let input = format!(
+        "[{}]",
+        (0..ARRAY_LEN)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    println!(
+        "Input     : array of {} numbers ({} bytes)",
+        ARRAY_LEN,
+        input.len()
+    );
+    println!("Iterations: {}", ITERATIONS);
+
+    let default = measure(|| json_grammar::exec(&input).is_ok());
+    let preallocated = measure(|| json_grammar_preallocated::exec(&input).is_ok());
+
+    println!(
+        "Default     : {:.1} milliseconds (average), {} reallocations",
+        default.0, default.1
+    );
+
+    println!(
+        "Preallocated: {:.1} milliseconds (average), {} reallocations {}",
+        preallocated.0,
+        preallocated.1,
+        if cfg!(debug_assertions) {
+            " [WARNING: debug mode heavily impacts performances]"
+        } else {
+            ""
+        }
+    );
+
+    assert!(preallocated.1 < default.1);
+}
+
+/// Get the average duration of a parsing function (in milliseconds) and the number of reallocations it performs
+fn measure(parse: impl Fn() -> bool) -> (f64, usize) {
+    let reallocations = REALLOCATIONS.load(Ordering::Relaxed);
+    assert!(parse(), "Failed to match input against JSON grammar");
+    let reallocations = REALLOCATIONS.load(Ordering::Relaxed) - reallocations;
+
+    let now = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        assert!(parse());
+    }
+
+    let average_time_ms = now.elapsed().as_micros() as f64 / 1000.0 / ITERATIONS as f64;
+
+    (average_time_ms, reallocations)
+}
@@ -0,0 +1,80 @@
+use peggy_macro::peggy_gen;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Allocator counting the allocations made by the parser
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[peggy_gen(filename = "../examples/keywords.peggy")]
+pub mod keywords_grammar {}
+
+static TEST_INPUT: &str =
+    "fn main() { let mut counter = 0; while running { counter = counter + 1; } return counter; }";
+static ITERATIONS: usize = 100_000;
+
+fn main() {
+    println!("Input     : {}", TEST_INPUT);
+    println!("Iterations: {}", ITERATIONS);
+
+    // Measure performance
+    let mut iteration = 0;
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let now = Instant::now();
+
+    // Tokenize the input
+    let success = loop {
+        iteration += 1;
+
+        let result = keywords_grammar::exec(TEST_INPUT).unwrap_or_else(|err| {
+            panic!("Failed to match input against keywords grammar:\n{}", err);
+        });
+
+        if iteration == ITERATIONS {
+            break result;
+        }
+    };
+
+    // Get elapsed time
+    let elapsed = now.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    // Get average execution time
+    let average_time_ms = elapsed.as_micros() as f64 / ITERATIONS as f64;
+
+    // Display performance
+    println!(
+        "Parsing   : {:.1} microseconds (average) {}",
+        average_time_ms,
+        if cfg!(debug_assertions) {
+            " [WARNING: debug mode heavily impacts performances]"
+        } else {
+            ""
+        }
+    );
+
+    println!(
+        "Allocs    : {:.1} (average)",
+        allocations as f64 / ITERATIONS as f64
+    );
+
+    // Ensure all tokens were matched
+    println!("Tokens    : {}", success.matched.len());
+    assert_eq!(success.matched.len(), 25);
+}
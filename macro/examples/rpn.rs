@@ -26,8 +26,11 @@ fn main() {
 }
 
 fn eval_expr(expr: &matched::expr) -> f64 {
-    expr.matched
-        .variants_ref(eval_num, eval_operation, eval_paren_expr)
+    expr.matched.variants_ref(
+        eval_num,
+        |op| eval_operation(op),
+        |paren| eval_paren_expr(paren),
+    )
 }
 
 fn eval_num(num: &matched::number) -> f64 {
@@ -57,7 +60,8 @@ fn eval_operation(op: &matched::operation) -> f64 {
 }
 
 fn eval_operand(op: &matched::operand) -> f64 {
-    op.matched.variants_ref(eval_num, eval_paren_expr)
+    op.matched
+        .variants_ref(eval_num, |paren| eval_paren_expr(paren))
 }
 
 fn eval_paren_expr(paren_expr: &matched::paren_expr) -> f64 {
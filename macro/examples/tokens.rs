@@ -0,0 +1,107 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../examples/tokens.peggy", token_input = true)]
+mod tokens_grammar {}
+
+use tokens_grammar::unions::Sw2;
+use tokens_grammar::{encode_tokens, matched, LexedInput, LexedToken};
+
+static TEST_INPUT: &str = "let a = 1 + 2; let b = a + 3 + a;";
+
+/// Token produced by the lexer, along with the input it was produced from
+struct Token<'a> {
+    kind: &'static str,
+    text: &'a str,
+}
+
+impl<'a> LexedToken for Token<'a> {
+    fn kind(&self) -> &str {
+        self.kind
+    }
+}
+
+fn main() {
+    let tokens = lex(TEST_INPUT);
+
+    // Encode the tokens to match them
+    let input = encode_tokens(&tokens);
+
+    let success = tokens_grammar::exec(input.text()).unwrap_or_else(|err| {
+        panic!("Failed to match tokens against the grammar:\n{}", err);
+    });
+
+    println!("Input: {}", TEST_INPUT);
+    println!("Tokens: {}", tokens.len());
+
+    for stmt in &success.matched {
+        let (name, expr) = &stmt.matched;
+
+        let operands: Vec<_> = std::iter::once(&expr.matched.0)
+            .chain(&expr.matched.1)
+            .map(|operand| token_text(&input, &tokens, operand))
+            .collect();
+
+        println!(
+            "{} = {}",
+            tokens[input.token_range(name.span)][0].text,
+            operands.join(" + ")
+        );
+    }
+
+    assert_eq!(success.matched.len(), 2);
+
+    // Unexpected tokens fail to match like unexpected characters
+    let invalid = encode_tokens(&lex("let c = ;"));
+    assert!(tokens_grammar::exec(invalid.text()).is_err());
+}
+
+/// Get the text of the token an operand was matched from
+fn token_text<'a>(input: &LexedInput, tokens: &[Token<'a>], operand: &matched::operand) -> &'a str {
+    let span = match &operand.matched {
+        Sw2::A(name) => name.span,
+        Sw2::B(number) => number.span,
+    };
+
+    tokens[input.token_index(span.start)].text
+}
+
+/// Split an input into tokens, skipping whitespaces
+fn lex(input: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+
+        let mut end = start + c.len_utf8();
+
+        if c.is_alphanumeric() {
+            while let Some((i, c)) = chars.peek().copied() {
+                if !c.is_alphanumeric() {
+                    break;
+                }
+
+                end = i + c.len_utf8();
+                chars.next();
+            }
+        }
+
+        let text = &input[start..end];
+
+        let kind = match text {
+            "let" => "let",
+            "=" => "=",
+            "+" => "+",
+            ";" => ";",
+            _ if c.is_ascii_digit() => "number",
+            _ if c.is_alphabetic() => "ident",
+            _ => "unknown",
+        };
+
+        tokens.push(Token { kind, text });
+    }
+
+    tokens
+}
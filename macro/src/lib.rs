@@ -1,22 +1,38 @@
 #![forbid(unsafe_code)]
 #![forbid(unused_must_use)]
 
-use lazy_static::lazy_static;
 use peggy::grammar::{parse_peg, pretty_format_parser_err};
-use peggy::rustgen::gen_rust_token_stream;
+use peggy::rustgen::{gen_rust_token_stream_with, RecursionWrapper, RustGenOptions};
 use proc_macro::TokenStream;
+use proc_macro2::Span;
 use quote::quote;
-use regex::Regex;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs;
 use std::path::PathBuf;
-use syn::{Ident, ItemMod, Visibility};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Error, Ident, ItemMod, Lit, LitStr, MetaNameValue, Token, Visibility};
 
-lazy_static! {
-    static ref ATTR_CONTENT: Regex = Regex::new(
-        "^filename\\s*=\\s*\"(?P<filename>[^\"]+)\"(?:,\\s*(debugger\\s*=\\s*\"(?P<debugger>[^\"]+)\"))?$"
-    ).unwrap();
-}
+/// Options accepted by the attribute, in any order
+static OPTION_NAMES: &[&str] = &[
+    "filename",
+    "debugger",
+    "partial_match",
+    "grapheme_boundaries",
+    "collect_trivia",
+    "simplify_marker_types",
+    "recursion_wrapper",
+    "checked_slicing",
+    "tree_printer",
+    "streaming",
+    "derive_default",
+    "indentation",
+    "preallocate_repetitions",
+    "token_input",
+    "sample_constructors",
+    "rename",
+];
 
 /// Options decoded from the attribute
 /// Input file (grammar)
@@ -25,13 +41,58 @@ struct Options {
 
     /// Debugger function path
     debugger: Option<String>,
+
+    /// Don't require the whole input to be matched
+    partial_match: bool,
+
+    /// Only match constant strings ending on a grapheme cluster boundary
+    grapheme_boundaries: bool,
+
+    /// Collect the input matched by silent patterns
+    collect_trivia: bool,
+
+    /// Simplify the type of optional and repeated constant strings
+    simplify_marker_types: bool,
+
+    /// Type used to store the data of recursive rules
+    recursion_wrapper: RecursionWrapper,
+
+    /// Return an error instead of panicking when a matcher consumes an invalid number of bytes
+    checked_slicing: bool,
+
+    /// Generate a method pretty-printing matched data with the matched input
+    tree_printer: bool,
+
+    /// Generate a function calling a callback with each element matched by the main rule's repetition
+    streaming: bool,
+
+    /// Derive `Default` on the types of rules only made of optional and repeated patterns
+    derive_default: bool,
+
+    /// Support indentation-based grammars through the `E_INDENT`, `E_DEDENT` and `E_NEWLINE` external rules
+    indentation: bool,
+
+    /// Reserve the capacity of repetitions' data once their first element is matched
+    preallocate_repetitions: bool,
+
+    /// Match a sequence of tokens produced by a separate lexer instead of a string
+    token_input: bool,
+
+    /// Generate `sample` constructors building minimal instances of matched rules' types
+    sample_constructors: bool,
+
+    /// Identifiers used for rules in the generated code instead of their name
+    rename: HashMap<String, String>,
 }
 
 #[proc_macro_attribute]
 pub fn peggy_gen(attr: TokenStream, item: TokenStream) -> TokenStream {
     let (mod_ident, mod_vis) = parse_input_mod(item);
 
-    let options = parse_options_attr(attr);
+    let options = match parse_options_attr(attr.into()) {
+        Ok(options) => options,
+        Err(err) => return err.to_compile_error().into(),
+    };
 
     if !options.grammar_file.exists() {
         panic!("Grammar file was not found at path (tip: path starts from your crate's \"src\" directory)");
@@ -72,25 +133,126 @@ fn parse_input_mod(item: TokenStream) -> (Ident, Visibility) {
     (mod_ident, item.vis)
 }
 
-fn parse_options_attr(attr: TokenStream) -> Options {
-    let attr = attr.to_string();
+/// Parse the attribute's options (syntax: `filename = "<path>", option = value, ...`, in any order)
+fn parse_options_attr(attr: proc_macro2::TokenStream) -> Result<Options, Error> {
+    let pairs = Punctuated::<MetaNameValue, Token![,]>::parse_terminated.parse2(attr)?;
+
+    let mut filename = None;
+    let mut seen = HashSet::new();
+
+    let mut options = Options {
+        grammar_file: PathBuf::new(),
+        debugger: None,
+        partial_match: false,
+        grapheme_boundaries: false,
+        collect_trivia: false,
+        simplify_marker_types: true,
+        recursion_wrapper: RecursionWrapper::Rc,
+        checked_slicing: false,
+        tree_printer: false,
+        streaming: false,
+        derive_default: false,
+        indentation: false,
+        preallocate_repetitions: false,
+        token_input: false,
+        sample_constructors: false,
+        rename: HashMap::new(),
+    };
+
+    for pair in &pairs {
+        let name = match pair.path.get_ident() {
+            Some(ident) if OPTION_NAMES.contains(&ident.to_string().as_str()) => ident.to_string(),
+            _ => {
+                return Err(Error::new_spanned(
+                    &pair.path,
+                    format!(
+                        "Unknown option (expected one of: {})",
+                        OPTION_NAMES.join(", ")
+                    ),
+                ))
+            }
+        };
+
+        if !seen.insert(name.clone()) {
+            return Err(Error::new_spanned(
+                &pair.path,
+                format!("Option '{}' is provided more than once", name),
+            ));
+        }
+
+        match name.as_str() {
+            "filename" => filename = Some(lit_str(pair)?.value()),
+            "debugger" => options.debugger = Some(lit_str(pair)?.value()),
+            "partial_match" => options.partial_match = lit_bool(pair)?,
+            "grapheme_boundaries" => options.grapheme_boundaries = lit_bool(pair)?,
+            "collect_trivia" => options.collect_trivia = lit_bool(pair)?,
+            "simplify_marker_types" => options.simplify_marker_types = lit_bool(pair)?,
+            "recursion_wrapper" => {
+                options.recursion_wrapper = match lit_str(pair)?.value().as_str() {
+                    "Box" => RecursionWrapper::Box,
+                    "Arc" => RecursionWrapper::Arc,
+                    "Rc" => RecursionWrapper::Rc,
+                    path => RecursionWrapper::Custom(path.to_string()),
+                }
+            }
+            "checked_slicing" => options.checked_slicing = lit_bool(pair)?,
+            "tree_printer" => options.tree_printer = lit_bool(pair)?,
+            "streaming" => options.streaming = lit_bool(pair)?,
+            "derive_default" => options.derive_default = lit_bool(pair)?,
+            "indentation" => options.indentation = lit_bool(pair)?,
+            "preallocate_repetitions" => options.preallocate_repetitions = lit_bool(pair)?,
+            "token_input" => options.token_input = lit_bool(pair)?,
+            "sample_constructors" => options.sample_constructors = lit_bool(pair)?,
+            "rename" => options.rename = parse_rename(lit_str(pair)?)?,
+            _ => unreachable!(),
+        }
+    }
+
+    let filename = filename.ok_or_else(|| {
+        Error::new(
+            Span::call_site(),
+            "Please provide a grammar file path under the form: #[peggy_gen(filename = \"<path>\")]",
+        )
+    })?;
+
+    options.grammar_file = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    options.grammar_file.push("src");
+    options.grammar_file.push(filename);
 
-    let captured = ATTR_CONTENT.captures(&attr).expect(
-        "Please provide a grammar file path under the form: #[peggy_grammar(filename = \"<path>\")] ('debugger' may be added with the same syntax)",
-    );
-    let filename = captured.name("filename").unwrap();
+    Ok(options)
+}
 
-    let mut grammar_file = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
-    grammar_file.push("src");
-    grammar_file.push(filename.as_str());
+/// Get the string value of an option
+fn lit_str(pair: &MetaNameValue) -> Result<&LitStr, Error> {
+    match &pair.lit {
+        Lit::Str(lit) => Ok(lit),
+        lit => Err(Error::new_spanned(lit, "Expected a string")),
+    }
+}
 
-    Options {
-        grammar_file,
-        debugger: captured.name("debugger").map(|m| m.as_str().to_string()),
+/// Get the boolean value of an option
+fn lit_bool(pair: &MetaNameValue) -> Result<bool, Error> {
+    match &pair.lit {
+        Lit::Bool(lit) => Ok(lit.value),
+        lit => Err(Error::new_spanned(lit, "Expected 'true' or 'false'")),
     }
 }
 
-fn grammar_to_rust(options: &Options) -> quote::__private::TokenStream {
+/// Parse the renamed rules (syntax: `rule: Ident, other_rule: OtherIdent`)
+fn parse_rename(list: &LitStr) -> Result<HashMap<String, String>, Error> {
+    list.value()
+        .split(',')
+        .map(|entry| match entry.split_once(':') {
+            Some((rule, ident)) => Ok((rule.trim().to_string(), ident.trim().to_string())),
+            None => Err(Error::new_spanned(
+                list,
+                format!("Invalid rename '{}', expected 'rule: Ident'", entry.trim()),
+            )),
+        })
+        .collect()
+}
+
+fn grammar_to_rust(options: &Options) -> proc_macro2::TokenStream {
     let grammar_src =
         fs::read_to_string(&options.grammar_file).expect("Provided file could not be read");
 
@@ -101,5 +263,73 @@ fn grammar_to_rust(options: &Options) -> quote::__private::TokenStream {
         )
     });
 
-    gen_rust_token_stream(&grammar, options.debugger.as_deref())
+    gen_rust_token_stream_with(
+        &grammar,
+        &RustGenOptions {
+            debugger: options.debugger.clone(),
+            partial_match: options.partial_match,
+            grapheme_boundaries: options.grapheme_boundaries,
+            collect_trivia: options.collect_trivia,
+            simplify_marker_types: options.simplify_marker_types,
+            recursion_wrapper: options.recursion_wrapper.clone(),
+            checked_slicing: options.checked_slicing,
+            tree_printer: options.tree_printer,
+            streaming: options.streaming,
+            derive_default: options.derive_default,
+            indentation: options.indentation,
+            preallocate_repetitions: options.preallocate_repetitions,
+            token_input: options.token_input,
+            sample_constructors: options.sample_constructors,
+            rename: options.rename.clone(),
+            ..RustGenOptions::new()
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_err(attr: proc_macro2::TokenStream) -> String {
+        match parse_options_attr(attr) {
+            Ok(_) => panic!("options were parsed successfully"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn options_in_any_order() {
+        let options = parse_options_attr(quote! {
+            streaming = true, rename = "a: B", filename = "grammar.peggy", indentation = true,
+        })
+        .unwrap();
+
+        assert!(options.grammar_file.ends_with("src/grammar.peggy"));
+        assert!(options.streaming);
+        assert!(options.indentation);
+        assert!(!options.partial_match);
+        assert!(options.simplify_marker_types);
+        assert_eq!(options.rename.get("a").map(String::as_str), Some("B"));
+    }
+
+    #[test]
+    fn invalid_options() {
+        assert!(parse_err(quote! {}).contains("Please provide a grammar file path"));
+        assert!(parse_err(quote! { indentation = true }).contains("grammar file path"));
+
+        let err = parse_err(quote! { filename = "a.peggy", indentaton = true });
+        assert!(err.starts_with("Unknown option"), "{}", err);
+
+        let err = parse_err(quote! { filename = "a.peggy", streaming = true, streaming = false });
+        assert_eq!(err, "Option 'streaming' is provided more than once");
+
+        let err = parse_err(quote! { filename = "a.peggy", streaming = "true" });
+        assert_eq!(err, "Expected 'true' or 'false'");
+
+        let err = parse_err(quote! { filename = a });
+        assert!(!err.is_empty());
+
+        let err = parse_err(quote! { filename = "a.peggy", rename = "a B" });
+        assert_eq!(err, "Invalid rename 'a B', expected 'rule: Ident'");
+    }
 }
@@ -0,0 +1,28 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/categories.peggy")]
+mod categories {}
+
+/// Characters from most general categories, including non-ASCII ones and combining marks
+const SAMPLE: &[char] = &[
+    'a', 'Z', 'ǅ', 'ʰ', '中', '\u{301}', '\u{903}', '\u{20DD}', '7', '٣', '５', '²', 'Ⅳ', '_', '‿',
+    '-', '(', '«', '!', '+', '€', '^', '©', ' ', '\n',
+];
+
+/// Get the sample characters matched after a category's initial
+fn matched_chars(initial: char) -> String {
+    SAMPLE
+        .iter()
+        .filter(|c| categories::exec(&format!("{}{}", initial, c)).is_ok())
+        .collect()
+}
+
+#[test]
+fn general_categories() {
+    assert_eq!(matched_chars('L'), "aZǅʰ中");
+    assert_eq!(matched_chars('M'), "\u{301}\u{903}\u{20DD}");
+    assert_eq!(matched_chars('N'), "7٣５");
+    assert_eq!(matched_chars('P'), "_‿-(«!");
+    assert_eq!(matched_chars('C'), "_‿");
+    assert_eq!(matched_chars('S'), "+€^©");
+}
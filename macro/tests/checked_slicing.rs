@@ -0,0 +1,52 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../examples/json.peggy")]
+mod json {}
+
+#[peggy_gen(filename = "../examples/json.peggy", checked_slicing = true)]
+mod checked_json {}
+
+#[peggy_gen(filename = "../examples/rpn.peggy")]
+mod rpn {}
+
+#[peggy_gen(filename = "../examples/rpn.peggy", checked_slicing = true)]
+mod checked_rpn {}
+
+#[test]
+fn same_output_as_unchecked_json() {
+    for (input, valid) in [
+        (
+            r#"{ "key": "value", "list": [1, -2.5, true, null, "é"] }"#,
+            true,
+        ),
+        ("[]", true),
+        (r#"{ "key" }"#, false),
+        (r#"["unterminated"#, false),
+    ] {
+        assert_eq!(json::exec(input).is_ok(), valid, "in {:?}", input);
+        assert_eq!(
+            format!("{:?}", json::exec(input)),
+            format!("{:?}", checked_json::exec(input)),
+            "in {:?}",
+            input
+        );
+    }
+}
+
+#[test]
+fn same_output_as_unchecked_rpn() {
+    for (input, valid) in [
+        ("(3 (9.3 3 /) +) (5 (2 3 /) /) /", true),
+        ("(1 2 +) 3 *", true),
+        ("(1 2 +", false),
+        ("1 x", false),
+    ] {
+        assert_eq!(rpn::exec(input).is_ok(), valid, "in {:?}", input);
+        assert_eq!(
+            format!("{:?}", rpn::exec(input)),
+            format!("{:?}", checked_rpn::exec(input)),
+            "in {:?}",
+            input
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/cuts.peggy")]
+mod cuts {}
+
+use cuts::unions::Sw2;
+use cuts::PegErrorContent;
+
+#[test]
+fn committed_member_matches() {
+    let data = cuts::exec("if (x)").unwrap();
+    assert!(matches!(data.matched.matched, Sw2::A(_)));
+}
+
+#[test]
+fn members_before_cut_backtrack() {
+    // The cut isn't reached, so the other members are tried
+    let data = cuts::exec("is x").unwrap();
+    assert!(matches!(data.matched.matched, Sw2::B(_)));
+
+    let err = cuts::exec("i").unwrap_err();
+    assert_eq!(err.rule, "stmt");
+    assert!(matches!(err.content, PegErrorContent::NoMatchInUnion(_)));
+}
+
+#[test]
+fn failure_after_cut_is_reported() {
+    // Without the cut, `if x` would be matched as a call
+    let err = cuts::exec("if x").unwrap_err();
+
+    assert_eq!(err.offset, 3);
+    assert_eq!(err.rule, "stmt");
+    assert!(
+        matches!(err.content, PegErrorContent::ExpectedCstString("(")),
+        "unexpected content: {:?}",
+        err.content
+    );
+    assert!(err.to_string().contains("Expected constant string: ("));
+
+    let err = cuts::exec("if (x").unwrap_err();
+
+    assert_eq!(err.offset, 5);
+    assert!(matches!(
+        err.content,
+        PegErrorContent::ExpectedCstString(")")
+    ));
+}
@@ -0,0 +1,32 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/peek.peggy")]
+mod peek {}
+
+#[peggy_gen(filename = "../tests/grammars/silent_negative.peggy")]
+mod silent_negative {}
+
+#[test]
+fn peek_rule() {
+    // The peeking rule doesn't capture any data nor consume the input
+    let data = peek::exec("b").unwrap();
+    assert_eq!(data.matched, peek::strings::Str_b);
+    assert_eq!((data.span.start, data.span.end), (0, 1));
+
+    let err = peek::exec("c").unwrap_err();
+    assert_eq!(err.offset, 0);
+}
+
+#[test]
+fn silent_negative_rule() {
+    // The silent and negative patterns don't capture any data
+    let data = silent_negative::exec("bd").unwrap();
+    assert_eq!(data.matched, silent_negative::strings::Str_d);
+    assert_eq!((data.span.start, data.span.end), (0, 2));
+
+    let err = silent_negative::exec("bc").unwrap_err();
+    assert_eq!(err.offset, 1);
+
+    let err = silent_negative::exec("d").unwrap_err();
+    assert_eq!(err.offset, 0);
+}
@@ -0,0 +1,33 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/modifiers.peggy", derive_default = true)]
+mod modifiers {}
+
+use modifiers::{matched, Span};
+
+#[test]
+fn default_matches_an_empty_input() {
+    let default = matched::modifiers::default();
+    assert_eq!(default.span, Span { start: 0, end: 0 });
+
+    // Optional constant strings are simplified to booleans
+    let (public, statik, attributes) = &default.matched;
+    assert!(!public);
+    assert!(!statik);
+    assert!(attributes.is_empty());
+
+    // Same data as when nothing is matched at the beginning of the input
+    let data = modifiers::exec("fn").unwrap();
+    assert_eq!(data.matched, default);
+}
+
+#[test]
+fn matched_data_differs_from_default() {
+    let data = modifiers::exec("pub #inline fn").unwrap();
+    assert_ne!(data.matched, matched::modifiers::default());
+
+    let (public, statik, attributes) = &data.matched.matched;
+    assert!(public);
+    assert!(!statik);
+    assert_eq!(attributes[0].matched, "inline");
+}
@@ -0,0 +1,28 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/digits.peggy")]
+mod digits {}
+
+#[test]
+fn digits_in_each_base() {
+    for input in ["0b0110", "0o1777", "0xC0ffee", "0", "7", "1024", "9000"] {
+        assert!(digits::exec(input).is_ok(), "{:?} doesn't match", input);
+    }
+}
+
+#[test]
+fn digits_out_of_their_base() {
+    for (input, offset) in [
+        ("0b012", 4),
+        ("0o78", 3),
+        ("0xfg", 3),
+        ("0x", 1),
+        ("01", 1),
+        ("00", 1),
+        ("٣", 0),
+        ("５", 0),
+    ] {
+        let err = digits::exec(input).unwrap_err();
+        assert_eq!(err.deepest().offset, offset, "in {:?}", input);
+    }
+}
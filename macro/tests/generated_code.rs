@@ -0,0 +1,133 @@
+//! Compare the code generated for the examples' grammars with the expected one, stored in the `golden` directory
+//!
+//! When a change of the generated code is intended, the expected files can be updated by running these tests with
+//! the `PEGGY_BLESS` environment variable set (e.g. `PEGGY_BLESS=1 cargo test -p peggy_macro --test generated_code`),
+//! and reviewing their diff.
+
+use peggy::grammar::parse_peg;
+use peggy::rustgen::{gen_rust_str_with, RustGenOptions};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Generate the code of an example's grammar, with one item per line to get readable diffs
+fn generate(example: &str, options: RustGenOptions) -> String {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let grammar = fs::read_to_string(dir.join(format!("{}.peggy", example))).unwrap();
+    let code = gen_rust_str_with(&parse_peg(&grammar).unwrap(), &options);
+
+    let mut lines = String::new();
+
+    for c in code.chars() {
+        // Tokens are separated by spaces, which aren't needed at the beginning of lines
+        if c == ' ' && lines.ends_with('\n') {
+            continue;
+        }
+
+        lines.push(c);
+
+        if matches!(c, ';' | '{' | '}') {
+            lines.push('\n');
+        }
+    }
+
+    lines
+}
+
+/// Compare the code generated for an example with the golden file of the provided name, or update the file if blessing
+fn check_golden(golden: &str, example: &str, options: RustGenOptions) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{}.rs.golden", golden));
+
+    let generated = generate(example, options);
+
+    if env::var_os("PEGGY_BLESS").is_some() {
+        fs::write(&path, generated).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "Golden file {} is missing, run the tests with PEGGY_BLESS=1 to create it",
+            path.display()
+        )
+    });
+
+    if let Some((line, (generated, expected))) = generated
+        .lines()
+        .zip(expected.lines())
+        .enumerate()
+        .find(|(_, (generated, expected))| generated != expected)
+    {
+        panic!(
+            "Code generated for the '{}' example differs from {} at line {}:\n  expected: {}\n  generated: {}\n\nRun the tests with PEGGY_BLESS=1 to update it if the change is intended",
+            example,
+            path.display(),
+            line + 1,
+            expected,
+            generated
+        );
+    }
+
+    assert_eq!(
+        generated.lines().count(),
+        expected.lines().count(),
+        "Code generated for the '{}' example doesn't have as many lines as {}, run the tests with PEGGY_BLESS=1 to update it if the change is intended",
+        example,
+        path.display()
+    );
+}
+
+#[test]
+fn float() {
+    check_golden("float", "float", RustGenOptions::new());
+}
+
+#[test]
+fn json() {
+    check_golden("json", "json", RustGenOptions::new());
+}
+
+#[test]
+fn json_preallocated() {
+    let mut options = RustGenOptions::new();
+    options.preallocate_repetitions = true;
+
+    check_golden("json_preallocated", "json", options);
+}
+
+#[test]
+fn keywords() {
+    check_golden("keywords", "keywords", RustGenOptions::new());
+}
+
+#[test]
+fn rpn() {
+    check_golden("rpn", "rpn", RustGenOptions::new());
+}
+
+#[test]
+fn indentation() {
+    let mut options = RustGenOptions::new();
+    options.indentation = true;
+
+    check_golden("indentation", "indentation", options);
+}
+
+#[test]
+fn tokens() {
+    let mut options = RustGenOptions::new();
+    options.token_input = true;
+
+    check_golden("tokens", "tokens", options);
+}
+
+#[test]
+fn debugger() {
+    let mut options = RustGenOptions::new();
+    options.debugger = Some("debugger".to_string());
+
+    check_golden("debugger", "debugger", options);
+}
@@ -0,0 +1,30 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/graphemes.peggy")]
+mod chars {}
+
+#[peggy_gen(
+    filename = "../tests/grammars/graphemes.peggy",
+    grapheme_boundaries = true
+)]
+mod graphemes {}
+
+#[test]
+fn constant_strings_in_grapheme_clusters() {
+    // `"e"` matches the beginning of the `e\u{301}` grapheme cluster, unless the option is enabled
+    assert!(chars::exec("e\u{301}").is_ok());
+
+    let err = graphemes::exec("e\u{301}").unwrap_err();
+    assert_eq!(err.offset, 0);
+    assert!(matches!(
+        err.content,
+        graphemes::PegErrorContent::ExpectedCstString("e")
+    ));
+}
+
+#[test]
+fn constant_strings_on_grapheme_boundaries() {
+    assert!(graphemes::exec("e").is_ok());
+    assert!(graphemes::exec("ee\u{301}").is_ok());
+    assert!(graphemes::exec("e\u{301}e").is_err());
+}
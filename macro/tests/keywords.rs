@@ -0,0 +1,37 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/keywords.peggy")]
+mod keywords {}
+
+use keywords::unions::Sw2;
+
+/// Get whether each statement is a keyword
+fn kinds(input: &str) -> Vec<(bool, &str)> {
+    keywords::exec(input)
+        .unwrap()
+        .matched
+        .iter()
+        .map(|stmt| match &stmt.matched {
+            Sw2::A(kw) => (true, kw.span.slice(input)),
+            Sw2::B(ident) => (false, ident.matched),
+        })
+        .collect()
+}
+
+#[test]
+fn keywords_match_whole_words() {
+    assert_eq!(
+        kinds("if iffy else elsewhere"),
+        [
+            (true, "if"),
+            (false, "iffy"),
+            (true, "else"),
+            (false, "elsewhere")
+        ]
+    );
+}
+
+#[test]
+fn keywords_at_end_of_input() {
+    assert_eq!(kinds("iffy if"), [(false, "iffy"), (true, "if")]);
+}
@@ -0,0 +1,39 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/markers.peggy")]
+mod simplified {}
+
+#[peggy_gen(
+    filename = "../tests/grammars/markers.peggy",
+    simplify_marker_types = false
+)]
+mod raw {}
+
+#[test]
+fn simplified_marker_types() {
+    let data = simplified::exec("&mutx**").unwrap();
+    let (_, mutability, stars) = &data.matched.matched;
+
+    let is_mut: bool = mutability.matched;
+    let count: usize = stars.matched;
+
+    assert!(is_mut);
+    assert_eq!(count, 2);
+
+    let data = simplified::exec("&").unwrap();
+    let (_, mutability, stars) = &data.matched.matched;
+    assert!(!mutability.matched);
+    assert_eq!(stars.matched, 0);
+}
+
+#[test]
+fn raw_marker_types() {
+    let data = raw::exec("&mutx**").unwrap();
+    let (_, mutability, stars) = &data.matched.matched;
+
+    let is_mut: &Option<raw::strings::Str_mut> = &mutability.matched;
+    let count: &Vec<raw::strings::Str___Multiply__> = &stars.matched;
+
+    assert!(is_mut.is_some());
+    assert_eq!(count.len(), 2);
+}
@@ -0,0 +1,112 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/lists.peggy")]
+mod lists {}
+
+use lists::PegErrorContent;
+
+/// Expected error for an invalid input
+struct NegativeCase {
+    input: &'static str,
+    offset: usize,
+    rule: &'static str,
+    content: fn(&PegErrorContent) -> bool,
+    message: &'static str,
+}
+
+/// Check if a union's error contains a failure of its `list` member at the provided offset
+fn list_failed_at(content: &PegErrorContent, offset: usize, expected: &str) -> bool {
+    match content {
+        PegErrorContent::NoMatchInUnion(errors) => errors.iter().any(|err| {
+            err.rule == "list"
+                && err.offset == offset
+                && matches!(err.content, PegErrorContent::ExpectedCstString(string) if string == expected)
+        }),
+        _ => false,
+    }
+}
+
+const CASES: &[NegativeCase] = &[
+    NegativeCase {
+        input: "",
+        offset: 0,
+        rule: "value",
+        content: |content| list_failed_at(content, 0, "["),
+        message: "Failed to match builtin rule [B_ASCII_DIGIT]: reached end of input",
+    },
+    NegativeCase {
+        input: "a",
+        offset: 0,
+        rule: "value",
+        content: |content| list_failed_at(content, 0, "["),
+        message: "Failed to match builtin rule [B_ASCII_DIGIT]: found character [a]",
+    },
+    // Union errors keep the furthest failure of each member
+    NegativeCase {
+        input: "[1, 2",
+        offset: 0,
+        rule: "value",
+        content: |content| list_failed_at(content, 5, "]"),
+        message: "Expected constant string: ]",
+    },
+    NegativeCase {
+        input: "[1,2]",
+        offset: 0,
+        rule: "value",
+        content: |content| list_failed_at(content, 2, "]"),
+        message: "at line 1, column 3",
+    },
+    NegativeCase {
+        input: "[[1], ]",
+        offset: 0,
+        rule: "value",
+        content: |content| list_failed_at(content, 4, "]"),
+        message: "at line 1, column 5",
+    },
+    // When the input isn't fully consumed, the error which stopped the last repetition explains why
+    NegativeCase {
+        input: "[1] ",
+        offset: 2,
+        rule: "list",
+        content: |content| matches!(content, PegErrorContent::ExpectedCstString(", ")),
+        message: "While matching rule [list] at line 1, column 3",
+    },
+    NegativeCase {
+        input: "[1, 2]]",
+        offset: 5,
+        rule: "list",
+        content: |content| matches!(content, PegErrorContent::ExpectedCstString(", ")),
+        message: "Expected constant string: , ",
+    },
+];
+
+#[test]
+fn negative_cases() {
+    for case in CASES {
+        let err = lists::exec(case.input).unwrap_err();
+
+        assert_eq!(err.offset, case.offset, "offset for {:?}", case.input);
+        assert_eq!(err.rule, case.rule, "rule for {:?}", case.input);
+        assert!(
+            (case.content)(&err.content),
+            "unexpected content for {:?}: {:?}",
+            case.input,
+            err.content
+        );
+
+        let message = err.to_string();
+        assert!(
+            message.contains(case.message),
+            "unexpected message for {:?}:\n{}",
+            case.input,
+            message
+        );
+    }
+}
+
+#[test]
+fn valid_inputs() {
+    for input in ["1", "[]", "[1, 23]", "[[1], [], [[4, 5]]]"] {
+        lists::exec(input).unwrap();
+    }
+}
@@ -0,0 +1,54 @@
+//! Generated parsers must compile without any warning, without disabling lints globally
+#![deny(warnings)]
+
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../examples/float.peggy")]
+pub mod float {}
+
+#[peggy_gen(filename = "../examples/json.peggy", preallocate_repetitions = true)]
+pub mod json {}
+
+#[peggy_gen(filename = "../examples/keywords.peggy", tree_printer = true)]
+pub mod keywords {}
+
+#[peggy_gen(filename = "../examples/rpn.peggy", sample_constructors = true)]
+pub mod rpn {}
+
+#[peggy_gen(filename = "../examples/indentation.peggy", indentation = true)]
+pub mod indentation {}
+
+#[peggy_gen(filename = "../examples/tokens.peggy", token_input = true)]
+pub mod tokens {}
+
+#[peggy_gen(filename = "../examples/debugger.peggy", debugger = "debugger")]
+pub mod debugger_grammar {}
+
+pub mod debugger {
+    pub fn entering_rule(_rule_name: &'static str, _input: &str, _offset: usize) {}
+
+    pub fn leaving_rule(
+        _rule_name: &'static str,
+        _input: &str,
+        _offset: usize,
+        _err: Option<super::debugger_grammar::PegError>,
+    ) {
+    }
+}
+
+#[peggy_gen(
+    filename = "../tests/grammars/spans.peggy",
+    collect_trivia = true,
+    recursion_wrapper = "Box",
+    checked_slicing = true,
+    derive_default = true
+)]
+pub mod spans {}
+
+#[test]
+fn generated_parsers_match() {
+    assert!(float::exec("1.5").is_ok());
+    assert!(json::exec("[1, {\"a\": true}]").is_ok());
+    assert!(rpn::exec("(1 2 +)").is_ok());
+    assert!(spans::exec("a (b) c").is_ok());
+}
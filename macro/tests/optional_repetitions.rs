@@ -0,0 +1,38 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/optional_repetitions.peggy")]
+mod repetitions {}
+
+use repetitions::rules;
+
+const INPUTS: &[&str] = &["!", "a!", "aaa!", "", "a", "b!", "aab!", "!a"];
+
+#[test]
+fn optional_repetitions_match_like_repetitions() {
+    for input in INPUTS {
+        let optional = rules::optional(input, input, 0);
+        let any = rules::any(input, input, 0);
+
+        match (optional, any) {
+            (Ok((optional, optional_len, _)), Ok((any, any_len, _))) => {
+                assert_eq!(optional_len, any_len, "in {:?}", input);
+
+                // The optional group is absent instead of counting no repetition
+                assert_ne!(optional.matched.0, Some(0), "in {:?}", input);
+                assert_eq!(
+                    optional.matched.0.unwrap_or(0),
+                    any.matched.0,
+                    "in {:?}",
+                    input
+                );
+            }
+            (Err(optional), Err(any)) => assert_eq!(optional.offset, any.offset, "in {:?}", input),
+            (optional, any) => panic!(
+                "in {:?}: {:?} doesn't match like {:?}",
+                input,
+                optional.is_ok(),
+                any.is_ok()
+            ),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use peggy_macro::peggy_gen;
+
+// Options can be provided in any order
+#[peggy_gen(partial_match = true, filename = "../tests/grammars/lists.peggy")]
+mod lists {}
+
+#[test]
+fn valid_prefixes() {
+    let (_, consumed) = lists::exec("[1, [2]] and more").unwrap();
+    assert_eq!(consumed, 8);
+
+    let (data, consumed) = lists::exec("12ab").unwrap();
+    assert_eq!(consumed, 2);
+    assert_eq!(data.span.end, 2);
+
+    // The whole input can still be matched
+    let (_, consumed) = lists::exec("[]").unwrap();
+    assert_eq!(consumed, 2);
+}
+
+#[test]
+fn invalid_prefixes() {
+    let err = lists::exec("[1, 2 and more").unwrap_err();
+    assert_eq!(err.deepest().offset, 5);
+
+    assert!(lists::exec("").is_err());
+    assert!(lists::exec("ab12").is_err());
+}
@@ -0,0 +1,62 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/recursion.peggy")]
+mod recursion {}
+
+use recursion::matched;
+use recursion::unions::{Sw2, Sw3};
+
+/// Sum all the numbers of an expression
+fn sum_expr(expr: &matched::expr) -> u64 {
+    let (first, others) = &expr.matched;
+    sum_term(first) + others.iter().map(|term| sum_term(term)).sum::<u64>()
+}
+
+fn sum_term(term: &matched::term) -> u64 {
+    let (first, others) = &term.matched;
+    sum_factor(first) + others.iter().map(|factor| sum_factor(factor)).sum::<u64>()
+}
+
+fn sum_factor(factor: &matched::factor) -> u64 {
+    match &factor.matched {
+        Sw3::A(number) => number.matched.parse().unwrap(),
+        Sw3::B(expr) => sum_expr(expr),
+        Sw3::C(list) => sum_list(list),
+    }
+}
+
+fn sum_list(list: &matched::list) -> u64 {
+    match &list.matched {
+        None => 0,
+        Some((first, others)) => {
+            sum_item(first) + others.iter().map(|item| sum_item(item)).sum::<u64>()
+        }
+    }
+}
+
+fn sum_item(item: &matched::item) -> u64 {
+    match &item.matched {
+        Sw2::A(expr) => sum_expr(expr),
+        Sw2::B(list) => sum_list(list),
+    }
+}
+
+#[test]
+fn overlapping_recursion_cycles() {
+    let data = recursion::exec("1+(2*[3,[4+5],[]])*6").unwrap();
+    assert_eq!(sum_expr(&data.matched), 21);
+
+    assert!(recursion::exec("1+[2,(3]").is_err());
+}
+
+#[peggy_gen(filename = "../tests/grammars/cycle_lifetimes.peggy")]
+mod cycle_lifetimes {}
+
+#[test]
+fn lifetimes_through_cycles() {
+    let input = "([x(-)]-)";
+    let data = cycle_lifetimes::exec(input).unwrap();
+
+    let first: &cycle_lifetimes::matched::b = &data.matched.matched[0];
+    assert_eq!(first.span.slice(input), "[x(-)]");
+}
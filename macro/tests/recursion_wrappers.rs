@@ -0,0 +1,82 @@
+use peggy_macro::peggy_gen;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::thread;
+
+#[peggy_gen(filename = "../tests/grammars/spans.peggy", recursion_wrapper = "Arc")]
+mod arc {}
+
+#[peggy_gen(
+    filename = "../tests/grammars/spans.peggy",
+    recursion_wrapper = "crate::Shared"
+)]
+mod custom {}
+
+/// Custom wrapper type, which only needs to be constructible and to dereference to the wrapped data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shared<T>(Arc<T>);
+
+impl<T> Shared<T> {
+    pub fn new(data: T) -> Self {
+        Self(Arc::new(data))
+    }
+}
+
+impl<T> Deref for Shared<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+const INPUT: &str = "a (b (c d)) e";
+
+fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+/// Count the words in an item
+fn count_arc_words(item: &arc::matched::item) -> usize {
+    match &item.matched {
+        arc::unions::Sw2::A(group) => group.matched.iter().map(|item| count_arc_words(item)).sum(),
+        arc::unions::Sw2::B(_) => 1,
+    }
+}
+
+/// Count the words in an item
+fn count_custom_words(item: &custom::matched::item) -> usize {
+    match &item.matched {
+        custom::unions::Sw2::A(group) => group
+            .matched
+            .iter()
+            .map(|item| count_custom_words(item))
+            .sum(),
+        custom::unions::Sw2::B(_) => 1,
+    }
+}
+
+#[test]
+fn arc_wrapped_data_is_send_and_sync() {
+    let data = arc::exec(INPUT).unwrap();
+    assert_send_sync(&data);
+
+    let shared = Arc::new(data);
+    let handle = {
+        let shared = Arc::clone(&shared);
+        thread::spawn(move || shared.matched.iter().map(count_arc_words).sum::<usize>())
+    };
+
+    assert_eq!(handle.join().unwrap(), 5);
+    assert_eq!(shared.matched.iter().map(count_arc_words).sum::<usize>(), 5);
+}
+
+#[test]
+fn custom_wrappers() {
+    let data = custom::exec(INPUT).unwrap();
+    assert_send_sync(&data);
+
+    assert_eq!(
+        data.matched.iter().map(count_custom_words).sum::<usize>(),
+        5
+    );
+    assert_eq!(data.clone(), data);
+}
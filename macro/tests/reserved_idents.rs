@@ -0,0 +1,89 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/reserved.peggy")]
+mod reserved {}
+
+#[peggy_gen(
+    filename = "../tests/grammars/reserved.peggy",
+    tree_printer = true,
+    sample_constructors = true,
+    derive_default = true,
+    preallocate_repetitions = true
+)]
+mod reserved_options {}
+
+#[peggy_gen(
+    filename = "../tests/grammars/renamed_reserved.peggy",
+    rename = "Some: Presence"
+)]
+mod renamed_reserved {}
+
+const INPUT: &str = concat!(
+    "execinput#",
+    "SuccessDataoffset12offset3",
+    "PegErrorlenusizeusize",
+    "PegErrorContentsourcebool",
+    "matchedresultString",
+    "rulesoutBoxRc",
+    "stringstriviaerr_0",
+    "unionsend_errSpan",
+    "patternscandidateTriviarule_namedata"
+);
+
+#[test]
+fn rules_named_like_generated_idents() {
+    let main: reserved::matched::main = reserved::exec(INPUT).unwrap();
+
+    let input: reserved::matched::input = main.matched.0.matched.1.unwrap();
+    let matched: char = input.matched.1.matched.matched;
+    assert_eq!(matched, '#');
+
+    let offsets: Vec<&str> = main
+        .matched
+        .1
+        .matched
+        .1
+        .iter()
+        .map(|offset| offset.matched.1.matched)
+        .collect();
+    assert_eq!(offsets, ["12", "3"]);
+
+    let count: usize = main.matched.2.matched.1.matched.1.matched;
+    assert_eq!(count, 2);
+
+    let flag: bool = main.matched.3.matched.1.matched.1.matched;
+    assert!(flag);
+
+    assert!(reserved::exec(&INPUT.replace("end_errSpan", "consumedp1")).is_ok());
+    assert!(reserved::exec(&INPUT.replace("Box", "Rc")).is_err());
+}
+
+#[test]
+fn rules_named_like_generated_idents_with_options() {
+    let data = reserved_options::exec(INPUT).unwrap();
+    assert!(data.print_tree(INPUT).starts_with("main @0.."));
+
+    // Samples of the rules shadowing primitive types hold the primitive types
+    let count: usize = reserved_options::matched::usize::sample().matched;
+    assert_eq!(count, 0);
+
+    let matched: char = reserved_options::matched::char::sample().matched.matched;
+    assert_eq!(matched, '\0');
+
+    let default = reserved_options::matched::bool::default();
+    assert!(!default.matched);
+}
+
+#[test]
+fn renamed_reserved_rules() {
+    let data = renamed_reserved::exec("some:1,22,333").unwrap();
+
+    let result: renamed_reserved::matched::Result = data.matched;
+    let offsets: Vec<_> = std::iter::once(&result.matched.2.matched.0)
+        .chain(result.matched.2.matched.1.iter().map(|(_, offset)| offset))
+        .map(|offset| offset.matched)
+        .collect();
+
+    assert_eq!(offsets, ["1", "22", "333"]);
+    assert!(renamed_reserved::exec("none:").is_err());
+}
@@ -0,0 +1,48 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(
+    filename = "../tests/grammars/samples.peggy",
+    sample_constructors = true
+)]
+mod samples {}
+
+use samples::matched;
+use samples::unions::Sw2;
+
+#[test]
+fn union_samples() {
+    // Both members build data of the same depth, so the first one is used, with an empty repetition
+    let value = matched::value::sample();
+
+    match &value.matched {
+        Sw2::A((_, digits)) => assert!(digits.is_empty()),
+        Sw2::B(_) => panic!("the second member was used"),
+    }
+
+    assert_eq!(value.span, samples::Span::default());
+
+    // Members which can't be built without the rule itself are skipped
+    let nested = matched::nested::sample();
+    assert!(matches!(nested.matched, Sw2::B(_)));
+}
+
+#[test]
+fn recursive_rule_samples() {
+    // The optional recursion is left empty
+    let (_, expr, _) = matched::expr::sample().matched;
+    assert!(expr.is_none());
+}
+
+#[test]
+fn repetition_samples() {
+    // '+' repetitions hold a single element, and builtin rules a placeholder character
+    let digits = matched::digits::sample();
+    assert_eq!(digits.matched.len(), 1);
+    assert_eq!(digits.matched[0].matched, '\0');
+
+    let (value, expr, nested, digits) = matched::main::sample().matched;
+    assert_eq!(value, matched::value::sample());
+    assert_eq!(expr, matched::expr::sample());
+    assert_eq!(nested, matched::nested::sample());
+    assert_eq!(digits, matched::digits::sample());
+}
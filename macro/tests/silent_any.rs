@@ -0,0 +1,11 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/silent_any.peggy")]
+mod silent_any {}
+
+#[test]
+fn silent_builtin_rules() {
+    for input in ["", "a", "héllo\n🎉"] {
+        assert_eq!(silent_any::exec(input).map_err(|err| err.offset), Ok(()));
+    }
+}
@@ -0,0 +1,33 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/silent_main.peggy")]
+mod silent_main {}
+
+#[peggy_gen(filename = "../tests/grammars/silent_rules_main.peggy")]
+mod silent_rules_main {}
+
+#[test]
+fn silent_main_rule() {
+    // The success data is `()`
+    assert_eq!(
+        silent_main::exec("ab cd ef").map_err(|err| err.offset),
+        Ok(())
+    );
+
+    silent_main::exec("").unwrap();
+
+    // The whole input must still be consumed
+    let err = silent_main::exec("ab cd  ef").unwrap_err();
+    assert_eq!(err.offset, 6);
+}
+
+#[test]
+fn main_rule_made_of_silent_rules() {
+    assert_eq!(
+        silent_rules_main::exec("abba").map_err(|err| err.offset),
+        Ok(())
+    );
+
+    let err = silent_rules_main::exec("abc").unwrap_err();
+    assert_eq!(err.offset, 2);
+}
@@ -0,0 +1,109 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/lines.peggy")]
+mod lines {}
+
+use lines::{PegErrorContent, SourceContext};
+
+/// Build a larger source where the provided input starts at column 37 (in characters) of line 12
+fn embed(input: &str) -> (String, SourceContext) {
+    let mut source = "// é\n".repeat(12);
+
+    // 37 characters, of which some take several bytes
+    source.push_str("let text = r#\"àéîõü");
+    source.push_str(&"-".repeat(37 - 19));
+
+    let context = SourceContext {
+        base_offset: source.len(),
+        base_line: 12,
+        base_col: 37,
+    };
+
+    source.push_str(input);
+    source.push_str("\"#;\n");
+
+    (source, context)
+}
+
+/// Get the line and column (in characters) of a byte offset
+fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+    (
+        before.matches('\n').count(),
+        before[line_start..].chars().count(),
+    )
+}
+
+#[test]
+fn errors_on_the_first_line() {
+    let input = "héllo wörld !";
+    let (source, context) = embed(input);
+
+    let err = lines::exec_in_context(input, context).unwrap_err();
+    let deepest = err.deepest();
+
+    assert_eq!(deepest.offset, 13);
+    assert_eq!(deepest.line_col(), (12, 37 + 11));
+    assert_eq!(
+        deepest.line_col(),
+        line_col(&source, deepest.original_offset())
+    );
+    assert_eq!(&source[deepest.original_offset()..][..2], " !");
+
+    // Without context, the coordinates are the input's ones
+    let err = lines::exec(input).unwrap_err();
+    assert_eq!(err.deepest().line_col(), (0, 11));
+    assert_eq!(err.deepest().original_offset(), 13);
+}
+
+#[test]
+fn errors_on_the_next_lines() {
+    let input = "héllo\nwörld 1\nsécond lïne !";
+    let (source, context) = embed(input);
+
+    let err = lines::exec_in_context(input, context).unwrap_err();
+    let deepest = err.deepest();
+
+    // Lines after the first one aren't shifted horizontally
+    assert_eq!(deepest.line_col(), (14, 11));
+    assert_eq!(
+        deepest.line_col(),
+        line_col(&source, deepest.original_offset())
+    );
+}
+
+#[test]
+fn union_errors_share_the_context() {
+    let input = "!";
+    let (_, context) = embed(input);
+
+    let err = lines::exec_in_context(input, context).unwrap_err();
+    assert_eq!(err.context, context);
+
+    let mut pending = vec![&err];
+    let mut nested = 0;
+
+    while let Some(err) = pending.pop() {
+        assert_eq!(err.context, context);
+
+        if let PegErrorContent::NoMatchInUnion(errors) = &err.content {
+            nested += errors.len();
+            pending.extend(errors);
+        }
+    }
+
+    assert!(nested > 0);
+}
+
+#[test]
+fn spans_in_the_larger_source() {
+    let input = "héllo wörld";
+    let (source, context) = embed(input);
+
+    let data = lines::exec_in_context(input, context).unwrap();
+    let span = context.span(data.matched.0.span);
+
+    assert_eq!(span.slice(&source), "héllo wörld");
+}
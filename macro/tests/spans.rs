@@ -0,0 +1,117 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/spans.peggy")]
+mod spans {}
+
+use spans::{char_index, matched, unions::Sw2, Span};
+use std::borrow::Borrow;
+
+const INPUT: &str = "ab (日本 🎉x) 語";
+
+/// Collect the span of each item, word and group, in matching order
+fn collect_spans<'a, I: Borrow<matched::item<'a>>>(
+    items: &[I],
+    out: &mut Vec<(&'static str, Span)>,
+) {
+    for item in items {
+        let item = item.borrow();
+        out.push(("item", item.span));
+
+        match &item.matched {
+            Sw2::A(group) => {
+                out.push(("group", group.span));
+                collect_spans(&group.matched, out);
+            }
+            Sw2::B(word) => out.push(("word", word.span)),
+        }
+    }
+}
+
+#[test]
+fn nested_spans_are_byte_ranges() {
+    let data = spans::exec(INPUT).unwrap();
+    assert_eq!(data.span, Span { start: 0, end: 21 });
+
+    let mut spans = vec![];
+    collect_spans(&data.matched, &mut spans);
+
+    let slices: Vec<_> = spans
+        .iter()
+        .map(|(kind, span)| (*kind, span.start, span.end, span.slice(INPUT)))
+        .collect();
+
+    assert_eq!(
+        slices,
+        vec![
+            ("item", 0, 3, "ab "),
+            ("word", 0, 2, "ab"),
+            ("item", 3, 18, "(日本 🎉x) "),
+            ("group", 3, 17, "(日本 🎉x)"),
+            ("item", 4, 11, "日本 "),
+            ("word", 4, 10, "日本"),
+            ("item", 11, 16, "🎉x"),
+            ("word", 11, 16, "🎉x"),
+            ("item", 18, 21, "語"),
+            ("word", 18, 21, "語"),
+        ]
+    );
+}
+
+#[test]
+fn char_index_counts_characters() {
+    let data = spans::exec(INPUT).unwrap();
+
+    let group = match &data.matched[1].matched {
+        Sw2::A(group) => group,
+        Sw2::B(_) => panic!("expected a group"),
+    };
+
+    assert_eq!(char_index(INPUT, group.span.start), 3);
+    assert_eq!(char_index(INPUT, group.span.end), 10);
+    assert_eq!(char_index(INPUT, group.matched[1].span.start), 7);
+    assert_eq!(char_index(INPUT, INPUT.len()), INPUT.chars().count());
+}
+
+#[test]
+fn char_index_clamps_invalid_offsets() {
+    // In the middle of '日' (bytes 4..7)
+    assert_eq!(char_index(INPUT, 5), 4);
+    // In the middle of '🎉' (bytes 11..15)
+    assert_eq!(char_index(INPUT, 14), 7);
+    // Past the end of the input
+    assert_eq!(char_index(INPUT, 1000), INPUT.chars().count());
+}
+
+#[test]
+fn error_offsets_are_byte_offsets() {
+    // The unclosed group is not matched, so the input is only consumed up to it
+    let input = "日本 (🎉";
+    let err = spans::exec(input).unwrap_err();
+    assert_eq!(err.offset, "日本 ".len());
+    assert_eq!(char_index(input, err.offset), 3);
+}
+
+#[test]
+fn rule_matchers_match_substrings() {
+    // Match the group in the middle of the input, without consuming what follows it
+    let offset = "ab ".len();
+    let (group, consumed, _) = spans::rules::group(INPUT, &INPUT[offset..], offset).unwrap();
+    assert_eq!(consumed, "(日本 🎉x)".len());
+    assert_eq!(group.span, Span { start: 3, end: 17 });
+    assert_eq!(group.matched.len(), 2);
+    assert_eq!(group.matched[1].span.slice(INPUT), "🎉x");
+
+    // Matchers only match the beginning of their input
+    let err = spans::rules::group(INPUT, &INPUT[offset + 1..], offset + 1).unwrap_err();
+    assert_eq!(err.offset, offset + 1);
+}
+
+#[test]
+fn grammar_hash_matches_the_grammar() {
+    let grammar = include_str!("grammars/spans.peggy");
+    assert!(peggy::verify_generated(grammar, spans::GRAMMAR_HASH));
+    assert!(!peggy::verify_generated(
+        &grammar.replace("item+", "item*"),
+        spans::GRAMMAR_HASH
+    ));
+}
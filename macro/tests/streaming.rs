@@ -0,0 +1,89 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/trivia.peggy")]
+mod collected {}
+
+#[peggy_gen(filename = "../tests/grammars/trivia.peggy", streaming = true)]
+mod streamed {}
+
+#[peggy_gen(
+    filename = "../tests/grammars/trivia.peggy",
+    streaming = true,
+    collect_trivia = true
+)]
+mod streamed_trivia {}
+
+#[peggy_gen(
+    filename = "../tests/grammars/trivia.peggy",
+    streaming = true,
+    partial_match = true
+)]
+mod streamed_prefix {}
+
+/// Get the elements streamed while matching the input (formatted for comparison), and the number of consumed bytes
+fn stream(input: &str) -> (Vec<String>, usize) {
+    let mut tokens = vec![];
+
+    let consumed =
+        streamed::exec_streaming(input, |token| tokens.push(format!("{:?}", token))).unwrap();
+
+    (tokens, consumed)
+}
+
+#[test]
+fn streamed_elements_match_collected_ones() {
+    let input = "f () a#x1  b";
+
+    let (tokens, consumed) = stream(input);
+    assert_eq!(consumed, input.len());
+
+    let success = collected::exec(input).unwrap();
+    let expected: Vec<_> = success
+        .matched
+        .iter()
+        .map(|stmt| format!("{:?}", stmt))
+        .collect();
+
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(tokens, expected);
+}
+
+#[test]
+fn streaming_errors() {
+    // The main rule is a '+' repetition
+    let err = streamed::exec_streaming("", |_| panic!("no element can be matched")).unwrap_err();
+    assert_eq!(err.deepest().offset, 0);
+
+    // Elements are streamed as they are matched, even if the input isn't fully consumed
+    let mut count = 0;
+    let err = streamed::exec_streaming("a b (", |_| count += 1).unwrap_err();
+    assert_eq!(count, 2);
+    assert_eq!(err.deepest().offset, 4);
+
+    let collected_err = collected::exec("a b (").unwrap_err();
+    assert_eq!(err.deepest().offset, collected_err.deepest().offset);
+
+    let mut count = 0;
+    let consumed = streamed_prefix::exec_streaming("a b (", |_| count += 1).unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(consumed, 3);
+}
+
+#[test]
+fn streaming_collects_trivia() {
+    let mut words = vec![];
+
+    let (consumed, trivia) =
+        streamed_trivia::exec_streaming("f () a#x1  b", |token| words.push(token.span.start))
+            .unwrap();
+
+    assert_eq!(consumed, 12);
+    assert_eq!(words, [0, 4, 9]);
+
+    let trivia: Vec<_> = trivia
+        .iter()
+        .map(|trivia| (trivia.span.start, trivia.span.end, trivia.kind))
+        .collect();
+
+    assert_eq!(trivia, [(1, 2, "call"), (4, 5, "stmt"), (6, 11, "stmt")]);
+}
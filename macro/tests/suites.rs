@@ -0,0 +1,44 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/suites.peggy")]
+mod suites {}
+
+use suites::matched;
+
+/// Get the slices matched by a list of letters
+fn letters<'a>(letters: &[matched::letter<'a>]) -> Vec<&'a str> {
+    letters.iter().map(|letter| letter.matched).collect()
+}
+
+#[test]
+fn groups_with_one_captured_member_are_not_tuples() {
+    let data = suites::exec("a-b-;;[a1,b];c-d-").unwrap();
+    let (one, _, _, silent_group) = &data.matched;
+
+    let one: &Vec<matched::letter> = &one.matched;
+    assert_eq!(letters(one), ["a", "b"]);
+
+    // Groups made of silent patterns are not captured either
+    let silent_group: &Vec<matched::letter> = &silent_group.matched;
+    assert_eq!(letters(silent_group), ["c", "d"]);
+}
+
+#[test]
+fn groups_with_multiple_captured_members_are_tuples() {
+    let data = suites::exec("a-;b-1c-2;[a1,b][c2,d];").unwrap();
+    let (_, two, three, _) = &data.matched;
+
+    let two: &Vec<(matched::letter, matched::digit)> = &two.matched;
+    let two: Vec<_> = two
+        .iter()
+        .map(|(letter, digit)| (letter.matched, digit.matched))
+        .collect();
+    assert_eq!(two, [("b", "1"), ("c", "2")]);
+
+    let three: &Vec<(matched::letter, matched::digit, matched::letter)> = &three.matched;
+    let three: Vec<_> = three
+        .iter()
+        .map(|(a, digit, b)| (a.matched, digit.matched, b.matched))
+        .collect();
+    assert_eq!(three, [("a", "1", "b"), ("c", "2", "d")]);
+}
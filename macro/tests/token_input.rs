@@ -0,0 +1,78 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/tokens.peggy", token_input = true)]
+mod tokens {}
+
+use tokens::unions::Sw2;
+use tokens::{encode_tokens, LexedToken};
+
+struct Token(&'static str);
+
+impl LexedToken for Token {
+    fn kind(&self) -> &str {
+        self.0
+    }
+}
+
+/// Get tokens from their kinds, separated by spaces
+fn lex(kinds: &'static str) -> Vec<Token> {
+    kinds.split(' ').map(Token).collect()
+}
+
+#[test]
+fn token_ranges() {
+    let tokens = lex("let ident = number ; let ident = ident ;");
+    let input = encode_tokens(&tokens);
+
+    let success = tokens::exec(input.text()).unwrap();
+    assert_eq!(input.token_range(success.span), 0..10);
+
+    let ranges: Vec<_> = success
+        .matched
+        .iter()
+        .map(|stmt| input.token_range(stmt.span))
+        .collect();
+
+    assert_eq!(ranges, [0..5, 5..10]);
+
+    let (name, _) = &success.matched[1].matched;
+    assert_eq!(input.token_range(name.span), 6..7);
+}
+
+#[test]
+fn unknown_tokens() {
+    let tokens = lex("let ident = ? ;");
+    let input = encode_tokens(&tokens);
+
+    assert_eq!(
+        input.text().chars().nth(3),
+        Some(tokens::UNKNOWN_TOKEN_MARKER)
+    );
+
+    // Unknown tokens are only matched by `B_ANY`, which captures the character encoding them
+    let success = tokens::exec(input.text()).unwrap();
+    let (_, value) = &success.matched[0].matched;
+
+    let any = match &value.matched {
+        Sw2::B(any) => any,
+        Sw2::A(_) => panic!("unknown token matched as a number"),
+    };
+
+    assert_eq!(any.matched, tokens::UNKNOWN_TOKEN_MARKER);
+    assert_eq!(input.token_range(any.span), 3..4);
+    assert_eq!(tokens[input.token_index(any.span.start)].kind(), "?");
+
+    // Known tokens are encoded by other characters, even when matched by `B_ANY`
+    let input = encode_tokens(&lex("let ident = ; ;"));
+    let success = tokens::exec(input.text()).unwrap();
+
+    match &success.matched[0].matched.1.matched {
+        Sw2::B(any) => assert_ne!(any.matched, tokens::UNKNOWN_TOKEN_MARKER),
+        Sw2::A(_) => panic!("';' token matched as a number"),
+    }
+
+    // Unknown tokens fail to match like any unexpected token
+    let input = encode_tokens(&lex("let ? = number ;"));
+    let err = tokens::exec(input.text()).unwrap_err();
+    assert_eq!(input.token_index(err.deepest().offset), 1);
+}
@@ -0,0 +1,101 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/spans.peggy", tree_printer = true)]
+mod spans {}
+
+#[peggy_gen(filename = "../tests/grammars/units.peggy")]
+mod units {}
+
+use spans::unions::Sw2;
+
+const INPUT: &str = "ab (日本 x) c";
+
+#[test]
+fn print_tree_snapshot() {
+    let data = spans::exec(INPUT).unwrap();
+
+    assert_eq!(
+        data.print_tree(INPUT),
+        r#"main @0..15 "ab (日本 x) c"
+  item @0..3 "ab "
+    word @0..2 "ab"
+  item @3..14 "(日本 x) "
+    group @3..13 "(日本 x)"
+      item @4..11 "日本 "
+        word @4..10 "日本"
+      item @11..12 "x"
+        word @11..12 "x"
+  item @14..15 "c"
+    word @14..15 "c"
+"#
+    );
+}
+
+#[test]
+fn print_subtree() {
+    let data = spans::exec(INPUT).unwrap();
+
+    let group = match &data.matched[1].matched {
+        Sw2::A(group) => group,
+        Sw2::B(_) => panic!("expected a group"),
+    };
+
+    assert_eq!(
+        group.print_tree(INPUT),
+        r#"group @3..13 "(日本 x)"
+  item @4..11 "日本 "
+    word @4..10 "日本"
+  item @11..12 "x"
+    word @11..12 "x"
+"#
+    );
+}
+
+#[test]
+fn dump_snapshot() {
+    let data = spans::exec(INPUT).unwrap();
+
+    assert_eq!(
+        spans::dump(&data),
+        r#"main @0..15
+  item @0..3
+    word "ab" @0..2
+  item @3..14
+    group @3..13
+      item @4..11
+        word "日本" @4..10
+      item @11..12
+        word "x" @11..12
+  item @14..15
+    word "c" @14..15
+"#
+    );
+}
+
+#[test]
+fn dump_strings_and_builtins() {
+    let data = units::exec("-12km").unwrap();
+
+    assert_eq!(
+        units::dump(&data),
+        r#"main @0..5
+  sign "-" @0..1
+  digit @1..2
+    B_ASCII_DIGIT '1' @1..2
+  digit @2..3
+    B_ASCII_DIGIT '2' @2..3
+  unit "km" @3..5
+"#
+    );
+
+    // Optional patterns which didn't match don't appear in the tree
+    let data = units::exec("7").unwrap();
+
+    assert_eq!(
+        units::dump(&data),
+        r#"main @0..1
+  digit @0..1
+    B_ASCII_DIGIT '7' @0..1
+"#
+    );
+}
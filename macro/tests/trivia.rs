@@ -0,0 +1,38 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/trivia.peggy", collect_trivia = true)]
+mod trivia {}
+
+/// Get the span and kind of each trivia collected while matching the input
+fn collected(input: &str) -> Vec<(usize, usize, &'static str)> {
+    let (_, trivia) = trivia::exec(input).unwrap();
+
+    trivia
+        .iter()
+        .map(|trivia| (trivia.span.start, trivia.span.end, trivia.kind))
+        .collect()
+}
+
+#[test]
+fn trivia_spans_and_kinds() {
+    assert_eq!(
+        collected("f () a#x1  b"),
+        [(1, 2, "call"), (4, 5, "stmt"), (6, 11, "stmt")]
+    );
+
+    assert_eq!(collected("a"), []);
+}
+
+#[test]
+fn trivia_of_failed_members_are_discarded() {
+    // The space after `a` is first matched by the `call` member, which then fails on `b`
+    assert_eq!(
+        collected("f () a b#x"),
+        [
+            (1, 2, "call"),
+            (4, 5, "stmt"),
+            (6, 7, "stmt"),
+            (8, 10, "stmt")
+        ]
+    );
+}
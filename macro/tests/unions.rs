@@ -0,0 +1,19 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/unions.peggy")]
+mod unions {}
+
+use unions::matched;
+use unions::unions::Sw3;
+
+#[test]
+fn silent_members_hold_unit_variants() {
+    let data = unions::exec("+,-;<>").unwrap();
+    // Unions made only of non-capturing members don't capture anything (`sign*` is not part of the data)
+    let ops: &Vec<matched::op> = &data.matched;
+
+    let ops: Vec<_> = ops.iter().map(|op| &op.matched).collect();
+    assert!(matches!(ops[0], Sw3::A(_)));
+    assert_eq!(*ops[1], Sw3::B(()));
+    assert!(matches!(ops[2], Sw3::C(_)));
+}
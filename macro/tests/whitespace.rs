@@ -0,0 +1,44 @@
+use peggy_macro::peggy_gen;
+
+#[peggy_gen(filename = "../tests/grammars/whitespace.peggy")]
+mod whitespace {}
+
+fn statements(input: &str) -> usize {
+    whitespace::exec(input)
+        .unwrap_or_else(|err| panic!("failed to match {:?}: {:?}", input, err))
+        .matched
+        .len()
+}
+
+#[test]
+fn whitespace_between_repetitions() {
+    assert_eq!(statements("let a = b;let c = d;"), 2);
+    assert_eq!(statements("let a = b; let c = d;"), 2);
+    assert_eq!(statements("let a=b;\n\n\tlet c =d;"), 2);
+}
+
+#[test]
+fn line_comments_between_statements() {
+    assert_eq!(statements("let a = b;// c\nlet c = d;"), 2);
+    assert_eq!(
+        statements("// first\nlet a = b; // second\n// third\nlet c = d;"),
+        2
+    );
+    assert_eq!(statements("let a = // value\n b;"), 1);
+}
+
+#[test]
+fn whitespace_around_main() {
+    assert_eq!(statements(" let a = b;"), 1);
+    assert_eq!(statements("let a = b; "), 1);
+    assert_eq!(statements("\n  let a = b;\n  let c = d;\n"), 2);
+    assert_eq!(statements("// only a comment\n"), 0);
+    assert_eq!(statements("  "), 0);
+    assert_eq!(statements(""), 0);
+}
+
+#[test]
+fn no_whitespace_inside_opted_out_rules() {
+    assert!(whitespace::exec("let a b = c;").is_err());
+    assert!(whitespace::exec("let ab = c;").is_ok());
+}
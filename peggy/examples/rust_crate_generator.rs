@@ -0,0 +1,29 @@
+use io::Read;
+use peggy::grammar::{parse_peg, pretty_format_parser_err};
+use peggy::rustgen::{gen_rust_crate, CrateOptions};
+use std::{env, io};
+
+fn main() {
+    // Get the output directory and the crate's name
+    let mut args = env::args().skip(1);
+    let dir = args
+        .next()
+        .expect("Please provide the output directory as the first argument");
+    let name = args
+        .next()
+        .unwrap_or_else(|| "generated_parser".to_string());
+
+    // Read the input grammar
+    let mut buffer = String::new();
+    io::stdin()
+        .read_to_string(&mut buffer)
+        .expect("Failed to read from STDIN");
+
+    // Parse the grammar
+    let pst = parse_peg(&buffer).unwrap_or_else(|err| {
+        eprintln!("{}", pretty_format_parser_err(&buffer, err));
+        panic!("Failed to parse grammar");
+    });
+
+    gen_rust_crate(&pst, &dir, &CrateOptions::new(name)).expect("Failed to write the crate");
+}
@@ -0,0 +1,395 @@
+use super::data::*;
+use super::graph::GrammarGraph;
+use super::recursive_rules;
+use super::utils::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+/// Compute the minimum number of bytes a rule must consume to match (`0` if it can match an empty input)
+///
+/// Returns `usize::MAX` if the rule can never match, e.g. because of an infinite recursion like `a = "(" a ")"`.
+/// External rules are considered to potentially match an empty input.
+///
+/// Panics if the rule doesn't exist in the provided syntax tree.
+pub fn min_length(pst: &PegSyntaxTree, rule: &str) -> usize {
+    rules_min_length(pst.rules())[rule]
+}
+
+/// Compute the minimum number of bytes each rule must consume to match (see [`min_length`])
+pub fn rules_min_length<'a>(rules: &Rules<'a>) -> HashMap<&'a str, usize> {
+    // Lengths can only decrease between two iterations, so this always converges
+    let mut lengths: HashMap<&'a str, usize> =
+        rules.keys().map(|name| (*name, usize::MAX)).collect();
+
+    loop {
+        let mut changed = false;
+
+        for (name, rule) in rules {
+            let length = pattern_min_length(&lengths, rule.pattern());
+
+            if length < lengths[name] {
+                lengths.insert(name, length);
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return lengths;
+        }
+    }
+}
+
+/// Find groups of rules with structurally identical bodies (see [`Pattern::structurally_eq`]), which may be merged
+///
+/// Each group contains at least two rules, in declaration order. Groups are ordered by the declaration of their first rule.
+pub fn find_duplicate_rules<'a>(pst: &PegSyntaxTree<'a>) -> Vec<Vec<&'a str>> {
+    let mut groups: Vec<Vec<&Rule<'a>>> = vec![];
+
+    for rule in pst.ordered_rules() {
+        match groups
+            .iter_mut()
+            .find(|group| group[0].pattern().structurally_eq(rule.pattern()))
+        {
+            Some(group) => group.push(rule),
+            None => groups.push(vec![rule]),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|group| group.len() > 1)
+        .map(|group| group.iter().map(|rule| rule.name()).collect())
+        .collect()
+}
+
+/// List the constant strings of a grammar, each with the rule it appears in and its location, in declaration order
+/// (e.g. to build keywords tables or syntax highlighting rules)
+///
+/// With `dedup`, only the first occurrence of each string in each rule is listed.
+pub fn constant_strings<'a>(
+    pst: &PegSyntaxTree<'a>,
+    dedup: bool,
+) -> Vec<(&'a str, &'a str, ParserLoc)> {
+    let mut strings = vec![];
+    let mut seen = HashSet::new();
+
+    pst.walk_rules(&mut |rule, pattern| {
+        if let RulePatternValue::CstString(string) = pattern.value() {
+            if !dedup || seen.insert((rule, *string)) {
+                strings.push((rule, *string, pattern.loc()));
+            }
+        }
+    });
+
+    strings
+}
+
+/// Find all the rules which reference a rule, directly or through other rules (e.g. to know which rules are impacted by a change)
+///
+/// The rule itself is only included if it is recursive. Builtin and external rules are not part of the
+/// references graph, so they have no dependents.
+pub fn dependents<'a>(pst: &PegSyntaxTree<'a>, rule: &str) -> HashSet<&'a str> {
+    // Reversed references graph: for each rule, the rules directly referencing it
+    let mut referenced_by: HashMap<&'a str, HashSet<&'a str>> = HashMap::new();
+
+    pst.walk_rules(&mut |name, pattern| {
+        if let RulePatternValue::Rule(referenced) = pattern.value() {
+            if !is_builtin_rule_name(referenced) && !is_external_rule_name(referenced) {
+                referenced_by.entry(referenced).or_default().insert(name);
+            }
+        }
+    });
+
+    let mut dependents = HashSet::new();
+    let mut queue = vec![rule];
+
+    while let Some(current) = queue.pop() {
+        for name in referenced_by.get(current).into_iter().flatten() {
+            if dependents.insert(*name) {
+                queue.push(name);
+            }
+        }
+    }
+
+    dependents
+}
+
+/// Statistics about a grammar, e.g. to report after generating a parser (see [`grammar_stats`])
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GrammarStats {
+    /// Number of rules
+    pub rules: usize,
+
+    /// Number of rules which never capture any data
+    pub silent_rules: usize,
+
+    /// Distinct constant strings
+    pub terminals: BTreeSet<String>,
+
+    /// Builtin rules referenced by the grammar
+    pub builtins: BTreeSet<String>,
+
+    /// Maximum nesting depth of a rule's pattern (a lone string or rule reference has a depth of 1)
+    pub max_nesting_depth: usize,
+
+    /// Number of unions
+    pub unions: usize,
+
+    /// Number of alternatives of the largest union
+    pub max_union_arity: usize,
+
+    /// Recursion cycles reachable from the entrypoint, each starting with its lexicographically smallest rule name
+    pub recursive_cycles: Vec<Vec<String>>,
+}
+
+impl fmt::Display for GrammarStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Rules               {}", self.rules)?;
+        writeln!(f, "Silent rules        {}", self.silent_rules)?;
+        writeln!(f, "Terminals           {}", self.terminals.len())?;
+        writeln!(f, "Builtins used       {}", self.builtins.len())?;
+
+        for builtin in &self.builtins {
+            writeln!(f, "  {}", builtin)?;
+        }
+
+        writeln!(f, "Max nesting depth   {}", self.max_nesting_depth)?;
+        writeln!(f, "Unions              {}", self.unions)?;
+        writeln!(f, "Max union arity     {}", self.max_union_arity)?;
+        write!(f, "Recursive cycles    {}", self.recursive_cycles.len())?;
+
+        for cycle in &self.recursive_cycles {
+            write!(f, "\n  {} -> {}", cycle.join(" -> "), cycle[0])?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compute statistics about a grammar
+pub fn grammar_stats(pst: &PegSyntaxTree) -> GrammarStats {
+    let mut stats = GrammarStats {
+        rules: pst.rules().len(),
+        silent_rules: GrammarGraph::new(pst)
+            .non_capturing_rules()
+            .values()
+            .filter(|mode| **mode == PatternMode::Silent)
+            .count(),
+        terminals: BTreeSet::new(),
+        builtins: BTreeSet::new(),
+        max_nesting_depth: 0,
+        unions: 0,
+        max_union_arity: 0,
+        recursive_cycles: recursive_rules::find_cycles(pst)
+            .into_iter()
+            .map(|cycle| cycle.into_iter().map(str::to_string).collect())
+            .collect(),
+    };
+
+    for rule in pst.rules().values() {
+        let depth = collect_pattern_stats(&mut stats, rule.pattern());
+        stats.max_nesting_depth = stats.max_nesting_depth.max(depth);
+    }
+
+    stats
+}
+
+/// Collect a pattern's statistics and return its nesting depth
+fn collect_pattern_stats(stats: &mut GrammarStats, pattern: &Pattern) -> usize {
+    match pattern.value() {
+        RulePatternValue::CstString(string) => {
+            stats.terminals.insert(string.to_string());
+        }
+        RulePatternValue::Rule(name) => {
+            if is_builtin_rule_name(name) {
+                stats.builtins.insert(name.to_string());
+            }
+        }
+        RulePatternValue::Union(patterns) => {
+            stats.unions += 1;
+            stats.max_union_arity = stats.max_union_arity.max(patterns.len());
+        }
+        RulePatternValue::Group(_)
+        | RulePatternValue::Until(_)
+        | RulePatternValue::Suite(_)
+        | RulePatternValue::Cut => {}
+    }
+
+    1 + pattern
+        .children()
+        .map(|child| collect_pattern_stats(stats, child))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Compute the minimum number of bytes a pattern must consume to match, given the current rules' minimum lengths
+fn pattern_min_length(lengths: &HashMap<&str, usize>, pattern: &Pattern) -> usize {
+    if matches!(
+        pattern.mode(),
+        Some(PatternMode::Peek) | Some(PatternMode::Negative)
+    ) {
+        return 0;
+    }
+
+    match pattern.repetition() {
+        Some(PatternRepetition::Any) | Some(PatternRepetition::Optional) => 0,
+        Some(PatternRepetition::OneOrMore) | None => value_min_length(lengths, pattern.value()),
+    }
+}
+
+/// Compute the minimum number of bytes each repetition of a pattern must consume to match, ignoring its repetition
+/// (e.g. `1` for `B_ANY*`), given the rules' minimum lengths (see [`rules_min_length`])
+#[cfg(feature = "rustgen")]
+pub(crate) fn repetition_min_length(lengths: &HashMap<&str, usize>, pattern: &Pattern) -> usize {
+    if matches!(
+        pattern.mode(),
+        Some(PatternMode::Peek) | Some(PatternMode::Negative)
+    ) {
+        return 0;
+    }
+
+    value_min_length(lengths, pattern.value())
+}
+
+/// Compute the minimum number of bytes a pattern's value must consume to match, given the current rules' minimum lengths
+fn value_min_length(lengths: &HashMap<&str, usize>, value: &RulePatternValue) -> usize {
+    match value {
+        RulePatternValue::CstString(string) => string.len(),
+
+        // Builtin rules match a single character, except zero-width ones
+        RulePatternValue::Rule(name) if is_zero_width_builtin_rule_name(name) => 0,
+        RulePatternValue::Rule(name) if is_valid_builtin_rule_name(name) => 1,
+        RulePatternValue::Rule(name) if is_external_rule_name(name) => 0,
+        // Undeclared rules are only found in syntax trees which weren't validated yet
+        RulePatternValue::Rule(name) => lengths.get(name).copied().unwrap_or(0),
+
+        RulePatternValue::Group(pattern) => pattern_min_length(lengths, pattern),
+
+        RulePatternValue::Suite(patterns) => patterns
+            .iter()
+            .map(|pattern| pattern_min_length(lengths, pattern))
+            .fold(0, usize::saturating_add),
+
+        RulePatternValue::Union(patterns) => patterns
+            .iter()
+            .map(|pattern| pattern_min_length(lengths, pattern))
+            .min()
+            .unwrap_or(0),
+
+        // The terminator may be found right away
+        RulePatternValue::Until(_) => 0,
+
+        RulePatternValue::Cut => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parser::{parse_peg, parse_peg_nocheck};
+    use super::*;
+
+    fn min_len(grammar: &str, rule: &str) -> usize {
+        min_length(&parse_peg(grammar).unwrap(), rule)
+    }
+
+    #[test]
+    fn min_length_of_terminals() {
+        assert_eq!(min_len(r#"main = "ab" "c""#, "main"), 3);
+        assert_eq!(min_len(r#"main = "é""#, "main"), 2);
+        assert_eq!(min_len("main = B_ANY B_ASCII_DIGIT", "main"), 2);
+        assert_eq!(min_len("main = B_LINE_START E_EXTERNAL", "main"), 0);
+        assert_eq!(min_len(r#"main = ~"abc" !"d" "e""#, "main"), 1);
+    }
+
+    #[test]
+    fn min_length_of_optional_patterns() {
+        assert_eq!(min_len(r#"main = "a"? "bc"* "d""#, "main"), 1);
+        assert_eq!(min_len(r#"main = ("a" "b")+"#, "main"), 2);
+        assert_eq!(min_len(r#"main = ("abc" | "d")? "e""#, "main"), 1);
+        assert_eq!(min_len(r#"main = "abc" | "d" | "ef""#, "main"), 1);
+    }
+
+    #[test]
+    fn min_length_of_recursive_rules() {
+        let grammar = r#"
+expr = "(" expr ")" | number
+number = B_ASCII_DIGIT+
+list = "[" (expr ("," list)?)? "]"
+main = expr list
+"#;
+
+        assert_eq!(min_len(grammar, "expr"), 1);
+        assert_eq!(min_len(grammar, "list"), 2);
+        assert_eq!(min_len(grammar, "main"), 3);
+    }
+
+    #[test]
+    fn min_length_of_infinite_recursion() {
+        let pst = parse_peg_nocheck(
+            r#"a = "(" a ")"
+b = a | "x"
+main = a "y""#,
+        )
+        .unwrap();
+
+        assert_eq!(min_length(&pst, "a"), usize::MAX);
+        assert_eq!(min_length(&pst, "b"), 1);
+        assert_eq!(min_length(&pst, "main"), usize::MAX);
+    }
+
+    #[test]
+    fn stats_of_grammar() {
+        let stats = grammar_stats(
+            &parse_peg(
+                r#"
+expr = "(" ws? expr (("+" | "-" | "*") expr)? ws? ")" | number
+number = @(B_ASCII_DIGIT+)
+ws = °B_WHITESPACE+
+main = expr+ "\n"?
+"#,
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(stats.rules, 4);
+        assert_eq!(stats.silent_rules, 1);
+        assert_eq!(
+            stats
+                .terminals
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>(),
+            ["(", ")", "*", "+", "-", "\\n"]
+        );
+        assert_eq!(
+            stats
+                .builtins
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>(),
+            ["B_ASCII_DIGIT", "B_WHITESPACE"]
+        );
+        // Union > suite > group > suite > group > union > string, in `expr`
+        assert_eq!(stats.max_nesting_depth, 7);
+        assert_eq!(stats.unions, 2);
+        assert_eq!(stats.max_union_arity, 3);
+        assert_eq!(stats.recursive_cycles, [vec!["expr".to_string()]]);
+
+        assert_eq!(
+            stats.to_string(),
+            "\
+Rules               4
+Silent rules        1
+Terminals           6
+Builtins used       2
+  B_ASCII_DIGIT
+  B_WHITESPACE
+Max nesting depth   7
+Unions              2
+Max union arity     3
+Recursive cycles    1
+  expr -> expr"
+        );
+    }
+}
@@ -1,4 +1,7 @@
+use crate::grammar::parser::ReparseInfo;
+use crate::grammar::utils::{is_builtin_rule_name, is_external_rule_name};
 use std::collections::HashMap;
+use std::fmt;
 use std::rc::Rc;
 
 /// A grammar's entrypoint rule
@@ -8,6 +11,11 @@ pub const GRAMMAR_ENTRYPOINT_RULE: &str = "main";
 #[derive(Debug)]
 pub struct PegSyntaxTree<'a> {
     pub(super) rules: Rules<'a>,
+    pub(super) source: &'a str,
+    pub(super) version_req: Option<(&'a str, ParserLoc)>,
+    pub(super) token_rules: Vec<&'a str>,
+    pub(super) keywords: Vec<&'a str>,
+    pub(super) reparse_info: ReparseInfo<'a>,
 }
 
 impl<'a> PegSyntaxTree<'a> {
@@ -16,10 +24,142 @@ impl<'a> PegSyntaxTree<'a> {
         &self.rules
     }
 
+    /// Get the rules of the syntax tree, in the order they were declared in
+    ///
+    /// Unlike [`PegSyntaxTree::rules`], the iteration order is deterministic.
+    pub fn ordered_rules(&self) -> Vec<&Rule<'a>> {
+        let mut rules: Vec<_> = self.rules.values().collect();
+        rules.sort_by_key(|rule| rule.decl_loc());
+        rules
+    }
+
+    /// Get the source grammar the syntax tree was compiled from
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Get the requirement on Peggy's version declared by the grammar with the `@peggy` directive, if any
+    pub fn version_req(&self) -> Option<&'a str> {
+        self.version_req.map(|(req, _)| req)
+    }
+
+    /// Get the rules declared as tokens with the `@token` directive, in declaration order
+    pub fn token_rules(&self) -> &[&'a str] {
+        &self.token_rules
+    }
+
+    /// Get the keywords declared with the `@keywords` directive, in declaration order
+    ///
+    /// Constant strings equal to a keyword only match if they aren't directly followed by a word character
+    /// (an alphanumeric character or an underscore, like for `B_WORD_BOUNDARY`).
+    pub fn keywords(&self) -> &[&'a str] {
+        &self.keywords
+    }
+
     /// Get the rule's main rule
     pub fn main_rule(&self) -> &Rule<'a> {
         &self.rules[GRAMMAR_ENTRYPOINT_RULE]
     }
+
+    /// Visit the patterns of all rules in declaration order, along with the name of the rule they are declared in
+    /// (see [`Pattern::walk`])
+    pub fn walk_rules<'p>(&'p self, f: &mut impl FnMut(&'a str, &'p Pattern<'a>)) {
+        for rule in self.ordered_rules() {
+            rule.pattern().walk(&mut |pattern| f(rule.name(), pattern));
+        }
+    }
+
+    /// Create a copy of the syntax tree, with the mode of all patterns set by the provided function
+    /// (see [`Pattern::map_modes`])
+    pub fn map_modes(&self, f: &impl Fn(&Pattern<'a>) -> Option<PatternMode>) -> Self {
+        Self {
+            rules: self
+                .rules
+                .iter()
+                .map(|(name, rule)| {
+                    (
+                        *name,
+                        Rule {
+                            name: rule.name,
+                            pattern: rule.pattern.map_modes(f),
+                            decl_loc: rule.decl_loc,
+                        },
+                    )
+                })
+                .collect(),
+            source: self.source,
+            version_req: self.version_req,
+            token_rules: self.token_rules.clone(),
+            keywords: self.keywords.clone(),
+            reparse_info: self.reparse_info.clone(),
+        }
+    }
+
+    /// Create a copy of the syntax tree where unions with a single member are replaced by a group of that member
+    /// (see [`Pattern::simplify_unions`]), or return `None` if there is no such union
+    pub fn simplify_unions(&self) -> Option<Self> {
+        let mut found = false;
+
+        self.walk_rules(&mut |_, pattern| {
+            found |= matches!(
+                pattern.value(),
+                RulePatternValue::Union(patterns) if patterns.len() == 1
+            );
+        });
+
+        if !found {
+            return None;
+        }
+
+        Some(Self {
+            rules: self
+                .rules
+                .iter()
+                .map(|(name, rule)| {
+                    (
+                        *name,
+                        Rule {
+                            name: rule.name,
+                            pattern: rule.pattern.simplify_unions(),
+                            decl_loc: rule.decl_loc,
+                        },
+                    )
+                })
+                .collect(),
+            source: self.source,
+            version_req: self.version_req,
+            token_rules: self.token_rules.clone(),
+            keywords: self.keywords.clone(),
+            reparse_info: self.reparse_info.clone(),
+        })
+    }
+
+    /// Map the locations of the rules, of their patterns and of the `@peggy` directive to the file the grammar is embedded in
+    /// (see [`SourceOrigin`])
+    pub(super) fn relocate(&mut self, origin: &SourceOrigin) {
+        for rule in self.rules.values_mut() {
+            rule.decl_loc = origin.map(rule.decl_loc);
+            rule.pattern = rule.pattern.relocated(origin);
+        }
+
+        if let Some((_, loc)) = &mut self.version_req {
+            *loc = origin.map(*loc);
+        }
+    }
+}
+
+#[cfg(test)]
+impl<'a> PegSyntaxTree<'a> {
+    /// Replace the value of a rule's pattern, to build trees the parser can't produce
+    pub(crate) fn set_rule_value(&mut self, rule: &str, value: RulePatternValue<'a>) {
+        self.rules.get_mut(rule).unwrap().pattern.value = value;
+    }
+
+    /// Remove a rule, to build trees the parser can't produce
+    #[cfg(feature = "rustgen")]
+    pub(crate) fn remove_rule(&mut self, rule: &str) {
+        self.rules.remove(rule).unwrap();
+    }
 }
 
 /// Rules from a [`PegSyntaxTree`]
@@ -40,7 +180,7 @@ pub struct Rule<'a> {
 
 impl<'a> Rule<'a> {
     pub fn name(&self) -> &'a str {
-        &self.name
+        self.name
     }
 
     pub fn pattern(&self) -> &Pattern<'a> {
@@ -50,10 +190,30 @@ impl<'a> Rule<'a> {
     pub fn decl_loc(&self) -> ParserLoc {
         self.decl_loc
     }
+
+    /// Get the span of the rule's declaration, from its name to the end of its content
+    pub fn span(&self) -> ParserSpan {
+        ParserSpan::new(self.decl_loc, pattern_end(&self.pattern))
+    }
+}
+
+/// Get the location right after a pattern's declaration
+///
+/// Unions spread across multiple lines end after their last member, which their span doesn't cover.
+fn pattern_end(pattern: &Pattern) -> ParserLoc {
+    let end = pattern.span().end();
+
+    match pattern.value() {
+        RulePatternValue::Union(patterns) => match patterns.last() {
+            Some(last) => end.max(pattern_end(last)),
+            None => end,
+        },
+        _ => end,
+    }
 }
 
 /// A rule's pattern, parsed by the [`parse_rule_pattern`] function
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Pattern<'a> {
     /// Pattern's beginning, relative to its parent
     pub(super) loc: ParserLoc,
@@ -82,6 +242,11 @@ impl<'a> Pattern<'a> {
         self.decl_length
     }
 
+    /// Get the span of the pattern's declaration in the input grammar
+    pub fn span(&self) -> ParserSpan {
+        ParserSpan::from_length(self.loc, self.decl_length)
+    }
+
     /// Get the pattern's repetition model
     pub fn repetition(&self) -> Option<PatternRepetition> {
         self.repetition
@@ -118,10 +283,191 @@ impl<'a> Pattern<'a> {
         }
     }
 
+    /// Does a cut inside of the pattern commit to its enclosing union member?
+    ///
+    /// That's not the case for repeated and negative patterns, as their inner failures don't make the member fail.
+    pub fn forwards_cuts(&self) -> bool {
+        self.repetition.is_none() && !self.is_negative()
+    }
+
+    /// Does the pattern contain a cut committing to its enclosing union member (see [`RulePatternValue::Cut`])?
+    pub fn has_cut(&self) -> bool {
+        self.forwards_cuts()
+            && match &self.value {
+                RulePatternValue::Cut => true,
+                RulePatternValue::Group(inner) => inner.has_cut(),
+                RulePatternValue::Suite(patterns) => patterns.iter().any(Pattern::has_cut),
+                _ => false,
+            }
+    }
+
     /// Get the pattern's value
     pub fn value(&self) -> &RulePatternValue<'a> {
         &self.value
     }
+
+    /// Get the pattern's direct inner patterns (none for constant strings, rules and cuts, one for groups and terminators)
+    pub fn children(&self) -> impl Iterator<Item = &Pattern<'a>> {
+        let children: &[Pattern<'a>] = match &self.value {
+            RulePatternValue::CstString(_) | RulePatternValue::Rule(_) | RulePatternValue::Cut => {
+                &[]
+            }
+            RulePatternValue::Group(inner) | RulePatternValue::Until(inner) => {
+                std::slice::from_ref(inner.as_ref())
+            }
+            RulePatternValue::Suite(patterns) | RulePatternValue::Union(patterns) => patterns,
+        };
+
+        children.iter()
+    }
+
+    /// Visit the pattern and all its inner patterns, parents first (preorder traversal)
+    ///
+    /// Referenced rules are not visited.
+    pub fn walk<'p>(&'p self, f: &mut impl FnMut(&'p Pattern<'a>)) {
+        f(self);
+
+        for child in self.children() {
+            child.walk(f);
+        }
+    }
+
+    /// Check if two patterns are structurally identical, ignoring their location in the input grammar
+    pub fn structurally_eq(&self, other: &Pattern) -> bool {
+        if self.mode != other.mode || self.repetition != other.repetition {
+            return false;
+        }
+
+        match (&self.value, &other.value) {
+            (RulePatternValue::CstString(a), RulePatternValue::CstString(b)) => a == b,
+            (RulePatternValue::Rule(a), RulePatternValue::Rule(b)) => a == b,
+            (RulePatternValue::Cut, RulePatternValue::Cut) => true,
+            (RulePatternValue::Group(a), RulePatternValue::Group(b))
+            | (RulePatternValue::Until(a), RulePatternValue::Until(b)) => a.structurally_eq(b),
+            (RulePatternValue::Suite(a), RulePatternValue::Suite(b))
+            | (RulePatternValue::Union(a), RulePatternValue::Union(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.structurally_eq(b))
+            }
+            _ => false,
+        }
+    }
+
+    /// Create a copy of the pattern, with its mode and the mode of all its inner patterns set by the provided function
+    /// (which is given the original pattern)
+    pub fn map_modes(&self, f: &impl Fn(&Pattern<'a>) -> Option<PatternMode>) -> Self {
+        Self {
+            loc: self.loc,
+            decl_length: self.decl_length,
+            mode: f(self),
+            repetition: self.repetition,
+            value: match &self.value {
+                RulePatternValue::CstString(string) => RulePatternValue::CstString(string),
+                RulePatternValue::Rule(name) => RulePatternValue::Rule(name),
+                RulePatternValue::Cut => RulePatternValue::Cut,
+                RulePatternValue::Group(inner) => {
+                    RulePatternValue::Group(Rc::new(inner.map_modes(f)))
+                }
+                RulePatternValue::Suite(patterns) => RulePatternValue::Suite(
+                    patterns
+                        .iter()
+                        .map(|pattern| pattern.map_modes(f))
+                        .collect(),
+                ),
+                RulePatternValue::Union(patterns) => RulePatternValue::Union(
+                    patterns
+                        .iter()
+                        .map(|pattern| pattern.map_modes(f))
+                        .collect(),
+                ),
+                RulePatternValue::Until(terminator) => {
+                    RulePatternValue::Until(Rc::new(terminator.map_modes(f)))
+                }
+            },
+        }
+    }
+
+    /// Create a copy of the pattern, with its location and the location of all its inner patterns mapped to the file
+    /// the grammar is embedded in (see [`SourceOrigin`])
+    pub(super) fn relocated(&self, origin: &SourceOrigin) -> Self {
+        Self {
+            loc: origin.map(self.loc),
+            decl_length: self.decl_length,
+            mode: self.mode,
+            repetition: self.repetition,
+            value: match &self.value {
+                RulePatternValue::CstString(string) => RulePatternValue::CstString(string),
+                RulePatternValue::Rule(name) => RulePatternValue::Rule(name),
+                RulePatternValue::Cut => RulePatternValue::Cut,
+                RulePatternValue::Group(inner) => {
+                    RulePatternValue::Group(Rc::new(inner.relocated(origin)))
+                }
+                RulePatternValue::Suite(patterns) => RulePatternValue::Suite(
+                    patterns
+                        .iter()
+                        .map(|pattern| pattern.relocated(origin))
+                        .collect(),
+                ),
+                RulePatternValue::Union(patterns) => RulePatternValue::Union(
+                    patterns
+                        .iter()
+                        .map(|pattern| pattern.relocated(origin))
+                        .collect(),
+                ),
+                RulePatternValue::Until(terminator) => {
+                    RulePatternValue::Until(Rc::new(terminator.relocated(origin)))
+                }
+            },
+        }
+    }
+
+    /// Create a copy of the pattern where unions with a single member, which match exactly like that member,
+    /// are replaced by a group of it
+    ///
+    /// The parser never produces such unions, but code generators rely on all unions having at least two members.
+    pub fn simplify_unions(&self) -> Self {
+        Self {
+            loc: self.loc,
+            decl_length: self.decl_length,
+            mode: self.mode,
+            repetition: self.repetition,
+            value: match &self.value {
+                RulePatternValue::CstString(string) => RulePatternValue::CstString(string),
+                RulePatternValue::Rule(name) => RulePatternValue::Rule(name),
+                RulePatternValue::Cut => RulePatternValue::Cut,
+                RulePatternValue::Group(inner) => {
+                    RulePatternValue::Group(Rc::new(inner.simplify_unions()))
+                }
+                RulePatternValue::Suite(patterns) => {
+                    RulePatternValue::Suite(patterns.iter().map(Self::simplify_unions).collect())
+                }
+                RulePatternValue::Union(patterns) if patterns.len() == 1 => {
+                    RulePatternValue::Group(Rc::new(patterns[0].simplify_unions()))
+                }
+                RulePatternValue::Union(patterns) => {
+                    RulePatternValue::Union(patterns.iter().map(Self::simplify_unions).collect())
+                }
+                RulePatternValue::Until(terminator) => {
+                    RulePatternValue::Until(Rc::new(terminator.simplify_unions()))
+                }
+            },
+        }
+    }
+}
+
+/// One-line summary of the pattern, including its mode and repetition, for diagnostics
+/// (e.g. "silent zero-or-more repetition (`*`) of rule 'expr'")
+impl<'a> fmt::Display for Pattern<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(mode) = self.mode {
+            write!(f, "{} ", mode)?;
+        }
+
+        if let Some(repetition) = self.repetition {
+            write!(f, "{} of ", repetition)?;
+        }
+
+        write!(f, "{}", self.value)
+    }
 }
 
 /// [Rule pattern](`RulePattern`)'s repetition
@@ -163,6 +509,18 @@ impl PatternRepetition {
     }
 }
 
+impl fmt::Display for PatternRepetition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Any => "zero-or-more",
+            Self::OneOrMore => "one-or-more",
+            Self::Optional => "zero-or-one",
+        };
+
+        write!(f, "{} repetition (`{}`)", name, self.symbol())
+    }
+}
+
 /// Pattern mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PatternMode {
@@ -179,8 +537,35 @@ pub enum PatternMode {
     Atomic,
 }
 
+impl PatternMode {
+    /// Get the symbol associated to a pattern's mode
+    pub fn symbol(self) -> char {
+        match self {
+            Self::Silent => '°',
+            Self::Peek => '~',
+            Self::Negative => '!',
+            Self::Atomic => '@',
+        }
+    }
+}
+
+impl fmt::Display for PatternMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Silent => "silent",
+                Self::Peek => "peek",
+                Self::Negative => "negative",
+                Self::Atomic => "atomic",
+            }
+        )
+    }
+}
+
 /// A single [`RulePattern`]'s value, indicating which content it must match
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum RulePatternValue<'a> {
     /// Match a constant string
     CstString(&'a str),
@@ -197,10 +582,92 @@ pub enum RulePatternValue<'a> {
     /// Match one of the provided patterns
     /// Evaluation is performed in order, and the first matching pattern will be used
     Union(Vec<Pattern<'a>>),
+
+    /// Match everything up to (but not including) the inner pattern, which is the terminator (`..pattern`)
+    /// Fails if the terminator is never found
+    Until(Rc<Pattern<'a>>),
+
+    /// Commit to the closest enclosing union member of the rule (`^`), which always matches without consuming anything
+    /// Once a member matched past a cut, the union's remaining members are not tried, and failing afterwards makes
+    /// the whole union fail (cuts inside of repeated or negative patterns have no effect)
+    Cut,
+}
+
+impl<'a> RulePatternValue<'a> {
+    /// Get the value's kind, without its data
+    pub fn kind(&self) -> PatternKind {
+        match self {
+            Self::CstString(_) => PatternKind::CstString,
+            Self::Rule(_) => PatternKind::Rule,
+            Self::Group(_) => PatternKind::Group,
+            Self::Suite(_) => PatternKind::Suite,
+            Self::Union(_) => PatternKind::Union,
+            Self::Until(_) => PatternKind::Until,
+            Self::Cut => PatternKind::Cut,
+        }
+    }
+}
+
+/// Kind of a [`RulePatternValue`], with one variant for each of its variants
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PatternKind {
+    /// Constant string
+    CstString,
+
+    /// Reference to another rule
+    Rule,
+
+    /// Group
+    Group,
+
+    /// Suite of patterns
+    Suite,
+
+    /// Union of patterns
+    Union,
+
+    /// Everything up to a terminator
+    Until,
+
+    /// Cut
+    Cut,
+}
+
+/// Maximum number of characters of a constant string displayed in a pattern's summary
+const SUMMARY_CST_STRING_MAX_CHARS: usize = 20;
+
+/// One-line summary of the value (e.g. "union of 3 alternatives"), which doesn't describe the inner patterns of
+/// suites and unions, and truncates long constant strings
+impl<'a> fmt::Display for RulePatternValue<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CstString(string) => {
+                let truncated: String = string.chars().take(SUMMARY_CST_STRING_MAX_CHARS).collect();
+
+                write!(f, "constant string \"{}", truncated.escape_debug())?;
+
+                if truncated.len() < string.len() {
+                    write!(f, "...")?;
+                }
+
+                write!(f, "\"")
+            }
+            Self::Rule(name) if is_builtin_rule_name(name) => write!(f, "builtin rule '{}'", name),
+            Self::Rule(name) if is_external_rule_name(name) => {
+                write!(f, "external rule '{}'", name)
+            }
+            Self::Rule(name) => write!(f, "rule '{}'", name),
+            Self::Group(inner) => write!(f, "group of {}", inner),
+            Self::Suite(patterns) => write!(f, "suite of {} patterns", patterns.len()),
+            Self::Union(patterns) => write!(f, "union of {} alternatives", patterns.len()),
+            Self::Until(terminator) => write!(f, "everything up to {}", terminator),
+            Self::Cut => write!(f, "cut"),
+        }
+    }
 }
 
 /// Location in the input grammar
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ParserLoc {
     /// Line number
     pub(super) line: usize,
@@ -235,4 +702,250 @@ impl ParserLoc {
             col: self.col + cols,
         }
     }
+
+    /// Get the byte offset of this location in the input grammar
+    ///
+    /// Returns `None` if the location is out of the input's bounds.
+    pub fn byte_offset(&self, input: &str) -> Option<usize> {
+        let line_start: usize = input
+            .split_inclusive('\n')
+            .take(self.line)
+            .map(str::len)
+            .sum();
+
+        let offset = line_start + self.col;
+
+        if offset <= input.len() {
+            Some(offset)
+        } else {
+            None
+        }
+    }
+}
+
+/// Range between two locations in the input grammar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserSpan {
+    /// Start location (inclusive)
+    pub(super) start: ParserLoc,
+
+    /// End location (exclusive)
+    pub(super) end: ParserLoc,
+}
+
+impl ParserSpan {
+    /// Create a new span
+    pub(crate) fn new(start: ParserLoc, end: ParserLoc) -> Self {
+        Self { start, end }
+    }
+
+    /// Create a span covering a single-line range
+    pub(crate) fn from_length(start: ParserLoc, length: usize) -> Self {
+        Self::new(start, start.with_add_cols(length))
+    }
+
+    /// Get the span's start location
+    pub fn start(&self) -> ParserLoc {
+        self.start
+    }
+
+    /// Get the span's end location
+    pub fn end(&self) -> ParserLoc {
+        self.end
+    }
+
+    /// Check if the span is empty
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    /// Get the input grammar's slice covered by the span
+    ///
+    /// Returns `None` if the span is out of the input's bounds.
+    pub fn slice<'a>(&self, input: &'a str) -> Option<&'a str> {
+        input.get(self.start.byte_offset(input)?..self.end.byte_offset(input)?)
+    }
+}
+
+/// Position of a grammar embedded in another file (e.g. a fenced block of a Markdown document or a Rust attribute),
+/// used by [`parse_peg_with_origin`] to report locations relative to that file
+///
+/// Lines are shifted by the line the grammar starts on, and columns by the length of the prefix stripped from
+/// their line when extracting the grammar (e.g. the indentation of a fenced block), if any. The columns of the
+/// grammar's first line are additionally shifted by the column it starts at.
+///
+/// Like in [`ParserLoc`], lines and columns start at 0, and columns and prefixes are counted in bytes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceOrigin {
+    /// Line the grammar starts on
+    line: usize,
+
+    /// Column the grammar starts at, on its first line
+    col: usize,
+
+    /// Length of the prefix stripped from each line
+    indent: usize,
+
+    /// Length of the prefix stripped from each line, overriding `indent` for the lines it covers
+    line_indents: Vec<usize>,
+}
+
+impl SourceOrigin {
+    /// Create an origin from the location the grammar starts at
+    pub fn new(line: usize, col: usize) -> Self {
+        Self {
+            line,
+            col,
+            indent: 0,
+            line_indents: vec![],
+        }
+    }
+
+    /// Set the length of the prefix stripped from each line of the grammar
+    pub fn with_indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Set the length of the prefix stripped from each line of the grammar, starting with its first line
+    ///
+    /// Lines after the last provided length use the [common one](`SourceOrigin::with_indent`).
+    pub fn with_line_indents(mut self, line_indents: Vec<usize>) -> Self {
+        self.line_indents = line_indents;
+        self
+    }
+
+    /// Get the location in the enclosing file corresponding to a location in the grammar
+    pub fn map(&self, loc: ParserLoc) -> ParserLoc {
+        let indent = self
+            .line_indents
+            .get(loc.line)
+            .copied()
+            .unwrap_or(self.indent);
+        let start_col = if loc.line == 0 { self.col } else { 0 };
+
+        ParserLoc::new(self.line + loc.line, start_col + indent + loc.col)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parser::{parse_peg, parse_rule_pattern};
+    use super::*;
+
+    /// Get the summary of a pattern
+    fn describe(pattern: &str) -> String {
+        parse_rule_pattern(pattern, ParserLoc::new(0, 0))
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn pattern_values_summaries() {
+        assert_eq!(describe(r#""if""#), r#"constant string "if""#);
+        assert_eq!(describe("B_ANY"), "builtin rule 'B_ANY'");
+        assert_eq!(describe("E_IDENT"), "external rule 'E_IDENT'");
+        assert_eq!(describe("expr"), "rule 'expr'");
+        assert_eq!(describe(r#"("a")"#), r#"group of constant string "a""#);
+        assert_eq!(describe(r#""a" b c"#), "suite of 3 patterns");
+        assert_eq!(describe(r#""a" | b"#), "union of 2 alternatives");
+        assert_eq!(
+            describe(r#".."*/""#),
+            r#"everything up to constant string "*/""#
+        );
+        assert_eq!(describe("^"), "cut");
+    }
+
+    #[test]
+    fn constant_strings_summaries() {
+        // Long strings are truncated, by characters
+        assert_eq!(
+            describe(r#""ééééééééééééééééééééé""#),
+            r#"constant string "éééééééééééééééééééé...""#
+        );
+        assert_eq!(
+            describe(r#""abcdefghijklmnopqrst""#),
+            r#"constant string "abcdefghijklmnopqrst""#
+        );
+
+        // Characters which aren't printable are escaped
+        assert_eq!(describe("\"a\u{200b}\""), r#"constant string "a\u{200b}""#);
+    }
+
+    #[test]
+    fn modes_and_repetitions_summaries() {
+        assert_eq!(
+            describe("°expr*"),
+            "silent zero-or-more repetition (`*`) of rule 'expr'"
+        );
+        assert_eq!(
+            describe("@B_ALPHABETIC+"),
+            "atomic one-or-more repetition (`+`) of builtin rule 'B_ALPHABETIC'"
+        );
+        assert_eq!(
+            describe(r#"~"a"?"#),
+            r#"peek zero-or-one repetition (`?`) of constant string "a""#
+        );
+        assert_eq!(
+            describe(r#"!("a" b)"#),
+            "negative group of suite of 2 patterns"
+        );
+    }
+
+    /// Wrap a pattern in a union of which it is the only member
+    fn single_member_union(member: &str) -> Pattern<'_> {
+        let member = parse_rule_pattern(member, ParserLoc::new(0, 0)).unwrap();
+
+        Pattern {
+            loc: member.loc,
+            decl_length: member.decl_length,
+            mode: None,
+            repetition: None,
+            value: RulePatternValue::Union(vec![member]),
+        }
+    }
+
+    #[test]
+    fn single_member_unions_are_simplified() {
+        let simplified = single_member_union(r#""a" b"#).simplify_unions();
+
+        match simplified.value() {
+            RulePatternValue::Group(inner) => assert!(inner
+                .structurally_eq(&parse_rule_pattern(r#""a" b"#, ParserLoc::new(0, 0)).unwrap())),
+            other => panic!("expected a group, got {:?}", other),
+        }
+
+        // Nested unions are simplified too, while keeping their mode and repetition
+        let nested = Pattern {
+            mode: Some(PatternMode::Silent),
+            repetition: Some(PatternRepetition::OneOrMore),
+            ..single_member_union(r#""b""#)
+        };
+
+        let pattern = Pattern {
+            value: RulePatternValue::Suite(vec![
+                parse_rule_pattern(r#"("a" | "c")"#, ParserLoc::new(0, 0)).unwrap(),
+                nested,
+            ]),
+            ..single_member_union("x")
+        };
+
+        assert!(pattern.simplify_unions().structurally_eq(
+            &parse_rule_pattern(r#"("a" | "c") °("b")+"#, ParserLoc::new(0, 0)).unwrap()
+        ));
+    }
+
+    #[test]
+    fn syntax_trees_without_single_member_unions() {
+        let mut pst = parse_peg(r#"main = "a" | "b" ("c" | "d")"#).unwrap();
+        assert!(pst.simplify_unions().is_none());
+
+        pst.set_rule_value("main", single_member_union(r#""a" | "b""#).value);
+
+        let simplified = pst.simplify_unions().unwrap();
+        assert!(simplified
+            .main_rule()
+            .pattern()
+            .structurally_eq(&parse_rule_pattern(r#"("a" | "b")"#, ParserLoc::new(0, 0)).unwrap()));
+    }
 }
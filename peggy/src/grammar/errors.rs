@@ -1,4 +1,4 @@
-use super::data::ParserLoc;
+use super::data::{ParserLoc, ParserSpan, PatternRepetition, SourceOrigin};
 use super::utils::BUILTIN_RULES;
 use std::fmt;
 
@@ -47,6 +47,11 @@ impl ParserError {
         self.length
     }
 
+    /// Get the span of the input the error applies on
+    pub fn span(&self) -> ParserSpan {
+        ParserSpan::from_length(self.loc, self.length)
+    }
+
     /// Get the error's content
     pub fn content(&self) -> &ParserErrorContent {
         &self.content
@@ -56,19 +61,40 @@ impl ParserError {
     pub fn tip(&self) -> Option<&'static str> {
         self.tip
     }
+
+    /// Map the error's locations to the file the grammar is embedded in (see [`SourceOrigin`])
+    pub(crate) fn relocated(mut self, origin: &SourceOrigin) -> Self {
+        self.loc = origin.map(self.loc);
+
+        match &mut self.content {
+            ParserErrorContent::UnclosedGroup { started_at }
+            | ParserErrorContent::UnterminatedCstString { started_at }
+            | ParserErrorContent::UnterminatedMultiLineComment { started_at } => {
+                *started_at = origin.map(*started_at);
+            }
+            _ => {}
+        }
+
+        self
+    }
 }
 
 /// Content of a [`ParserError`]
 #[derive(Debug)]
 pub enum ParserErrorContent {
     ExpectedRuleDeclaration,
+    RuleNameStartsWithDigit,
+    RuleNameStartsWithSymbol(char),
+    IllegalCharInRuleName(char),
     IllegalSymbol(char),
-    ExpectedRuleAssignmentOp,
+    MissingAssignmentOp,
+    EmptyRuleBody,
     ReservedUppercaseRuleName,
     DuplicateRuleName,
     ExpectedPattern,
     UnclosedGroup { started_at: ParserLoc },
     ExpectedPatternSeparatorOrEndOfLine(char),
+    UnexpectedWhitespace(char),
     ExpectedFollowContinuation,
     ExpectedUnionContinuation,
     UnterminatedCstString { started_at: ParserLoc },
@@ -79,16 +105,51 @@ pub enum ParserErrorContent {
     UnusedRule,
     EmptyConstantString,
     PotentiallyEmptyUnionMember,
+    EmptyUnionMember,
+    InvalidRuleName(RuleNameError),
+    ControlCharInCstString(char),
+    EmptySuite,
+    EmptyUnion,
+    RepeatedUntilPattern,
+    ModifiedCut,
+    UnknownDirective,
+    InvalidDirectiveArguments,
+    DuplicateDirective,
+    InvalidArgumentsCount { expected: usize, found: usize },
+    RecursiveParametricRule,
+    ExpectedPatternAfterModeMarker(char),
+    UnexpectedClosingParenthesis,
+    DatalessEntrypoint,
+    IncompatibleGrammarVersion { required: String },
+    GrammarTooLarge { max_len: usize },
+    TooManyRules { max: usize },
+    RuleTooLong { max_len: usize },
+    NestingTooDeep { max: usize },
+    TooManyValidationSteps { max: usize },
 }
 
 impl fmt::Display for ParserErrorContent {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::ExpectedRuleDeclaration => write!(f, "Expected rule declaration"),
+            Self::RuleNameStartsWithDigit => {
+                write!(f, "Expected rule declaration, but rule names cannot start with a digit")
+            }
+            Self::RuleNameStartsWithSymbol(c) => write!(
+                f,
+                "Expected rule declaration, but rule names must start with an alphabetic character (found '{}')",
+                c
+            ),
+            Self::IllegalCharInRuleName(c) => write!(
+                f,
+                "Illegal symbol '{}' in rule name, only alphanumeric and underscore characters are allowed",
+                c
+            ),
             Self::IllegalSymbol(s) => write!(f, "Illegal symbol '{}'", s),
-            Self::ExpectedRuleAssignmentOp => {
+            Self::MissingAssignmentOp => {
                 write!(f, "Expected rule assignment operator (=)")
             }
+            Self::EmptyRuleBody => write!(f, "Rules cannot be empty"),
             Self::ReservedUppercaseRuleName => {
                 write!(
                     f,
@@ -108,6 +169,11 @@ impl fmt::Display for ParserErrorContent {
             Self::ExpectedPatternSeparatorOrEndOfLine(c) => {
                 write!(f, "Expected pattern separator or end of line, but found character [{}] instead", c)
             }
+            Self::UnexpectedWhitespace(c) => write!(
+                f,
+                "Patterns can only be separated by spaces and tabs, but found whitespace character U+{:04X} instead",
+                *c as u32
+            ),
             Self::ExpectedFollowContinuation => write!(
                 f,
                 "Expected continuation of list of rule patterns (white space)"
@@ -145,6 +211,340 @@ impl fmt::Display for ParserErrorContent {
             Self::PotentiallyEmptyUnionMember => {
                 write!(f, "Detected potentially-empty union member")
             }
+            Self::EmptyUnionMember => write!(f, "Union members cannot be empty"),
+            Self::InvalidRuleName(err) => write!(f, "Invalid rule name: {}", err),
+            Self::ControlCharInCstString(c) => write!(
+                f,
+                "Constant strings cannot contain control characters (found {:?})",
+                c
+            ),
+            Self::EmptySuite => write!(f, "Suites must contain at least one pattern"),
+            Self::EmptyUnion => write!(f, "Unions must contain at least one member"),
+            Self::RepeatedUntilPattern => write!(
+                f,
+                "Until patterns ('..') can only use the '?' repetition"
+            ),
+            Self::ModifiedCut => write!(
+                f,
+                "Cuts ('^') can't have a mode or a repetition, nor be used as a terminator"
+            ),
+            Self::UnknownDirective => write!(f, "Unknown directive"),
+            Self::InvalidDirectiveArguments => write!(f, "Invalid directive arguments"),
+            Self::DuplicateDirective => write!(f, "This directive was already declared"),
+            Self::InvalidArgumentsCount { expected, found } => write!(
+                f,
+                "This rule takes {} argument(s), but {} were provided",
+                expected, found
+            ),
+            Self::RecursiveParametricRule => {
+                write!(f, "Parametric rules cannot call themselves")
+            }
+            Self::ExpectedPatternAfterModeMarker(marker) => {
+                write!(f, "Expected a pattern after the '{}' mode marker", marker)
+            }
+            Self::UnexpectedClosingParenthesis => {
+                write!(f, "Unexpected closing parenthesis, no group is opened")
+            }
+            Self::DatalessEntrypoint => write!(
+                f,
+                "The main rule doesn't capture any data, as this pattern makes it non-capturing"
+            ),
+            Self::IncompatibleGrammarVersion { required } => write!(
+                f,
+                "This grammar requires Peggy {}, but this is Peggy v{}",
+                required,
+                env!("CARGO_PKG_VERSION")
+            ),
+            Self::GrammarTooLarge { max_len } => write!(
+                f,
+                "The grammar is larger than the maximum allowed size ({} bytes)",
+                max_len
+            ),
+            Self::TooManyRules { max } => write!(
+                f,
+                "The grammar declares more rules than the maximum allowed ({})",
+                max
+            ),
+            Self::RuleTooLong { max_len } => write!(
+                f,
+                "This rule's content is longer than the maximum allowed length ({} bytes)",
+                max_len
+            ),
+            Self::NestingTooDeep { max } => write!(
+                f,
+                "This group is nested deeper than the maximum allowed depth ({})",
+                max
+            ),
+            Self::TooManyValidationSteps { max } => write!(
+                f,
+                "Validation followed more rule references than the maximum allowed ({}) when reaching this one",
+                max
+            ),
+        }
+    }
+}
+
+impl ParserErrorContent {
+    /// Get an automatic fix for the error, if there is an obvious one
+    ///
+    /// The fix applies to the error's span (see [`ParserError::span`]).
+    pub fn quick_fix(&self) -> Option<QuickFix> {
+        match self {
+            Self::RuleNameStartsWithDigit
+            | Self::InvalidRuleName(RuleNameError::StartsWithDigit) => {
+                Some(QuickFix::Insert("rule_"))
+            }
+            Self::RuleNameStartsWithSymbol('_')
+            | Self::InvalidRuleName(RuleNameError::IllegalFirstChar('_')) => {
+                Some(QuickFix::Insert("rule"))
+            }
+            Self::IllegalCharInRuleName(_) => Some(QuickFix::Replace("_")),
+            Self::MissingAssignmentOp => Some(QuickFix::Insert("= ")),
+            Self::UnexpectedWhitespace(_) => Some(QuickFix::Replace(" ")),
+            _ => None,
+        }
+    }
+}
+
+/// Automatic fix of a [`ParserError`] (see [`ParserErrorContent::quick_fix`]), which editors can offer to apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuickFix {
+    /// Insert a string at the beginning of the error's span
+    Insert(&'static str),
+
+    /// Replace the error's span with a string
+    Replace(&'static str),
+}
+
+/// Error returned by [`validate_rule_name`](`super::validate_rule_name`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleNameError {
+    /// The name is empty
+    Empty,
+
+    /// The name starts with a digit
+    StartsWithDigit,
+
+    /// The name starts with a character that is neither alphabetic nor a digit
+    IllegalFirstChar(char),
+
+    /// The name contains a character that is neither alphanumeric nor an underscore (`at` is a byte offset)
+    IllegalChar { c: char, at: usize },
+
+    /// The name starts with a prefix reserved to builtin (`B_`) or external (`E_`) rules
+    ReservedPrefix,
+
+    /// The name is a keyword that generated parsers can't use as an identifier (e.g. `self`)
+    UnescapableKeyword,
+}
+
+impl fmt::Display for RuleNameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "rule names cannot be empty"),
+            Self::StartsWithDigit => write!(f, "rule names cannot start with a digit"),
+            Self::IllegalFirstChar(c) => write!(
+                f,
+                "rule names must start with an alphabetic character, found '{}'",
+                c
+            ),
+            Self::IllegalChar { c, .. } => write!(
+                f,
+                "rule names can only contain alphanumeric and underscore characters, found '{}'",
+                c
+            ),
+            Self::ReservedPrefix => write!(
+                f,
+                "rule names starting with 'B_' or 'E_' are reserved for builtin and external rules"
+            ),
+            Self::UnescapableKeyword => {
+                write!(
+                    f,
+                    "this keyword can't be used as an identifier in generated parsers"
+                )
+            }
+        }
+    }
+}
+
+/// Global parsing warning
+///
+/// Unlike [errors](`ParserError`), warnings don't prevent a grammar from being used, but usually indicate a bug in it.
+#[derive(Debug)]
+pub struct ParserWarning {
+    loc: ParserLoc,
+    length: usize,
+    rule: String,
+    content: ParserWarningContent,
+    tip: Option<&'static str>,
+}
+
+impl ParserWarning {
+    /// Create a new parsing warning
+    pub(crate) fn new(
+        loc: ParserLoc,
+        length: usize,
+        rule: &str,
+        content: ParserWarningContent,
+        tip: Option<&'static str>,
+    ) -> Self {
+        Self {
+            loc,
+            length,
+            rule: rule.to_string(),
+            content,
+            tip,
+        }
+    }
+
+    /// Get the location of a warning
+    pub fn loc(&self) -> &ParserLoc {
+        &self.loc
+    }
+
+    /// Get the line number of a warning
+    pub fn line(&self) -> usize {
+        self.loc.line()
+    }
+
+    /// Get the column number of a warning
+    pub fn col(&self) -> usize {
+        self.loc.col()
+    }
+
+    /// Get the input length the warning applies on
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// Get the span of the input the warning applies on
+    pub fn span(&self) -> ParserSpan {
+        ParserSpan::from_length(self.loc, self.length)
+    }
+
+    /// Get the name of the rule the warning was emitted in
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    /// Get the warning's content
+    pub fn content(&self) -> &ParserWarningContent {
+        &self.content
+    }
+
+    /// Get the optional warning's tip
+    pub fn tip(&self) -> Option<&'static str> {
+        self.tip
+    }
+}
+
+/// Content of a [`ParserWarning`]
+#[derive(Debug)]
+pub enum ParserWarningContent {
+    UnreachableAfterGreedyAny(String),
+    EquivalentToBuiltinRule(&'static str),
+    ContradictsLookahead(String),
+    RepeatedNonConsumingPattern(String),
+    RedundantOptionalRepetition(PatternRepetition),
+    DuplicateUnionMember { first: ParserLoc },
+    IneffectiveCut,
+}
+
+impl fmt::Display for ParserWarningContent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnreachableAfterGreedyAny(pattern) => write!(
+                f,
+                "This {} can never match as the greedy 'B_ANY' repetition before it consumes all the remaining input",
+                pattern
+            ),
+            Self::EquivalentToBuiltinRule(name) => write!(
+                f,
+                "This rule matches exactly the same characters as the builtin rule '{}'",
+                name
+            ),
+            Self::ContradictsLookahead(pattern) => write!(
+                f,
+                "This {} can never match as it contradicts the peek or negative pattern before it",
+                pattern
+            ),
+            Self::RepeatedNonConsumingPattern(pattern) => write!(
+                f,
+                "This {} never consumes anything, so repeating it either changes nothing or loops forever",
+                pattern
+            ),
+            Self::RedundantOptionalRepetition(equivalent) => write!(
+                f,
+                "This optional group of a repetition matches the same input as its content with a {}",
+                equivalent
+            ),
+            Self::DuplicateUnionMember { first } => write!(
+                f,
+                "This union member is identical to the one at line {}, column {}, so it can never match",
+                first.line() + 1,
+                first.col() + 1
+            ),
+            Self::IneffectiveCut => write!(
+                f,
+                "This cut isn't part of any union member, so it has no effect"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parser::{parse_peg, parse_rule_header};
+    use super::*;
+
+    /// Apply the quick fix of an error located on the first line of a grammar
+    fn apply_quick_fix(grammar: &str, err: &ParserError) -> String {
+        assert_eq!(err.line(), 0);
+
+        let (start, end) = (err.col(), err.col() + err.length());
+
+        match err.content().quick_fix() {
+            Some(QuickFix::Insert(string)) => {
+                format!("{}{}{}", &grammar[..start], string, &grammar[start..])
+            }
+            Some(QuickFix::Replace(string)) => {
+                format!("{}{}{}", &grammar[..start], string, &grammar[end..])
+            }
+            None => panic!("no quick fix for {:?}", err.content()),
+        }
+    }
+
+    #[test]
+    fn header_quick_fixes() {
+        for (header, fixed) in [
+            (r#"1a = "a""#, r#"rule_1a = "a""#),
+            (r#"_a = "a""#, r#"rule_a = "a""#),
+            (r#"a-b = "a""#, r#"a_b = "a""#),
+            (r#"a "a""#, r#"a = "a""#),
+            (r#"list(1e) = 1e"#, r#"list(rule_1e) = 1e"#),
+            (r#"list(_e) = _e"#, r#"list(rule_e) = _e"#),
+        ] {
+            let err = parse_rule_header(header).unwrap_err();
+            assert_eq!(apply_quick_fix(header, &err), fixed);
+            parse_rule_header(fixed).unwrap();
+        }
+    }
+
+    #[test]
+    fn whitespace_quick_fix() {
+        let grammar = "main = \"a\"\u{a0}\"b\"";
+        let err = parse_peg(grammar).unwrap_err();
+
+        let fixed = apply_quick_fix(grammar, &err);
+        assert_eq!(fixed, r#"main = "a" "b""#);
+        parse_peg(&fixed).unwrap();
+    }
+
+    #[test]
+    fn errors_without_quick_fix() {
+        for grammar in [r#"main = 'a'"#, r#"main = ("a""#, "a = \"a\"", "main ="] {
+            let err = parse_peg(grammar).unwrap_err();
+            assert_eq!(err.content().quick_fix(), None, "{}", grammar);
         }
     }
 }
@@ -0,0 +1,300 @@
+use crate::grammar::data::*;
+use crate::grammar::non_capturing_pat;
+use std::collections::{HashMap, HashSet};
+
+/// Graph of the references between the rules of a grammar, along with the properties derived from it
+///
+/// The graph is built in a single pass over the syntax tree, and its strongly connected components are found with
+/// Tarjan's algorithm, so the analyses relying on it (non-capturing rules, recursive references, lifetime requirements)
+/// don't need to walk the rules again.
+pub(crate) struct GrammarGraph<'a> {
+    /// Strongly connected components of the graph, each component appearing after the components it references
+    components: Vec<Vec<&'a str>>,
+
+    /// Index of the component each rule belongs to
+    #[cfg(feature = "rustgen")]
+    component_of: HashMap<&'a str, usize>,
+
+    /// Rules which don't capture any data, and the mode shared by their patterns
+    non_capturing: HashMap<&'a str, PatternMode>,
+}
+
+impl<'a> GrammarGraph<'a> {
+    /// Build the graph of a syntax tree
+    ///
+    /// References to undeclared rules (e.g. builtin rules) are ignored.
+    pub fn new(pst: &'a PegSyntaxTree) -> Self {
+        let ordered_rules: Vec<_> = pst.ordered_rules().iter().map(|rule| rule.name()).collect();
+
+        let mut references: HashMap<_, _> =
+            ordered_rules.iter().map(|name| (*name, vec![])).collect();
+
+        pst.walk_rules(&mut |rule, pattern| {
+            if let RulePatternValue::Rule(name) = pattern.value() {
+                if pst.rules().contains_key(name) {
+                    references.get_mut(rule).unwrap().push(*name);
+                }
+            }
+        });
+
+        let mut tarjan = Tarjan {
+            references: &references,
+            indexes: HashMap::new(),
+            low_links: HashMap::new(),
+            stack: vec![],
+            on_stack: HashSet::new(),
+            components: vec![],
+        };
+
+        for name in ordered_rules {
+            if !tarjan.indexes.contains_key(name) {
+                tarjan.visit(name);
+            }
+        }
+
+        let components = tarjan.components;
+
+        let mut graph = Self {
+            #[cfg(feature = "rustgen")]
+            component_of: components
+                .iter()
+                .enumerate()
+                .flat_map(|(i, component)| component.iter().map(move |name| (*name, i)))
+                .collect(),
+            components,
+            non_capturing: HashMap::new(),
+        };
+
+        graph.non_capturing = graph
+            .fixpoint(pst, None, |_, pattern, modes| {
+                non_capturing_pat::pattern_mode(pattern, modes)
+            })
+            .into_iter()
+            .filter_map(|(name, mode)| Some((name, mode?)))
+            .collect();
+
+        graph
+    }
+
+    /// Check if a rule's reference to another rule leads back to itself
+    ///
+    /// This is the case if both rules belong to the same strongly connected component (including a rule referencing
+    /// itself), so storing the referenced rule's data behind an indirection breaks all cycles.
+    #[cfg(feature = "rustgen")]
+    pub fn is_recursive_reference(&self, from: &str, to: &str) -> bool {
+        match (self.component_of.get(from), self.component_of.get(to)) {
+            (Some(from), Some(to)) => from == to,
+            _ => false,
+        }
+    }
+
+    /// Get the mode shared by all the patterns of a rule which doesn't capture any data
+    pub fn non_capturing_mode(&self, name: &str) -> Option<PatternMode> {
+        self.non_capturing.get(name).copied()
+    }
+
+    /// Get the rules which don't capture any data, and the mode shared by their patterns
+    pub fn non_capturing_rules(&self) -> &HashMap<&'a str, PatternMode> {
+        &self.non_capturing
+    }
+
+    /// Compute a property of all declared rules from their name and pattern, and the properties of the rules they reference
+    ///
+    /// Components are evaluated after the components they reference, and the rules of a component are evaluated
+    /// repeatedly from the `initial` value until none of them change, so `eval` must only make the values evolve in
+    /// a single direction for this to terminate.
+    pub fn fixpoint<T: Clone + PartialEq>(
+        &self,
+        pst: &'a PegSyntaxTree,
+        initial: T,
+        eval: impl Fn(&'a str, &'a Pattern, &HashMap<&'a str, T>) -> T,
+    ) -> HashMap<&'a str, T> {
+        let mut values = HashMap::new();
+
+        for component in &self.components {
+            for name in component {
+                values.insert(*name, initial.clone());
+            }
+
+            loop {
+                let mut changed = false;
+
+                for name in component {
+                    let value = eval(name, pst.rules()[name].pattern(), &values);
+
+                    if values[name] != value {
+                        values.insert(*name, value);
+                        changed = true;
+                    }
+                }
+
+                if !changed {
+                    break;
+                }
+            }
+        }
+
+        values
+    }
+}
+
+/// State of Tarjan's algorithm (see [`GrammarGraph::new`])
+struct Tarjan<'a, 'g> {
+    /// Declared rules referenced by each rule, in order of appearance (with duplicates)
+    references: &'g HashMap<&'a str, Vec<&'a str>>,
+
+    /// Order in which the rules were visited
+    indexes: HashMap<&'a str, usize>,
+
+    /// Smallest index reachable from each rule through the rules still on the stack
+    low_links: HashMap<&'a str, usize>,
+
+    /// Visited rules whose component isn't known yet
+    stack: Vec<&'a str>,
+    on_stack: HashSet<&'a str>,
+
+    /// Components found so far
+    components: Vec<Vec<&'a str>>,
+}
+
+impl<'a, 'g> Tarjan<'a, 'g> {
+    fn visit(&mut self, rule: &'a str) {
+        let index = self.indexes.len();
+        self.indexes.insert(rule, index);
+        self.low_links.insert(rule, index);
+        self.stack.push(rule);
+        self.on_stack.insert(rule);
+
+        for name in &self.references[rule] {
+            if !self.indexes.contains_key(name) {
+                self.visit(name);
+                let low_link = self.low_links[rule].min(self.low_links[name]);
+                self.low_links.insert(rule, low_link);
+            } else if self.on_stack.contains(name) {
+                let low_link = self.low_links[rule].min(self.indexes[name]);
+                self.low_links.insert(rule, low_link);
+            }
+        }
+
+        // The rule is the root of a component, which is made of the rules above it on the stack
+        if self.low_links[rule] == index {
+            let start = self.stack.iter().rposition(|name| *name == rule).unwrap();
+            let component: Vec<_> = self.stack.drain(start..).collect();
+
+            for name in &component {
+                self.on_stack.remove(name);
+            }
+
+            self.components.push(component);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parser::parse_peg;
+    use super::*;
+
+    /// Get the components of a grammar's graph, with sorted rules
+    fn components(grammar: &str) -> Vec<Vec<String>> {
+        let pst = parse_peg(grammar).unwrap();
+        let graph = GrammarGraph::new(&pst);
+
+        graph
+            .components
+            .iter()
+            .map(|component| {
+                let mut component: Vec<_> = component.iter().map(|name| name.to_string()).collect();
+                component.sort();
+                component
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mutually_recursive_triples() {
+        let grammar = r#"
+a = "a" b?
+b = "b" c
+c = "c" a
+main = a "!"
+"#;
+
+        // Components appear after the components they reference
+        assert_eq!(components(grammar), [vec!["a", "b", "c"], vec!["main"]]);
+    }
+
+    #[test]
+    fn overlapping_cycles() {
+        // `a` and `b` are in two cycles (a -> b -> a and b -> c -> b), `d` only references the cycles
+        let grammar = r#"
+a = "a" b?
+b = "b" (a | c)?
+c = "c" b
+d = "d" c
+main = a d
+"#;
+
+        assert_eq!(
+            components(grammar),
+            [vec!["a", "b", "c"], vec!["d"], vec!["main"]]
+        );
+    }
+
+    #[test]
+    fn self_references_and_chains() {
+        let grammar = r#"
+a = "a" a?
+b = a "b"
+main = b b
+"#;
+
+        assert_eq!(components(grammar), [vec!["a"], vec!["b"], vec!["main"]]);
+    }
+
+    #[test]
+    fn non_capturing_rules() {
+        let grammar = r#"
+ws = °" "+
+spaces = ws ws?
+peek = ~"a" ~spaces
+sep = ws "," ws
+main = spaces peek sep
+"#;
+
+        let pst = parse_peg(grammar).unwrap();
+        let graph = GrammarGraph::new(&pst);
+
+        assert_eq!(graph.non_capturing_mode("ws"), Some(PatternMode::Silent));
+        assert_eq!(
+            graph.non_capturing_mode("spaces"),
+            Some(PatternMode::Silent)
+        );
+        assert_eq!(graph.non_capturing_mode("peek"), Some(PatternMode::Peek));
+        assert_eq!(graph.non_capturing_mode("sep"), None);
+        assert_eq!(graph.non_capturing_mode("main"), None);
+        assert_eq!(graph.non_capturing_rules().len(), 3);
+    }
+
+    #[cfg(feature = "rustgen")]
+    #[test]
+    fn recursive_references() {
+        let pst =
+            parse_peg("a = \"a\" b?\nb = \"b\" (a | c)?\nc = \"c\" b\nd = \"d\" a? d?\nmain = d c")
+                .unwrap();
+        let graph = GrammarGraph::new(&pst);
+
+        for (from, to) in [("a", "b"), ("b", "a"), ("b", "c"), ("c", "b"), ("d", "d")] {
+            assert!(graph.is_recursive_reference(from, to), "{} -> {}", from, to);
+        }
+
+        for (from, to) in [("d", "a"), ("main", "d"), ("main", "c"), ("a", "B_ANY")] {
+            assert!(
+                !graph.is_recursive_reference(from, to),
+                "{} -> {}",
+                from,
+                to
+            );
+        }
+    }
+}
@@ -0,0 +1,59 @@
+/// Compute the hash of a grammar's source, which is embedded in generated parsers (see [`verify_generated`])
+///
+/// The hash is stable across platforms and versions, and doesn't depend on the line endings (`\n` or `\r\n`).
+pub fn grammar_hash(grammar_src: &str) -> String {
+    // 64-bit FNV-1a
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for line in grammar_src.lines() {
+        for byte in line.bytes().chain(std::iter::once(b'\n')) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    format!("{:016x}", hash)
+}
+
+/// Check if a parser was generated from the provided grammar, using the hash it embeds (its `GRAMMAR_HASH` constant)
+///
+/// This allows to detect parsers which weren't regenerated after their grammar was modified.
+pub fn verify_generated(grammar_src: &str, generated_hash: &str) -> bool {
+    grammar_hash(grammar_src) == generated_hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_are_stable() {
+        // Hashes are embedded in generated code, so they must never change
+        assert_eq!(grammar_hash(""), "cbf29ce484222325");
+        assert_eq!(grammar_hash("main = \"a\""), grammar_hash("main = \"a\"\n"));
+        assert_ne!(grammar_hash("main = \"a\""), grammar_hash("main = \"b\""));
+        assert_ne!(
+            grammar_hash("main = \"a\""),
+            grammar_hash("main = \"a\"\n\n")
+        );
+    }
+
+    #[test]
+    fn hashes_ignore_line_endings() {
+        assert_eq!(
+            grammar_hash("a = \"a\"\nmain = a\n"),
+            grammar_hash("a = \"a\"\r\nmain = a\r\n")
+        );
+    }
+
+    #[test]
+    fn verify_generated_hashes() {
+        let grammar = "main = \"a\"\n";
+        let hash = grammar_hash(grammar);
+
+        assert!(verify_generated(grammar, &hash));
+        assert!(verify_generated(&grammar.replace('\n', "\r\n"), &hash));
+        assert!(!verify_generated("main = \"b\"\n", &hash));
+        assert!(!verify_generated(grammar, ""));
+    }
+}
@@ -0,0 +1,153 @@
+use super::data::*;
+
+/// Chain of nodes under a position of the input grammar, returned by [`node_at`]
+#[derive(Debug, Clone)]
+pub struct NodePath<'p, 'a> {
+    rule: &'p Rule<'a>,
+    patterns: Vec<&'p Pattern<'a>>,
+}
+
+impl<'p, 'a> NodePath<'p, 'a> {
+    /// Get the rule the position is in
+    pub fn rule(&self) -> &'p Rule<'a> {
+        self.rule
+    }
+
+    /// Get the patterns containing the position, from the rule's pattern to the innermost one
+    ///
+    /// Empty if the position is on the rule's name.
+    pub fn patterns(&self) -> &[&'p Pattern<'a>] {
+        &self.patterns
+    }
+
+    /// Get the innermost pattern containing the position, if any
+    pub fn innermost(&self) -> Option<&'p Pattern<'a>> {
+        self.patterns.last().copied()
+    }
+}
+
+/// Find the rule and patterns under a position of the input grammar (line and column both start at 0), e.g. for editor tooltips
+///
+/// Positions on separators and whitespaces resolve to the enclosing suite or union.
+/// Returns `None` if the position is not in a rule (e.g. on an empty line or a comment between rules).
+///
+/// As parametric rules are expanded at each call, positions in their content resolve to one of the rules calling them.
+pub fn node_at<'p, 'a>(
+    pst: &'p PegSyntaxTree<'a>,
+    line: usize,
+    col: usize,
+) -> Option<NodePath<'p, 'a>> {
+    let loc = ParserLoc::new(line, col);
+
+    for rule in pst.ordered_rules() {
+        let name = ParserSpan::from_length(rule.decl_loc(), rule.name().len());
+
+        if name.start() <= loc && loc < name.end() {
+            return Some(NodePath {
+                rule,
+                patterns: vec![],
+            });
+        }
+
+        let mut patterns = vec![];
+
+        if find_patterns_at(rule.pattern(), loc, &mut patterns) {
+            return Some(NodePath { rule, patterns });
+        }
+
+        // Positions between the rule's name and the end of its content which are not in any pattern
+        // (e.g. on the assignment operator, or on the union separator starting a continuation line)
+        if rule.decl_loc() <= loc && loc < rule.span().end() {
+            return Some(NodePath {
+                rule,
+                patterns: vec![rule.pattern()],
+            });
+        }
+    }
+
+    None
+}
+
+/// Find the patterns containing a location, from the provided pattern to the innermost one
+///
+/// Returns `false` if the location isn't in the pattern.
+fn find_patterns_at<'p, 'a>(
+    pattern: &'p Pattern<'a>,
+    loc: ParserLoc,
+    path: &mut Vec<&'p Pattern<'a>>,
+) -> bool {
+    path.push(pattern);
+
+    for child in pattern.children() {
+        if find_patterns_at(child, loc, path) {
+            return true;
+        }
+    }
+
+    let span = pattern.span();
+    let contains = span.start() <= loc && loc < span.end();
+
+    if !contains {
+        path.pop();
+    }
+
+    contains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parser::parse_peg;
+    use super::*;
+
+    const GRAMMAR: &str = "a = \"hello\" b+\nb = \"x\"\n\n# Comment\nmain = a";
+
+    /// Get the rule's name and the innermost pattern under a position
+    fn lookup<'p>(pst: &'p PegSyntaxTree, line: usize, col: usize) -> Option<(&'p str, usize)> {
+        node_at(pst, line, col).map(|path| (path.rule().name(), path.patterns().len()))
+    }
+
+    #[test]
+    fn positions_in_string_literals() {
+        let pst = parse_peg(GRAMMAR).unwrap();
+
+        // On the opening quote, inside the string and on the closing quote
+        for col in [4, 7, 10] {
+            let path = node_at(&pst, 0, col).unwrap();
+            assert_eq!(path.rule().name(), "a");
+            assert_eq!(path.patterns().len(), 2);
+            assert!(matches!(
+                path.innermost().unwrap().value(),
+                RulePatternValue::CstString("hello")
+            ));
+        }
+    }
+
+    #[test]
+    fn positions_on_repetition_symbols() {
+        let pst = parse_peg(GRAMMAR).unwrap();
+
+        let path = node_at(&pst, 0, 13).unwrap();
+        let innermost = path.innermost().unwrap();
+        assert!(matches!(innermost.value(), RulePatternValue::Rule("b")));
+        assert_eq!(innermost.repetition(), Some(PatternRepetition::OneOrMore));
+
+        // Past the repetition symbol
+        assert_eq!(lookup(&pst, 0, 14), None);
+    }
+
+    #[test]
+    fn positions_outside_of_patterns() {
+        let pst = parse_peg(GRAMMAR).unwrap();
+
+        // Rule's name
+        assert_eq!(lookup(&pst, 0, 0), Some(("a", 0)));
+        // Assignment operator and separator, which belong to the enclosing suite
+        assert_eq!(lookup(&pst, 0, 2), Some(("a", 1)));
+        assert_eq!(lookup(&pst, 0, 11), Some(("a", 1)));
+
+        // Between rules
+        assert_eq!(lookup(&pst, 2, 0), None);
+        assert_eq!(lookup(&pst, 3, 4), None);
+        assert_eq!(lookup(&pst, 10, 0), None);
+    }
+}
@@ -4,16 +4,26 @@
 //!
 //! These can then be used either with the [generators](`crate::generators`), or with the [built-in runtime](`crate::runtime`).
 
+mod analysis;
 pub mod data;
 mod errors;
+pub(crate) mod graph;
+mod hash;
+mod lookup;
+pub(crate) mod non_capturing_pat;
 mod parser;
+pub(crate) mod recursive_rules;
 mod report;
 mod singles;
 pub(crate) mod utils;
 mod validator;
 
+pub use analysis::*;
 pub use data::*;
 pub use errors::*;
+pub use hash::*;
+pub use lookup::*;
 pub use parser::*;
 pub use report::*;
+pub use utils::{sanitize_rule_name, validate_rule_name};
 pub use validator::*;
@@ -0,0 +1,42 @@
+use crate::grammar::data::*;
+use crate::grammar::utils::{
+    is_builtin_rule_name, is_external_rule_name, is_zero_width_builtin_rule_name,
+};
+use std::collections::HashMap;
+
+/// Get the mode shared by all the pieces of a pattern which doesn't capture any data,
+/// from the modes of the non-capturing rules (see [`super::graph::GrammarGraph::non_capturing_rules`])
+pub fn pattern_mode(
+    pattern: &Pattern,
+    rules: &HashMap<&str, Option<PatternMode>>,
+) -> Option<PatternMode> {
+    if let Some(mode) = pattern.mode() {
+        Some(mode)
+    } else {
+        match pattern.value() {
+            RulePatternValue::CstString(_) | RulePatternValue::Until(_) => None,
+
+            // Zero-width builtin rules and cuts never capture anything
+            RulePatternValue::Rule(name) if is_zero_width_builtin_rule_name(name) => {
+                Some(PatternMode::Silent)
+            }
+            RulePatternValue::Cut => Some(PatternMode::Silent),
+            RulePatternValue::Rule(name)
+                if is_builtin_rule_name(name) || is_external_rule_name(name) =>
+            {
+                None
+            }
+            RulePatternValue::Rule(name) => rules.get(name).copied().flatten(),
+            RulePatternValue::Group(group) => pattern_mode(group, rules),
+            RulePatternValue::Suite(patterns) | RulePatternValue::Union(patterns) => {
+                let prev = pattern_mode(patterns.first().unwrap(), rules);
+                prev.filter(|prev| {
+                    patterns
+                        .iter()
+                        .skip(1)
+                        .all(|pat| pattern_mode(pat, rules) == Some(*prev))
+                })
+            }
+        }
+    }
+}
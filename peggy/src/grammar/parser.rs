@@ -1,36 +1,165 @@
+use super::analysis::rules_min_length;
 use super::data::*;
-use super::errors::{ParserError, ParserErrorContent};
+use super::errors::{ParserError, ParserErrorContent, ParserWarning, RuleNameError};
 use super::singles;
 use super::utils::*;
-use super::validator::validate_parsed_peg;
-use std::collections::HashMap;
+use super::validator::{
+    validate_affected_rules, validate_parsed_peg, validate_parsed_peg_with_limits,
+};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
 /// Compile a Peggy grammar to a [syntax tree](`PegSyntaxTree`)
-pub fn parse_peg(grammar: &str) -> Result<PegSyntaxTree, ParserError> {
+///
+/// Warnings are discarded, use [`parse_peg_with_warnings`] to get them.
+pub fn parse_peg(grammar: &str) -> Result<PegSyntaxTree<'_>, ParserError> {
+    parse_peg_with_warnings(grammar).map(|(parsed, _)| parsed)
+}
+
+/// Compile a Peggy grammar to a [syntax tree](`PegSyntaxTree`), and get the [warnings](`ParserWarning`) emitted during validation
+pub fn parse_peg_with_warnings(
+    grammar: &str,
+) -> Result<(PegSyntaxTree<'_>, Vec<ParserWarning>), ParserError> {
     let parsed = parse_peg_nocheck(grammar)?;
 
     // Ensure the syntax tree is valid
-    validate_parsed_peg(&parsed)?;
+    let warnings = validate_parsed_peg(&parsed)?;
+
+    Ok((parsed, warnings))
+}
+
+/// Compile a Peggy grammar embedded in another file (e.g. a fenced block of a Markdown document) to a [syntax tree](`PegSyntaxTree`)
+///
+/// The locations of the returned error and of the syntax tree's rules and patterns are relative to the enclosing file,
+/// as described by the provided [origin](`SourceOrigin`), so errors can be displayed by [`pretty_format_parser_err`](`crate::grammar::pretty_format_parser_err`)
+/// using that file's content. The [syntax tree's source](`PegSyntaxTree::source`) is still the grammar itself.
+///
+/// Warnings are discarded.
+pub fn parse_peg_with_origin<'a>(
+    grammar: &'a str,
+    origin: &SourceOrigin,
+) -> Result<PegSyntaxTree<'a>, ParserError> {
+    let mut parsed = parse_peg(grammar).map_err(|err| err.relocated(origin))?;
+    parsed.relocate(origin);
+    Ok(parsed)
+}
+
+/// Compile a Peggy grammar to a [syntax tree](`PegSyntaxTree`), failing if it exceeds the provided [limits](`ParseLimits`)
+///
+/// This is meant to compile untrusted grammars (e.g. submitted by users of a service) without letting them use
+/// an unbounded amount of time or memory. Warnings are discarded.
+pub fn parse_peg_with_limits<'a>(
+    grammar: &'a str,
+    limits: &ParseLimits,
+) -> Result<PegSyntaxTree<'a>, ParserError> {
+    let parsed = parse_peg_nocheck_with_limits(grammar, limits)?;
+
+    validate_parsed_peg_with_limits(&parsed, limits)?;
 
     Ok(parsed)
 }
 
+/// Limits enforced by [`parse_peg_with_limits`]
+///
+/// All limits are disabled by default, e.g. `ParseLimits { max_rules: 1000, ..ParseLimits::default() }` only limits
+/// the number of rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// Maximum size of the grammar, in bytes, including the content of parametric rules each time they are expanded
+    pub max_input_len: usize,
+
+    /// Maximum number of declared rules, including parametric ones
+    pub max_rules: usize,
+
+    /// Maximum length of a rule's content, in bytes, including its continuation lines
+    pub max_rule_len: usize,
+
+    /// Maximum nesting of groups, including the arguments of parametric rules' calls and their expansion
+    pub max_nesting: usize,
+
+    /// Maximum number of rule references followed by validation, when it looks for what a pattern may capture or consume
+    /// through the rules it uses
+    pub max_validation_steps: usize,
+}
+
+impl ParseLimits {
+    /// Create a new set of limits, all of them being disabled
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_input_len: usize::MAX,
+            max_rules: usize::MAX,
+            max_rule_len: usize::MAX,
+            max_nesting: usize::MAX,
+            max_validation_steps: usize::MAX,
+        }
+    }
+}
+
 /// Compile a Peggy grammar but don't check for validity (e.g. inexistant rule names, etc.)
 ///
 /// A bit faster than [`parse_peg`] but less safe due to the lack of check.
 ///
 /// Near-instant checks are still performed, like ensuring the presence of a `main` rule.
-pub fn parse_peg_nocheck(input: &str) -> Result<PegSyntaxTree, ParserError> {
+pub fn parse_peg_nocheck(input: &str) -> Result<PegSyntaxTree<'_>, ParserError> {
+    parse_peg_nocheck_with_limits(input, &ParseLimits::default())
+}
+
+/// Compile a Peggy grammar without checking for validity, failing if it exceeds the provided limits
+fn parse_peg_nocheck_with_limits<'a>(
+    input: &'a str,
+    limits: &ParseLimits,
+) -> Result<PegSyntaxTree<'a>, ParserError> {
+    if input.len() > limits.max_input_len {
+        return Err(ParserError::new(
+            ParserLoc::new(0, 0),
+            0,
+            ParserErrorContent::GrammarTooLarge {
+                max_len: limits.max_input_len,
+            },
+            None,
+        ));
+    }
+
     // Collected rules
     let mut rules = HashMap::new();
 
     // Is a multi-line comment opened?
     let mut multi_line_comment_opened = None;
 
+    // Rule whose content may still be continued on the next lines (see [`PendingRule`])
+    let mut pending_rule: Option<PendingRule> = None;
+
+    // Declared rules, whose content is parsed once all parametric rules are known
+    let mut declared_rules = vec![];
+
+    // Names of the declared rules, to detect duplicates
+    let mut declared_names = HashSet::new();
+
+    // Collected directives
+    let mut directives = Directives::default();
+
+    // Precedence block whose first level is expected on the next line
+    let mut precedence_block: Option<PrecedenceBlock> = None;
+
     // Iterate over each line, as there should be one rule per non-empty line
     for (l, line) in input.lines().enumerate() {
+        // Some editors start files with a byte order mark, which is skipped like indentation
+        let bom_len = if l == 0 && line.starts_with(BYTE_ORDER_MARK) {
+            BYTE_ORDER_MARK.len_utf8()
+        } else {
+            0
+        };
+
         // Left trim
-        let (line, trimmed) = trim_start_and_count(line);
+        let (line, trimmed) = trim_start_and_count(&line[bom_len..]);
+        let trimmed = bom_len + trimmed;
 
         if line.trim_end() == "###" {
             multi_line_comment_opened = if multi_line_comment_opened.is_none() {
@@ -50,114 +179,143 @@ pub fn parse_peg_nocheck(input: &str) -> Result<PegSyntaxTree, ParserError> {
             continue;
         }
 
-        let mut chars = line.chars();
+        // Lines starting with an union separator (|) following a precedence block declare its levels
+        if line.starts_with('|')
+            && (precedence_block.is_some() || is_precedence_level(&pending_rule))
+        {
+            // Each level's operands are matched by the next level, the last one using the block's operand
+            let (operand, previous) = match precedence_block.take() {
+                Some(block) => (block.operand, None),
+                None => {
+                    let previous = pending_rule.take().unwrap();
+                    (previous.precedence.unwrap().operand, Some(previous))
+                }
+            };
 
-        // Get the first character of the line...
-        let c = chars.next().unwrap();
+            let level = parse_precedence_level(line, ParserLoc::new(l, trimmed), operand)?;
 
-        // ...which must be a rule's name (syntax: `rule = <content>`)
-        if !c.is_alphabetic() {
-            return Err(ParserError::new(
-                ParserLoc::new(l, trimmed),
-                1,
-                ParserErrorContent::ExpectedRuleDeclaration,
-                Some(match c {
-                    '0'..='9' => "digits are not allowed to begin a rule's name",
-                    _ => "only alphabetic characters are allowed to begin a rule's name",
-                }),
-            ));
-        }
+            declare_rule_name(&mut declared_names, level.name, level.decl_loc, limits)?;
 
-        // Length of the rule's name (1 as we already checked the first character)
-        let mut rule_name_length = 1;
+            if let Some(mut previous) = previous {
+                previous.precedence.as_mut().unwrap().operand = (level.name, level.decl_loc);
+                declared_rules.push(previous);
+            }
 
-        // Indicate if the name is finished, but the loop is still active.
-        // Used to count spaces separating the name and the assignment operator (=)
-        let mut rule_name_ended = false;
+            pending_rule = Some(level);
 
-        // Number of spaces separating the name and the assignment operator (=)
-        let mut rule_op_spaces_sep = 0;
+            continue;
+        }
 
-        // Iterate over characters
-        loop {
-            match chars.next() {
-                // Identifier-compliant characters
-                Some(c) if !rule_name_ended && (c.is_alphanumeric() || c == '_') => {
-                    rule_name_length += c.len_utf8();
-                }
+        // Lines starting with an union separator (|) continue the previous rule's content
+        if line.starts_with('|') {
+            let pending = pending_rule.as_mut().ok_or_else(|| {
+                ParserError::new(
+                    ParserLoc::new(l, trimmed),
+                    1,
+                    ParserErrorContent::ExpectedRuleDeclaration,
+                    Some("union continuation lines must follow a rule declaration"),
+                )
+            })?;
 
-                // Assignment operator (indicates the beginning of the rule's content)
-                Some('=') => break,
+            let (content, content_col) = strip_leading_union_sep(line, ParserLoc::new(l, trimmed))?;
 
-                // Whitespaces (optional, can be used to separate the name and the assignment operator)
-                Some(c) if c.is_whitespace() => {
-                    rule_name_ended = true;
-                    rule_op_spaces_sep += 1;
-                }
+            pending
+                .lines
+                .push((content, ParserLoc::new(l, content_col)));
 
-                // Other characters (= non-compliant)
-                Some(c) => {
-                    return Err(ParserError::new(
-                        ParserLoc::new(l, trimmed + rule_name_length),
-                        1,
-                        ParserErrorContent::IllegalSymbol(c),
-                        Some("only alphanumeric and underscore characters are allowed in a rule's name")
-                    ));
-                }
+            continue;
+        }
 
-                // End of line without an assignment operator
-                None => {
-                    return Err(ParserError::new(
-                        ParserLoc::new(l, trimmed + rule_name_length),
-                        1,
-                        ParserErrorContent::ExpectedRuleAssignmentOp,
-                        Some("you may have forgot to add the rule assignment operator '='"),
-                    ));
-                }
-            }
+        // The previous rule can't be continued anymore, so it is now complete
+        if let Some(pending) = pending_rule.take() {
+            declared_rules.push(pending);
+        }
+
+        if let Some(block) = precedence_block.take() {
+            return Err(block.missing_levels_error());
         }
 
-        // Collect the rule's name
-        let rule_name = &line[..rule_name_length];
+        // Directives (syntax: `@directive <arguments>`)
+        if let Some(directive) = line.strip_prefix('@') {
+            precedence_block =
+                parse_directive(directive, ParserLoc::new(l, trimmed + 1), &mut directives)?;
+            continue;
+        }
 
-        // Detect reserved rule names
-        if is_reserved_rule_name(rule_name) {
-            return Err(ParserError::new(
-                ParserLoc::new(l, trimmed),
-                rule_name_length,
-                ParserErrorContent::ReservedUppercaseRuleName,
-                Some("try to use a name that doesn't start by 'B_' (builtin rules) or 'E_' (external rules)"),
-            ));
+        // Otherwise, the line declares a rule (syntax: `rule = <content>`)
+        let header = parse_rule_header_at(line, ParserLoc::new(l, trimmed))?;
+        let rule_name = header.name;
+
+        declare_rule_name(
+            &mut declared_names,
+            rule_name,
+            ParserLoc::new(l, trimmed),
+            limits,
+        )?;
+
+        let mut pending = PendingRule {
+            name: rule_name,
+            params: header.params,
+            decl_loc: ParserLoc::new(l, trimmed),
+            op_loc: header.op_loc,
+            lines: vec![],
+            precedence: None,
+        };
+
+        // The content may be empty if the rule's members are provided on the next lines
+        if !is_finished_line(header.content) {
+            let (content, content_col) =
+                strip_leading_union_sep(header.content, header.content_loc)?;
+
+            pending
+                .lines
+                .push((content, ParserLoc::new(l, content_col)));
         }
 
-        // Detect duplicate rules
-        if rules.contains_key(rule_name) {
+        // Save the new rule once all its continuation lines have been collected
+        pending_rule = Some(pending);
+    }
+
+    // Save the last rule
+    if let Some(pending) = pending_rule.take() {
+        declared_rules.push(pending);
+    }
+
+    if let Some(block) = precedence_block {
+        return Err(block.missing_levels_error());
+    }
+
+    for pending in &declared_rules {
+        if pending.content_len() > limits.max_rule_len {
             return Err(ParserError::new(
-                ParserLoc::new(l, trimmed),
-                rule_name_length,
-                ParserErrorContent::DuplicateRuleName,
+                pending.decl_loc,
+                pending.name.len(),
+                ParserErrorContent::RuleTooLong {
+                    max_len: limits.max_rule_len,
+                },
                 None,
             ));
         }
+    }
 
-        // Get the column the rule's content starts at
-        let mut start_column = rule_name_length + rule_op_spaces_sep + 1;
+    // Parse the rules' content, expanding the calls to parametric rules
+    let (templates, declared_rules): (Vec<_>, Vec<_>) = declared_rules
+        .into_iter()
+        .partition(|rule| !rule.params.is_empty());
 
-        // Left-trim the content
-        let (line, trimmed_2) = trim_start_and_count(&line[start_column..]);
-        start_column += trimmed + trimmed_2;
+    let parametric = ParametricRules::new(templates.clone(), limits, input.len());
 
-        // Parse and save the new rule
-        rules.insert(
-            rule_name,
-            Rule {
-                name: rule_name,
-                decl_loc: ParserLoc::new(l, trimmed),
-                pattern: parse_rule_pattern(line, ParserLoc::new(l, start_column))?,
-            },
-        );
+    // Parametric rules called by each rule, to know which ones stay called when a rule is parsed again
+    let mut parametric_calls = HashMap::new();
+
+    for pending in declared_rules {
+        let (name, rule) = pending.finalize(&parametric)?;
+        parametric_calls.insert(name, parametric.take_called());
+        rules.insert(name, rule);
     }
 
+    parametric.ensure_all_called(parametric_calls.values())?;
+
     // Ensure all multi-line comments have been closed
     if let Some((line, col)) = multi_line_comment_opened {
         return Err(ParserError::new(
@@ -182,201 +340,1889 @@ pub fn parse_peg_nocheck(input: &str) -> Result<PegSyntaxTree, ParserError> {
         ));
     }
 
+    // Insert calls to the whitespace rule, if any
+    let rules = directives.apply(rules)?;
+
     // Success!
-    Ok(PegSyntaxTree { rules })
+    Ok(PegSyntaxTree {
+        rules,
+        source: input,
+        version_req: directives.version_req,
+        token_rules: directives.token_rules(),
+        keywords: directives.keywords(),
+        reparse_info: ReparseInfo {
+            directives,
+            parametric_rules: templates,
+            parametric_calls,
+            limits: *limits,
+        },
+    })
 }
 
-/// Parse a rule's content (e.g. `<content>` in `rule = <content>`)
-pub fn parse_rule_pattern(input: &str, base_loc: ParserLoc) -> Result<Pattern, ParserError> {
-    // This function is not supposed to be called with an empty content, so we can directly parse the first pattern of the rule
-    let (first_pattern, pattern_len, stopped_because_of) = parse_sub_pattern(input, base_loc)?;
+/// Header of a rule's declaration, which is everything up to its content (e.g. `rule(param) = ` in `rule(param) = "a" param`)
+#[derive(Debug, Clone)]
+pub struct RuleHeader<'a> {
+    name: &'a str,
+    name_span: ParserSpan,
+    params: singles::LocatedSlices<'a>,
+    op_loc: ParserLoc,
+    content: &'a str,
+    content_loc: ParserLoc,
+}
 
-    // Remove the first pattern's content from the remaining input
-    let input = &input[pattern_len..];
+impl<'a> RuleHeader<'a> {
+    /// Get the rule's name
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
 
-    // Make a global pattern
-    // This will contain everything the rule's content is made of
-    // The data is built depending on the reason why the pattern parser stopped previously
-    match stopped_because_of {
-        // If the parser stopped because it got to the end of the line, return the pattern as it is
-        PatternParserStoppedBecauseOf::End => Ok(first_pattern),
+    /// Get the span of the rule's name
+    pub fn name_span(&self) -> ParserSpan {
+        self.name_span
+    }
 
-        // If the parser stopped because of a continuation separator (whitespace) or an union separator (|),
-        // all items of the follow/union should be collected at once
-        PatternParserStoppedBecauseOf::ContinuationSep
-        | PatternParserStoppedBecauseOf::UnionSep => parse_pattern_suite_or_union(
-            input,
-            base_loc,
-            first_pattern,
-            pattern_len,
-            stopped_because_of,
-        ),
+    /// Get the parameters of the rule with the location of their name, which are empty if the rule isn't parametric
+    pub fn params(&self) -> &[(&'a str, ParserLoc)] {
+        &self.params
     }
-}
 
-/// Parse a pattern's suite or union
-pub fn parse_pattern_suite_or_union<'a>(
-    input: &'a str,
-    base_loc: ParserLoc,
-    first_pattern: Pattern<'a>,
-    first_pattern_consumed: usize,
-    stopped_at: PatternParserStoppedBecauseOf,
-) -> Result<Pattern<'a>, ParserError> {
-    // The `patterns` variable contains the parsed patterns
-    // The `unions` variable contains each member of the pending union. If the whole rule's content is not an union, this will remain empty.
-    // When an union separator is detected, the content of `patterns` is moved to `unions` in order to separate each member of the union.
-    let (mut patterns, mut unions) = if stopped_at == PatternParserStoppedBecauseOf::UnionSep {
-        (vec![], vec![create_union_child(vec![first_pattern])])
-    } else {
-        (vec![first_pattern], vec![])
-    };
+    /// Get the rule's content on the header's line, which may be empty or only made of a comment if the content is
+    /// provided on the next lines
+    pub fn content(&self) -> &'a str {
+        self.content
+    }
 
-    // Local modifyable input
-    let mut input = input;
+    /// Get the location the rule's content starts at, after the assignment operator and the whitespaces following it
+    pub fn content_loc(&self) -> ParserLoc {
+        self.content_loc
+    }
+}
 
-    // Local modifyable location
-    let mut pattern_loc = base_loc.with_add_cols(first_pattern_consumed);
+/// Parse the header of a rule's declaration (syntax: `rule = <content>` or `rule(param1, param2) = <content>`),
+/// without parsing its content
+///
+/// This allows tools (e.g. formatters or editors) to find the rules declared in a grammar without compiling it.
+/// The provided line may start with whitespaces, but lines which don't declare a rule (e.g. empty lines, comments,
+/// directives and union continuation lines) must be skipped beforehand as they are rejected.
+///
+/// As the line isn't part of a grammar, all locations (including the errors') are on the first line.
+pub fn parse_rule_header(line: &str) -> Result<RuleHeader<'_>, ParserError> {
+    parse_rule_header_at(line, ParserLoc::new(0, 0))
+}
 
-    loop {
-        // Parse the next pattern
-        let (next_pattern, next_pattern_len, next_stopped_because_of) =
-            parse_sub_pattern(input, pattern_loc)?;
+/// Parse the header of a rule's declaration, from a line starting at the provided location
+fn parse_rule_header_at(line: &str, base_loc: ParserLoc) -> Result<RuleHeader<'_>, ParserError> {
+    let (line, trimmed) = trim_start_and_count(line);
+    let base_loc = base_loc.with_add_cols(trimmed);
 
-        // Push it to the list of pending patterns
-        patterns.push(next_pattern);
+    let mut chars = line.chars();
 
-        // Remove it from the remaining input
-        input = &input[next_pattern_len..];
+    // Get the first character of the line...
+    let c = chars.next().ok_or_else(|| {
+        ParserError::new(
+            base_loc,
+            0,
+            ParserErrorContent::ExpectedRuleDeclaration,
+            None,
+        )
+    })?;
 
-        // Trim the remaining input
-        let trimmed = count_start_whitespaces(input);
-        input = &input[trimmed..];
+    // ...which must be a rule's name (syntax: `rule = <content>`)
+    if !c.is_alphabetic() {
+        return Err(ParserError::new(
+            base_loc,
+            1,
+            if c.is_ascii_digit() {
+                ParserErrorContent::RuleNameStartsWithDigit
+            } else {
+                ParserErrorContent::RuleNameStartsWithSymbol(c)
+            },
+            None,
+        ));
+    }
 
-        // Update the column number
-        pattern_loc.add_cols(next_pattern_len + trimmed);
+    // Length of the rule's name (in bytes, starting with the already-checked first character)
+    let rule_name_length = c.len_utf8()
+        + chars
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .map(char::len_utf8)
+            .sum::<usize>();
 
-        // Check the reason why the parser stopped here
-        match next_stopped_because_of {
-            // If it stopped because it was the end of the input, it's time to return the whole collected data
-            PatternParserStoppedBecauseOf::End => {
-                break Ok(Pattern {
-                    loc: base_loc,
-                    decl_length: pattern_loc.col - base_loc.col + 1,
-                    repetition: None,
-                    mode: None,
-                    // If the parser stopped on the first pattern because it encountered an union separator, the remaining content
-                    // should be put inside an union.
-                    // Otherwise, and if no union separator was found during the parsing on the whole rule's content,
-                    // a simple suite can be made from the patterns.
-                    value: if stopped_at != PatternParserStoppedBecauseOf::UnionSep
-                        && unions.is_empty()
-                    {
-                        // Avoid making a whole suite wrapper for a single pattern
-                        if patterns.len() == 1 {
-                            break Ok(patterns.into_iter().next().unwrap());
-                        } else {
-                            RulePatternValue::Suite(patterns)
-                        }
-                    } else {
-                        // Otherwise, terminate the union
-                        if !patterns.is_empty() {
-                            unions.push(create_union_child(patterns));
-                        }
+    // Offset of the next character to parse in the line
+    let mut offset = rule_name_length + count_start_whitespaces(&line[rule_name_length..]);
 
-                        assert!(unions.len() >= 2);
+    // Parameters of the rule (syntax: `rule(param1, param2) = <content>`)
+    let mut params = vec![];
 
-                        RulePatternValue::Union(unions)
-                    },
-                });
-            }
+    if line[offset..].starts_with('(') {
+        let (parsed, len) = parse_rule_params(&line[offset..], base_loc.with_add_cols(offset))?;
 
-            // If a continuation separator (whitespace) was encountered, just go to the next pattern (= do nothing for now)
-            PatternParserStoppedBecauseOf::ContinuationSep => {}
+        params = parsed;
+        offset += len;
+        offset += count_start_whitespaces(&line[offset..]);
+    }
 
-            // If an union separator (|) was encountered...
-            PatternParserStoppedBecauseOf::UnionSep => {
-                // The whole pattern is now considered as an union, given that unions have precedence over everything else
+    // Assignment operator (indicates the beginning of the rule's content)
+    match line[offset..].chars().next() {
+        Some('=') => {}
 
-                // Put the current patterns in the union's members list
-                unions.push(create_union_child(patterns));
+        // Other characters right after the name (= non-compliant)
+        Some(c) if offset == rule_name_length => {
+            return Err(ParserError::new(
+                base_loc.with_add_cols(offset),
+                1,
+                ParserErrorContent::IllegalCharInRuleName(c),
+                None,
+            ));
+        }
 
-                // Prepare for the next union's member (if any)
-                patterns = vec![];
-            }
+        // End of line or other characters without an assignment operator
+        _ => {
+            return Err(ParserError::new(
+                base_loc.with_add_cols(offset),
+                1,
+                ParserErrorContent::MissingAssignmentOp,
+                Some("you may have forgot to add the rule assignment operator '='"),
+            ));
         }
     }
-}
 
-/// Parse a sub-pattern
-/// The success return value is made of the parsed pattern, the consumed input length, and the reason why the parser stopped at this specific symbol
-pub fn parse_sub_pattern(
-    input: &str,
-    mut base_loc: ParserLoc,
-) -> Result<(Pattern, usize, PatternParserStoppedBecauseOf), ParserError> {
-    // Left-trim the input
-    let (input, trimmed) = trim_start_and_count(input);
-    base_loc.add_cols(trimmed);
+    // Collect the rule's name
+    let name = &line[..rule_name_length];
 
-    // Parse the first piece (note that the entire pattern may be made of a single one)
-    let (first_pattern, first_pattern_len) = parse_pattern_piece(input, base_loc)?;
+    // Detect reserved rule names
+    check_rule_name(name, base_loc)?;
 
-    // Remove it from the remaining input
-    let input = &input[first_pattern_len..];
+    // Left-trim the content, which starts right after the assignment operator
+    let (content, content_trimmed) = trim_start_and_count(&line[offset + 1..]);
 
-    // If the remaining input is empty, the first piece was the whole pattern's content
-    if is_finished_line(input) {
-        return Ok((
-            first_pattern,
-            first_pattern_len + trimmed,
-            PatternParserStoppedBecauseOf::End,
-        ));
-    }
+    Ok(RuleHeader {
+        name,
+        name_span: ParserSpan::from_length(base_loc, rule_name_length),
+        params,
+        op_loc: base_loc.with_add_cols(offset),
+        content,
+        content_loc: base_loc.with_add_cols(offset + 1 + content_trimmed),
+    })
+}
 
-    // Get the first character's following the first piece
-    let next_char = input.chars().next().unwrap();
+/// Parts of a grammar which aren't in its syntax tree, but are required to parse one of its rules again
+/// (see [`PegSyntaxTree::reparse_rule`])
+#[derive(Debug, Clone)]
+pub(super) struct ReparseInfo<'a> {
+    /// Directives declared in the grammar
+    directives: Directives<'a>,
 
-    let (remaining, add_trimmed) = trim_start_and_count(input);
-    let is_union_sep = remaining.starts_with('|');
+    /// Declared parametric rules
+    parametric_rules: Vec<PendingRule<'a>>,
 
-    // If we find an union separator or a whitespace, we can stop here
-    // The parent will be in charge of continuing the processing
-    if is_union_sep || next_char.is_whitespace() {
-        Ok((
-            first_pattern,
-            trimmed + first_pattern_len + add_trimmed + if is_union_sep { 1 } else { 0 },
-            if is_union_sep {
-                PatternParserStoppedBecauseOf::UnionSep
-            } else {
-                PatternParserStoppedBecauseOf::ContinuationSep
-            },
-        ))
-    }
-    // Otherwise (if we find an unexpected character)...
-    else {
-        // That's an error, as the content should not end right now.
-        Err(ParserError::new(
-            base_loc.with_add_cols(first_pattern_len),
-            1,
-            ParserErrorContent::ExpectedPatternSeparatorOrEndOfLine(next_char),
-            Some("adding another pattern to the suite requires a whitespace, or a vertical bar (|) for an union")
-        ))
-    }
+    /// Parametric rules called by each rule (directly or through other parametric rules)
+    parametric_calls: HashMap<&'a str, HashSet<&'a str>>,
+
+    /// Limits the grammar was parsed with, which also apply to the rules parsed again
+    limits: ParseLimits,
 }
 
-/// Parse a rule's piece, which means a single value
-///
-/// This function's success return value is the parsed piece and the consumed input length
-fn parse_pattern_piece(
-    input: &str,
-    mut base_loc: ParserLoc,
-) -> Result<(Pattern, usize), ParserError> {
-    let (input, trimmed, mode) = if let Some(input) = input.strip_prefix("°") {
-        (input, 2, Some(PatternMode::Silent))
-    } else if let Some(input) = input.strip_prefix("@") {
-        (input, 1, Some(PatternMode::Atomic))
-    } else if let Some(input) = input.strip_prefix("!") {
-        (input, 1, Some(PatternMode::Negative))
-    } else if let Some(input) = input.strip_prefix("~") {
+impl<'a> PegSyntaxTree<'a> {
+    /// Replace the content of a rule by parsing it again, then validate it along with the rules depending on it
+    ///
+    /// The new content is what follows the rule's assignment operator, and may be spread across multiple lines like
+    /// in a grammar (each continuation line starting with `|`). This is much cheaper than compiling the whole grammar
+    /// again, e.g. for editors updating the syntax tree as a rule is being edited. Directives and calls to parametric
+    /// rules are handled the same way as in the grammar, and the rules whose usage changed are checked again.
+    ///
+    /// The locations of the new patterns (and of the errors they cause) are mapped to the edited grammar using the
+    /// provided [origin](`SourceOrigin`), which is where `new_content` starts (e.g. right after the rule's assignment
+    /// operator). The syntax tree must have been validated beforehand, as only the rules affected by the change are
+    /// validated again, following the [limits](`ParseLimits`) the grammar was parsed with.
+    ///
+    /// Returns the warnings of the rules affected by the change, which replace their previous ones.
+    /// On error, the syntax tree is left unchanged.
+    pub fn reparse_rule(
+        &mut self,
+        name: &str,
+        new_content: &'a str,
+        origin: &SourceOrigin,
+    ) -> Result<Vec<ParserWarning>, ParserError> {
+        let name = match self.rules.get(name) {
+            Some(rule) => rule.name,
+            None => {
+                return Err(ParserError::new(
+                    ParserLoc::new(0, 0),
+                    0,
+                    ParserErrorContent::UnknownRule,
+                    Some("only declared rules (excluding parametric ones) can be parsed again"),
+                ))
+            }
+        };
+
+        let limits = self.reparse_info.limits;
+
+        let mut pending = PendingRule {
+            name,
+            params: vec![],
+            decl_loc: self.rules[name].decl_loc,
+            // The new content doesn't include the assignment operator
+            op_loc: ParserLoc::new(0, 0),
+            lines: vec![],
+            precedence: None,
+        };
+
+        for (l, line) in new_content.lines().enumerate() {
+            let (line, trimmed) = trim_start_and_count(line);
+
+            if is_finished_line(line) {
+                continue;
+            }
+
+            if l > 0 && !line.starts_with('|') {
+                return Err(ParserError::new(
+                    ParserLoc::new(l, trimmed),
+                    1,
+                    ParserErrorContent::ExpectedUnionContinuation,
+                    Some("each line of a rule's content after the first one must start with '|'"),
+                )
+                .relocated(origin));
+            }
+
+            let (content, content_col) = strip_leading_union_sep(line, ParserLoc::new(l, trimmed))
+                .map_err(|err| err.relocated(origin))?;
+
+            pending
+                .lines
+                .push((content, ParserLoc::new(l, content_col)));
+        }
+
+        if pending.content_len() > limits.max_rule_len {
+            return Err(ParserError::new(
+                ParserLoc::new(0, 0),
+                0,
+                ParserErrorContent::RuleTooLong {
+                    max_len: limits.max_rule_len,
+                },
+                None,
+            )
+            .relocated(origin));
+        }
+
+        let info = &self.reparse_info;
+
+        let parametric = ParametricRules::new(
+            info.parametric_rules.clone(),
+            &limits,
+            self.source.len() + new_content.len(),
+        );
+
+        let pattern = pending
+            .parse_pattern(&parametric)
+            .map_err(|err| err.relocated(origin))?
+            .relocated(origin);
+
+        let called = parametric.take_called();
+
+        parametric.ensure_all_called(
+            info.parametric_calls
+                .iter()
+                .filter(|(caller, _)| **caller != name)
+                .map(|(_, calls)| calls)
+                .chain(std::iter::once(&called)),
+        )?;
+
+        // Rules whose pattern is replaced, along with their previous pattern to restore it on error
+        let mut replaced = vec![];
+
+        let whitespace = info.directives.whitespace_insertion(&self.rules);
+        let previous = std::mem::replace(&mut self.rules.get_mut(name).unwrap().pattern, pattern);
+        replaced.push((name, previous));
+
+        if let Some(whitespace) = whitespace {
+            // Rules used by the whitespace rule may change, as well as whether it can match an empty input
+            let updated = info.directives.whitespace_insertion(&self.rules).unwrap();
+
+            let names: Vec<_> = self.rules.keys().copied().collect();
+
+            for rule_name in names {
+                let was_untouched = whitespace.untouched.contains(rule_name);
+                let is_untouched = updated.untouched.contains(rule_name);
+
+                let rebuild = if rule_name == name {
+                    !is_untouched
+                } else {
+                    was_untouched != is_untouched
+                        || (!is_untouched && whitespace.repetition != updated.repetition)
+                };
+
+                if !rebuild {
+                    continue;
+                }
+
+                let rule = self.rules.get_mut(rule_name).unwrap();
+                let mut pattern = rule.pattern.clone();
+
+                if rule_name != name && !was_untouched {
+                    pattern = remove_rule_whitespace(rule_name, pattern, whitespace.rule);
+                }
+
+                if !is_untouched {
+                    pattern = insert_rule_whitespace(
+                        rule_name,
+                        pattern,
+                        updated.rule,
+                        updated.repetition,
+                    );
+                }
+
+                let previous = std::mem::replace(&mut rule.pattern, pattern);
+
+                if rule_name != name {
+                    replaced.push((rule_name, previous));
+                }
+            }
+        }
+
+        let mut affected = HashSet::new();
+
+        for (rule_name, _) in &replaced {
+            affected.insert(*rule_name);
+            affected.extend(super::analysis::dependents(self, rule_name));
+        }
+
+        let result = self
+            .reparse_info
+            .directives
+            .check_keywords_used(&self.rules)
+            .and_then(|()| validate_affected_rules(self, &limits, &affected).into_result());
+
+        match result {
+            Ok(warnings) => {
+                self.reparse_info.parametric_calls.insert(name, called);
+                Ok(warnings)
+            }
+            Err(err) => {
+                for (rule_name, previous) in replaced {
+                    self.rules.get_mut(rule_name).unwrap().pattern = previous;
+                }
+
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Directives declared in a grammar
+///
+/// ```text
+/// @whitespace ws
+/// @no_whitespace string
+/// @peggy 0.7
+/// @token ident number ws
+/// @keywords if else while
+/// ```
+#[derive(Debug, Clone, Default)]
+struct Directives<'a> {
+    /// Requirement on Peggy's version (`@peggy <requirement>`), and its location
+    version_req: Option<(&'a str, ParserLoc)>,
+
+    /// Rule matching skippable trivia (`@whitespace <rule>`), and the location of its name
+    whitespace: Option<(&'a str, ParserLoc)>,
+
+    /// Rules opting out of whitespace skipping (`@no_whitespace <rules>`), and the location of their name
+    no_whitespace: Vec<(&'a str, ParserLoc)>,
+
+    /// Rules matching the tokens of the generated tokenizer (`@token <rules>`), and the location of their name
+    tokens: Vec<(&'a str, ParserLoc)>,
+
+    /// Constant strings only matching at a word boundary (`@keywords <strings>`), and their location
+    keywords: Vec<(&'a str, ParserLoc)>,
+}
+
+impl<'a> Directives<'a> {
+    /// Get the names of the token rules, in the order they were first declared in
+    fn token_rules(&self) -> Vec<&'a str> {
+        let mut token_rules = vec![];
+
+        for (name, _) in &self.tokens {
+            if !token_rules.contains(name) {
+                token_rules.push(*name);
+            }
+        }
+
+        token_rules
+    }
+
+    /// Get the keywords, in the order they were first declared in
+    fn keywords(&self) -> Vec<&'a str> {
+        let mut keywords = vec![];
+
+        for (keyword, _) in &self.keywords {
+            if !keywords.contains(keyword) {
+                keywords.push(*keyword);
+            }
+        }
+
+        keywords
+    }
+
+    /// Apply the directives to the parsed rules
+    ///
+    /// A silent call to the whitespace rule is inserted between each pair of consecutive members of all suites,
+    /// between the iterations of repetitions and around the `main` rule's content, repeated unless the whitespace
+    /// rule may match an empty input.
+    ///
+    /// Atomic patterns, rules which opted out and rules used by the whitespace rule itself are left untouched.
+    fn apply(&self, rules: Rules<'a>) -> Result<Rules<'a>, ParserError> {
+        for (name, loc) in &self.no_whitespace {
+            if !rules.contains_key(name) {
+                return Err(ParserError::new(
+                    *loc,
+                    name.len(),
+                    ParserErrorContent::UnknownRule,
+                    Some("only declared rules can opt out of whitespace skipping"),
+                ));
+            }
+        }
+
+        for (name, loc) in &self.tokens {
+            if !rules.contains_key(name) {
+                return Err(ParserError::new(
+                    *loc,
+                    name.len(),
+                    ParserErrorContent::UnknownRule,
+                    Some("only declared rules can be tokens"),
+                ));
+            }
+        }
+
+        self.check_keywords_used(&rules)?;
+        self.check_whitespace_rule(&rules)?;
+
+        let whitespace = match self.whitespace_insertion(&rules) {
+            Some(whitespace) => whitespace,
+            None => return Ok(rules),
+        };
+
+        Ok(rules
+            .into_iter()
+            .map(|(name, rule)| {
+                if whitespace.untouched.contains(name) {
+                    return (name, rule);
+                }
+
+                let pattern = insert_rule_whitespace(
+                    name,
+                    rule.pattern,
+                    whitespace.rule,
+                    whitespace.repetition,
+                );
+
+                (name, Rule { pattern, ..rule })
+            })
+            .collect())
+    }
+
+    /// Ensure all keywords are used as constant strings in the provided rules
+    fn check_keywords_used(&self, rules: &Rules<'a>) -> Result<(), ParserError> {
+        for (keyword, loc) in &self.keywords {
+            let mut used = false;
+
+            for rule in rules.values() {
+                rule.pattern().walk(&mut |pattern| {
+                    if let RulePatternValue::CstString(string) = pattern.value() {
+                        used |= string == keyword;
+                    }
+                });
+            }
+
+            if !used {
+                return Err(ParserError::new(
+                    *loc,
+                    keyword.len(),
+                    ParserErrorContent::InvalidDirectiveArguments,
+                    Some("keywords must be used as constant strings in the grammar"),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ensure the whitespace rule, if any, exists
+    fn check_whitespace_rule(&self, rules: &Rules<'a>) -> Result<(), ParserError> {
+        let (whitespace, loc) = match self.whitespace {
+            Some(whitespace) => whitespace,
+            None => return Ok(()),
+        };
+
+        if !rules.contains_key(whitespace)
+            && !is_external_rule_name(whitespace)
+            && !is_valid_builtin_rule_name(whitespace)
+        {
+            return Err(ParserError::new(
+                loc,
+                whitespace.len(),
+                if is_builtin_rule_name(whitespace) {
+                    ParserErrorContent::UnknownBuiltinRule
+                } else {
+                    ParserErrorContent::UnknownRule
+                },
+                None,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Get how calls to the whitespace rule are inserted in the provided rules, if there is a whitespace rule
+    ///
+    /// The rules used by the whitespace rule are expected to be left untouched, as they are in the syntax tree.
+    fn whitespace_insertion(&self, rules: &Rules<'a>) -> Option<WhitespaceInsertion<'a>> {
+        let (whitespace, _) = self.whitespace?;
+
+        let mut untouched = HashSet::new();
+        list_reachable_rules(rules, whitespace, &mut untouched);
+        untouched.extend(self.no_whitespace.iter().map(|(name, _)| *name));
+
+        // Repeating a rule which may match an empty input would loop forever
+        let repetition = if is_external_rule_name(whitespace)
+            || rules_min_length(rules).get(whitespace) == Some(&0)
+        {
+            None
+        } else {
+            Some(PatternRepetition::Any)
+        };
+
+        Some(WhitespaceInsertion {
+            rule: whitespace,
+            repetition,
+            untouched,
+        })
+    }
+}
+
+/// How calls to the whitespace rule are inserted in the rules (see [`Directives::apply`])
+struct WhitespaceInsertion<'a> {
+    /// Whitespace rule
+    rule: &'a str,
+
+    /// Repetition of the inserted calls
+    repetition: Option<PatternRepetition>,
+
+    /// Rules left untouched, which are the ones used by the whitespace rule and the ones which opted out
+    untouched: HashSet<&'a str>,
+}
+
+/// Parse a directive (syntax: `@directive <arguments>`), the provided input starting right after the '@' symbol
+///
+/// Returns the precedence block opened by a `@precedence` directive, whose levels are declared on the next lines.
+fn parse_directive<'a>(
+    input: &'a str,
+    base_loc: ParserLoc,
+    directives: &mut Directives<'a>,
+) -> Result<Option<PrecedenceBlock<'a>>, ParserError> {
+    // Ignore trailing comments
+    let input = input.split('#').next().unwrap();
+
+    let mut words = vec![];
+    let mut word_start = None;
+
+    for (i, c) in input
+        .char_indices()
+        .chain(std::iter::once((input.len(), ' ')))
+    {
+        match (c.is_whitespace(), word_start) {
+            (false, None) => word_start = Some(i),
+            (true, Some(start)) => {
+                words.push((&input[start..i], base_loc.with_add_cols(start)));
+                word_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    let (name, name_loc) = match words.first() {
+        Some(word) => *word,
+        None => return Err(ParserError::new(
+            base_loc,
+            0,
+            ParserErrorContent::UnknownDirective,
+            Some("available directives are '@whitespace', '@no_whitespace', '@token', '@keywords', '@precedence' and '@peggy'"),
+        )),
+    };
+
+    let args = &words[1..];
+
+    match name {
+        "whitespace" => {
+            if directives.whitespace.is_some() {
+                return Err(ParserError::new(
+                    name_loc,
+                    name.len(),
+                    ParserErrorContent::DuplicateDirective,
+                    Some("only a single whitespace rule can be declared"),
+                ));
+            }
+
+            match args {
+                [arg] => directives.whitespace = Some(*arg),
+                _ => {
+                    return Err(ParserError::new(
+                        name_loc,
+                        name.len(),
+                        ParserErrorContent::InvalidDirectiveArguments,
+                        Some("the '@whitespace' directive takes a single rule name"),
+                    ))
+                }
+            }
+        }
+
+        "no_whitespace" => {
+            if args.is_empty() {
+                return Err(ParserError::new(
+                    name_loc,
+                    name.len(),
+                    ParserErrorContent::InvalidDirectiveArguments,
+                    Some("the '@no_whitespace' directive takes one or more rule names"),
+                ));
+            }
+
+            directives.no_whitespace.extend(args);
+        }
+
+        "token" => {
+            if args.is_empty() {
+                return Err(ParserError::new(
+                    name_loc,
+                    name.len(),
+                    ParserErrorContent::InvalidDirectiveArguments,
+                    Some("the '@token' directive takes one or more rule names"),
+                ));
+            }
+
+            directives.tokens.extend(args);
+        }
+
+        "keywords" => {
+            if args.is_empty() {
+                return Err(ParserError::new(
+                    name_loc,
+                    name.len(),
+                    ParserErrorContent::InvalidDirectiveArguments,
+                    Some("the '@keywords' directive takes one or more keywords (e.g. '@keywords if else')"),
+                ));
+            }
+
+            directives.keywords.extend(args);
+        }
+
+        "peggy" => {
+            if directives.version_req.is_some() {
+                return Err(ParserError::new(
+                    name_loc,
+                    name.len(),
+                    ParserErrorContent::DuplicateDirective,
+                    Some("only a single version requirement can be declared"),
+                ));
+            }
+
+            // The requirement may contain spaces (e.g. `>= 0.7, < 0.9`)
+            let (req, req_loc) = match args.first() {
+                Some((_, loc)) => (input[loc.col() - base_loc.col()..].trim_end(), *loc),
+                None => return Err(ParserError::new(
+                    name_loc,
+                    name.len(),
+                    ParserErrorContent::InvalidDirectiveArguments,
+                    Some("the '@peggy' directive takes a version requirement (e.g. '@peggy 0.7')"),
+                )),
+            };
+
+            #[cfg(feature = "version-check")]
+            if semver::VersionReq::parse(req).is_err() {
+                return Err(ParserError::new(
+                    req_loc,
+                    req.len(),
+                    ParserErrorContent::InvalidDirectiveArguments,
+                    Some("the version requirement must follow Cargo's syntax (e.g. '0.7' or '>=0.7, <0.9')"),
+                ));
+            }
+
+            directives.version_req = Some((req, req_loc));
+        }
+
+        "precedence" => match args {
+            [operand] => {
+                return Ok(Some(PrecedenceBlock {
+                    name_loc,
+                    operand: *operand,
+                }))
+            }
+            _ => {
+                return Err(ParserError::new(
+                    name_loc,
+                    name.len(),
+                    ParserErrorContent::InvalidDirectiveArguments,
+                    Some("the '@precedence' directive takes the rule matching the operands (e.g. '@precedence atom')"),
+                ))
+            }
+        },
+
+        _ => return Err(ParserError::new(
+            name_loc,
+            name.len(),
+            ParserErrorContent::UnknownDirective,
+            Some("available directives are '@whitespace', '@no_whitespace', '@token', '@keywords', '@precedence' and '@peggy'"),
+        )),
+    }
+
+    Ok(None)
+}
+
+/// Precedence block (`@precedence <operand>`), whose levels are declared on the next lines from the lowest to the
+/// highest precedence, each one with its associativity, the name of its rule and its operators:
+///
+/// ```text
+/// @precedence atom
+///     | left sum "+" | "-"
+///     | left product "*" | "/"
+///     | right power "^"
+/// ```
+///
+/// Each level is turned into a rule matching its operands separated by its operators (see [`PrecedenceLevel`]),
+/// the operands of each level being matched by the next level, and the ones of the last level by the block's operand.
+#[derive(Debug, Clone, Copy)]
+struct PrecedenceBlock<'a> {
+    /// Location of the directive's name
+    name_loc: ParserLoc,
+
+    /// Rule matching the operands of the last level, and the location of its name
+    operand: (&'a str, ParserLoc),
+}
+
+impl PrecedenceBlock<'_> {
+    /// Get the error for a block which isn't followed by any level
+    fn missing_levels_error(&self) -> ParserError {
+        ParserError::new(
+            self.name_loc,
+            "precedence".len(),
+            ParserErrorContent::InvalidDirectiveArguments,
+            Some("the '@precedence' directive must be followed by its levels on the next lines, from the lowest to the highest precedence (e.g. '| left sum \"+\" | \"-\"')"),
+        )
+    }
+}
+
+/// Parse a level of a [precedence block](`PrecedenceBlock`) (syntax: `| <associativity> <rule> <operators>`),
+/// the provided line starting with the union separator
+///
+/// Returns the level's rule, whose operands are matched by the provided rule
+fn parse_precedence_level<'a>(
+    line: &'a str,
+    base_loc: ParserLoc,
+    operand: (&'a str, ParserLoc),
+) -> Result<PendingRule<'a>, ParserError> {
+    let invalid_level = |loc: ParserLoc, len: usize| {
+        ParserError::new(
+            loc,
+            len,
+            ParserErrorContent::InvalidDirectiveArguments,
+            Some("precedence levels are made of their associativity ('left' or 'right'), the name of their rule and their operators (e.g. '| left sum \"+\" | \"-\"')"),
+        )
+    };
+
+    let (rest, trimmed) = trim_start_and_count(&line[1..]);
+    let mut offset = 1 + trimmed;
+
+    let associativity_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+
+    let associativity = match &rest[..associativity_len] {
+        "left" => Associativity::Left,
+        "right" => Associativity::Right,
+        _ => {
+            return Err(invalid_level(
+                base_loc.with_add_cols(offset),
+                associativity_len,
+            ))
+        }
+    };
+
+    offset += associativity_len;
+    offset += count_start_whitespaces(&line[offset..]);
+
+    let name_len = line[offset..]
+        .find(|c: char| !c.is_alphanumeric() && c != '_')
+        .unwrap_or(line.len() - offset);
+
+    let name = &line[offset..offset + name_len];
+    let name_loc = base_loc.with_add_cols(offset);
+
+    check_rule_name(name, name_loc)?;
+
+    offset += name_len;
+
+    let (operators, trimmed) = trim_start_and_count(&line[offset..]);
+
+    if trimmed == 0 || is_finished_line(operators) {
+        return Err(invalid_level(name_loc, name_len));
+    }
+
+    Ok(PendingRule {
+        name,
+        params: vec![],
+        decl_loc: name_loc,
+        op_loc: name_loc,
+        lines: vec![(operators, base_loc.with_add_cols(offset + trimmed))],
+        precedence: Some(PrecedenceLevel {
+            associativity,
+            operand,
+        }),
+    })
+}
+
+/// Check if a pending rule is a level of a [precedence block](`PrecedenceBlock`)
+fn is_precedence_level(pending: &Option<PendingRule>) -> bool {
+    matches!(
+        pending,
+        Some(PendingRule {
+            precedence: Some(_),
+            ..
+        })
+    )
+}
+
+/// List the declared rules a rule uses (directly or indirectly), including itself
+///
+/// Unknown rules are ignored, as the syntax tree wasn't validated yet.
+fn list_reachable_rules<'a>(rules: &Rules<'a>, name: &'a str, reachable: &mut HashSet<&'a str>) {
+    if let Some(rule) = rules.get(name) {
+        if reachable.insert(name) {
+            rule.pattern().walk(&mut |pattern| {
+                if let RulePatternValue::Rule(name) = pattern.value() {
+                    list_reachable_rules(rules, name, reachable);
+                }
+            });
+        }
+    }
+}
+
+/// Insert calls to the whitespace rule in a rule's pattern (see [`insert_whitespace`])
+///
+/// Whitespaces are also allowed before and after the content of the `main` rule, which is turned into
+/// a suite (e.g. `main = stmt*` behaves like `main = °ws* (stmt*) °ws*`).
+fn insert_rule_whitespace<'a>(
+    name: &str,
+    pattern: Pattern<'a>,
+    whitespace: &'a str,
+    repetition: Option<PatternRepetition>,
+) -> Pattern<'a> {
+    let pattern = insert_whitespace(pattern, whitespace, repetition);
+
+    if name != GRAMMAR_ENTRYPOINT_RULE {
+        return pattern;
+    }
+
+    let (loc, decl_length, end) = (pattern.loc, pattern.decl_length, pattern.span().end());
+
+    Pattern {
+        loc,
+        decl_length,
+        mode: None,
+        repetition: None,
+        value: RulePatternValue::Suite(vec![
+            whitespace_call(loc, whitespace, repetition),
+            Pattern {
+                loc,
+                decl_length,
+                mode: None,
+                repetition: None,
+                value: RulePatternValue::Group(Rc::new(pattern)),
+            },
+            whitespace_call(end, whitespace, repetition),
+        ]),
+    }
+}
+
+/// Remove the calls to the whitespace rule inserted by [`insert_rule_whitespace`] from a rule's pattern
+fn remove_rule_whitespace<'a>(
+    name: &str,
+    pattern: Pattern<'a>,
+    whitespace: &'a str,
+) -> Pattern<'a> {
+    let pattern = match (name, pattern.value) {
+        (GRAMMAR_ENTRYPOINT_RULE, RulePatternValue::Suite(mut members))
+            if members.len() == 3 && is_whitespace_call(&members[0], whitespace) =>
+        {
+            match members.swap_remove(1).value {
+                RulePatternValue::Group(inner) => Rc::unwrap_or_clone(inner),
+                _ => unreachable!(),
+            }
+        }
+        (_, value) => Pattern { value, ..pattern },
+    };
+
+    remove_whitespace(pattern, whitespace)
+}
+
+/// Create a silent call to the whitespace rule, inserted at the provided location with an empty length
+fn whitespace_call<'a>(
+    loc: ParserLoc,
+    whitespace: &'a str,
+    repetition: Option<PatternRepetition>,
+) -> Pattern<'a> {
+    Pattern {
+        loc,
+        decl_length: 0,
+        mode: Some(PatternMode::Silent),
+        repetition,
+        value: RulePatternValue::Rule(whitespace),
+    }
+}
+
+/// Check if a pattern is a call to the whitespace rule created by [`whitespace_call`]
+///
+/// Inserted calls are recognized by their empty length, as patterns written in the grammar can't be empty.
+fn is_whitespace_call(pattern: &Pattern, whitespace: &str) -> bool {
+    pattern.decl_length == 0
+        && matches!(pattern.value, RulePatternValue::Rule(name) if name == whitespace)
+}
+
+/// Insert a silent call to the whitespace rule between each pair of consecutive members of the pattern's suites,
+/// and between the iterations of its `*` and `+` repetitions
+///
+/// The inserted patterns are located at the member they precede, with an empty length. Repetitions are turned into
+/// a repetition of a group prefixing each iteration with the call (e.g. `stmt*` behaves like `(°ws* stmt)*`),
+/// except for repetitions of the whitespace rule itself.
+fn insert_whitespace<'a>(
+    pattern: Pattern<'a>,
+    whitespace: &'a str,
+    repetition: Option<PatternRepetition>,
+) -> Pattern<'a> {
+    if pattern.is_atomic() {
+        return pattern;
+    }
+
+    let unwrap = |inner: Rc<Pattern<'a>>| {
+        Rc::new(insert_whitespace(
+            Rc::unwrap_or_clone(inner),
+            whitespace,
+            repetition,
+        ))
+    };
+
+    let value = match pattern.value {
+        value @ RulePatternValue::CstString(_)
+        | value @ RulePatternValue::Rule(_)
+        | value @ RulePatternValue::Cut => value,
+        RulePatternValue::Group(inner) => RulePatternValue::Group(unwrap(inner)),
+        RulePatternValue::Until(inner) => RulePatternValue::Until(unwrap(inner)),
+        RulePatternValue::Union(patterns) => RulePatternValue::Union(
+            patterns
+                .into_iter()
+                .map(|pattern| insert_whitespace(pattern, whitespace, repetition))
+                .collect(),
+        ),
+        RulePatternValue::Suite(patterns) => {
+            let mut members = vec![];
+            let mut after_cut = false;
+
+            for (i, pattern) in patterns.into_iter().enumerate() {
+                // Whitespaces are already allowed before a cut, which doesn't consume anything
+                if i > 0 && !after_cut {
+                    members.push(whitespace_call(pattern.loc, whitespace, repetition));
+                }
+
+                after_cut = matches!(pattern.value, RulePatternValue::Cut);
+                members.push(insert_whitespace(pattern, whitespace, repetition));
+            }
+
+            RulePatternValue::Suite(members)
+        }
+    };
+
+    let pattern = Pattern { value, ..pattern };
+
+    match pattern.repetition {
+        Some(PatternRepetition::Any) | Some(PatternRepetition::OneOrMore) if !matches!(pattern.value, RulePatternValue::Rule(name) if name == whitespace) =>
+        {
+            let (loc, decl_length) = (pattern.loc, pattern.decl_length);
+
+            Pattern {
+                loc,
+                decl_length,
+                mode: pattern.mode,
+                repetition: pattern.repetition,
+                value: RulePatternValue::Group(Rc::new(Pattern {
+                    loc,
+                    decl_length,
+                    mode: None,
+                    repetition: None,
+                    value: RulePatternValue::Suite(vec![
+                        whitespace_call(loc, whitespace, repetition),
+                        Pattern {
+                            mode: None,
+                            repetition: None,
+                            ..pattern
+                        },
+                    ]),
+                })),
+            }
+        }
+        _ => pattern,
+    }
+}
+
+/// Remove the calls to the whitespace rule inserted by [`insert_whitespace`] from the pattern's suites and repetitions
+fn remove_whitespace<'a>(pattern: Pattern<'a>, whitespace: &'a str) -> Pattern<'a> {
+    if pattern.is_atomic() {
+        return pattern;
+    }
+
+    // Repetitions were turned into a group prefixing each iteration with a call to the whitespace rule,
+    // which is the only way to get a suite starting with such a call
+    let pattern = match pattern.value {
+        RulePatternValue::Group(inner) => match &inner.value {
+            RulePatternValue::Suite(members)
+                if members.len() == 2 && is_whitespace_call(&members[0], whitespace) =>
+            {
+                Pattern {
+                    mode: pattern.mode,
+                    repetition: pattern.repetition,
+                    ..members[1].clone()
+                }
+            }
+            _ => Pattern {
+                value: RulePatternValue::Group(inner),
+                ..pattern
+            },
+        },
+        value => Pattern { value, ..pattern },
+    };
+
+    let unwrap =
+        |inner: Rc<Pattern<'a>>| Rc::new(remove_whitespace(Rc::unwrap_or_clone(inner), whitespace));
+
+    let value = match pattern.value {
+        value @ RulePatternValue::CstString(_)
+        | value @ RulePatternValue::Rule(_)
+        | value @ RulePatternValue::Cut => value,
+        RulePatternValue::Group(inner) => RulePatternValue::Group(unwrap(inner)),
+        RulePatternValue::Until(inner) => RulePatternValue::Until(unwrap(inner)),
+        RulePatternValue::Union(patterns) => RulePatternValue::Union(
+            patterns
+                .into_iter()
+                .map(|pattern| remove_whitespace(pattern, whitespace))
+                .collect(),
+        ),
+        RulePatternValue::Suite(patterns) => RulePatternValue::Suite(
+            patterns
+                .into_iter()
+                .filter(|pattern| !is_whitespace_call(pattern, whitespace))
+                .map(|pattern| remove_whitespace(pattern, whitespace))
+                .collect(),
+        ),
+    };
+
+    Pattern { value, ..pattern }
+}
+
+/// A rule whose content is still being parsed, as it may be spread across multiple lines
+///
+/// Each continuation line starts with an union separator (`|`) and adds new members to the rule's union:
+///
+/// ```text
+/// value =
+///     | "true"
+///     | "false"
+/// ```
+#[derive(Debug, Clone)]
+struct PendingRule<'a> {
+    /// Rule's name
+    name: &'a str,
+
+    /// Rule's parameters, and the location of their name (empty if the rule isn't parametric)
+    params: Vec<(&'a str, ParserLoc)>,
+
+    /// Declaration location
+    decl_loc: ParserLoc,
+
+    /// Location of the assignment operator
+    op_loc: ParserLoc,
+
+    /// Unparsed content of each of the rule's lines, and the location it starts at
+    lines: Vec<(&'a str, ParserLoc)>,
+
+    /// Level of a precedence block the rule was declared by, whose only line is made of the level's operators
+    precedence: Option<PrecedenceLevel<'a>>,
+}
+
+/// Level of a [precedence block](`PrecedenceBlock`)
+///
+/// Left-associative levels match their operands separated by their operators (`sum = product (("+" | "-") product)*`),
+/// while right-associative ones match their right-hand side recursively (`power = atom ("^" power)?`), so the
+/// nesting of their data follows their associativity.
+#[derive(Debug, Clone, Copy)]
+struct PrecedenceLevel<'a> {
+    /// Associativity of the level's operators
+    associativity: Associativity,
+
+    /// Rule matching the level's operands, and the location of its name
+    operand: (&'a str, ParserLoc),
+}
+
+/// Associativity of the operators of a [precedence level](`PrecedenceLevel`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Associativity {
+    Left,
+    Right,
+}
+
+impl<'a> PendingRule<'a> {
+    /// Get the length of the rule's content, in bytes
+    fn content_len(&self) -> usize {
+        self.lines.iter().map(|(content, _)| content.len()).sum()
+    }
+
+    /// Parse the rule's content from all of its lines
+    fn parse_pattern(&self, parametric: &ParametricRules<'a>) -> Result<Pattern<'a>, ParserError> {
+        let mut lines = self
+            .lines
+            .iter()
+            .map(|(content, loc)| parse_rule_pattern_with(content, *loc, parametric))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let pattern = match lines.len() {
+            0 => {
+                return Err(ParserError::new(
+                    self.op_loc,
+                    1,
+                    ParserErrorContent::EmptyRuleBody,
+                    Some("you need to provide a rule pattern, either after the assignment operator or on the next lines prefixed by '|'"),
+                ))
+            }
+
+            1 => lines.remove(0),
+
+            // Each line is a member of the rule's union (or several members if the line is an union itself)
+            _ => {
+                let loc = lines[0].loc();
+                let decl_length = lines[0].decl_length();
+
+                let mut members = vec![];
+
+                for line in lines {
+                    match line {
+                        Pattern {
+                            value: RulePatternValue::Union(line_members),
+                            mode: None,
+                            repetition: None,
+                            ..
+                        } => members.extend(line_members),
+                        line => members.push(line),
+                    }
+                }
+
+                Pattern {
+                    loc,
+                    decl_length,
+                    mode: None,
+                    repetition: None,
+                    value: RulePatternValue::Union(members),
+                }
+            }
+        };
+
+        Ok(pattern)
+    }
+
+    /// Build the rule's pattern from the operators of its [precedence level](`PrecedenceLevel`)
+    fn expand_precedence_level(
+        &self,
+        level: &PrecedenceLevel<'a>,
+        operators: Pattern<'a>,
+    ) -> Pattern<'a> {
+        let (loc, decl_length) = (operators.loc, operators.decl_length);
+
+        let pattern = |repetition, value| Pattern {
+            loc,
+            decl_length,
+            mode: None,
+            repetition,
+            value,
+        };
+
+        let rule = |(name, loc): (&'a str, ParserLoc)| Pattern {
+            loc,
+            decl_length: name.len(),
+            mode: None,
+            repetition: None,
+            value: RulePatternValue::Rule(name),
+        };
+
+        let (rhs, repetition) = match level.associativity {
+            Associativity::Left => (rule(level.operand), PatternRepetition::Any),
+            Associativity::Right => (
+                rule((self.name, self.decl_loc)),
+                PatternRepetition::Optional,
+            ),
+        };
+
+        let operators = pattern(None, RulePatternValue::Group(Rc::new(operators)));
+        let operation = pattern(None, RulePatternValue::Suite(vec![operators, rhs]));
+
+        pattern(
+            None,
+            RulePatternValue::Suite(vec![
+                rule(level.operand),
+                pattern(
+                    Some(repetition),
+                    RulePatternValue::Group(Rc::new(operation)),
+                ),
+            ]),
+        )
+    }
+
+    /// Build the final rule from all of its lines
+    fn finalize(
+        self,
+        parametric: &ParametricRules<'a>,
+    ) -> Result<(&'a str, Rule<'a>), ParserError> {
+        let mut pattern = self.parse_pattern(parametric)?;
+
+        if let Some(level) = &self.precedence {
+            pattern = self.expand_precedence_level(level, pattern);
+        }
+
+        Ok((
+            self.name,
+            Rule {
+                name: self.name,
+                decl_loc: self.decl_loc,
+                pattern,
+            },
+        ))
+    }
+}
+
+/// Parametric rules declared in a grammar (e.g. `list(elem) = elem ("," elem)*`)
+///
+/// They don't appear in the syntax tree: each call (e.g. `list(number)`) is replaced by a group
+/// containing the rule's content, where the parameters are replaced by the call's arguments.
+///
+/// As they are provided to the whole pattern parser, they also enforce the [limits](`ParseLimits`) on nesting
+/// and expansions.
+#[derive(Default)]
+pub(super) struct ParametricRules<'a> {
+    /// Declared parametric rules
+    rules: HashMap<&'a str, PendingRule<'a>>,
+
+    /// Parametric rules being expanded, used to detect recursion
+    expanding: RefCell<Vec<&'a str>>,
+
+    /// Parametric rules which have been called at least once
+    called: RefCell<HashSet<&'a str>>,
+
+    /// Limits to enforce
+    limits: ParseLimits,
+
+    /// Size of the grammar, in bytes, including the content of the parametric rules expanded so far
+    expanded_len: Cell<usize>,
+
+    /// Number of groups (and expansions) the pattern being parsed is currently nested in
+    nesting: Cell<usize>,
+}
+
+impl<'a> ParametricRules<'a> {
+    fn new(rules: Vec<PendingRule<'a>>, limits: &ParseLimits, input_len: usize) -> Self {
+        Self {
+            rules: rules.into_iter().map(|rule| (rule.name, rule)).collect(),
+            limits: *limits,
+            expanded_len: Cell::new(input_len),
+            ..Self::default()
+        }
+    }
+
+    /// Parse a nested pattern (e.g. a group's content), which starts at the provided location
+    pub(super) fn nested<T>(
+        &self,
+        loc: ParserLoc,
+        parse: impl FnOnce() -> Result<T, ParserError>,
+    ) -> Result<T, ParserError> {
+        if self.nesting.get() >= self.limits.max_nesting {
+            return Err(ParserError::new(
+                loc,
+                1,
+                ParserErrorContent::NestingTooDeep {
+                    max: self.limits.max_nesting,
+                },
+                None,
+            ));
+        }
+
+        self.nesting.set(self.nesting.get() + 1);
+        let parsed = parse();
+        self.nesting.set(self.nesting.get() - 1);
+
+        parsed
+    }
+
+    /// Expand a call to a parametric rule, located at the provided location
+    fn expand(
+        &self,
+        name: &'a str,
+        loc: ParserLoc,
+        args: Vec<Pattern<'a>>,
+    ) -> Result<Pattern<'a>, ParserError> {
+        let rule = self.rules.get(name).ok_or_else(|| {
+            ParserError::new(
+                loc,
+                name.len(),
+                ParserErrorContent::UnknownRule,
+                Some("only rules declared with parameters (e.g. 'list(elem) = ...') can be called with arguments"),
+            )
+        })?;
+
+        if rule.params.len() != args.len() {
+            return Err(ParserError::new(
+                loc,
+                name.len(),
+                ParserErrorContent::InvalidArgumentsCount {
+                    expected: rule.params.len(),
+                    found: args.len(),
+                },
+                None,
+            ));
+        }
+
+        if self.expanding.borrow().contains(&name) {
+            return Err(ParserError::new(
+                loc,
+                name.len(),
+                ParserErrorContent::RecursiveParametricRule,
+                Some("parametric rules are expanded at each call, so they can't call themselves, even indirectly"),
+            ));
+        }
+
+        // Parametric rules can't call themselves, but expanding them may still produce huge patterns
+        // (e.g. if each one calls the previous one twice), so their content counts in the grammar's size
+        let expanded_len = self.expanded_len.get().saturating_add(rule.content_len());
+
+        if expanded_len > self.limits.max_input_len {
+            return Err(ParserError::new(
+                loc,
+                name.len(),
+                ParserErrorContent::GrammarTooLarge {
+                    max_len: self.limits.max_input_len,
+                },
+                Some("the content of parametric rules counts in the grammar's size each time they are called"),
+            ));
+        }
+
+        self.expanded_len.set(expanded_len);
+
+        self.expanding.borrow_mut().push(name);
+        let pattern = self.nested(loc, || rule.parse_pattern(self));
+        self.expanding.borrow_mut().pop();
+
+        self.called.borrow_mut().insert(name);
+
+        let args = rule
+            .params
+            .iter()
+            .map(|(param, _)| *param)
+            .zip(args)
+            .collect();
+
+        Ok(bind_params(pattern?, &args))
+    }
+
+    /// Ensure a reference without arguments, located at the provided location, doesn't refer to a parametric rule
+    ///
+    /// References to the parameters of the rule being expanded are allowed, as they are replaced by their argument.
+    fn ensure_not_parametric(&self, name: &'a str, loc: ParserLoc) -> Result<(), ParserError> {
+        let rule = match self.rules.get(name) {
+            Some(rule) => rule,
+            None => return Ok(()),
+        };
+
+        let is_param = self.expanding.borrow().last().is_some_and(|expanding| {
+            self.rules[expanding]
+                .params
+                .iter()
+                .any(|(param, _)| *param == name)
+        });
+
+        if is_param {
+            return Ok(());
+        }
+
+        Err(ParserError::new(
+            loc,
+            name.len(),
+            ParserErrorContent::InvalidArgumentsCount {
+                expected: rule.params.len(),
+                found: 0,
+            },
+            Some("parametric rules must be called with their arguments (e.g. 'list(elem)')"),
+        ))
+    }
+
+    /// Get the parametric rules called since the last call to this function
+    fn take_called(&self) -> HashSet<&'a str> {
+        std::mem::take(&mut self.called.borrow_mut())
+    }
+
+    /// Ensure all parametric rules have been called, from the parametric rules called by each rule
+    fn ensure_all_called<'c>(
+        &self,
+        calls: impl IntoIterator<Item = &'c HashSet<&'a str>>,
+    ) -> Result<(), ParserError>
+    where
+        'a: 'c,
+    {
+        let called: HashSet<_> = calls.into_iter().flatten().copied().collect();
+
+        let mut uncalled: Vec<_> = self
+            .rules
+            .values()
+            .filter(|rule| !called.contains(rule.name))
+            .collect();
+
+        uncalled.sort_by_key(|rule| rule.decl_loc);
+
+        match uncalled.first() {
+            None => Ok(()),
+            Some(rule) => Err(ParserError::new(
+                rule.decl_loc,
+                rule.name.len(),
+                ParserErrorContent::UnusedRule,
+                Some("if you are doing some testing, you can comment out the rule by starting it with the '#' symbol"),
+            )),
+        }
+    }
+}
+
+/// Replace the parameters used in a parametric rule's content by their argument
+///
+/// Parameters used with a mode or a repetition are replaced by a group containing their argument.
+fn bind_params<'a>(pattern: Pattern<'a>, args: &HashMap<&'a str, Pattern<'a>>) -> Pattern<'a> {
+    let bind = |inner: Rc<Pattern<'a>>| Rc::new(bind_params(Rc::unwrap_or_clone(inner), args));
+
+    let value = match pattern.value {
+        RulePatternValue::Rule(name) => match args.get(name) {
+            None => RulePatternValue::Rule(name),
+            Some(arg) if pattern.mode.is_none() && pattern.repetition.is_none() => {
+                return arg.clone()
+            }
+            Some(arg) => RulePatternValue::Group(Rc::new(arg.clone())),
+        },
+        value @ RulePatternValue::CstString(_) | value @ RulePatternValue::Cut => value,
+        RulePatternValue::Group(inner) => RulePatternValue::Group(bind(inner)),
+        RulePatternValue::Until(inner) => RulePatternValue::Until(bind(inner)),
+        RulePatternValue::Suite(patterns) => RulePatternValue::Suite(
+            patterns
+                .into_iter()
+                .map(|pattern| bind_params(pattern, args))
+                .collect(),
+        ),
+        RulePatternValue::Union(patterns) => RulePatternValue::Union(
+            patterns
+                .into_iter()
+                .map(|pattern| bind_params(pattern, args))
+                .collect(),
+        ),
+    };
+
+    Pattern { value, ..pattern }
+}
+
+/// Register the name of a declared rule, detecting duplicate rules and enforcing the [maximum number of rules](`ParseLimits::max_rules`)
+fn declare_rule_name<'a>(
+    declared_names: &mut HashSet<&'a str>,
+    name: &'a str,
+    loc: ParserLoc,
+    limits: &ParseLimits,
+) -> Result<(), ParserError> {
+    if !declared_names.insert(name) {
+        return Err(ParserError::new(
+            loc,
+            name.len(),
+            ParserErrorContent::DuplicateRuleName,
+            None,
+        ));
+    }
+
+    if declared_names.len() > limits.max_rules {
+        return Err(ParserError::new(
+            loc,
+            name.len(),
+            ParserErrorContent::TooManyRules {
+                max: limits.max_rules,
+            },
+            None,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check a rule's name (or a parameter's name), located at the provided location
+fn check_rule_name(name: &str, loc: ParserLoc) -> Result<(), ParserError> {
+    match validate_rule_name(name) {
+        Ok(()) => Ok(()),
+        Err(RuleNameError::ReservedPrefix) => Err(ParserError::new(
+            loc,
+            name.len(),
+            ParserErrorContent::ReservedUppercaseRuleName,
+            Some("try to use a name that doesn't start by 'B_' (builtin rules) or 'E_' (external rules)"),
+        )),
+        Err(err) => Err(ParserError::new(
+            loc,
+            name.len(),
+            ParserErrorContent::InvalidRuleName(err),
+            Some("you can use the 'sanitize_rule_name' function to get a valid name"),
+        )),
+    }
+}
+
+/// Parse a rule's parameters list (e.g. `(elem, sep)`), the provided input starting with the opening parenthesis
+///
+/// Returns the parameters with the location of their name, and the consumed input length
+fn parse_rule_params(
+    input: &str,
+    base_loc: ParserLoc,
+) -> Result<(singles::LocatedSlices<'_>, usize), ParserError> {
+    let end = input.find(')').ok_or_else(|| {
+        ParserError::new(
+            base_loc,
+            1,
+            ParserErrorContent::UnclosedGroup {
+                started_at: base_loc,
+            },
+            Some("you may need to add a closing parenthesis ')' after the parameters, which must be on a single line"),
+        )
+    })?;
+
+    let mut params: singles::LocatedSlices = vec![];
+    let mut offset = 1;
+
+    for param in input[1..end].split(',') {
+        let (name, trimmed) = trim_start_and_count(param);
+        let name = name.trim_end();
+        let loc = base_loc.with_add_cols(offset + trimmed);
+
+        check_rule_name(name, loc)?;
+
+        if params.iter().any(|(other, _)| *other == name) {
+            return Err(ParserError::new(
+                loc,
+                name.len(),
+                ParserErrorContent::DuplicateRuleName,
+                Some("each parameter must have a different name"),
+            ));
+        }
+
+        params.push((name, loc));
+        offset += param.len() + 1;
+    }
+
+    Ok((params, end + 1))
+}
+
+/// Strip the optional union separator (`|`) that may start a rule's content, for readability purposes
+///
+/// Returns the remaining content (left-trimmed) and the column it starts at
+fn strip_leading_union_sep(input: &str, base_loc: ParserLoc) -> Result<(&str, usize), ParserError> {
+    match input.strip_prefix('|') {
+        None => Ok((input, base_loc.col())),
+        Some(content) => {
+            let (content, trimmed) = trim_start_and_count(content);
+            ensure_union_member_follows(content, base_loc, base_loc.with_add_cols(1 + trimmed))?;
+            Ok((content, base_loc.col() + 1 + trimmed))
+        }
+    }
+}
+
+/// Ensure an union separator (`|`) is followed by an actual union member
+///
+/// Empty union members are forbidden, as the '?' repetition should be used instead.
+/// Treating them as an always-matching pattern would silently make all the following members unreachable.
+///
+/// The provided input must start right after the separator
+fn ensure_union_member_follows(
+    input: &str,
+    sep_loc: ParserLoc,
+    input_loc: ParserLoc,
+) -> Result<(), ParserError> {
+    let (input, trimmed) = trim_start_and_count(input);
+
+    if is_finished_line(input) {
+        Err(ParserError::new(
+            sep_loc,
+            1,
+            ParserErrorContent::EmptyUnionMember,
+            Some("to make the previous member optional, use the '?' repetition instead"),
+        ))
+    } else if input.starts_with('|') {
+        Err(ParserError::new(
+            input_loc.with_add_cols(trimmed),
+            1,
+            ParserErrorContent::EmptyUnionMember,
+            Some("to make a member optional, use the '?' repetition instead"),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Parse a rule's content (e.g. `<content>` in `rule = <content>`)
+///
+/// As no parametric rule is known, calls to parametric rules are rejected.
+pub fn parse_rule_pattern(input: &str, base_loc: ParserLoc) -> Result<Pattern<'_>, ParserError> {
+    parse_rule_pattern_with(input, base_loc, &ParametricRules::default())
+}
+
+/// Parse a rule's content, expanding the calls to the provided parametric rules
+pub(super) fn parse_rule_pattern_with<'a>(
+    input: &'a str,
+    base_loc: ParserLoc,
+    parametric: &ParametricRules<'a>,
+) -> Result<Pattern<'a>, ParserError> {
+    // This function is not supposed to be called with an empty content, so we can directly parse the first pattern of the rule
+    let (first_pattern, pattern_len, stopped_because_of) =
+        parse_sub_pattern_with(input, base_loc, parametric)?;
+
+    // Remove the first pattern's content from the remaining input
+    let input = &input[pattern_len..];
+
+    // Make a global pattern
+    // This will contain everything the rule's content is made of
+    // The data is built depending on the reason why the pattern parser stopped previously
+    match stopped_because_of {
+        // If the parser stopped because it got to the end of the line, return the pattern as it is
+        PatternParserStoppedBecauseOf::End => Ok(first_pattern),
+
+        // If the parser stopped because of a continuation separator (whitespace) or an union separator (|),
+        // all items of the follow/union should be collected at once
+        PatternParserStoppedBecauseOf::ContinuationSep
+        | PatternParserStoppedBecauseOf::UnionSep => {
+            if stopped_because_of == PatternParserStoppedBecauseOf::UnionSep {
+                ensure_union_member_follows(
+                    input,
+                    base_loc.with_add_cols(pattern_len - 1),
+                    base_loc.with_add_cols(pattern_len),
+                )?;
+            }
+
+            parse_pattern_suite_or_union_with(
+                input,
+                base_loc,
+                first_pattern,
+                pattern_len,
+                stopped_because_of,
+                parametric,
+            )
+        }
+    }
+}
+
+/// Parse a pattern's suite or union, expanding the calls to the provided parametric rules
+fn parse_pattern_suite_or_union_with<'a>(
+    input: &'a str,
+    base_loc: ParserLoc,
+    first_pattern: Pattern<'a>,
+    first_pattern_consumed: usize,
+    stopped_at: PatternParserStoppedBecauseOf,
+    parametric: &ParametricRules<'a>,
+) -> Result<Pattern<'a>, ParserError> {
+    // The `patterns` variable contains the parsed patterns
+    // The `unions` variable contains each member of the pending union. If the whole rule's content is not an union, this will remain empty.
+    // When an union separator is detected, the content of `patterns` is moved to `unions` in order to separate each member of the union.
+    let (mut patterns, mut unions) = if stopped_at == PatternParserStoppedBecauseOf::UnionSep {
+        (vec![], vec![create_union_child(vec![first_pattern])])
+    } else {
+        (vec![first_pattern], vec![])
+    };
+
+    // Local modifyable input
+    let mut input = input;
+
+    // Local modifyable location
+    let mut pattern_loc = base_loc.with_add_cols(first_pattern_consumed);
+
+    loop {
+        // Parse the next pattern
+        let (next_pattern, next_pattern_len, next_stopped_because_of) =
+            parse_sub_pattern_with(input, pattern_loc, parametric)?;
+
+        // Push it to the list of pending patterns
+        patterns.push(next_pattern);
+
+        // Remove it from the remaining input
+        input = &input[next_pattern_len..];
+
+        // Ensure union separators are followed by a member
+        if next_stopped_because_of == PatternParserStoppedBecauseOf::UnionSep {
+            ensure_union_member_follows(
+                input,
+                pattern_loc.with_add_cols(next_pattern_len - 1),
+                pattern_loc.with_add_cols(next_pattern_len),
+            )?;
+        }
+
+        // Update the column number
+        // Whitespaces following an union separator are left to the next pattern, which checks them
+        pattern_loc.add_cols(next_pattern_len);
+
+        // Check the reason why the parser stopped here
+        match next_stopped_because_of {
+            // If it stopped because it was the end of the input, it's time to return the whole collected data
+            PatternParserStoppedBecauseOf::End => {
+                break Ok(Pattern {
+                    loc: base_loc,
+                    // Trailing whitespaces are not part of the pattern
+                    decl_length: pattern_loc.col - base_loc.col,
+                    repetition: None,
+                    mode: None,
+                    // If the parser stopped on the first pattern because it encountered an union separator, the remaining content
+                    // should be put inside an union.
+                    // Otherwise, and if no union separator was found during the parsing on the whole rule's content,
+                    // a simple suite can be made from the patterns.
+                    value: if stopped_at != PatternParserStoppedBecauseOf::UnionSep
+                        && unions.is_empty()
+                    {
+                        // Avoid making a whole suite wrapper for a single pattern
+                        if patterns.len() == 1 {
+                            break Ok(patterns.into_iter().next().unwrap());
+                        } else {
+                            RulePatternValue::Suite(patterns)
+                        }
+                    } else {
+                        // Otherwise, terminate the union
+                        if !patterns.is_empty() {
+                            unions.push(create_union_child(patterns));
+                        }
+
+                        assert!(unions.len() >= 2);
+
+                        RulePatternValue::Union(unions)
+                    },
+                });
+            }
+
+            // If a continuation separator (whitespace) was encountered, just go to the next pattern (= do nothing for now)
+            PatternParserStoppedBecauseOf::ContinuationSep => {}
+
+            // If an union separator (|) was encountered...
+            PatternParserStoppedBecauseOf::UnionSep => {
+                // The whole pattern is now considered as an union, given that unions have precedence over everything else
+
+                // Put the current patterns in the union's members list
+                unions.push(create_union_child(patterns));
+
+                // Prepare for the next union's member (if any)
+                patterns = vec![];
+            }
+        }
+    }
+}
+
+/// Parse a sub-pattern, expanding the calls to the provided parametric rules
+/// The success return value is made of the parsed pattern, the consumed input length, and the reason why the parser stopped at this specific symbol
+fn parse_sub_pattern_with<'a>(
+    input: &'a str,
+    mut base_loc: ParserLoc,
+    parametric: &ParametricRules<'a>,
+) -> Result<(Pattern<'a>, usize, PatternParserStoppedBecauseOf), ParserError> {
+    // Left-trim the input
+    let (input, trimmed) = trim_separator(input, base_loc)?;
+    base_loc.add_cols(trimmed);
+
+    // Parse the first piece (note that the entire pattern may be made of a single one)
+    let (first_pattern, first_pattern_len) = parse_pattern_piece(input, base_loc, parametric)?;
+
+    // Remove it from the remaining input
+    let input = &input[first_pattern_len..];
+
+    // If the remaining input is empty, the first piece was the whole pattern's content
+    if is_finished_line(input) {
+        return Ok((
+            first_pattern,
+            first_pattern_len + trimmed,
+            PatternParserStoppedBecauseOf::End,
+        ));
+    }
+
+    // Get the first character's following the first piece
+    let next_char = input.chars().next().unwrap();
+
+    let (remaining, add_trimmed) =
+        trim_separator(input, base_loc.with_add_cols(first_pattern_len))?;
+    let is_union_sep = remaining.starts_with('|');
+
+    // If we find an union separator or a whitespace, we can stop here
+    // The parent will be in charge of continuing the processing
+    if is_union_sep || next_char.is_whitespace() {
+        Ok((
+            first_pattern,
+            trimmed + first_pattern_len + add_trimmed + if is_union_sep { 1 } else { 0 },
+            if is_union_sep {
+                PatternParserStoppedBecauseOf::UnionSep
+            } else {
+                PatternParserStoppedBecauseOf::ContinuationSep
+            },
+        ))
+    }
+    // Closing parenthesis are consumed by the group they close, so this one doesn't close anything
+    else if next_char == ')' {
+        Err(unexpected_closing_parenthesis(
+            base_loc.with_add_cols(first_pattern_len),
+        ))
+    }
+    // Otherwise (if we find an unexpected character)...
+    else {
+        // That's an error, as the content should not end right now.
+        Err(ParserError::new(
+            base_loc.with_add_cols(first_pattern_len),
+            1,
+            ParserErrorContent::ExpectedPatternSeparatorOrEndOfLine(next_char),
+            Some("adding another pattern to the suite requires a whitespace, or a vertical bar (|) for an union")
+        ))
+    }
+}
+
+/// Left-trim the whitespaces separating two pieces of a pattern, and get the number of removed characters
+///
+/// Only spaces and tabs are allowed, as other whitespaces (e.g. non-breaking spaces) are invisible in most editors
+/// and almost always pasted by accident.
+fn trim_separator(input: &str, base_loc: ParserLoc) -> Result<(&str, usize), ParserError> {
+    let (remaining, trimmed) = trim_start_and_count(input);
+
+    match input[..trimmed]
+        .char_indices()
+        .find(|(_, c)| *c != ' ' && *c != '\t')
+    {
+        Some((i, c)) => Err(ParserError::new(
+            base_loc.with_add_cols(i),
+            c.len_utf8(),
+            ParserErrorContent::UnexpectedWhitespace(c),
+            Some("replace it with a regular space"),
+        )),
+        None => Ok((remaining, trimmed)),
+    }
+}
+
+/// Parse a rule's piece, which means a single value
+///
+/// This function's success return value is the parsed piece and the consumed input length
+fn parse_pattern_piece<'a>(
+    input: &'a str,
+    mut base_loc: ParserLoc,
+    parametric: &ParametricRules<'a>,
+) -> Result<(Pattern<'a>, usize), ParserError> {
+    let (input, trimmed, mode) = if let Some(input) = input.strip_prefix("°") {
+        (input, 2, Some(PatternMode::Silent))
+    } else if let Some(input) = input.strip_prefix("@") {
+        (input, 1, Some(PatternMode::Atomic))
+    } else if let Some(input) = input.strip_prefix("!") {
+        (input, 1, Some(PatternMode::Negative))
+    } else if let Some(input) = input.strip_prefix("~") {
         (input, 1, Some(PatternMode::Peek))
     } else {
         (input, 0, None)
@@ -385,26 +2231,69 @@ fn parse_pattern_piece(
     // Update the base location
     base_loc.add_cols(trimmed);
 
+    // Check if the piece matches everything up to a terminator (`..pattern`)
+    let (value_input, until_prefix_len) = match input.strip_prefix("..") {
+        Some(value_input) => (value_input, 2),
+        None => (input, 0),
+    };
+
+    let value_loc = base_loc.with_add_cols(until_prefix_len);
+
     let (value, len) =
     // Check if the value is a constant string
-    if let Some((string, len)) = singles::cst_string(input, base_loc)? {
+    if let Some((string, len)) = singles::cst_string(value_input, value_loc)? {
         (RulePatternValue::CstString(string), len)
     }
     // Check if the value is a rule's name
-    else if let Some((name, len)) = singles::rule_name(input, base_loc)? {
-        (RulePatternValue::Rule(name), len)
+    else if let Some((name, len)) = singles::rule_name(value_input, value_loc)? {
+        // Calls to parametric rules (`rule(arg1, arg2)`) are replaced by a group containing the rule's content
+        match singles::call_args(&value_input[len..], value_loc.with_add_cols(len))? {
+            None => {
+                parametric.ensure_not_parametric(name, value_loc)?;
+                (RulePatternValue::Rule(name), len)
+            }
+            Some((args, args_len)) => {
+                let args = args
+                    .into_iter()
+                    .map(|(arg, loc)| {
+                        parametric.nested(loc, || parse_rule_pattern_with(arg, loc, parametric))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let expanded = parametric.expand(name, value_loc, args)?;
+
+                (RulePatternValue::Group(Rc::new(expanded)), len + args_len)
+            }
+        }
     }
     // Check if the value is a group (`(...)`)
-    else if let Some((group, len)) = singles::group(input, base_loc)? {
+    else if let Some((group, len)) = singles::group(value_input, value_loc, parametric)? {
         (RulePatternValue::Group(group), len)
     }
+    // Check if the value is a cut (`^`)
+    else if value_input.starts_with('^') {
+        (RulePatternValue::Cut, 1)
+    }
+    // Mode markers must be directly followed by the pattern they apply to
+    else if let (Some(mode), 0) = (mode, until_prefix_len) {
+        return Err(ParserError::new(
+            value_loc,
+            0,
+            ParserErrorContent::ExpectedPatternAfterModeMarker(mode.symbol()),
+            Some("modes apply to the pattern right after them, without any whitespace in between")
+        ));
+    }
+    // Closing parenthesis are consumed by the group they close, so this one doesn't close anything
+    else if value_input.starts_with(')') {
+        return Err(unexpected_closing_parenthesis(value_loc));
+    }
     // If it's none of the above, it is syntax error
     else {
         return Err(ParserError::new(
-            base_loc,
+            value_loc,
             0,
             ParserErrorContent::ExpectedPattern,
-            Some(match input.chars().next() {
+            Some(match value_input.chars().next() {
                 Some('\'') => "strings require double quotes",
                 Some(_) => "You may either open a group with '(', a string with '\"', or specify a rule's name",
                 None => "you need to provide a rule pattern, such as a group, a string or another rule's name"
@@ -412,15 +2301,58 @@ fn parse_pattern_piece(
         ));
     };
 
+    let is_cut = matches!(value, RulePatternValue::Cut);
+
+    // Wrap the value as the terminator of an until pattern
+    let (value, len) = if until_prefix_len > 0 {
+        (
+            RulePatternValue::Until(Rc::new(Pattern {
+                loc: value_loc,
+                decl_length: len,
+                value,
+                mode: None,
+                repetition: None,
+            })),
+            until_prefix_len + len,
+        )
+    } else {
+        (value, len)
+    };
+
     // Get the piece's repetition model (* + ?) following it
     let repetition = input[len..]
         .chars()
         .next()
         .and_then(PatternRepetition::parse);
 
+    // Until patterns stop right before their terminator, so repeating them would loop forever
+    if until_prefix_len > 0
+        && matches!(
+            repetition,
+            Some(PatternRepetition::Any) | Some(PatternRepetition::OneOrMore)
+        )
+    {
+        return Err(ParserError::new(
+            base_loc.with_add_cols(len),
+            1,
+            ParserErrorContent::RepeatedUntilPattern,
+            Some("until patterns already consume everything up to their terminator"),
+        ));
+    }
+
     // Compute the consumed size
     let decl_length = len + if repetition.is_some() { 1 } else { 0 };
 
+    // Cuts only commit to their union member as plain pieces
+    if is_cut && (mode.is_some() || repetition.is_some() || until_prefix_len > 0) {
+        return Err(ParserError::new(
+            base_loc.with_add_cols(until_prefix_len),
+            decl_length - until_prefix_len,
+            ParserErrorContent::ModifiedCut,
+            Some("cuts don't match anything, so they can only be used on their own"),
+        ));
+    }
+
     // Success!
     Ok((
         Pattern {
@@ -434,6 +2366,16 @@ fn parse_pattern_piece(
     ))
 }
 
+/// Create the error for a closing parenthesis which doesn't close any group, located at the provided location
+fn unexpected_closing_parenthesis(loc: ParserLoc) -> ParserError {
+    ParserError::new(
+        loc,
+        1,
+        ParserErrorContent::UnexpectedClosingParenthesis,
+        Some("you may need to remove it, or to add an opening parenthesis '(' before it"),
+    )
+}
+
 // Create an union child (see usage)
 fn create_union_child(patterns: Vec<Pattern>) -> Pattern {
     assert_ne!(patterns.len(), 0);
@@ -454,8 +2396,1002 @@ fn create_union_child(patterns: Vec<Pattern>) -> Pattern {
 
 /// Reason by the [pattern parser](`parse_rule_pattern`) stopped at a specific moment
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum PatternParserStoppedBecauseOf {
+enum PatternParserStoppedBecauseOf {
     End,
     ContinuationSep,
     UnionSep,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn main_pattern(grammar: &str) -> Pattern<'_> {
+        parse_peg(grammar).unwrap().main_rule().pattern().clone()
+    }
+
+    fn union_members<'a>(pattern: &'a Pattern) -> &'a [Pattern<'a>] {
+        match pattern.value() {
+            RulePatternValue::Union(members) => members,
+            other => panic!("expected an union, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn leading_union_separator() {
+        let pattern = main_pattern(r#"main = | "a" | "b""#);
+        assert_eq!(union_members(&pattern).len(), 2);
+        assert!(pattern.structurally_eq(&main_pattern(r#"main = "a" | "b""#)));
+    }
+
+    #[test]
+    fn multi_line_union_body() {
+        let pattern = main_pattern("main =\n    | \"a\"\n    | \"b\" \"c\"\n    | \"d\"");
+        let members = union_members(&pattern);
+
+        assert_eq!(members.len(), 3);
+        assert_eq!(members[1].loc(), ParserLoc::new(2, 6));
+        assert!(pattern.structurally_eq(&main_pattern(r#"main = "a" | "b" "c" | "d""#)));
+    }
+
+    fn parse_err(grammar: &str) -> ParserError {
+        parse_peg(grammar).unwrap_err()
+    }
+
+    #[test]
+    fn trailing_union_separator() {
+        let err = parse_err(r#"main = "a" |"#);
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::EmptyUnionMember
+        ));
+        assert_eq!((err.line(), err.col(), err.length()), (0, 11, 1));
+    }
+
+    #[test]
+    fn consecutive_union_separators() {
+        let err = parse_err(r#"main = "a" | | "b""#);
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::EmptyUnionMember
+        ));
+        assert_eq!((err.line(), err.col(), err.length()), (0, 13, 1));
+    }
+
+    #[test]
+    fn leading_union_separator_is_not_an_empty_member() {
+        let pattern = main_pattern(r#"main = | "a""#);
+        assert!(pattern.structurally_eq(&main_pattern(r#"main = "a""#)));
+
+        let err = parse_err(r#"main = | | "a""#);
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::EmptyUnionMember
+        ));
+    }
+
+    /// Check that a rule behaves like the provided pattern once whitespaces are inserted
+    fn assert_whitespace_inserted(grammar: &str, rule: &str, expected: &str) {
+        let pst = parse_peg(grammar).unwrap();
+        let expected = parse_rule_pattern(expected, ParserLoc::new(0, 0)).unwrap();
+        let pattern = pst.rules()[rule].pattern();
+
+        assert!(
+            pattern.structurally_eq(&expected),
+            "unexpected pattern for rule {}: {:#?}",
+            rule,
+            pattern
+        );
+    }
+
+    const WHITESPACE_GRAMMAR: &str = r#"
+@whitespace ws
+@no_whitespace raw
+
+ws = B_WHITESPACE | comment
+comment = "//" ..B_NEWLINE_LF
+ident = @(B_ASCII_ALPHABETIC+)
+stmt = "let" ident "=" ident ";"
+list = ident ("," ident)* ws+
+raw = "<" ident+ ">"
+main = (stmt | list | raw)*
+"#;
+
+    #[test]
+    fn whitespace_between_suite_members() {
+        assert_whitespace_inserted(
+            WHITESPACE_GRAMMAR,
+            "stmt",
+            r#""let" °ws* ident °ws* "=" °ws* ident °ws* ";""#,
+        );
+    }
+
+    #[test]
+    fn whitespace_between_repetitions() {
+        assert_whitespace_inserted(
+            WHITESPACE_GRAMMAR,
+            "list",
+            r#"ident °ws* (°ws* ("," °ws* ident))* °ws* ws+"#,
+        );
+    }
+
+    #[test]
+    fn whitespace_around_main() {
+        assert_whitespace_inserted(
+            WHITESPACE_GRAMMAR,
+            "main",
+            "°ws* ((°ws* (stmt | list | raw))*) °ws*",
+        );
+    }
+
+    #[test]
+    fn whitespace_untouched_rules() {
+        assert_whitespace_inserted(WHITESPACE_GRAMMAR, "raw", r#""<" ident+ ">""#);
+        assert_whitespace_inserted(WHITESPACE_GRAMMAR, "ident", "@(B_ASCII_ALPHABETIC+)");
+        assert_whitespace_inserted(WHITESPACE_GRAMMAR, "comment", r#""//" ..B_NEWLINE_LF"#);
+    }
+
+    #[test]
+    fn whitespace_around_silent_main() {
+        let grammar = "@whitespace ws\nws = °\" \"\nmain = °\"x\"";
+        assert_whitespace_inserted(grammar, "main", r#"°ws* (°"x") °ws*"#);
+    }
+
+    #[test]
+    fn inserted_whitespace_is_removable() {
+        let pst = parse_peg(WHITESPACE_GRAMMAR).unwrap();
+
+        let without_directive = WHITESPACE_GRAMMAR.replace("@whitespace ws", "");
+        let original = parse_peg_nocheck(&without_directive).unwrap();
+
+        for name in ["stmt", "list", "main"] {
+            let pattern = pst.rules()[name].pattern().clone();
+            let removed = remove_rule_whitespace(name, pattern, "ws");
+
+            assert!(
+                removed.structurally_eq(original.rules()[name].pattern()),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn parametric_rules_expansion() {
+        let pattern = main_pattern("list(elem, sep) = elem (sep elem)*\nmain = list(\"a\", \",\")");
+        let expected = main_pattern(r#"main = ("a" ("," "a")*)"#);
+        assert!(pattern.structurally_eq(&expected), "{:#?}", pattern);
+    }
+
+    #[test]
+    fn nested_parametric_rules() {
+        // Calls in arguments
+        let grammar = "pair(a, b) = a b\nlist(e) = e (\",\" e)*\nmain = list(pair(\"x\", \"y\"))";
+        let expected = main_pattern(r#"main = (("x" "y") ("," ("x" "y"))*)"#);
+        assert!(main_pattern(grammar).structurally_eq(&expected));
+
+        // Calls in the content of parametric rules, forwarding their parameters
+        let grammar = "pair(a, b) = a b\ntwice(e) = pair(e, e)\nmain = twice(\"x\"+)";
+        let expected = main_pattern(r#"main = (("x"+ "x"+))"#);
+        assert!(main_pattern(grammar).structurally_eq(&expected));
+    }
+
+    #[test]
+    fn recursive_parametric_rules() {
+        let err = parse_err("list(e) = e list(e)?\nmain = list(\"a\")");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::RecursiveParametricRule
+        ));
+        assert_eq!((err.line(), err.col(), err.length()), (0, 12, 4));
+
+        // Indirect recursion
+        let err = parse_err("a(x) = b(x)\nb(x) = x a(x)\nmain = a(\"a\")");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::RecursiveParametricRule
+        ));
+        assert_eq!((err.line(), err.col()), (1, 9));
+
+        // Calling the same rule multiple times is not a recursion
+        parse_peg("list(e) = e (\",\" e)*\nmain = list(list(\"a\"))").unwrap();
+    }
+
+    #[test]
+    fn parametric_rules_arity() {
+        let err = parse_err("list(e, s) = e (s e)*\nmain = list(\"a\")");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::InvalidArgumentsCount {
+                expected: 2,
+                found: 1
+            }
+        ));
+        assert_eq!((err.line(), err.col(), err.length()), (1, 7, 4));
+
+        let err = parse_err("list(e) = e+\nmain = list(\"a\", \"b\")");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::InvalidArgumentsCount {
+                expected: 1,
+                found: 2
+            }
+        ));
+
+        // Referring to a parametric rule without arguments is not allowed either
+        let err = parse_err("list(e) = e\nmain = list");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::InvalidArgumentsCount {
+                expected: 1,
+                found: 0
+            }
+        ));
+        assert_eq!((err.line(), err.col(), err.length()), (1, 7, 4));
+
+        // Only parametric rules can be called with arguments
+        let err = parse_err("a = \"a\"\nmain = a(\"b\")");
+        assert!(matches!(err.content(), ParserErrorContent::UnknownRule));
+        assert_eq!((err.line(), err.col(), err.length()), (1, 7, 1));
+    }
+
+    #[test]
+    fn parameters_shadow_parametric_rules() {
+        let grammar = "inner(x) = x \"!\"\nouter(inner) = inner inner(\"b\")\nmain = outer(\"a\")";
+        let expected = main_pattern(r#"main = ("a" ("b" "!"))"#);
+        assert!(main_pattern(grammar).structurally_eq(&expected));
+    }
+
+    #[test]
+    fn duplicate_parameters() {
+        let err = parse_err("list(e, e) = e\nmain = list(\"a\", \"b\")");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::DuplicateRuleName
+        ));
+        assert_eq!((err.line(), err.col(), err.length()), (0, 8, 1));
+    }
+
+    /// Get the marker and the location of an `ExpectedPatternAfterModeMarker` error
+    fn mode_marker_err(grammar: &str) -> (char, usize, usize, usize) {
+        let err = parse_err(grammar);
+
+        match err.content() {
+            ParserErrorContent::ExpectedPatternAfterModeMarker(marker) => {
+                (*marker, err.line(), err.col(), err.length())
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn mode_markers_at_end_of_line() {
+        assert_eq!(mode_marker_err("main = \"a\" @"), ('@', 0, 12, 0));
+        assert_eq!(
+            mode_marker_err("main = \"a\" !\na = \"b\""),
+            ('!', 0, 12, 0)
+        );
+
+        // The silent marker is two bytes long
+        assert_eq!(mode_marker_err("main = \"a\" °"), ('°', 0, 13, 0));
+    }
+
+    #[test]
+    fn mode_markers_followed_by_whitespace() {
+        assert_eq!(mode_marker_err("main = \"a\" ~ \"b\""), ('~', 0, 12, 0));
+    }
+
+    #[test]
+    fn mode_markers_before_union_separators() {
+        assert_eq!(mode_marker_err("main = \"a\" @| \"b\""), ('@', 0, 12, 0));
+        assert_eq!(mode_marker_err("main = \"a\" | !| \"b\""), ('!', 0, 14, 0));
+    }
+
+    #[test]
+    fn mode_markers_before_closing_parenthesis() {
+        assert_eq!(mode_marker_err("main = (\"a\" °)"), ('°', 0, 14, 0));
+        assert_eq!(mode_marker_err("main = (\"a\" | (~))"), ('~', 0, 16, 0));
+    }
+
+    /// Get the location of an `UnclosedGroup` error, and the location it reports the group as started at
+    fn unclosed_group_err(grammar: &str) -> ((usize, usize, usize), (usize, usize)) {
+        let err = parse_err(grammar);
+
+        match err.content() {
+            ParserErrorContent::UnclosedGroup { started_at } => (
+                (err.line(), err.col(), err.length()),
+                (started_at.line(), started_at.col()),
+            ),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unclosed_groups() {
+        assert_eq!(
+            unclosed_group_err("main = (\"a\" \"b\""),
+            ((0, 7, 1), (0, 7))
+        );
+
+        // Only the unclosed group is reported
+        assert_eq!(
+            unclosed_group_err("main = \"a\" (\"b\" (\"c\")"),
+            ((0, 11, 1), (0, 11))
+        );
+
+        // Groups can't span multiple lines
+        assert_eq!(
+            unclosed_group_err("a = \"a\"\nmain = a (\"b\"\n    | \"c\")"),
+            ((1, 9, 1), (1, 9))
+        );
+    }
+
+    #[test]
+    fn unclosed_parameters_and_arguments() {
+        assert_eq!(
+            unclosed_group_err("list(e, s = e+\nmain = list(\"a\", \",\")"),
+            ((0, 4, 1), (0, 4))
+        );
+
+        assert_eq!(
+            unclosed_group_err("list(e) = e+\nmain = list(\"a\""),
+            ((1, 11, 1), (1, 11))
+        );
+    }
+
+    #[test]
+    fn leading_byte_order_mark() {
+        use super::super::pretty_format_parser_err;
+
+        let pst = parse_peg("\u{feff}a = \"a\"\nmain = a").unwrap();
+        assert_eq!(pst.rules()["a"].decl_loc(), ParserLoc::new(0, 3));
+        assert_eq!(pst.main_rule().decl_loc(), ParserLoc::new(1, 0));
+
+        // Errors on the first line account for the byte order mark
+        let err = parse_err("\u{feff}main = 'a'");
+        assert!(matches!(err.content(), ParserErrorContent::ExpectedPattern));
+        assert_eq!((err.line(), err.col()), (0, 10));
+
+        let report = pretty_format_parser_err("\u{feff}main = 'a'", err);
+        assert!(report.starts_with("ERROR: At line 1, column 8:\n\n1 | main = 'a'\n"));
+
+        // Byte order marks are only skipped at the beginning of the grammar
+        let err = parse_err("main = \"a\"\n\u{feff}a = \"a\"");
+        assert!(!matches!(err.content(), ParserErrorContent::UnusedRule));
+    }
+
+    #[test]
+    fn multi_line_comment_delimiters_with_trailing_whitespaces() {
+        parse_peg("###  \nnot a rule\n###\t\nmain = \"a\"").unwrap();
+
+        // Leading whitespaces are allowed as well
+        parse_peg("  ### \nnot a rule\n\t###\nmain = \"a\"").unwrap();
+
+        let err = parse_err("main = \"a\"\n### \nnot a rule\n");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::UnterminatedMultiLineComment { started_at } if *started_at == ParserLoc::new(1, 0)
+        ));
+
+        // Other characters on the delimiter's line make it a regular comment
+        let err = parse_err("###a\nnot a rule\n###\nmain = \"a\"");
+        assert!(!matches!(
+            err.content(),
+            ParserErrorContent::UnterminatedMultiLineComment { .. }
+        ));
+    }
+
+    #[test]
+    fn rule_content_columns() {
+        for (decl, col) in [("a=b", 2), ("a =b", 3), ("a= b", 3), ("a = b", 4)] {
+            // The unknown rule is reported where it's used
+            let err = parse_err(&format!("main = a\n{}", decl));
+            assert!(matches!(err.content(), ParserErrorContent::UnknownRule));
+            assert_eq!(
+                (err.line(), err.col(), err.length()),
+                (1, col, 1),
+                "{}",
+                decl
+            );
+
+            let grammar = format!("main = a\n{}\nb = \"b\"", decl);
+            let pst = parse_peg(&grammar).unwrap();
+            assert_eq!(
+                pst.rules()["a"].pattern().loc(),
+                ParserLoc::new(1, col),
+                "{}",
+                decl
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_rule_headers() {
+        let err = parse_err("main = a\na b = \"b\"");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::MissingAssignmentOp
+        ));
+        assert_eq!((err.line(), err.col()), (1, 2));
+
+        let err = parse_err("main = a\na");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::MissingAssignmentOp
+        ));
+        assert_eq!((err.line(), err.col()), (1, 1));
+
+        let err = parse_err("main = a\na-b = \"b\"");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::IllegalCharInRuleName('-')
+        ));
+        assert_eq!((err.line(), err.col(), err.length()), (1, 1, 1));
+    }
+
+    #[test]
+    fn main_rule_spacing_variants() {
+        let expected = main_pattern(r#"main = "x""#);
+
+        for (decl, col) in [
+            (r#"main="x""#, 5),
+            (r#"main ="x""#, 6),
+            (r#"main= "x""#, 6),
+            (r#"main = "x""#, 7),
+        ] {
+            let pattern = main_pattern(decl);
+            assert!(pattern.structurally_eq(&expected), "{}", decl);
+            assert_eq!(pattern.loc(), ParserLoc::new(0, col), "{}", decl);
+
+            // Errors in the rule's body are located relative to where it starts
+            let err = parse_err(&format!("{})", decl));
+            assert!(matches!(
+                err.content(),
+                ParserErrorContent::UnexpectedClosingParenthesis
+            ));
+            assert_eq!((err.line(), err.col()), (0, col + 3), "{}", decl);
+        }
+    }
+
+    #[test]
+    fn keywords_directive() {
+        let pst =
+            parse_peg("@keywords if else\n@keywords if\nmain = (\"if\" | \"else\" | \"iffy\")+")
+                .unwrap();
+        assert_eq!(pst.keywords(), ["if", "else"]);
+
+        let err = parse_err("@keywords if while\nmain = \"if\"");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::InvalidDirectiveArguments
+        ));
+        assert_eq!((err.line(), err.col(), err.length()), (0, 13, 5));
+
+        let err = parse_err("@keywords\nmain = \"if\"");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::InvalidDirectiveArguments
+        ));
+    }
+
+    fn rule_pattern<'a>(tree: &'a PegSyntaxTree, name: &str) -> &'a Pattern<'a> {
+        tree.rules()[name].pattern()
+    }
+
+    #[test]
+    fn reparsed_rule_locations() {
+        let mut tree = parse_peg("main = a b\na = \"x\"\nb = \"y\"").unwrap();
+        let edited = "main = a b\na = \"x\"\nb = \"z\" a";
+        let origin = SourceOrigin::new(2, 4);
+
+        tree.reparse_rule("b", "\"z\" a", &origin).unwrap();
+
+        let pattern = rule_pattern(&tree, "b");
+        assert_eq!(pattern.loc(), ParserLoc::new(2, 4));
+        assert_eq!(pattern.span().slice(edited), Some("\"z\" a"));
+
+        let err = tree.reparse_rule("b", "\"z\" (a", &origin).unwrap_err();
+        assert_eq!(*err.loc(), ParserLoc::new(2, 8));
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::UnclosedGroup { started_at } if *started_at == ParserLoc::new(2, 8)
+        ));
+
+        // Continuation lines keep their own columns
+        tree.reparse_rule("b", "\"z\"\n    | a", &origin).unwrap();
+
+        let members = union_members(rule_pattern(&tree, "b"));
+        assert_eq!(members[0].loc(), ParserLoc::new(2, 4));
+        assert_eq!(members[1].loc(), ParserLoc::new(3, 6));
+
+        let err = tree.reparse_rule("b", "\"z\"\n  a", &origin).unwrap_err();
+        assert_eq!(*err.loc(), ParserLoc::new(3, 2));
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::ExpectedUnionContinuation
+        ));
+    }
+
+    #[test]
+    fn reparsed_rule_references() {
+        let mut tree = parse_peg("main = a b\na = \"x\"\nb = \"y\"").unwrap();
+        let origin = SourceOrigin::new(2, 4);
+
+        tree.reparse_rule("b", "\"z\" a", &origin).unwrap();
+
+        let members = match rule_pattern(&tree, "b").value() {
+            RulePatternValue::Suite(members) => members,
+            other => panic!("expected a suite, got {:?}", other),
+        };
+
+        assert!(matches!(members[1].value(), RulePatternValue::Rule("a")));
+
+        // Rules depending on the reparsed one are validated again
+        let err = tree
+            .reparse_rule("a", "", &SourceOrigin::new(1, 4))
+            .unwrap_err();
+        assert_eq!(*err.loc(), ParserLoc::new(1, 4));
+        assert!(matches!(err.content(), ParserErrorContent::EmptyRuleBody));
+
+        // Referencing an unknown rule leaves the tree unchanged
+        let err = tree.reparse_rule("b", "c", &origin).unwrap_err();
+        assert_eq!(*err.loc(), ParserLoc::new(2, 4));
+        assert!(matches!(err.content(), ParserErrorContent::UnknownRule));
+        assert_eq!(
+            rule_pattern(&tree, "b").span().slice("\n\n    \"z\" a"),
+            Some("\"z\" a")
+        );
+
+        // Dropping the last reference to a rule makes it unused
+        tree.reparse_rule("b", "\"z\"", &origin).unwrap();
+
+        let err = tree
+            .reparse_rule("main", "b", &SourceOrigin::new(0, 7))
+            .unwrap_err();
+        assert_eq!(*err.loc(), ParserLoc::new(1, 0));
+        assert!(matches!(err.content(), ParserErrorContent::UnusedRule));
+
+        tree.reparse_rule("main", "b a", &SourceOrigin::new(0, 7))
+            .unwrap();
+        assert_eq!(rule_pattern(&tree, "main").loc(), ParserLoc::new(0, 7));
+    }
+
+    #[test]
+    fn reparsed_rule_limits() {
+        let limits = ParseLimits {
+            max_rule_len: 8,
+            ..ParseLimits::default()
+        };
+
+        let mut tree = parse_peg_with_limits("main = a\na = \"x\"", &limits).unwrap();
+
+        let err = tree
+            .reparse_rule("a", "\"0123456789\"", &SourceOrigin::new(1, 4))
+            .unwrap_err();
+
+        assert_eq!(*err.loc(), ParserLoc::new(1, 4));
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::RuleTooLong { max_len: 8 }
+        ));
+
+        tree.reparse_rule("a", "\"012345\"", &SourceOrigin::new(1, 4))
+            .unwrap();
+    }
+
+    fn limits_err(grammar: &str, limits: ParseLimits) -> ParserError {
+        match parse_peg_with_limits(grammar, &limits) {
+            Ok(_) => panic!("grammar doesn't exceed the limits"),
+            Err(err) => err,
+        }
+    }
+
+    /// Get a grammar whose `main` rule calls a parametric rule expanded `2^depth` times
+    /// (each rule calling the previous one twice)
+    fn exponential_expansion_grammar(depth: usize) -> String {
+        let mut grammar = format!("main = t{}(\"a\")\n", depth);
+
+        for i in (2..=depth).rev() {
+            grammar.push_str(&format!("t{}(x) = t{}(x) t{}(x)\n", i, i - 1, i - 1));
+        }
+
+        grammar.push_str("t1(x) = x x");
+        grammar
+    }
+
+    #[test]
+    fn grammar_size_limit() {
+        let grammar = "main = \"abc\"";
+
+        let err = limits_err(
+            grammar,
+            ParseLimits {
+                max_input_len: grammar.len() - 1,
+                ..ParseLimits::new()
+            },
+        );
+
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::GrammarTooLarge { max_len: 11 }
+        ));
+        assert_eq!((*err.loc(), err.length()), (ParserLoc::new(0, 0), 0));
+
+        parse_peg_with_limits(
+            grammar,
+            &ParseLimits {
+                max_input_len: grammar.len(),
+                ..ParseLimits::new()
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn grammar_size_limit_with_parametric_expansions() {
+        // Expanded 1024 times, which is fine without limits
+        let grammar = exponential_expansion_grammar(10);
+        parse_peg(&grammar).unwrap();
+
+        let limits = ParseLimits {
+            max_input_len: 4_000,
+            ..ParseLimits::new()
+        };
+
+        let err = limits_err(&grammar, limits);
+
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::GrammarTooLarge { max_len: 4_000 }
+        ));
+
+        // The error is reported on the call whose expansion exceeded the limit (line 9 being `t2(x) = t1(x) t1(x)`)
+        assert_eq!((*err.loc(), err.length()), (ParserLoc::new(9, 8), 2));
+
+        // Expansions stop at the limit, so huge expansions (2^60 here) fail early
+        let err = limits_err(&exponential_expansion_grammar(60), limits);
+
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::GrammarTooLarge { max_len: 4_000 }
+        ));
+    }
+
+    #[test]
+    fn rules_count_limit() {
+        let limits = ParseLimits {
+            max_rules: 2,
+            ..ParseLimits::new()
+        };
+
+        parse_peg_with_limits("main = a\na = \"x\"", &limits).unwrap();
+
+        let err = limits_err("main = a\na = b\nb = \"x\"", limits);
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::TooManyRules { max: 2 }
+        ));
+        assert_eq!((*err.loc(), err.length()), (ParserLoc::new(2, 0), 1));
+
+        // Parametric rules count as well
+        let err = limits_err("main = a\na = list(\"x\")\nlist(x) = x", limits);
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::TooManyRules { max: 2 }
+        ));
+        assert_eq!((*err.loc(), err.length()), (ParserLoc::new(2, 0), 4));
+    }
+
+    #[test]
+    fn rule_length_limit() {
+        let limits = ParseLimits {
+            max_rule_len: 8,
+            ..ParseLimits::new()
+        };
+
+        parse_peg_with_limits("main = a\na = \"012345\"", &limits).unwrap();
+
+        let err = limits_err("main = a\na = \"0123456\"", limits);
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::RuleTooLong { max_len: 8 }
+        ));
+        assert_eq!((*err.loc(), err.length()), (ParserLoc::new(1, 0), 1));
+
+        // Continuation lines count as well
+        let err = limits_err("main = a\nrule = \"012\"\n     | \"345\"", limits);
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::RuleTooLong { max_len: 8 }
+        ));
+        assert_eq!((*err.loc(), err.length()), (ParserLoc::new(1, 0), 4));
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let limits = ParseLimits {
+            max_nesting: 2,
+            ..ParseLimits::new()
+        };
+
+        parse_peg_with_limits("main = ((\"a\"))", &limits).unwrap();
+
+        let err = limits_err("main = ((\"a\") (((\"b\"))))", limits);
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::NestingTooDeep { max: 2 }
+        ));
+        assert_eq!((*err.loc(), err.length()), (ParserLoc::new(0, 15), 1));
+
+        // Parametric rules' arguments and expansions count as well
+        let err = limits_err("w(x) = (x)\nmain = w(w(\"a\"))", limits);
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::NestingTooDeep { max: 2 }
+        ));
+        assert_eq!((*err.loc(), err.length()), (ParserLoc::new(0, 7), 1));
+    }
+
+    #[test]
+    fn unicode_whitespace_separators() {
+        for (grammar, col, c) in [
+            // Non-breaking space
+            ("main = \"a\"\u{a0}\"b\"", 10, '\u{a0}'),
+            ("main = \"a\" \u{a0}\"b\"", 11, '\u{a0}'),
+            // Ideographic space
+            ("main = \"a\"\u{3000}\"b\"", 10, '\u{3000}'),
+            ("main = \"a\"\t\u{3000}| \"b\"", 11, '\u{3000}'),
+            ("main = (\"a\"\u{3000}\"b\")", 11, '\u{3000}'),
+        ] {
+            let err = parse_err(grammar);
+
+            assert_eq!(*err.loc(), ParserLoc::new(0, col), "in {:?}", grammar);
+            assert_eq!(err.length(), c.len_utf8(), "in {:?}", grammar);
+            assert!(
+                matches!(err.content(), ParserErrorContent::UnexpectedWhitespace(found) if *found == c),
+                "in {:?}: {:?}",
+                grammar,
+                err.content()
+            );
+        }
+
+        // Spaces and tabs are still allowed
+        assert!(parse_peg("main = \"a\" \t \"b\"\t|\t\"c\"").is_ok());
+    }
+
+    #[test]
+    fn empty_rule_bodies() {
+        for grammar in ["main =", "main = ", "main =\t"] {
+            let err = parse_peg(grammar).unwrap_err();
+
+            // The error points at the assignment operator
+            assert_eq!(*err.loc(), ParserLoc::new(0, 5), "in {:?}", grammar);
+            assert_eq!(err.length(), 1, "in {:?}", grammar);
+            assert!(
+                matches!(err.content(), ParserErrorContent::EmptyRuleBody),
+                "in {:?}: {:?}",
+                grammar,
+                err.content()
+            );
+        }
+    }
+
+    #[test]
+    fn rule_header_edge_cases() {
+        let header = parse_rule_header("  rule = \"a\"").unwrap();
+        assert_eq!(header.name(), "rule");
+        assert_eq!(header.name_span().start(), ParserLoc::new(0, 2));
+        assert_eq!(header.name_span().end(), ParserLoc::new(0, 6));
+        assert_eq!(header.content(), "\"a\"");
+        assert_eq!(header.content_loc(), ParserLoc::new(0, 9));
+
+        // Content provided on the next lines
+        let header = parse_rule_header("rule=").unwrap();
+        assert_eq!(header.content(), "");
+        assert_eq!(header.content_loc(), ParserLoc::new(0, 5));
+
+        let header = parse_rule_header("rule =   # comment").unwrap();
+        assert_eq!(header.content(), "# comment");
+        assert_eq!(header.content_loc(), ParserLoc::new(0, 9));
+
+        // Parameters with whitespaces around them
+        let header = parse_rule_header("list ( elem ,sep ) = elem").unwrap();
+        assert_eq!(header.name(), "list");
+        assert_eq!(
+            header.params(),
+            &[
+                ("elem", ParserLoc::new(0, 7)),
+                ("sep", ParserLoc::new(0, 13))
+            ]
+        );
+        assert_eq!(header.content(), "elem");
+        assert_eq!(header.content_loc(), ParserLoc::new(0, 21));
+
+        // The header doesn't check the content
+        let header = parse_rule_header("rule = (((").unwrap();
+        assert_eq!(header.content(), "(((");
+
+        use ParserErrorContent::*;
+
+        let header_err = |line, col, length, check: fn(&ParserErrorContent) -> bool| {
+            let err = parse_rule_header(line).unwrap_err();
+
+            assert_eq!(*err.loc(), ParserLoc::new(0, col), "in {:?}", line);
+            assert_eq!(err.length(), length, "in {:?}", line);
+            assert!(check(err.content()), "in {:?}: {:?}", line, err.content());
+        };
+
+        header_err("", 0, 0, |c| matches!(c, ExpectedRuleDeclaration));
+        header_err("   ", 3, 0, |c| matches!(c, ExpectedRuleDeclaration));
+        header_err("# comment", 0, 1, |c| {
+            matches!(c, RuleNameStartsWithSymbol('#'))
+        });
+        header_err("| \"a\"", 0, 1, |c| {
+            matches!(c, RuleNameStartsWithSymbol('|'))
+        });
+        header_err("1rule = x", 0, 1, |c| matches!(c, RuleNameStartsWithDigit));
+        header_err("rule", 4, 1, |c| matches!(c, MissingAssignmentOp));
+        header_err("rule other = x", 5, 1, |c| matches!(c, MissingAssignmentOp));
+        header_err("rule-a = x", 4, 1, |c| {
+            matches!(c, IllegalCharInRuleName('-'))
+        });
+        header_err("  B_ANY = x", 2, 5, |c| {
+            matches!(c, ReservedUppercaseRuleName)
+        });
+        header_err("list(elem = elem", 4, 1, |c| {
+            matches!(c, UnclosedGroup { .. })
+        });
+    }
+
+    /// Precedence block of the README, with the rules using it
+    const PRECEDENCE_GRAMMAR: &str = r#"@precedence atom
+    | left sum "+" | "-"
+    | left product "*" | "/"
+    | right power "^"
+
+main = sum
+atom = number | "(" sum ")"
+number = @(B_ASCII_DIGIT+)"#;
+
+    #[test]
+    #[cfg(feature = "peggygen")]
+    fn precedence_levels_expansion() {
+        use crate::peggygen::gen_peggy;
+
+        // Levels are expanded like the rules of the README
+        let expanded = r#"
+sum = product (("+" | "-") product)*
+product = power (("*" | "/") power)*
+power = atom (("^") power)?
+
+main = sum
+atom = number | "(" sum ")"
+number = @(B_ASCII_DIGIT+)"#;
+
+        assert_eq!(
+            gen_peggy(&parse_peg(PRECEDENCE_GRAMMAR).unwrap()),
+            gen_peggy(&parse_peg(expanded).unwrap())
+        );
+
+        // Left-associative levels repeat their operations, while right-associative ones recurse
+        let level = |associativity: &str| {
+            let grammar = format!(
+                "@precedence atom\n| {} sum \"+\"\nmain = sum\natom = \"a\"",
+                associativity
+            );
+
+            gen_peggy(&parse_peg(&grammar).unwrap())
+        };
+
+        assert!(level("left").starts_with("sum = atom ((\"+\") atom)*\n"));
+        assert!(level("right").starts_with("sum = atom ((\"+\") sum)?\n"));
+    }
+
+    #[test]
+    fn precedence_levels_locations() {
+        let pst = parse_peg(PRECEDENCE_GRAMMAR).unwrap();
+
+        assert_eq!(pst.rules()["sum"].decl_loc(), ParserLoc::new(1, 11));
+        assert_eq!(pst.rules()["power"].decl_loc(), ParserLoc::new(3, 12));
+
+        // The operators keep their location in the grammar
+        let operators = match pst.rules()["product"].pattern().value() {
+            RulePatternValue::Suite(pieces) => pieces[1].clone(),
+            other => panic!("expected a suite, got {:?}", other),
+        };
+
+        assert_eq!(operators.loc(), ParserLoc::new(2, 19));
+    }
+
+    #[test]
+    fn precedence_block_without_levels() {
+        // Block at the end of the grammar
+        let err = parse_err("main = atom\natom = \"a\"\n@precedence atom");
+        assert_eq!(*err.loc(), ParserLoc::new(2, 1));
+        assert_eq!(err.length(), "precedence".len());
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::InvalidDirectiveArguments
+        ));
+
+        // Block followed by a regular rule
+        let err = parse_err("@precedence atom\nmain = atom\natom = \"a\"");
+        assert_eq!(*err.loc(), ParserLoc::new(0, 1));
+        assert_eq!(err.length(), "precedence".len());
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::InvalidDirectiveArguments
+        ));
+
+        // Blocks need an operand
+        let err = parse_err("@precedence\n| left sum \"+\"\nmain = sum");
+        assert_eq!(*err.loc(), ParserLoc::new(0, 1));
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::InvalidDirectiveArguments
+        ));
+    }
+
+    #[test]
+    fn invalid_precedence_levels() {
+        let level_err = |level: &str| {
+            parse_err(&format!(
+                "@precedence atom\n{}\nmain = sum\natom = \"a\"",
+                level
+            ))
+        };
+
+        // Invalid associativity
+        let err = level_err("  | middle sum \"+\"");
+        assert_eq!(*err.loc(), ParserLoc::new(1, 4));
+        assert_eq!(err.length(), "middle".len());
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::InvalidDirectiveArguments
+        ));
+
+        // Level without operators
+        for level in ["| left sum", "| left sum   ", "| left sum # comment"] {
+            let err = level_err(level);
+            assert_eq!(*err.loc(), ParserLoc::new(1, 7), "with {:?}", level);
+            assert_eq!(err.length(), "sum".len());
+            assert!(matches!(
+                err.content(),
+                ParserErrorContent::InvalidDirectiveArguments
+            ));
+        }
+
+        // Duplicate level name
+        let err = level_err("| left sum \"+\"\n| left sum \"*\"");
+        assert_eq!(*err.loc(), ParserLoc::new(2, 7));
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::DuplicateRuleName
+        ));
+
+        // Level named like a regular rule
+        let err = level_err("| left atom \"+\"");
+        assert_eq!(*err.loc(), ParserLoc::new(3, 0));
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::DuplicateRuleName
+        ));
+    }
+
+    #[test]
+    fn rules_after_precedence_block() {
+        // Union continuation lines following a regular rule continue it instead of declaring levels
+        let grammar = "@precedence atom\n| left sum \"+\"\nmain = sum\n  | \"b\"\natom = \"a\"";
+        let pst = parse_peg(grammar).unwrap();
+
+        assert_eq!(pst.rules().len(), 3);
+        assert_eq!(union_members(pst.main_rule().pattern()).len(), 2);
+
+        // Levels can't follow a regular rule, as they are parsed as its members
+        let err = parse_err(
+            "@precedence atom\n| left sum \"+\"\nmain = sum\n| left product \"*\"\natom = \"a\"",
+        );
+        assert_eq!(*err.loc(), ParserLoc::new(3, 2));
+        assert!(matches!(err.content(), ParserErrorContent::UnknownRule));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::grammar::data::*;
+use crate::grammar::utils::*;
+
+/// List the recursion cycles reachable from the entrypoint
+///
+/// Each cycle is listed once, starting with its lexicographically smallest rule name.
+pub fn find_cycles<'a>(pst: &'a PegSyntaxTree) -> Vec<Vec<&'a str>> {
+    let mut cycles: Vec<Vec<&'a str>> = vec![];
+
+    find_in_rule(
+        pst,
+        &mut vec![],
+        &mut |path, name| {
+            let start = path.iter().position(|rule| *rule == name).unwrap();
+            let mut cycle = path[start..].to_vec();
+
+            let smallest = (0..cycle.len()).min_by_key(|i| cycle[*i]).unwrap();
+            cycle.rotate_left(smallest);
+
+            if !cycles.contains(&cycle) {
+                cycles.push(cycle);
+            }
+        },
+        GRAMMAR_ENTRYPOINT_RULE,
+    );
+
+    cycles.sort();
+    cycles
+}
+
+pub fn find_in_rule<'a>(
+    pst: &'a PegSyntaxTree,
+    path: &mut Vec<&'a str>,
+    on_recursion: &mut impl FnMut(&[&'a str], &'a str),
+    rule_name: &'a str,
+) {
+    if is_valid_builtin_rule_name(rule_name) || is_external_rule_name(rule_name) {
+        return;
+    }
+
+    path.push(rule_name);
+
+    let rule = pst.rules().get(rule_name).unwrap();
+    build_rules_list(pst, path, on_recursion, rule.pattern().value());
+
+    path.pop();
+}
+
+pub fn build_rules_list<'a>(
+    pst: &'a PegSyntaxTree,
+    path: &mut Vec<&'a str>,
+    on_recursion: &mut impl FnMut(&[&'a str], &'a str),
+    pattern_value: &'a RulePatternValue,
+) {
+    match pattern_value {
+        RulePatternValue::CstString(_) | RulePatternValue::Cut => {}
+        RulePatternValue::Rule(name) => {
+            if path.contains(name) {
+                on_recursion(path, name);
+            } else {
+                find_in_rule(pst, path, on_recursion, name);
+            }
+        }
+        RulePatternValue::Group(pattern) | RulePatternValue::Until(pattern) => {
+            build_rules_list(pst, path, on_recursion, pattern.value())
+        }
+        RulePatternValue::Suite(patterns) | RulePatternValue::Union(patterns) => {
+            for pattern in patterns {
+                build_rules_list(pst, path, on_recursion, pattern.value());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parser::parse_peg;
+    use super::*;
+
+    #[test]
+    fn cycles_are_listed_once() {
+        let pst = parse_peg("a = \"a\" b?\nb = \"b\" c?\nc = \"c\" a\nmain = c b").unwrap();
+        assert_eq!(find_cycles(&pst), [vec!["a", "b", "c"]]);
+    }
+
+    #[test]
+    fn overlapping_cycles() {
+        let pst = parse_peg("a = \"a\" b?\nb = \"b\" (a | c)?\nc = \"c\" b\nmain = a").unwrap();
+        assert_eq!(find_cycles(&pst), [vec!["a", "b"], vec!["b", "c"]]);
+    }
+}
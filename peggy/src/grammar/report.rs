@@ -1,28 +1,170 @@
-use super::errors::ParserError;
+use super::data::ParserLoc;
+use super::errors::{ParserError, ParserWarning};
+use super::utils::BYTE_ORDER_MARK;
 
 /// Format in a human-readable way a compilation error
 pub fn pretty_format_parser_err(input: &str, err: ParserError) -> String {
-    let line = if err.line() < input.lines().count() {
-        input.lines().nth(err.line()).unwrap()
+    pretty_format_report(
+        input,
+        "ERROR",
+        err.loc(),
+        err.length(),
+        &format!("{}", err.content()),
+        err.tip(),
+    )
+}
+
+/// Format in a human-readable way a compilation warning
+pub fn pretty_format_parser_warning(input: &str, warning: &ParserWarning) -> String {
+    pretty_format_report(
+        input,
+        "WARNING",
+        warning.loc(),
+        warning.length(),
+        &format!("In rule [{}]: {}", warning.rule(), warning.content()),
+        warning.tip(),
+    )
+}
+
+/// Format a report pointing to a location of the input
+fn pretty_format_report(
+    input: &str,
+    kind: &str,
+    loc: &ParserLoc,
+    length: usize,
+    message: &str,
+    tip: Option<&str>,
+) -> String {
+    let line = if loc.line() < input.lines().count() {
+        input.lines().nth(loc.line()).unwrap()
     } else {
         ""
     };
-    let padding =
-        " ".repeat(line[..err.col()].chars().count() + (err.line() + 1).to_string().len() + 3);
-    let tip = err.tip().map(|tip| format!("\n{}Tip: {}", padding, tip));
+
+    // The byte order mark is invisible, so it isn't displayed to keep the pointer aligned
+    let (line, col) = match line.strip_prefix(BYTE_ORDER_MARK) {
+        Some(stripped) if loc.line() == 0 => (
+            stripped,
+            loc.col().saturating_sub(BYTE_ORDER_MARK.len_utf8()),
+        ),
+        _ => (line, loc.col()),
+    };
+
+    let padding = " ".repeat(line[..col].chars().count() + (loc.line() + 1).to_string().len() + 3);
+    let tip = tip.map(|tip| format!("\n{}Tip: {}", padding, tip));
 
     format!(
-        "ERROR: At line {}, column {}:\n\n{} | {}\n{}{}{}{}",
-        err.line() + 1,
-        err.col() + 1,
-        err.line() + 1,
+        "{}: At line {}, column {}:\n\n{} | {}\n{}{}{}{}",
+        kind,
+        loc.line() + 1,
+        col + 1,
+        loc.line() + 1,
         line,
         padding,
-        "^".repeat(if err.length() == 0 { 1 } else { err.length() }),
-        format!("{}", err.content())
+        "^".repeat(if length == 0 { 1 } else { length }),
+        message
             .lines()
             .map(|line| format!("\n{}{}", padding, line))
             .collect::<String>(),
-        tip.unwrap_or_else(String::new)
+        tip.unwrap_or_default()
     )
 }
+
+/// Editor-agnostic diagnostic built from a [`ParserError`] or a [`ParserWarning`], which maps directly to
+/// diagnostics of editors' protocols (e.g. the Language Server Protocol's)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Start position (inclusive), as a line number and a column counted in characters (both starting at 0)
+    pub start: (usize, usize),
+
+    /// End position (exclusive), in the same format as the start position
+    pub end: (usize, usize),
+
+    /// Message describing the problem, followed by a tip on a new line if there is one
+    pub message: String,
+
+    /// Severity of the problem
+    pub severity: DiagnosticSeverity,
+}
+
+/// Severity of a [`Diagnostic`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticSeverity {
+    /// The grammar can't be used
+    Error,
+
+    /// The grammar can be used, but probably contains a bug
+    Warning,
+}
+
+impl ParserError {
+    /// Convert the error to a [`Diagnostic`], using the input grammar to compute characters columns
+    pub fn to_diagnostic(&self, input: &str) -> Diagnostic {
+        build_diagnostic(
+            input,
+            self.loc(),
+            self.length(),
+            format!("{}", self.content()),
+            self.tip(),
+            DiagnosticSeverity::Error,
+        )
+    }
+}
+
+impl ParserWarning {
+    /// Convert the warning to a [`Diagnostic`], using the input grammar to compute characters columns
+    pub fn to_diagnostic(&self, input: &str) -> Diagnostic {
+        build_diagnostic(
+            input,
+            self.loc(),
+            self.length(),
+            format!("In rule [{}]: {}", self.rule(), self.content()),
+            self.tip(),
+            DiagnosticSeverity::Warning,
+        )
+    }
+}
+
+/// Build a diagnostic from a report's location and length (in bytes)
+fn build_diagnostic(
+    input: &str,
+    loc: &ParserLoc,
+    length: usize,
+    message: String,
+    tip: Option<&str>,
+    severity: DiagnosticSeverity,
+) -> Diagnostic {
+    let line = input.lines().nth(loc.line()).unwrap_or("");
+
+    // The byte order mark is invisible, so editors don't count it as a character
+    let (line, col) = match line.strip_prefix(BYTE_ORDER_MARK) {
+        Some(stripped) if loc.line() == 0 => (
+            stripped,
+            loc.col().saturating_sub(BYTE_ORDER_MARK.len_utf8()),
+        ),
+        _ => (line, loc.col()),
+    };
+
+    // Reports never span multiple lines, and must not cut a character in half
+    let mut start = col.min(line.len());
+    while !line.is_char_boundary(start) {
+        start -= 1;
+    }
+
+    let mut end = (col + length).min(line.len()).max(start);
+    while !line.is_char_boundary(end) {
+        end += 1;
+    }
+
+    let message = match tip {
+        Some(tip) => format!("{}\nTip: {}", message, tip),
+        None => message,
+    };
+
+    Diagnostic {
+        start: (loc.line(), line[..start].chars().count()),
+        end: (loc.line(), line[..end].chars().count()),
+        message,
+        severity,
+    }
+}
@@ -1,8 +1,11 @@
 use super::data::{ParserLoc, Pattern, PatternRepetition};
 use super::errors::{ParserError, ParserErrorContent};
-use super::parser::parse_rule_pattern;
+use super::parser::{parse_rule_pattern_with, ParametricRules};
 use std::rc::Rc;
 
+/// Slices of the input grammar, with their location
+pub type LocatedSlices<'a> = Vec<(&'a str, ParserLoc)>;
+
 /// Try to match a constant string pattern
 pub fn cst_string(input: &str, base_loc: ParserLoc) -> Result<Option<(&str, usize)>, ParserError> {
     let mut chars = input.chars();
@@ -51,15 +54,19 @@ pub fn cst_string(input: &str, base_loc: ParserLoc) -> Result<Option<(&str, usiz
 pub fn rule_name(input: &str, base_loc: ParserLoc) -> Result<Option<(&str, usize)>, ParserError> {
     let mut chars = input.chars();
 
-    match chars.next() {
-        Some(c) if c.is_alphabetic() => {}
+    let mut name_len = match chars.next() {
+        Some(c) if c.is_alphabetic() => c.len_utf8(),
         Some(_) | None => return Ok(None),
-    }
-
-    let mut name_len = 1;
+    };
 
     for c in chars {
-        if c.is_whitespace() || PatternRepetition::parse(c).is_some() || c == '|' || c == '#' {
+        if c.is_whitespace()
+            || PatternRepetition::parse(c).is_some()
+            || c == '|'
+            || c == '#'
+            || c == '('
+            || c == ')'
+        {
             break;
         }
 
@@ -75,14 +82,15 @@ pub fn rule_name(input: &str, base_loc: ParserLoc) -> Result<Option<(&str, usize
         name_len += c.len_utf8();
     }
 
-    Ok(Some((&input[..=name_len - 1], name_len)))
+    Ok(Some((&input[..name_len], name_len)))
 }
 
 /// Try to match a group
-pub fn group(
-    input: &str,
+pub fn group<'a>(
+    input: &'a str,
     base_loc: ParserLoc,
-) -> Result<Option<(Rc<Pattern>, usize)>, ParserError> {
+    parametric: &ParametricRules<'a>,
+) -> Result<Option<(Rc<Pattern<'a>>, usize)>, ParserError> {
     let mut chars = input.chars();
 
     let mut opened_string = false;
@@ -97,12 +105,12 @@ pub fn group(
     loop {
         let next_c = chars.next().ok_or_else(|| {
             ParserError::new(
-                base_loc.with_add_cols(group_length),
-                0,
+                base_loc,
+                1,
                 ParserErrorContent::UnclosedGroup {
                     started_at: base_loc,
                 },
-                Some("you may need to add a closing parenthesis ')'"),
+                Some("you may need to add a closing parenthesis ')', groups must be closed on the line they are opened on"),
             )
         })?;
 
@@ -116,7 +124,9 @@ pub fn group(
             continue;
         }
 
-        if next_c == '(' {
+        if next_c == '"' {
+            opened_string = true;
+        } else if next_c == '(' {
             inner_groups += 1;
         } else if next_c == ')' {
             if inner_groups == 0 {
@@ -127,11 +137,67 @@ pub fn group(
         }
     }
 
-    Ok(Some((
-        Rc::new(parse_rule_pattern(
+    let content = parametric.nested(base_loc, || {
+        parse_rule_pattern_with(
             &input[1..group_length - 1],
             base_loc.with_add_cols(1),
-        )?),
-        group_length,
-    )))
+            parametric,
+        )
+    })?;
+
+    Ok(Some((Rc::new(content), group_length)))
+}
+
+/// Try to match the arguments of a parametric rule's call (e.g. `(number, ",")` in `list(number, ",")`)
+///
+/// Arguments are separated by commas which are neither in a constant string nor in a group.
+/// They are returned unparsed, with their location.
+pub fn call_args(
+    input: &str,
+    base_loc: ParserLoc,
+) -> Result<Option<(LocatedSlices<'_>, usize)>, ParserError> {
+    match input.chars().next() {
+        Some('(') => {}
+        Some(_) | None => return Ok(None),
+    }
+
+    let mut args = vec![];
+    let mut arg_start = 1;
+
+    let mut opened_string = false;
+    let mut inner_groups: u32 = 0;
+
+    for (i, c) in input.char_indices().skip(1) {
+        if opened_string {
+            if c == '"' {
+                opened_string = false;
+            }
+
+            continue;
+        }
+
+        match c {
+            '"' => opened_string = true,
+            '(' => inner_groups += 1,
+            ')' if inner_groups > 0 => inner_groups -= 1,
+            ',' | ')' if inner_groups == 0 => {
+                args.push((&input[arg_start..i], base_loc.with_add_cols(arg_start)));
+                arg_start = i + 1;
+
+                if c == ')' {
+                    return Ok(Some((args, i + 1)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Err(ParserError::new(
+        base_loc,
+        1,
+        ParserErrorContent::UnclosedGroup {
+            started_at: base_loc,
+        },
+        Some("you may need to add a closing parenthesis ')' after the arguments, which must be on a single line"),
+    ))
 }
@@ -1,11 +1,13 @@
+use super::errors::RuleNameError;
+
 /// Left-trim an input and get the number of removed characters
-pub fn trim_start_and_count(input: &str) -> (&str, usize) {
+pub(crate) fn trim_start_and_count(input: &str) -> (&str, usize) {
     let trimmed = count_start_whitespaces(input);
     (&input[trimmed..], trimmed)
 }
 
 /// Count start whitespaces
-pub fn count_start_whitespaces(input: &str) -> usize {
+pub(crate) fn count_start_whitespaces(input: &str) -> usize {
     input
         .chars()
         .take_while(|c| c.is_whitespace())
@@ -16,11 +18,14 @@ pub fn count_start_whitespaces(input: &str) -> usize {
 /// * The line is empty
 /// * The line is only made of spaces
 /// * The line is only made of spaces and a comment (`# ...`)
-pub fn is_finished_line(line: &str) -> bool {
+pub(crate) fn is_finished_line(line: &str) -> bool {
     let trimmed = line.trim();
     trimmed.is_empty() || trimmed.starts_with('#')
 }
 
+/// Unicode byte order mark, which some editors put at the beginning of grammar files
+pub(crate) const BYTE_ORDER_MARK: char = '\u{feff}';
+
 /// Check if a rule's name refers to a builtin one
 pub fn is_builtin_rule_name(name: &str) -> bool {
     name.starts_with("B_")
@@ -31,16 +36,86 @@ pub fn is_valid_builtin_rule_name(name: &str) -> bool {
     BUILTIN_RULES.contains(&name)
 }
 
+/// Check if a rule's name refers to a zero-width builtin rule, which only checks the position it is matched at
+/// without consuming (nor capturing) anything
+pub fn is_zero_width_builtin_rule_name(name: &str) -> bool {
+    ZERO_WIDTH_BUILTIN_RULES.contains(&name)
+}
+
 /// Check if a rule's name refers to an external one
 pub fn is_external_rule_name(name: &str) -> bool {
     name.starts_with("E_")
 }
 
+/// Check if a rule's name refers to one of the [indentation external rules](`INDENTATION_EXTERNAL_RULES`)
+#[cfg(feature = "rustgen")]
+pub fn is_indentation_rule_name(name: &str) -> bool {
+    INDENTATION_EXTERNAL_RULES.contains(&name)
+}
+
 /// Check if a rule name is reserved
 pub fn is_reserved_rule_name(name: &str) -> bool {
     is_builtin_rule_name(name) || is_external_rule_name(name)
 }
 
+/// Keywords that can't be used as rule names, as they can't be turned into raw identifiers by generators
+pub static UNESCAPABLE_KEYWORDS: &[&str] = &["self", "Self", "super", "crate"];
+
+/// Check if a name can be used to declare a rule
+///
+/// Rule names must start with an alphabetic character, be only made of alphanumeric and underscore characters,
+/// and can't start with a reserved prefix (`B_` for builtin rules, `E_` for external rules).
+/// Some keywords are also forbidden (`self`, `Self`, `super` and `crate`), as generators can't turn them into raw identifiers.
+pub fn validate_rule_name(name: &str) -> Result<(), RuleNameError> {
+    let mut chars = name.char_indices();
+
+    match chars.next() {
+        None => return Err(RuleNameError::Empty),
+        Some((_, c)) if c.is_ascii_digit() => return Err(RuleNameError::StartsWithDigit),
+        Some((_, c)) if !c.is_alphabetic() => return Err(RuleNameError::IllegalFirstChar(c)),
+        Some(_) => {}
+    }
+
+    if let Some((at, c)) = chars.find(|(_, c)| !c.is_alphanumeric() && *c != '_') {
+        return Err(RuleNameError::IllegalChar { c, at });
+    }
+
+    if is_reserved_rule_name(name) {
+        return Err(RuleNameError::ReservedPrefix);
+    }
+
+    if UNESCAPABLE_KEYWORDS.contains(&name) {
+        return Err(RuleNameError::UnescapableKeyword);
+    }
+
+    Ok(())
+}
+
+/// Turn any string into a valid rule name (see [`validate_rule_name`])
+///
+/// Illegal characters are replaced by underscores, and names starting with a non-alphabetic character or a reserved prefix
+/// are prefixed with `rule_`. Note that two different strings may be turned into the same rule name.
+pub fn sanitize_rule_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+
+    if sanitized.is_empty() {
+        return "rule".to_string();
+    }
+
+    if !sanitized.starts_with(char::is_alphabetic) || is_reserved_rule_name(&sanitized) {
+        sanitized.insert_str(0, "rule_");
+    }
+
+    if UNESCAPABLE_KEYWORDS.contains(&sanitized.as_str()) {
+        sanitized.push('_');
+    }
+
+    sanitized
+}
+
 /// Exhaustive list of all builtin rules
 pub static BUILTIN_RULES: &[&str] = &[
     "B_ANY",
@@ -65,8 +140,95 @@ pub static BUILTIN_RULES: &[&str] = &[
     "B_NUMERIC",
     "B_UPPERCASE",
     "B_WHITESPACE",
+    "B_LETTER",
+    "B_MARK",
+    "B_DECIMAL_NUMBER",
+    "B_PUNCTUATION",
+    "B_CONNECTOR_PUNCTUATION",
+    "B_SYMBOL",
     "B_BIN_DIGIT",
     "B_OCTAL_DIGIT",
     "B_DEC_DIGIT",
+    "B_DIGIT_NONZERO",
     "B_HEX_DIGIT",
+    "B_LINE_START",
+    "B_WORD_BOUNDARY",
 ];
+
+/// Builtin rules which don't consume any character (see [`is_zero_width_builtin_rule_name`])
+pub static ZERO_WIDTH_BUILTIN_RULES: &[&str] = &["B_LINE_START", "B_WORD_BOUNDARY"];
+
+/// External rules matching the markers inserted by the indentation preprocessing of generated parsers
+/// (see the `indentation` option of the Rust generator)
+#[cfg(feature = "rustgen")]
+pub static INDENTATION_EXTERNAL_RULES: &[&str] = &["E_INDENT", "E_DEDENT", "E_NEWLINE"];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_names() {
+        assert_eq!(validate_rule_name(""), Err(RuleNameError::Empty));
+        assert_eq!(
+            validate_rule_name("1rule"),
+            Err(RuleNameError::StartsWithDigit)
+        );
+        assert_eq!(
+            validate_rule_name("_rule"),
+            Err(RuleNameError::IllegalFirstChar('_'))
+        );
+        assert_eq!(
+            validate_rule_name("rule-name"),
+            Err(RuleNameError::IllegalChar { c: '-', at: 4 })
+        );
+        assert_eq!(
+            validate_rule_name("B_x"),
+            Err(RuleNameError::ReservedPrefix)
+        );
+        assert_eq!(
+            validate_rule_name("E_x"),
+            Err(RuleNameError::ReservedPrefix)
+        );
+        assert_eq!(
+            validate_rule_name("self"),
+            Err(RuleNameError::UnescapableKeyword)
+        );
+        assert_eq!(
+            validate_rule_name("super"),
+            Err(RuleNameError::UnescapableKeyword)
+        );
+
+        assert_eq!(validate_rule_name("main"), Ok(()));
+        assert_eq!(validate_rule_name("rule_2"), Ok(()));
+        assert_eq!(validate_rule_name("règle"), Ok(()));
+        assert_eq!(validate_rule_name("規則"), Ok(()));
+    }
+
+    #[test]
+    fn sanitize_names() {
+        assert_eq!(sanitize_rule_name(""), "rule");
+        assert_eq!(sanitize_rule_name("1rule"), "rule_1rule");
+        assert_eq!(sanitize_rule_name("rule-name"), "rule_name");
+        assert_eq!(sanitize_rule_name("B_x"), "rule_B_x");
+        assert_eq!(sanitize_rule_name("self"), "self_");
+        assert_eq!(sanitize_rule_name("super"), "super_");
+        assert_eq!(sanitize_rule_name("main"), "main");
+        assert_eq!(sanitize_rule_name("règle"), "règle");
+        assert_eq!(sanitize_rule_name("規則"), "規則");
+    }
+
+    #[test]
+    fn sanitized_names_are_valid() {
+        for name in [
+            "", "1", "_", "B_", "E_x", "self", "a b", "🎉", "規則", "main",
+        ] {
+            assert_eq!(
+                validate_rule_name(&sanitize_rule_name(name)),
+                Ok(()),
+                "{:?}",
+                name
+            );
+        }
+    }
+}
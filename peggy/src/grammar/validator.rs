@@ -1,36 +1,314 @@
 use super::data::*;
-use super::errors::{ParserError, ParserErrorContent};
+use super::errors::{ParserError, ParserErrorContent, ParserWarning, ParserWarningContent};
+use super::graph::GrammarGraph;
+use super::parser::ParseLimits;
+use super::report::Diagnostic;
 use super::utils::*;
 use std::collections::HashSet;
 
 /// Validate a Peggy expression parsed with [`super::parse_peg_nocheck`]
 ///
 /// Expressions parsed with [`super::parse_peg`] don't require this check, as it is already performed automatically.
-pub fn validate_parsed_peg(pst: &PegSyntaxTree) -> Result<(), ParserError> {
-    let mut ordered_rules: Vec<_> = pst.rules().iter().collect();
-    ordered_rules.sort_by_key(|(_, rule)| rule.decl_loc.line());
+///
+/// Returns the list of [warnings](`ParserWarning`) emitted for the grammar, or its first error (see [`validate`] to get
+/// all of them).
+pub fn validate_parsed_peg(pst: &PegSyntaxTree) -> Result<Vec<ParserWarning>, ParserError> {
+    validate_parsed_peg_with_limits(pst, &ParseLimits::default())
+}
+
+/// Validate a Peggy expression parsed with [`super::parse_peg_nocheck`], following at most
+/// [`ParseLimits::max_validation_steps`] rule references (the other limits only apply to parsing)
+///
+/// Validation is linear in the size of the syntax tree, except for two checks which look through the used rules
+/// whether a pattern may capture (for the `main` rule) or consume (for each repeated pattern) anything. As they explore
+/// each path of the rules graph, they are exponential in the number of rules in the worst case (e.g. if each rule uses
+/// the next one twice). Each rule reference they follow counts as a step, so validation is `O(n + s)` with `n` the size
+/// of the syntax tree and `s` the maximum number of steps.
+pub fn validate_parsed_peg_with_limits(
+    pst: &PegSyntaxTree,
+    limits: &ParseLimits,
+) -> Result<Vec<ParserWarning>, ParserError> {
+    validate_with_limits(pst, limits).into_result()
+}
+
+/// Validate a Peggy expression parsed with [`super::parse_peg_nocheck`], collecting all of its errors and warnings
+///
+/// Unlike [`validate_parsed_peg`], validation doesn't stop at the first error, which allows tools to report every
+/// problem of a grammar at once.
+pub fn validate(pst: &PegSyntaxTree) -> ValidationReport {
+    validate_with_limits(pst, &ParseLimits::default())
+}
+
+/// Validate a Peggy expression parsed with [`super::parse_peg_nocheck`], collecting all of its errors and warnings
+/// (see [`validate_parsed_peg_with_limits`] for the limits)
+///
+/// Each check reports at most one error per rule. The checks of the whole grammar (e.g. unused rules) are skipped
+/// if a rule references an unknown rule or contains an invalid pattern, as they rely on a well-formed syntax tree.
+pub fn validate_with_limits(pst: &PegSyntaxTree, limits: &ParseLimits) -> ValidationReport {
+    validate_rules(pst, limits, None)
+}
+
+/// Validate the provided rules of a syntax tree whose other rules are known to be valid (e.g. after one of its rules
+/// changed, the rule and its dependents), along with the checks of the whole grammar they may affect
+pub(super) fn validate_affected_rules(
+    pst: &PegSyntaxTree,
+    limits: &ParseLimits,
+    affected: &HashSet<&str>,
+) -> ValidationReport {
+    validate_rules(pst, limits, Some(affected))
+}
+
+/// Validate the provided rules of a syntax tree (all of them if `None`)
+fn validate_rules(
+    pst: &PegSyntaxTree,
+    limits: &ParseLimits,
+    affected: Option<&HashSet<&str>>,
+) -> ValidationReport {
+    let is_affected = |name: &str| affected.is_none_or(|affected| affected.contains(name));
+
+    let ordered_rules = pst.ordered_rules();
+    let mut errors = vec![];
+    let mut warnings = vec![];
+    let mut steps = ValidationSteps {
+        remaining: limits.max_validation_steps,
+        max: limits.max_validation_steps,
+        exhausted: false,
+    };
+
+    // Whether a rule references an unknown rule or contains an invalid pattern
+    let mut malformed = false;
 
     // Validate each rule one by one
-    for (_, rule) in &ordered_rules {
-        validate_pattern_recursive(pst.rules(), rule.pattern())?;
-        check_potentially_empty_union_members(pst.rules(), rule.pattern())?;
+    for rule in ordered_rules.iter().filter(|rule| is_affected(rule.name())) {
+        if let Err(err) = validate_pattern_recursive(pst.rules(), rule.pattern()) {
+            errors.push(err);
+            malformed = true;
+            continue;
+        }
+
+        if let Err(err) = check_potentially_empty_union_members(rule.pattern()) {
+            errors.push(err);
+        }
+
+        check_unreachable_after_greedy_any(rule.name(), rule.pattern(), &mut warnings);
+        check_contradicted_lookaheads(rule.name(), rule.pattern(), &mut warnings);
+        check_redundant_optional_repetitions(rule.name(), rule.pattern(), &mut warnings);
+        check_duplicate_union_members(rule.name(), rule.pattern(), &mut warnings);
+        check_ineffective_cuts(rule.name(), rule.pattern(), false, &mut warnings);
+
+        // Once the steps are exhausted, every check following a rule reference would fail again
+        if !steps.exhausted {
+            if let Err(err) = check_repeated_non_consuming(
+                pst.rules(),
+                rule.name(),
+                rule.pattern(),
+                &mut warnings,
+                &mut steps,
+            ) {
+                errors.push(err);
+            }
+        }
+
+        check_equivalent_builtin_rule(rule, &mut warnings);
+    }
+
+    if !malformed {
+        // Detect unused rules (token rules are used by the generated tokenizer)
+        let mut used = list_used_rules(pst.rules());
+
+        for name in pst.token_rules() {
+            if used.insert(name) {
+                list_pattern_used_rules(pst.rules(), pst.rules()[name].pattern(), &mut used);
+            }
+        }
+
+        for rule in &ordered_rules {
+            if rule.name() != GRAMMAR_ENTRYPOINT_RULE && !used.contains(rule.name()) {
+                errors.push(ParserError::new(
+                    rule.decl_loc(),
+                    rule.name().len(),
+                    ParserErrorContent::UnusedRule,
+                    Some("if you are doing some testing, you can comment out the rule by starting it with the '#' symbol")
+                ));
+            }
+        }
+
+        if !steps.exhausted && is_affected(GRAMMAR_ENTRYPOINT_RULE) {
+            if let Err(err) = check_entrypoint_data(pst, &mut steps) {
+                errors.push(err);
+            }
+        }
+    }
+
+    // The version requirement can't change without compiling the grammar again
+    #[cfg(feature = "version-check")]
+    if affected.is_none() {
+        if let Err(err) = check_version_req(pst) {
+            errors.push(err);
+        }
+    }
+
+    ValidationReport { errors, warnings }
+}
+
+/// Result of [`validate`], which contains all the errors and warnings of a grammar
+#[derive(Debug)]
+pub struct ValidationReport {
+    errors: Vec<ParserError>,
+    warnings: Vec<ParserWarning>,
+}
+
+impl ValidationReport {
+    /// Get the errors, which prevent the grammar from being used, in order of appearance
+    pub fn errors(&self) -> &[ParserError] {
+        &self.errors
+    }
+
+    /// Get the warnings, which usually indicate a bug in the grammar
+    pub fn warnings(&self) -> &[ParserWarning] {
+        &self.warnings
     }
 
-    // Detect unused rules
-    let used = list_used_rules(pst.rules());
+    /// Check if the grammar can be used, which is the case if there is no error
+    ///
+    /// Warnings can be treated as fatal by checking them in addition (e.g. `report.warnings().is_empty()`).
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Convert all errors and warnings to [diagnostics](`Diagnostic`), errors first
+    pub fn to_diagnostics(&self, input: &str) -> Vec<Diagnostic> {
+        self.errors
+            .iter()
+            .map(|err| err.to_diagnostic(input))
+            .chain(
+                self.warnings
+                    .iter()
+                    .map(|warning| warning.to_diagnostic(input)),
+            )
+            .collect()
+    }
+
+    /// Get the warnings, or the first error if there is any
+    pub fn into_result(self) -> Result<Vec<ParserWarning>, ParserError> {
+        match self.errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(self.warnings),
+        }
+    }
+}
+
+/// Number of rule references validation can still follow (see [`ParseLimits::max_validation_steps`])
+struct ValidationSteps {
+    remaining: usize,
+    max: usize,
+
+    /// Whether a step was refused
+    exhausted: bool,
+}
+
+impl ValidationSteps {
+    /// Take a step to follow the provided rule reference, failing if none remains
+    fn take(&mut self, reference: &Pattern) -> Result<(), ParserError> {
+        if self.remaining == 0 {
+            self.exhausted = true;
 
-    for (name, rule) in &ordered_rules {
-        if **name != GRAMMAR_ENTRYPOINT_RULE && !used.contains(**name) {
             return Err(ParserError::new(
-                rule.decl_loc(),
-                name.len(),
-                ParserErrorContent::UnusedRule,
-                Some("if you are doing some testing, you can comment out the rule by starting it with the '#' symbol")
+                reference.loc(),
+                reference.decl_length(),
+                ParserErrorContent::TooManyValidationSteps { max: self.max },
+                Some("this usually means many rules use the same rules multiple times, which makes validation expensive"),
             ));
         }
+
+        self.remaining -= 1;
+        Ok(())
     }
+}
 
-    Ok(())
+/// Ensure the entrypoint rule either produces data or is entirely silent
+///
+/// Silent entrypoints produce no data at all, but other non-capturing ones (e.g. `main = ~"a"` or `main = °"a" !"b"`)
+/// have no type generators could return.
+fn check_entrypoint_data(
+    pst: &PegSyntaxTree,
+    steps: &mut ValidationSteps,
+) -> Result<(), ParserError> {
+    let main_rule = pst.main_rule();
+
+    if GrammarGraph::new(pst).non_capturing_mode(GRAMMAR_ENTRYPOINT_RULE)
+        == Some(PatternMode::Silent)
+        || !is_dataless_pattern(pst.rules(), main_rule.pattern(), &mut HashSet::new(), steps)?
+    {
+        return Ok(());
+    }
+
+    let culprit = first_moded_pattern(main_rule.pattern()).unwrap_or(main_rule.pattern());
+
+    Err(ParserError::new(
+        culprit.loc(),
+        culprit.decl_length(),
+        ParserErrorContent::DatalessEntrypoint,
+        Some("the main rule must capture some data, or be made entirely silent with the '°' mode marker"),
+    ))
+}
+
+/// Check if a pattern never captures any data, because of its mode or the mode of all of its inner patterns
+fn is_dataless_pattern<'a>(
+    rules: &Rules<'a>,
+    pattern: &Pattern<'a>,
+    visiting: &mut HashSet<&'a str>,
+    steps: &mut ValidationSteps,
+) -> Result<bool, ParserError> {
+    if pattern.is_dataless() {
+        return Ok(true);
+    }
+
+    if pattern.is_atomic() {
+        return Ok(false);
+    }
+
+    match pattern.value() {
+        RulePatternValue::CstString(_) | RulePatternValue::Until(_) => Ok(false),
+        RulePatternValue::Cut => Ok(true),
+        RulePatternValue::Rule(name) => match rules.get(name) {
+            // Recursive rules are assumed to capture data, as they can't be made only of themselves
+            Some(rule) if visiting.insert(name) => {
+                steps.take(pattern)?;
+                let dataless = is_dataless_pattern(rules, rule.pattern(), visiting, steps);
+                visiting.remove(name);
+                dataless
+            }
+            _ => Ok(false),
+        },
+        RulePatternValue::Group(inner) => is_dataless_pattern(rules, inner, visiting, steps),
+        RulePatternValue::Suite(patterns) | RulePatternValue::Union(patterns) => {
+            for pattern in patterns {
+                if !is_dataless_pattern(rules, pattern, visiting, steps)? {
+                    return Ok(false);
+                }
+            }
+
+            Ok(true)
+        }
+    }
+}
+
+/// Find the first pattern with a mode, from the provided pattern to its innermost ones
+fn first_moded_pattern<'p, 'a>(pattern: &'p Pattern<'a>) -> Option<&'p Pattern<'a>> {
+    if pattern.mode().is_some() {
+        return Some(pattern);
+    }
+
+    match pattern.value() {
+        RulePatternValue::CstString(_) | RulePatternValue::Rule(_) | RulePatternValue::Cut => None,
+        RulePatternValue::Group(inner) | RulePatternValue::Until(inner) => {
+            first_moded_pattern(inner)
+        }
+        RulePatternValue::Suite(patterns) | RulePatternValue::Union(patterns) => {
+            patterns.iter().find_map(first_moded_pattern)
+        }
+    }
 }
 
 /// Validate a [`RulePattern`] recursively
@@ -39,8 +317,23 @@ fn validate_pattern_recursive<'a>(
     pattern: &'a Pattern,
 ) -> Result<(), ParserError> {
     match pattern.value() {
-        // Constant strings don't need any validation
-        RulePatternValue::CstString(_) => Ok(()),
+        // Constant strings can't contain raw control characters, which can't be represented reliably in the grammar
+        RulePatternValue::CstString(string) => {
+            match string.char_indices().find(|(_, c)| c.is_control()) {
+                None => Ok(()),
+                Some((i, c)) => Err(ParserError::new(
+                    // Skip the opening quote
+                    pattern.loc().with_add_cols(1 + i),
+                    c.len_utf8(),
+                    ParserErrorContent::ControlCharInCstString(c),
+                    Some(match c {
+                        // Matching operates on the raw input, so newlines are matched like any other character
+                        '\n' | '\r' => "newlines can be matched with the B_NEWLINE_LF and B_NEWLINE_CR builtin rules",
+                        _ => "use builtin rules to match control characters (e.g. B_NEWLINE_LF)",
+                    }),
+                )),
+            }
+        }
 
         // For rules, ensure the specified one exists
         RulePatternValue::Rule(name) => {
@@ -65,11 +358,29 @@ fn validate_pattern_recursive<'a>(
             }
         }
 
-        // Develop groups
-        RulePatternValue::Group(pattern) => validate_pattern_recursive(rules, pattern),
+        RulePatternValue::Cut => Ok(()),
+
+        // Develop groups and terminators
+        RulePatternValue::Group(pattern) | RulePatternValue::Until(pattern) => {
+            validate_pattern_recursive(rules, pattern)
+        }
 
         // Develop suites and unions
         RulePatternValue::Suite(patterns) | RulePatternValue::Union(patterns) => {
+            // The parser never produces empty suites or unions, but generators can't handle them
+            if patterns.is_empty() {
+                return Err(ParserError::new(
+                    pattern.loc(),
+                    pattern.decl_length(),
+                    if matches!(pattern.value(), RulePatternValue::Suite(_)) {
+                        ParserErrorContent::EmptySuite
+                    } else {
+                        ParserErrorContent::EmptyUnion
+                    },
+                    None,
+                ));
+            }
+
             for pattern in patterns {
                 validate_pattern_recursive(rules, pattern)?;
             }
@@ -79,6 +390,35 @@ fn validate_pattern_recursive<'a>(
     }
 }
 
+/// Ensure the running version of Peggy satisfies the grammar's requirement, if any (see [`PegSyntaxTree::version_req`])
+#[cfg(feature = "version-check")]
+fn check_version_req(pst: &PegSyntaxTree) -> Result<(), ParserError> {
+    let (req, loc) = match pst.version_req {
+        Some(version_req) => version_req,
+        None => return Ok(()),
+    };
+
+    let version = semver::Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+
+    // Requirements were already checked when parsing the directive
+    let satisfied = semver::VersionReq::parse(req)
+        .map(|parsed| parsed.matches(&version))
+        .unwrap_or(false);
+
+    if satisfied {
+        Ok(())
+    } else {
+        Err(ParserError::new(
+            loc,
+            req.len(),
+            ParserErrorContent::IncompatibleGrammarVersion {
+                required: req.to_string(),
+            },
+            Some("use a version of Peggy matching the requirement, or update the grammar and its '@peggy' directive"),
+        ))
+    }
+}
+
 /// List all used rules (directly or inderectly, starting from the entrypoint rule)
 pub fn list_used_rules<'a>(rules: &Rules<'a>) -> HashSet<&'a str> {
     let mut used_rules = HashSet::new();
@@ -96,9 +436,8 @@ fn list_pattern_used_rules<'a>(
     pattern: &Pattern<'a>,
     used_rules: &mut HashSet<&'a str>,
 ) {
-    match pattern.value() {
-        RulePatternValue::CstString(_) => {}
-        RulePatternValue::Rule(name) => {
+    pattern.walk(&mut |pattern| {
+        if let RulePatternValue::Rule(name) = pattern.value() {
             if !is_valid_builtin_rule_name(name)
                 && !is_external_rule_name(name)
                 && used_rules.insert(name)
@@ -106,20 +445,11 @@ fn list_pattern_used_rules<'a>(
                 list_pattern_used_rules(rules, rules[name].pattern(), used_rules)
             }
         }
-        RulePatternValue::Group(pattern) => list_pattern_used_rules(rules, pattern, used_rules),
-        RulePatternValue::Suite(patterns) | RulePatternValue::Union(patterns) => {
-            for pattern in patterns {
-                list_pattern_used_rules(rules, pattern, used_rules);
-            }
-        }
-    }
+    });
 }
 
 /// Check for potentially-empty union members, which could cause infinite loops
-fn check_potentially_empty_union_members<'a>(
-    rules: &Rules<'a>,
-    pattern: &Pattern<'a>,
-) -> Result<bool, ParserError> {
+fn check_potentially_empty_union_members(pattern: &Pattern) -> Result<bool, ParserError> {
     match pattern.repetition() {
         Some(PatternRepetition::Any) => return Ok(true),
         Some(PatternRepetition::OneOrMore) => {}
@@ -130,15 +460,23 @@ fn check_potentially_empty_union_members<'a>(
     match pattern.value() {
         RulePatternValue::CstString(_) => Ok(false),
 
-        RulePatternValue::Rule(_) => Ok(false),
+        RulePatternValue::Rule(name) => Ok(is_zero_width_builtin_rule_name(name)),
+
+        RulePatternValue::Cut => Ok(true),
 
         // Develop groups
-        RulePatternValue::Group(pattern) => check_potentially_empty_union_members(rules, pattern),
+        RulePatternValue::Group(pattern) => check_potentially_empty_union_members(pattern),
+
+        // The terminator may be found right away
+        RulePatternValue::Until(pattern) => {
+            check_potentially_empty_union_members(pattern)?;
+            Ok(true)
+        }
 
         // Develop suites and unions
         RulePatternValue::Suite(patterns) => {
             for pattern in patterns {
-                let is_potentially_empty = check_potentially_empty_union_members(rules, pattern)?;
+                let is_potentially_empty = check_potentially_empty_union_members(pattern)?;
 
                 if !is_potentially_empty {
                     return Ok(false);
@@ -150,7 +488,7 @@ fn check_potentially_empty_union_members<'a>(
 
         RulePatternValue::Union(patterns) => {
             for pattern in patterns {
-                let is_potentially_empty = check_potentially_empty_union_members(rules, pattern)?;
+                let is_potentially_empty = check_potentially_empty_union_members(pattern)?;
 
                 if is_potentially_empty {
                     return Err(ParserError::new(
@@ -166,3 +504,796 @@ fn check_potentially_empty_union_members<'a>(
         }
     }
 }
+
+/// Check for terminals following a greedy `B_ANY` repetition in suites, which can never match
+/// as the repetition consumes all the remaining input without ever backtracking
+fn check_unreachable_after_greedy_any(
+    rule: &str,
+    pattern: &Pattern,
+    warnings: &mut Vec<ParserWarning>,
+) {
+    pattern.walk(&mut |pattern| {
+        if let RulePatternValue::Suite(patterns) = pattern.value() {
+            for (pattern, next) in patterns.iter().zip(patterns.iter().skip(1)) {
+                if is_greedy_any(pattern) && is_required_terminal(next) {
+                    warnings.push(ParserWarning::new(
+                        next.loc(),
+                        next.decl_length(),
+                        rule,
+                        ParserWarningContent::UnreachableAfterGreedyAny(next.to_string()),
+                        Some("match up to the terminal instead, e.g. ..\"end\" \"end\""),
+                    ));
+                }
+            }
+        }
+    });
+}
+
+/// Check if a pattern consumes all the remaining input (e.g. `B_ANY*`)
+fn is_greedy_any(pattern: &Pattern) -> bool {
+    if matches!(
+        pattern.mode(),
+        Some(PatternMode::Peek) | Some(PatternMode::Negative)
+    ) {
+        return false;
+    }
+
+    match pattern.repetition() {
+        Some(PatternRepetition::Any) | Some(PatternRepetition::OneOrMore) => {
+            matches_any_char(pattern)
+        }
+        Some(PatternRepetition::Optional) => false,
+        None => match pattern.value() {
+            RulePatternValue::Group(inner) => is_greedy_any(inner),
+            _ => false,
+        },
+    }
+}
+
+/// Check if a single iteration of a pattern matches any character (e.g. `B_ANY` or `(B_ANY)`)
+fn matches_any_char(pattern: &Pattern) -> bool {
+    match pattern.value() {
+        RulePatternValue::Rule(name) => *name == "B_ANY",
+        RulePatternValue::Group(inner) => {
+            inner.repetition().is_none()
+                && !matches!(
+                    inner.mode(),
+                    Some(PatternMode::Peek) | Some(PatternMode::Negative)
+                )
+                && matches_any_char(inner)
+        }
+        _ => false,
+    }
+}
+
+/// Check if a pattern is a terminal (constant string or builtin rule) which requires at least one character to match
+fn is_required_terminal(pattern: &Pattern) -> bool {
+    if matches!(
+        pattern.repetition(),
+        Some(PatternRepetition::Any) | Some(PatternRepetition::Optional)
+    ) || matches!(pattern.mode(), Some(PatternMode::Negative))
+    {
+        return false;
+    }
+
+    match pattern.value() {
+        RulePatternValue::CstString(_) => true,
+        RulePatternValue::Rule(name) => {
+            is_valid_builtin_rule_name(name) && !is_zero_width_builtin_rule_name(name)
+        }
+        RulePatternValue::Group(inner) => is_required_terminal(inner),
+        RulePatternValue::Suite(_)
+        | RulePatternValue::Union(_)
+        | RulePatternValue::Until(_)
+        | RulePatternValue::Cut => false,
+    }
+}
+
+/// Check for constant strings in suites which contradict the peek or negative constant strings preceding them
+/// (e.g. `~"a" "b"` or `!"a" "ab"`), which means the suite can never match
+///
+/// As peek and negative patterns don't consume anything, the following pieces are matched at the same position.
+fn check_contradicted_lookaheads(rule: &str, pattern: &Pattern, warnings: &mut Vec<ParserWarning>) {
+    pattern.walk(&mut |pattern| {
+        let RulePatternValue::Suite(patterns) = pattern.value() else {
+            return;
+        };
+
+        // Strings the input must start with (peek patterns) or must not start with (negative patterns)
+        // at the current position
+        let mut peeked: Vec<&str> = vec![];
+        let mut rejected: Vec<&str> = vec![];
+
+        for pattern in patterns {
+            let string = fixed_cst_string(pattern);
+
+            if let Some(string) = string {
+                let contradicts = if pattern.is_negative() {
+                    peeked.iter().any(|peeked| peeked.starts_with(string))
+                } else {
+                    peeked.iter().any(|peeked| {
+                        !peeked.starts_with(string) && !string.starts_with(peeked)
+                    }) || rejected.iter().any(|rejected| string.starts_with(rejected))
+                };
+
+                if contradicts {
+                    warnings.push(ParserWarning::new(
+                        pattern.loc(),
+                        pattern.decl_length(),
+                        rule,
+                        ParserWarningContent::ContradictsLookahead(pattern.to_string()),
+                        Some("peek (~) and negative (!) patterns don't consume anything, so the next pattern is matched at the same position"),
+                    ));
+                }
+            }
+
+            match (pattern.mode(), string) {
+                (Some(PatternMode::Peek), Some(string)) => peeked.push(string),
+                (Some(PatternMode::Negative), Some(string)) => rejected.push(string),
+                (Some(PatternMode::Peek), None) | (Some(PatternMode::Negative), None) => {}
+
+                // The pattern consumed some input (or may have)
+                _ => {
+                    peeked.clear();
+                    rejected.clear();
+                }
+            }
+        }
+    });
+}
+
+/// Warn about optional groups containing a single repetition (e.g. `(a+)?` or `(a*)?`), which match the same input as
+/// the repetition alone (respectively `a*` and `a*`)
+///
+/// The optional group only wraps the repetition's data in an `Option`, while an empty repetition already means
+/// the content is absent.
+fn check_redundant_optional_repetitions(
+    rule: &str,
+    pattern: &Pattern,
+    warnings: &mut Vec<ParserWarning>,
+) {
+    pattern.walk(&mut |pattern| {
+        if pattern.repetition() != Some(PatternRepetition::Optional) {
+            return;
+        }
+
+        let RulePatternValue::Group(inner) = pattern.value() else {
+            return;
+        };
+
+        // Peek and negative patterns don't match like their content, e.g. `(!"a"+)?` always matches but `!"a"*` never does
+        if inner.is_negative() || inner.mode() == Some(PatternMode::Peek) {
+            return;
+        }
+
+        let equivalent = match inner.repetition() {
+            Some(PatternRepetition::OneOrMore) | Some(PatternRepetition::Any) => PatternRepetition::Any,
+            Some(PatternRepetition::Optional) => PatternRepetition::Optional,
+            None => return,
+        };
+
+        warnings.push(ParserWarning::new(
+            pattern.loc(),
+            pattern.decl_length(),
+            rule,
+            ParserWarningContent::RedundantOptionalRepetition(equivalent),
+            Some("an empty repetition already means the content is absent, and its data doesn't need to be wrapped in an Option"),
+        ));
+    });
+}
+
+/// Warn about union members identical to an earlier member of the same union (e.g. `"a" | b | "a"`), including when
+/// they are only wrapped in a group which doesn't change how they match (e.g. `"a" | ("a")`)
+///
+/// As union members are tried in order, the duplicated member can only be tried after the identical one failed to match.
+fn check_duplicate_union_members(rule: &str, pattern: &Pattern, warnings: &mut Vec<ParserWarning>) {
+    pattern.walk(&mut |pattern| {
+        let RulePatternValue::Union(members) = pattern.value() else {
+            return;
+        };
+
+        for (i, member) in members.iter().enumerate() {
+            let Some(first) = members[..i]
+                .iter()
+                .find(|earlier| is_same_matching(earlier, member))
+            else {
+                continue;
+            };
+
+            warnings.push(ParserWarning::new(
+                member.loc(),
+                member.decl_length(),
+                rule,
+                ParserWarningContent::DuplicateUnionMember { first: first.loc() },
+                Some("remove this member"),
+            ));
+        }
+    });
+}
+
+/// Check if two patterns match the same input in the same way, ignoring their location and the groups which don't
+/// change how their content matches (see [`Pattern::structurally_eq`])
+fn is_same_matching(a: &Pattern, b: &Pattern) -> bool {
+    let (a, b) = (strip_plain_groups(a), strip_plain_groups(b));
+
+    if a.mode() != b.mode() || a.repetition() != b.repetition() {
+        return false;
+    }
+
+    match (a.value(), b.value()) {
+        (RulePatternValue::CstString(a), RulePatternValue::CstString(b)) => a == b,
+        (RulePatternValue::Rule(a), RulePatternValue::Rule(b)) => a == b,
+        (RulePatternValue::Cut, RulePatternValue::Cut) => true,
+        (RulePatternValue::Group(a), RulePatternValue::Group(b))
+        | (RulePatternValue::Until(a), RulePatternValue::Until(b)) => is_same_matching(a, b),
+        (RulePatternValue::Suite(a), RulePatternValue::Suite(b))
+        | (RulePatternValue::Union(a), RulePatternValue::Union(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| is_same_matching(a, b))
+        }
+        _ => false,
+    }
+}
+
+/// Get the content of a group without mode nor repetition (e.g. `"a"` for `(("a"))`), or the pattern itself
+fn strip_plain_groups<'p, 'a>(pattern: &'p Pattern<'a>) -> &'p Pattern<'a> {
+    match pattern.value() {
+        RulePatternValue::Group(inner)
+            if pattern.mode().is_none() && pattern.repetition().is_none() =>
+        {
+            strip_plain_groups(inner)
+        }
+        _ => pattern,
+    }
+}
+
+/// Warn about cuts which don't commit to any union member (e.g. `a = "b" ^ "c"` or `a = ("b" ^ "c")* | "d"`)
+///
+/// Cuts only commit to the closest enclosing union member of their rule, outside of repeated and negative patterns
+/// (see [`Pattern::forwards_cuts`]).
+fn check_ineffective_cuts(
+    rule: &str,
+    pattern: &Pattern,
+    in_member: bool,
+    warnings: &mut Vec<ParserWarning>,
+) {
+    let in_member = in_member && pattern.forwards_cuts();
+
+    match pattern.value() {
+        RulePatternValue::Cut if !in_member => warnings.push(ParserWarning::new(
+            pattern.loc(),
+            pattern.decl_length(),
+            rule,
+            ParserWarningContent::IneffectiveCut,
+            Some("remove this cut, or move it to the union member it should commit to"),
+        )),
+        RulePatternValue::CstString(_) | RulePatternValue::Rule(_) | RulePatternValue::Cut => {}
+        RulePatternValue::Group(inner) => check_ineffective_cuts(rule, inner, in_member, warnings),
+        RulePatternValue::Suite(patterns) => {
+            for pattern in patterns {
+                check_ineffective_cuts(rule, pattern, in_member, warnings);
+            }
+        }
+        RulePatternValue::Union(members) => {
+            for member in members {
+                check_ineffective_cuts(rule, member, true, warnings);
+            }
+        }
+        RulePatternValue::Until(terminator) => {
+            check_ineffective_cuts(rule, terminator, false, warnings)
+        }
+    }
+}
+
+/// Warn about repetitions applied to patterns which never consume anything (e.g. `(~"a")*` or `B_LINE_START?`)
+///
+/// Such patterns match at the same position on every iteration, so the repetition either changes nothing
+/// or loops forever.
+fn check_repeated_non_consuming(
+    rules: &Rules,
+    rule: &str,
+    pattern: &Pattern,
+    warnings: &mut Vec<ParserWarning>,
+    steps: &mut ValidationSteps,
+) -> Result<(), ParserError> {
+    if pattern.repetition().is_some()
+        && is_non_consuming_pattern(rules, pattern, &mut HashSet::new(), steps)?
+    {
+        warnings.push(ParserWarning::new(
+            pattern.loc(),
+            pattern.decl_length(),
+            rule,
+            ParserWarningContent::RepeatedNonConsumingPattern(pattern.to_string()),
+            Some("peek (~) and negative (!) patterns, as well as zero-width builtin rules, don't consume anything: the repetition symbol can be removed"),
+        ));
+
+        return Ok(());
+    }
+
+    for child in pattern.children() {
+        check_repeated_non_consuming(rules, rule, child, warnings, steps)?;
+    }
+
+    Ok(())
+}
+
+/// Check if a pattern never consumes anything, ignoring its repetition
+fn is_non_consuming_pattern<'a>(
+    rules: &Rules<'a>,
+    pattern: &Pattern<'a>,
+    visiting: &mut HashSet<&'a str>,
+    steps: &mut ValidationSteps,
+) -> Result<bool, ParserError> {
+    if matches!(
+        pattern.mode(),
+        Some(PatternMode::Peek) | Some(PatternMode::Negative)
+    ) {
+        return Ok(true);
+    }
+
+    match pattern.value() {
+        RulePatternValue::CstString(_) | RulePatternValue::Until(_) => Ok(false),
+        RulePatternValue::Rule(name) if is_zero_width_builtin_rule_name(name) => Ok(true),
+        RulePatternValue::Cut => Ok(true),
+        RulePatternValue::Rule(name) => match rules.get(name) {
+            // Recursive rules are assumed to consume something, as they can't be made only of themselves
+            Some(rule) if visiting.insert(name) => {
+                steps.take(pattern)?;
+                let non_consuming =
+                    is_non_consuming_pattern(rules, rule.pattern(), visiting, steps);
+                visiting.remove(name);
+                non_consuming
+            }
+            _ => Ok(false),
+        },
+        RulePatternValue::Group(inner) => is_non_consuming_pattern(rules, inner, visiting, steps),
+        RulePatternValue::Suite(patterns) | RulePatternValue::Union(patterns) => {
+            for pattern in patterns {
+                if !is_non_consuming_pattern(rules, pattern, visiting, steps)? {
+                    return Ok(false);
+                }
+            }
+
+            Ok(true)
+        }
+    }
+}
+
+/// Get the constant string a pattern always matches, ignoring its mode (e.g. `"a"` or `("a")`)
+///
+/// Returns `None` if the pattern may match something else (e.g. if it has a repetition)
+fn fixed_cst_string<'a>(pattern: &Pattern<'a>) -> Option<&'a str> {
+    if pattern.repetition().is_some() {
+        return None;
+    }
+
+    match pattern.value() {
+        RulePatternValue::CstString(string) => Some(string),
+        RulePatternValue::Group(inner) if inner.mode().is_none() => fixed_cst_string(inner),
+        _ => None,
+    }
+}
+
+/// Check if a rule's body is a union matching exactly the same characters as a builtin rule
+/// (e.g. `"0" | "1" | ... | "9"` for `B_ASCII_DIGIT`)
+fn check_equivalent_builtin_rule(rule: &Rule, warnings: &mut Vec<ParserWarning>) {
+    let pattern = rule.pattern();
+
+    // Rules only aliasing a builtin rule are fine
+    if !matches!(pattern.value(), RulePatternValue::Union(_)) {
+        return;
+    }
+
+    let mask = match ascii_chars_mask(pattern) {
+        Some(mask) => mask,
+        None => return,
+    };
+
+    let equivalent = BUILTIN_RULES
+        .iter()
+        .find(|name| builtin_rule_ascii_chars_mask(name) == Some(mask));
+
+    if let Some(name) = equivalent {
+        warnings.push(ParserWarning::new(
+            pattern.loc(),
+            pattern.decl_length(),
+            rule.name(),
+            ParserWarningContent::EquivalentToBuiltinRule(name),
+            Some("builtin rules are clearer and faster to match"),
+        ));
+    }
+}
+
+/// Get the set of ASCII characters a pattern matches, as a bit mask (bit `n` being the character with code `n`)
+///
+/// Returns `None` if the pattern doesn't match a single ASCII character from a fixed set
+/// (e.g. if it matches longer strings, or uses a repetition).
+fn ascii_chars_mask(pattern: &Pattern) -> Option<u128> {
+    if pattern.mode().is_some() || pattern.repetition().is_some() {
+        return None;
+    }
+
+    match pattern.value() {
+        RulePatternValue::CstString(string) => {
+            let mut chars = string.chars();
+
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii() => Some(1 << c as u32),
+                _ => None,
+            }
+        }
+        RulePatternValue::Rule(name) => builtin_rule_ascii_chars_mask(name),
+        RulePatternValue::Group(inner) => ascii_chars_mask(inner),
+        RulePatternValue::Union(patterns) => patterns
+            .iter()
+            .try_fold(0, |mask, pattern| Some(mask | ascii_chars_mask(pattern)?)),
+        RulePatternValue::Suite(_) | RulePatternValue::Until(_) | RulePatternValue::Cut => None,
+    }
+}
+
+/// Get the set of characters a builtin rule matches, as a bit mask (see [`ascii_chars_mask`])
+///
+/// Returns `None` if the rule doesn't exist or can match non-ASCII characters.
+fn builtin_rule_ascii_chars_mask(name: &str) -> Option<u128> {
+    let matches: fn(&u8) -> bool = match name {
+        "B_NEWLINE_CR" => |c| *c == b'\r',
+        "B_NEWLINE_LF" => |c| *c == b'\n',
+        "B_DOUBLE_QUOTE" => |c| *c == b'"',
+        "B_ASCII" => |_| true,
+        "B_ASCII_ALPHABETIC" => u8::is_ascii_alphabetic,
+        "B_ASCII_ALPHANUMERIC" => u8::is_ascii_alphanumeric,
+        "B_ASCII_CONTROL" => u8::is_ascii_control,
+        "B_ASCII_DIGIT" => u8::is_ascii_digit,
+        "B_ASCII_GRAPHIC" => u8::is_ascii_graphic,
+        "B_ASCII_HEXDIGIT" => u8::is_ascii_hexdigit,
+        "B_ASCII_LOWERCASE" => u8::is_ascii_lowercase,
+        "B_ASCII_PUNCTUATION" => u8::is_ascii_punctuation,
+        "B_ASCII_UPPERCASE" => u8::is_ascii_uppercase,
+        "B_ASCII_WHITESPACE" => u8::is_ascii_whitespace,
+        "B_BIN_DIGIT" => |c| matches!(*c, b'0'..=b'1'),
+        "B_OCTAL_DIGIT" => |c| matches!(*c, b'0'..=b'7'),
+        "B_DEC_DIGIT" => u8::is_ascii_digit,
+        "B_DIGIT_NONZERO" => |c| matches!(*c, b'1'..=b'9'),
+        "B_HEX_DIGIT" => u8::is_ascii_hexdigit,
+        _ => return None,
+    };
+
+    Some((0..128u8).filter(matches).fold(0, |mask, c| mask | 1 << c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parser::{parse_peg, parse_peg_nocheck, parse_peg_with_warnings};
+    use super::*;
+
+    fn parse_err(grammar: &str) -> ParserError {
+        parse_peg(grammar).unwrap_err()
+    }
+
+    #[test]
+    fn control_chars_in_cst_strings() {
+        let err = parse_err("main = \"a\tb\"");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::ControlCharInCstString('\t')
+        ));
+        assert_eq!((err.line(), err.col(), err.length()), (0, 9, 1));
+
+        // Other characters are accepted, even outside of the ASCII range
+        parse_peg("main = \"`→ \u{a0}\"").unwrap();
+    }
+
+    #[test]
+    fn newlines_in_cst_strings() {
+        let err = parse_err("main = \"a\rb\"");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::ControlCharInCstString('\r')
+        ));
+        assert!(err.tip().unwrap().contains("B_NEWLINE_LF and B_NEWLINE_CR"));
+
+        // Grammars are split on line feeds, so the string is injected in the tree
+        let mut pst = parse_peg_nocheck(r#"main = "a""#).unwrap();
+        pst.set_rule_value("main", RulePatternValue::CstString("a\nb"));
+
+        let err = validate_parsed_peg(&pst).unwrap_err();
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::ControlCharInCstString('\n')
+        ));
+        assert_eq!((err.col(), err.length()), (9, 1));
+        assert!(err.tip().unwrap().contains("B_NEWLINE_LF and B_NEWLINE_CR"));
+
+        // Other control characters keep the generic hint
+        let err = parse_err("main = \"a\tb\"");
+        assert!(!err.tip().unwrap().contains("B_NEWLINE_CR"));
+    }
+
+    #[test]
+    fn empty_suites_and_unions() {
+        let mut pst = parse_peg_nocheck(r#"main = "a" | "b""#).unwrap();
+        pst.set_rule_value("main", RulePatternValue::Union(vec![]));
+
+        let err = validate_parsed_peg(&pst).unwrap_err();
+        assert!(matches!(err.content(), ParserErrorContent::EmptyUnion));
+        assert_eq!((err.line(), err.col()), (0, 7));
+
+        let mut pst = parse_peg_nocheck(r#"main = "a" "b""#).unwrap();
+        pst.set_rule_value("main", RulePatternValue::Suite(vec![]));
+
+        let err = validate_parsed_peg(&pst).unwrap_err();
+        assert!(matches!(err.content(), ParserErrorContent::EmptySuite));
+    }
+
+    #[test]
+    fn silent_entrypoints() {
+        parse_peg("main = °\"x\"").unwrap();
+        parse_peg("main = °(\"x\" \"y\")").unwrap();
+        parse_peg("a = °\"x\"\nmain = a").unwrap();
+    }
+
+    #[test]
+    fn dataless_entrypoints() {
+        let err = parse_err("main = ~\"a\"");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::DatalessEntrypoint
+        ));
+        assert_eq!((err.line(), err.col(), err.length()), (0, 8, 3));
+
+        // Silent and negative patterns make a non-silent, non-capturing suite
+        let err = parse_err("main = °\"a\" !\"b\"");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::DatalessEntrypoint
+        ));
+        assert_eq!((err.line(), err.col()), (0, 9));
+
+        let err = parse_err("a = !\"a\"\nmain = a");
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::DatalessEntrypoint
+        ));
+
+        // A single capturing pattern is enough
+        parse_peg("main = °\"a\" !\"b\" \"c\"").unwrap();
+    }
+
+    /// Get the messages of the warnings emitted for a grammar
+    fn warning_messages(grammar: &str) -> Vec<String> {
+        let (_, warnings) = parse_peg_with_warnings(grammar).unwrap();
+        warnings
+            .iter()
+            .map(|warning| warning.content().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn warnings_describe_patterns() {
+        assert_eq!(
+            warning_messages(r#"main = B_ANY* "end""#),
+            [
+                r#"This constant string "end" can never match as the greedy 'B_ANY' repetition before it consumes all the remaining input"#
+            ]
+        );
+
+        assert_eq!(
+            warning_messages(r#"main = !"a" "a""#),
+            [
+                r#"This constant string "a" can never match as it contradicts the peek or negative pattern before it"#
+            ]
+        );
+
+        assert_eq!(
+            warning_messages(r#"main = ~"a"* "b""#),
+            [
+                r#"This peek zero-or-more repetition (`*`) of constant string "a" never consumes anything, so repeating it either changes nothing or loops forever"#
+            ]
+        );
+    }
+
+    #[test]
+    fn redundant_optional_repetitions() {
+        let found = |grammar| -> Vec<_> {
+            let (_, warnings) = parse_peg_with_warnings(grammar).unwrap();
+
+            warnings
+                .iter()
+                .filter_map(|warning| match warning.content() {
+                    ParserWarningContent::RedundantOptionalRepetition(equivalent) => {
+                        Some((warning.col(), warning.length(), *equivalent))
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            found("main = \"a\" (b+)? (b*)? (b?)?\nb = \"b\""),
+            [
+                (11, 5, PatternRepetition::Any),
+                (17, 5, PatternRepetition::Any),
+                (23, 5, PatternRepetition::Optional),
+            ]
+        );
+
+        assert_eq!(
+            warning_messages("main = (\"a\"+)?"),
+            ["This optional group of a repetition matches the same input as its content with a zero-or-more repetition (`*`)"]
+        );
+
+        // Groups which don't only contain a repetition, or don't match like it, are fine
+        for grammar in [
+            "main = (\"a\"+ \"b\")? \"c\"",
+            "main = (\"a\" | \"b\"+)? \"c\"",
+            "main = (~\"a\"+)? \"b\"",
+            "main = (!\"a\"+)? \"b\"",
+            "main = (\"a\"+)+",
+            "main = \"a\"*",
+        ] {
+            assert_eq!(found(grammar), [], "in {:?}", grammar);
+        }
+    }
+
+    #[test]
+    fn digit_builtins_ascii_chars() {
+        let chars = |name| -> String {
+            let mask = builtin_rule_ascii_chars_mask(name).unwrap();
+            (0..128u8)
+                .filter(|c| mask >> c & 1 == 1)
+                .map(char::from)
+                .collect()
+        };
+
+        assert_eq!(chars("B_BIN_DIGIT"), "01");
+        assert_eq!(chars("B_OCTAL_DIGIT"), "01234567");
+        assert_eq!(chars("B_DEC_DIGIT"), "0123456789");
+        assert_eq!(chars("B_DIGIT_NONZERO"), "123456789");
+        assert_eq!(chars("B_HEX_DIGIT"), "0123456789ABCDEFabcdef");
+
+        // Rules matching non-ASCII digits don't have a mask
+        assert_eq!(builtin_rule_ascii_chars_mask("B_NUMERIC"), None);
+    }
+
+    #[test]
+    fn general_category_builtins() {
+        let names = [
+            "B_LETTER",
+            "B_MARK",
+            "B_DECIMAL_NUMBER",
+            "B_PUNCTUATION",
+            "B_CONNECTOR_PUNCTUATION",
+            "B_SYMBOL",
+        ];
+
+        let (_, warnings) =
+            parse_peg_with_warnings(&format!("main = ({})+", names.join(" | "))).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        // These rules also match non-ASCII characters, so they don't have a mask
+        for name in names {
+            assert!(!is_zero_width_builtin_rule_name(name), "{}", name);
+            assert_eq!(builtin_rule_ascii_chars_mask(name), None, "{}", name);
+        }
+    }
+
+    /// Get the location of each duplicate union member, and of the member it duplicates
+    fn duplicate_members(grammar: &str) -> Vec<(ParserLoc, ParserLoc)> {
+        let (_, warnings) = parse_peg_with_warnings(grammar).unwrap();
+
+        warnings
+            .iter()
+            .filter_map(|warning| match warning.content() {
+                ParserWarningContent::DuplicateUnionMember { first } => {
+                    Some((*warning.loc(), *first))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn duplicate_union_members() {
+        let at = |col| ParserLoc::new(0, col);
+
+        assert_eq!(duplicate_members(r#"main = "a" | "a""#), [(at(13), at(7))]);
+
+        // Each duplicate points at the first identical member
+        assert_eq!(
+            duplicate_members("main = \"a\" | b | (\"a\") | \"c\" | b | ((\"a\"))\nb = \"b\""),
+            [(at(17), at(7)), (at(31), at(13)), (at(35), at(7))]
+        );
+
+        // Nested unions and unions spread across lines
+        assert_eq!(
+            duplicate_members("main = \"x\" (\"a\" b | \"c\" | \"a\" b)\nb = \"b\""),
+            [(at(26), at(12))]
+        );
+        assert_eq!(
+            duplicate_members("main =\n  | \"a\"\n  | \"b\"\n  | \"a\""),
+            [(ParserLoc::new(3, 4), ParserLoc::new(1, 4))]
+        );
+    }
+
+    #[test]
+    fn near_duplicate_union_members() {
+        for grammar in [
+            r#"main = "a" | "A""#,
+            r#"main = "a" | "a"+"#,
+            r#"main = "x" "a"? | "x" "a"*"#,
+            r#"main = °"a" | "a""#,
+            r#"main = @("a") | "a""#,
+            r#"main = ("a" "b") | ("a" | "b")"#,
+            r#"main = "a" "b" | "a" "b" "c""#,
+            r#"main = "b" "a" | "a" "b""#,
+            r#"main = "<" .."a" | "<" .."aa""#,
+            "main = B_ANY | b\nb = B_ANY",
+        ] {
+            assert_eq!(duplicate_members(grammar), [], "in {:?}", grammar);
+        }
+    }
+
+    /// Get a grammar whose rules `d0` to `d{depth}` each use the next one twice, which makes the number of paths from
+    /// `d0` to the last one exponential, all the paths ending on a non-capturing, non-consuming pattern
+    fn diamond_grammar(main: &str, depth: usize) -> String {
+        let mut grammar = format!("main = {}\n", main);
+
+        for i in 0..depth {
+            grammar.push_str(&format!("d{} = d{} d{}\n", i, i + 1, i + 1));
+        }
+
+        grammar.push_str(&format!("d{} = ~\"x\"", depth));
+        grammar
+    }
+
+    fn steps_err(grammar: &str, max_validation_steps: usize) -> ParserError {
+        let pst = parse_peg_nocheck(grammar).unwrap();
+
+        let limits = ParseLimits {
+            max_validation_steps,
+            ..ParseLimits::new()
+        };
+
+        validate_parsed_peg_with_limits(&pst, &limits).unwrap_err()
+    }
+
+    #[test]
+    fn validation_steps_limit() {
+        // Finding that the main rule doesn't capture anything requires following each path (2^10 here)
+        let grammar = diamond_grammar("d0", 10);
+        let err = parse_err(&grammar);
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::DatalessEntrypoint
+        ));
+
+        let err = steps_err(&grammar, 1000);
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::TooManyValidationSteps { max: 1000 }
+        ));
+        // The error is reported on the reference validation couldn't follow (line 10 being `d9 = d10 d10`)
+        assert_eq!((*err.loc(), err.length()), (ParserLoc::new(10, 5), 3));
+
+        // Huge explorations (2^60 paths here) stop at the limit
+        let err = steps_err(&diamond_grammar("d0", 60), 1000);
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::TooManyValidationSteps { max: 1000 }
+        ));
+
+        // Same for finding that a repeated pattern doesn't consume anything
+        let grammar = diamond_grammar("\"a\" d0*", 10);
+        let warnings = parse_peg_with_warnings(&grammar).unwrap().1;
+        assert!(matches!(
+            warnings[0].content(),
+            ParserWarningContent::RepeatedNonConsumingPattern(_)
+        ));
+
+        let err = steps_err(&grammar, 1000);
+        assert!(matches!(
+            err.content(),
+            ParserErrorContent::TooManyValidationSteps { max: 1000 }
+        ));
+        assert_eq!((*err.loc(), err.length()), (ParserLoc::new(10, 5), 3));
+    }
+}
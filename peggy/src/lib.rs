@@ -2,11 +2,13 @@
 //!
 //! Peggy is a small parser generator based on PEG syntaxes. It is heavily inspired by [Pest](https://github.com/pest-parser/pest).
 //!
-//! It provides functions to [parse text-based grammars](`compiler::parse_peg`) as well as a quick [runtime](`runtime::execute`).
+//! It provides functions to [parse text-based grammars](`grammar::parse_peg`) as well as a quick [runtime](`runtime::execute`).
 //!
 //! To get the best possible performances, allocations are reduced to the string minimum, especially on the heap's side.  
 //! All string manipulations are performed using slices and lifetimes, except for error reporting which doesn't require as much performances.
 //!
+//! The most commonly used items are re-exported in the [`prelude`] module.
+//!
 //! ## Parser generator
 //!
 //! See the `prn` example in the `peggy_derive` crate.
@@ -14,7 +16,9 @@
 //! ## Without parser generator
 //!
 //! ```rust
-//! use peggy::compiler::{parse_peg, pretty_format_parser_err};
+//! # #[cfg(feature = "runtime")]
+//! # fn main() {
+//! use peggy::grammar::{parse_peg, pretty_format_parser_err};
 //! use peggy::runtime::{execute, RuntimeContext, RuntimeOptions};
 //!
 //! // 1. Define the grammar
@@ -45,12 +49,18 @@
 //! .unwrap_or_else(|err| panic!("{}", err));
 //!
 //! // 5. Play with the parsed content!
+//! # }
+//! # #[cfg(not(feature = "runtime"))]
+//! # fn main() {}
 //! ```
 
 #![forbid(unsafe_code)]
 #![forbid(unused_must_use)]
 
 pub mod grammar;
+pub mod prelude;
+
+pub use grammar::verify_generated;
 
 #[cfg(feature = "rustgen")]
 pub mod rustgen;
@@ -6,11 +6,64 @@ use crate::grammar::{Pattern, PatternMode, PegSyntaxTree, RulePatternValue};
 ///
 /// Note that blank lines and comments, as well as additional whitespaces, won't be restored.
 pub fn gen_peggy(pst: &PegSyntaxTree) -> String {
-    pst.rules()
+    gen_peggy_with(pst, &PeggyGenOptions::new())
+}
+
+/// Generate a Peggy grammar from its syntax tree, using the provided [options](`PeggyGenOptions`)
+pub fn gen_peggy_with(pst: &PegSyntaxTree, options: &PeggyGenOptions) -> String {
+    let rules = pst
+        .ordered_rules()
         .iter()
-        .map(|(name, rule)| format!("{} = {}", name, gen_peggy_pattern(rule.pattern())))
+        .map(|rule| gen_peggy_rule(rule.name(), rule.pattern(), options))
         .collect::<Vec<_>>()
-        .join("\n")
+        .join("\n");
+
+    let rules = if pst.token_rules().is_empty() {
+        rules
+    } else {
+        format!("@token {}\n{}", pst.token_rules().join(" "), rules)
+    };
+
+    let rules = if pst.keywords().is_empty() {
+        rules
+    } else {
+        format!("@keywords {}\n{}", pst.keywords().join(" "), rules)
+    };
+
+    match pst.version_req() {
+        Some(req) => format!("@peggy {}\n{}", req, rules),
+        None => rules,
+    }
+}
+
+/// Generate a Peggy code for a single rule declaration
+pub fn gen_peggy_rule(name: &str, pattern: &Pattern, options: &PeggyGenOptions) -> String {
+    let single_line = format!("{} = {}", name, gen_peggy_pattern(pattern));
+
+    let max_width = match options.max_union_width {
+        Some(max_width) => max_width,
+        None => return single_line,
+    };
+
+    match pattern.value() {
+        // Only the rule's top-level union can be split across multiple lines
+        RulePatternValue::Union(members)
+            if pattern.mode().is_none()
+                && pattern.repetition().is_none()
+                && single_line.chars().count() > max_width =>
+        {
+            format!(
+                "{} ={}",
+                name,
+                members
+                    .iter()
+                    .map(|member| format!("\n    | {}", gen_peggy_pattern(member)))
+                    .collect::<String>()
+            )
+        }
+
+        _ => single_line,
+    }
 }
 
 /// Generate a Peggy code for a single [`RulePattern`]
@@ -35,8 +88,10 @@ pub fn gen_peggy_pattern(pattern: &Pattern) -> String {
 /// Generate a Peggy code for a single [`RulePatternValue`]
 pub fn gen_peggy_pattern_value(value: &RulePatternValue) -> String {
     match value {
-        RulePatternValue::CstString(string) => format!("{:?}", string),
+        // Constant strings have no escaping mechanism, so they are written as-is
+        RulePatternValue::CstString(string) => format!("\"{}\"", string),
         RulePatternValue::Rule(name) => name.to_string(),
+        RulePatternValue::Cut => "^".to_string(),
         RulePatternValue::Group(inner) => format!("({})", gen_peggy_pattern(inner.as_ref())),
         RulePatternValue::Suite(patterns) => patterns
             .iter()
@@ -48,5 +103,96 @@ pub fn gen_peggy_pattern_value(value: &RulePatternValue) -> String {
             .map(gen_peggy_pattern)
             .collect::<Vec<_>>()
             .join(" | "),
+        RulePatternValue::Until(terminator) => {
+            format!("..{}", gen_peggy_pattern(terminator.as_ref()))
+        }
+    }
+}
+
+/// Options for the [Peggy generator](`gen_peggy_with`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PeggyGenOptions {
+    /// Split rules made of an union on multiple lines (one `| member` per line) when they are wider than this number of characters.
+    /// With `None`, each rule is always generated on a single line.
+    pub max_union_width: Option<usize>,
+}
+
+impl PeggyGenOptions {
+    /// Create a new set of generation options
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_peg;
+
+    fn assert_round_trips(grammar: &str, options: &PeggyGenOptions) {
+        let parsed = parse_peg(grammar).unwrap();
+        let generated = gen_peggy_with(&parsed, options);
+        let reparsed = parse_peg(&generated).unwrap();
+
+        assert_eq!(parsed.rules().len(), reparsed.rules().len());
+
+        for (name, rule) in parsed.rules() {
+            let other = reparsed.rules().get(name).unwrap();
+            assert!(
+                rule.pattern().structurally_eq(other.pattern()),
+                "rule {} changed after formatting:\n{}",
+                name,
+                generated
+            );
+        }
+
+        // Formatting must be stable
+        assert_eq!(gen_peggy_with(&reparsed, options), generated);
+    }
+
+    const GRAMMAR: &str = r#"
+value = object | array | string "?" | °"null"
+object = "{" value* "}"
+array = "[" (value ("," value)*)? "]"
+string = @(B_DOUBLE_QUOTE B_ASCII_ALPHANUMERIC* B_DOUBLE_QUOTE)
+path = "C:\\" @(B_ASCII_ALPHANUMERIC+)
+main = value | path
+"#;
+
+    #[test]
+    fn round_trip_single_line() {
+        assert_round_trips(GRAMMAR, &PeggyGenOptions::new());
+    }
+
+    #[test]
+    fn round_trip_multi_line_unions() {
+        let options = PeggyGenOptions {
+            max_union_width: Some(10),
+        };
+
+        let parsed = parse_peg(GRAMMAR).unwrap();
+        let rule = parsed.rules().get("value").unwrap();
+
+        assert_eq!(
+            gen_peggy_rule("value", rule.pattern(), &options),
+            "value =\n    | object\n    | array\n    | string \"?\"\n    | °\"null\""
+        );
+
+        assert_round_trips(GRAMMAR, &options);
+    }
+
+    #[test]
+    fn narrow_rules_stay_on_one_line() {
+        let options = PeggyGenOptions {
+            max_union_width: Some(80),
+        };
+
+        let parsed = parse_peg(GRAMMAR).unwrap();
+        let rule = parsed.rules().get("value").unwrap();
+
+        assert_eq!(
+            gen_peggy_rule("value", rule.pattern(), &options),
+            "value = object | array | string \"?\" | °\"null\""
+        );
     }
 }
@@ -0,0 +1,33 @@
+//! # Peggy's Prelude
+//!
+//! This module re-exports the items most programs using Peggy need, to be glob-imported with `use peggy::prelude::*`.
+//!
+//! Only the items listed here and the public items of the [`grammar`](crate::grammar) module and of the enabled generators
+//! are part of the stable API.
+
+pub use crate::grammar::{
+    constant_strings, dependents, find_duplicate_rules, grammar_hash, grammar_stats, min_length,
+    node_at, parse_peg, parse_peg_nocheck, parse_peg_with_limits, parse_peg_with_origin,
+    parse_peg_with_warnings, pretty_format_parser_err, pretty_format_parser_warning, validate,
+    validate_parsed_peg, validate_parsed_peg_with_limits, validate_with_limits, verify_generated,
+    Diagnostic, DiagnosticSeverity, GrammarStats, NodePath, ParseLimits, ParserError,
+    ParserErrorContent, ParserLoc, ParserSpan, ParserWarning, ParserWarningContent, Pattern,
+    PatternKind, PatternMode, PatternRepetition, PegSyntaxTree, Rule, RulePatternValue,
+    SourceOrigin, ValidationReport,
+};
+
+#[cfg(feature = "rustgen")]
+pub use crate::rustgen::{
+    compile_to_rust, gen_rust_crate, gen_rust_str, gen_rust_str_with, gen_rust_token_stream,
+    gen_rust_token_stream_with, rule_type_shapes, try_gen_rust_str_with,
+    try_gen_rust_token_stream_with, CrateOptions, GenError, RecursionWrapper, RustGenOptions,
+    SilenceOverride, TypeShape,
+};
+
+#[cfg(feature = "peggygen")]
+pub use crate::peggygen::{gen_peggy, gen_peggy_with, PeggyGenOptions};
+
+#[cfg(feature = "runtime")]
+pub use crate::runtime::{
+    execute, MatchedData, MatchedRule, RuntimeContext, RuntimeError, RuntimeOptions,
+};
@@ -1,12 +1,23 @@
-/// Match using a one-character builtin rule
+use unicode_general_category::{get_general_category, GeneralCategory as Gc};
+
+/// Match using a builtin rule, at the provided byte offset of the whole subject string
 ///
 /// If the rule does not exist, the `None` value will be returned.
 /// If it exists but does not match, this function will return `false`.
-/// Otherwise, it will return `true`, indicating a match. A single character should be consumed from the input in that case.
-pub fn match_builtin_rule(rule_name: &str, next_char: Option<char>) -> Option<bool> {
+/// Otherwise, it will return `true`, indicating a match. A single character should be consumed from the input in that case
+/// (which may be made of multiple bytes), except for [zero-width rules](`crate::grammar::utils::ZERO_WIDTH_BUILTIN_RULES`)
+/// which don't consume anything.
+pub fn match_builtin_rule(rule_name: &str, subject: &str, offset: usize) -> Option<bool> {
+    let next_char = subject[offset..].chars().next();
+
     Some(match rule_name {
         "B_ANY" => next_char.is_some(),
 
+        "B_LINE_START" => offset == 0 || subject.as_bytes()[offset - 1] == b'\n',
+        "B_WORD_BOUNDARY" => {
+            is_word_char(subject[..offset].chars().next_back()) != is_word_char(next_char)
+        }
+
         "B_NEWLINE_CR" => next_char.map(|c| c == '\r').unwrap_or(false),
         "B_NEWLINE_LF" => next_char.map(|c| c == '\n').unwrap_or(false),
 
@@ -34,8 +45,133 @@ pub fn match_builtin_rule(rule_name: &str, next_char: Option<char>) -> Option<bo
         "B_UPPERCASE" => next_char.map(|c| c.is_uppercase()).unwrap_or(false),
         "B_WHITESPACE" => next_char.map(|c| c.is_whitespace()).unwrap_or(false),
 
+        "B_BIN_DIGIT" => next_char.map(|c| matches!(c, '0'..='1')).unwrap_or(false),
+        "B_OCTAL_DIGIT" => next_char.map(|c| matches!(c, '0'..='7')).unwrap_or(false),
+        "B_DEC_DIGIT" => next_char.map(|c| c.is_ascii_digit()).unwrap_or(false),
+        "B_DIGIT_NONZERO" => next_char.map(|c| matches!(c, '1'..='9')).unwrap_or(false),
+        "B_HEX_DIGIT" => next_char.map(|c| c.is_ascii_hexdigit()).unwrap_or(false),
+
+        "B_LETTER" => next_char
+            .map(|c| {
+                matches!(
+                    get_general_category(c),
+                    Gc::UppercaseLetter
+                        | Gc::LowercaseLetter
+                        | Gc::TitlecaseLetter
+                        | Gc::ModifierLetter
+                        | Gc::OtherLetter
+                )
+            })
+            .unwrap_or(false),
+        "B_MARK" => next_char
+            .map(|c| {
+                matches!(
+                    get_general_category(c),
+                    Gc::NonspacingMark | Gc::SpacingMark | Gc::EnclosingMark
+                )
+            })
+            .unwrap_or(false),
+        "B_DECIMAL_NUMBER" => next_char
+            .map(|c| get_general_category(c) == Gc::DecimalNumber)
+            .unwrap_or(false),
+        "B_PUNCTUATION" => next_char
+            .map(|c| {
+                matches!(
+                    get_general_category(c),
+                    Gc::ConnectorPunctuation
+                        | Gc::DashPunctuation
+                        | Gc::OpenPunctuation
+                        | Gc::ClosePunctuation
+                        | Gc::InitialPunctuation
+                        | Gc::FinalPunctuation
+                        | Gc::OtherPunctuation
+                )
+            })
+            .unwrap_or(false),
+        "B_CONNECTOR_PUNCTUATION" => next_char
+            .map(|c| get_general_category(c) == Gc::ConnectorPunctuation)
+            .unwrap_or(false),
+        "B_SYMBOL" => next_char
+            .map(|c| {
+                matches!(
+                    get_general_category(c),
+                    Gc::MathSymbol | Gc::CurrencySymbol | Gc::ModifierSymbol | Gc::OtherSymbol
+                )
+            })
+            .unwrap_or(false),
+
         _ => return None,
         // NOTE: When adding a new item to this list, the `BUILTIN_RULES` static also needs to be updated
         // NOTE: Also needs to be updated the Rust generator for these rules
     })
 }
+
+/// Check if a character is part of a word (alphanumeric or underscore), for word boundaries
+pub(crate) fn is_word_char(c: Option<char>) -> bool {
+    c.is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Get the characters a builtin rule matches among the ASCII ones and a few non-ASCII digits
+    fn matched_chars(rule_name: &str) -> String {
+        (0..128u8)
+            .map(char::from)
+            .chain(['٣', '５', '²', 'ⅳ'])
+            .filter(|c| match_builtin_rule(rule_name, &c.to_string(), 0).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn digit_rules() {
+        assert_eq!(matched_chars("B_BIN_DIGIT"), "01");
+        assert_eq!(matched_chars("B_OCTAL_DIGIT"), "01234567");
+        assert_eq!(matched_chars("B_DEC_DIGIT"), "0123456789");
+        assert_eq!(matched_chars("B_DIGIT_NONZERO"), "123456789");
+        assert_eq!(matched_chars("B_HEX_DIGIT"), "0123456789ABCDEFabcdef");
+
+        // Unlike the ASCII rules, `B_NUMERIC` also matches non-ASCII digits
+        assert_eq!(matched_chars("B_NUMERIC"), "0123456789٣５²ⅳ");
+    }
+
+    #[test]
+    fn general_category_rules() {
+        // Characters from most general categories, including non-ASCII ones and combining marks
+        let sample = "aZǅʰ中\u{301}\u{903}\u{20DD}7٣５²Ⅳ_‿-(«!+€^© \n";
+
+        let matched = |rule_name| -> String {
+            sample
+                .char_indices()
+                .filter(|(i, _)| match_builtin_rule(rule_name, sample, *i).unwrap())
+                .map(|(_, c)| c)
+                .collect()
+        };
+
+        assert_eq!(matched("B_LETTER"), "aZǅʰ中");
+        assert_eq!(matched("B_MARK"), "\u{301}\u{903}\u{20DD}");
+        assert_eq!(matched("B_DECIMAL_NUMBER"), "7٣５");
+        assert_eq!(matched("B_PUNCTUATION"), "_‿-(«!");
+        assert_eq!(matched("B_CONNECTOR_PUNCTUATION"), "_‿");
+        assert_eq!(matched("B_SYMBOL"), "+€^©");
+    }
+
+    #[test]
+    fn digit_rules_at_offsets() {
+        let subject = "x0é9";
+
+        assert_eq!(
+            match_builtin_rule("B_DIGIT_NONZERO", subject, 1),
+            Some(false)
+        );
+        assert_eq!(match_builtin_rule("B_DEC_DIGIT", subject, 1), Some(true));
+        assert_eq!(
+            match_builtin_rule("B_DIGIT_NONZERO", subject, 4),
+            Some(true)
+        );
+        assert_eq!(match_builtin_rule("B_HEX_DIGIT", subject, 5), Some(false));
+
+        assert_eq!(match_builtin_rule("B_DIGIT", subject, 1), None);
+    }
+}
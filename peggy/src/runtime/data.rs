@@ -46,6 +46,9 @@ pub enum MatchedData<'a> {
     /// Matched an atomic pattern
     AtomicPattern(&'a str),
 
+    /// Matched an until pattern (contains the input preceding the terminator)
+    UntilPattern(&'a str),
+
     /// Matched a silent pattern
     SilentPattern,
 
@@ -12,6 +12,9 @@ pub struct RuntimeError<'a> {
 
     /// Error's content
     pub(crate) content: RuntimeErrorContent<'a>,
+
+    /// Did the error happen after a cut, committing to the enclosing union member?
+    pub(crate) committed: bool,
 }
 
 impl<'a> RuntimeError<'a> {
@@ -25,9 +28,16 @@ impl<'a> RuntimeError<'a> {
             subject,
             cursor,
             content,
+            committed: false,
         }
     }
 
+    /// (Internal) Forget about the cut the error happened after, so it doesn't commit to any other union member
+    pub(crate) fn uncommitted(mut self) -> Self {
+        self.committed = false;
+        self
+    }
+
     /// Get the runtime's optional cursor, used to indicate the location of the error in the input
     pub fn cursor(&self) -> &Option<RuntimeCursor<'a>> {
         &self.cursor
@@ -138,6 +148,9 @@ pub enum RuntimeErrorContent<'a> {
     /// Remaining content was found after the end of the grammar
     UnexpectedContent,
 
+    /// Reached the end of the input without finding the terminator of an until pattern
+    TerminatorNotFound,
+
     /// Failed to match against builtin rule
     BuiltinRule(&'a str),
 
@@ -190,6 +203,9 @@ impl<'a> RuntimeErrorContent<'a> {
             Self::UnexpectedContent => {
                 "End of content was expected, found additional symbol".to_string()
             }
+            Self::TerminatorNotFound => {
+                "Reached the end of input without finding the terminator".to_string()
+            }
             Self::BuiltinRule(name) => {
                 format!("Failed to match against builtin rule \"{}\"", name)
             }
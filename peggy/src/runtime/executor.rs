@@ -1,7 +1,9 @@
 use super::builtin;
 use super::data::{MatchedData, MatchedRule};
 use super::errors::{RuntimeError, RuntimeErrorContent, RuntimeTreeItem};
-use crate::grammar::utils::{is_builtin_rule_name, is_external_rule_name};
+use crate::grammar::utils::{
+    is_builtin_rule_name, is_external_rule_name, is_zero_width_builtin_rule_name,
+};
 use crate::grammar::{Pattern, PatternRepetition, PegSyntaxTree, RulePatternValue};
 use std::rc::Rc;
 
@@ -13,7 +15,7 @@ pub fn execute<'a, 'b: 'a>(
 
     // Match the whole string against the main rule
     let (data, len) = match_pattern(
-        &ctx,
+        ctx,
         ctx.subject,
         RuntimeCursor::new(0, vec![]),
         main_rule.pattern(),
@@ -39,7 +41,7 @@ pub fn execute<'a, 'b: 'a>(
 ///
 /// Arguments are the syntax tree, the original subject string, the input to use for the match, the cursor indicating the match's location,
 /// and finally the pattern used for the match.
-pub fn match_pattern<'a, 'b: 'a>(
+pub(crate) fn match_pattern<'a, 'b: 'a>(
     ctx: &RuntimeContext<'a, 'b>,
     mut input: &'a str,
     cursor: RuntimeCursor<'a>,
@@ -49,6 +51,14 @@ pub fn match_pattern<'a, 'b: 'a>(
     let try_match = |input: &'a str, cursor: RuntimeCursor<'a>| {
         match_pattern_value(ctx, input, cursor, pattern.value(), pattern.is_silent())
             .map(|(data, len)| (data.filter(|_| !pattern.is_silent()), len))
+            // Cuts inside of repeated or negative patterns don't commit to the enclosing union member
+            .map_err(|err| {
+                if pattern.forwards_cuts() {
+                    err
+                } else {
+                    err.uncommitted()
+                }
+            })
     };
 
     // The matching method depends on the repetition model (see [`crate::compiler::RuleRepetition`])
@@ -161,7 +171,7 @@ pub fn match_pattern<'a, 'b: 'a>(
 ///
 /// Arguments are the syntax tree, the original subject string, the input to use for the match, the cursor indicating the match's location,
 /// and finally the pattern's value used for the match.
-pub fn match_pattern_value<'a, 'b: 'a>(
+pub(crate) fn match_pattern_value<'a, 'b: 'a>(
     ctx: &RuntimeContext<'a, 'b>,
     input: &'a str,
     cursor: RuntimeCursor<'a>,
@@ -171,7 +181,10 @@ pub fn match_pattern_value<'a, 'b: 'a>(
     match pattern_value {
         // Match against a constant string
         RulePatternValue::CstString(string) => {
-            if input.starts_with(string) {
+            if input.starts_with(string)
+                && ends_on_grapheme_boundary(ctx, input, string.len())
+                && ends_on_keyword_boundary(ctx, input, string)
+            {
                 Ok((
                     if is_silent {
                         None
@@ -217,19 +230,22 @@ pub fn match_pattern_value<'a, 'b: 'a>(
                 }
             };
 
-            match_pattern(ctx, input, cursor, rule.pattern()).map(|(data, len)| {
-                (
-                    if is_silent {
-                        None
-                    } else {
-                        Some(MatchedData::Rule(Rc::new(MatchedRule {
-                            name,
-                            data: data.unwrap_or(MatchedData::SilentPattern),
-                        })))
-                    },
-                    len,
-                )
-            })
+            // Cuts only commit to the unions of their own rule
+            match_pattern(ctx, input, cursor, rule.pattern())
+                .map_err(RuntimeError::uncommitted)
+                .map(|(data, len)| {
+                    (
+                        if is_silent {
+                            None
+                        } else {
+                            Some(MatchedData::Rule(Rc::new(MatchedRule {
+                                name,
+                                data: data.unwrap_or(MatchedData::SilentPattern),
+                            })))
+                        },
+                        len,
+                    )
+                })
         }
 
         // Match against a group (= against the group's content)
@@ -237,7 +253,7 @@ pub fn match_pattern_value<'a, 'b: 'a>(
             ctx,
             input,
             cursor.with_child(RuntimeTreeItem::Group),
-            &inner_pattern,
+            inner_pattern,
         ),
 
         // Match a suite of patterns
@@ -245,9 +261,10 @@ pub fn match_pattern_value<'a, 'b: 'a>(
             let mut input = input;
             let mut column = 0;
             let mut matched = vec![];
+            let mut committed = false;
 
             for (i, pattern) in patterns.iter().enumerate() {
-                let (data, len) = match_pattern(
+                let (data, len) = match match_pattern(
                     ctx,
                     input,
                     cursor.with_child_and_additional_offset(
@@ -255,7 +272,16 @@ pub fn match_pattern_value<'a, 'b: 'a>(
                         column,
                     ),
                     pattern,
-                )?;
+                ) {
+                    Ok(result) => result,
+                    Err(mut err) => {
+                        err.committed |= committed;
+                        return Err(err);
+                    }
+                };
+
+                // Pieces containing a cut passed it if they matched
+                committed |= pattern.has_cut();
 
                 if let Some(data) = data {
                     matched.push(data);
@@ -297,7 +323,13 @@ pub fn match_pattern_value<'a, 'b: 'a>(
                         } else {
                             greedy_candidate = Some(result);
                         }
+
+                        // Don't try the remaining members after a cut
+                        if pattern.has_cut() {
+                            break;
+                        }
                     }
+                    Err(err) if err.committed => return Err(err.uncommitted()),
                     Err(err) => errors.push(err),
                 }
             }
@@ -312,26 +344,75 @@ pub fn match_pattern_value<'a, 'b: 'a>(
                 ))
             }
         }
+
+        // Cuts always match, and are handled by suites and unions
+        RulePatternValue::Cut => Ok((None, 0)),
+
+        // Match everything up to the terminator, by trying it at each character
+        RulePatternValue::Until(terminator) => {
+            let mut len = 0;
+
+            loop {
+                match match_pattern(
+                    ctx,
+                    &input[len..],
+                    cursor.with_additional_offset(len),
+                    terminator,
+                ) {
+                    Ok(_) => {
+                        break Ok((
+                            if is_silent {
+                                None
+                            } else {
+                                Some(MatchedData::UntilPattern(&input[..len]))
+                            },
+                            len,
+                        ))
+                    }
+                    err @ Err(RuntimeError {
+                        content: RuntimeErrorContent::RuleNotFound(_),
+                        ..
+                    }) => return err,
+                    Err(_) => {}
+                }
+
+                match input[len..].chars().next() {
+                    Some(c) => len += c.len_utf8(),
+                    None => {
+                        break Err(RuntimeError::new(
+                            ctx.subject,
+                            Some(cursor),
+                            RuntimeErrorContent::TerminatorNotFound,
+                        ))
+                    }
+                }
+            }
+        }
     }
 }
 
 /// Match the input against a builtin rule
-pub fn match_builtin_rule<'a, 'b: 'a>(
+pub(crate) fn match_builtin_rule<'a, 'b: 'a>(
     ctx: &RuntimeContext<'a, 'b>,
     input: &'a str,
     cursor: RuntimeCursor<'a>,
     rule_name: &'a str,
 ) -> Result<(MatchedData<'a>, usize), RuntimeError<'a>> {
-    let next_char = input.chars().next();
+    // Zero-width rules don't consume the next character
+    let next_char = if is_zero_width_builtin_rule_name(rule_name) {
+        None
+    } else {
+        input.chars().next()
+    };
 
     // Look for builtin rules
-    match builtin::match_builtin_rule(rule_name, next_char) {
+    match builtin::match_builtin_rule(rule_name, ctx.subject, cursor.offset) {
         Some(true) => Ok((
             MatchedData::BuiltinRule {
                 name: rule_name,
                 symbol: next_char,
             },
-            if next_char.is_some() { 1 } else { 0 },
+            next_char.map(char::len_utf8).unwrap_or(0),
         )),
         Some(false) => Err(RuntimeError::new(
             ctx.subject,
@@ -347,7 +428,7 @@ pub fn match_builtin_rule<'a, 'b: 'a>(
 }
 
 /// Match the input against an external rule
-pub fn match_external_rule<'a, 'b: 'a>(
+pub(crate) fn match_external_rule<'a, 'b: 'a>(
     ctx: &RuntimeContext<'a, 'b>,
     input: &'a str,
     cursor: RuntimeCursor<'a>,
@@ -405,7 +486,7 @@ pub struct RuntimeContext<'a, 'b: 'a> {
 }
 
 /// Runtime options
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy)]
 pub struct RuntimeOptions {
     /// Make the rules as lazy as possible.
     /// By default, all rules in unions are evaluated, and the one consuming the most data is kept.
@@ -413,6 +494,11 @@ pub struct RuntimeOptions {
     /// This can improve performances a little, but may cause hard to debug problems if you grammar isn't optimized for this setting.
     /// With this enabled, unions will always need to have the most-consuming rules first, and only after the less-consuming ones.
     pub lazy_unions: bool,
+
+    /// Only match constant strings if they end on a grapheme cluster boundary (e.g. `"e"` won't match the beginning of `"e\u{301}"`).
+    /// This makes matching constant strings slower.
+    #[cfg(feature = "grapheme-boundaries")]
+    pub grapheme_boundaries: bool,
 }
 
 impl RuntimeOptions {
@@ -422,10 +508,26 @@ impl RuntimeOptions {
     }
 }
 
-impl Default for RuntimeOptions {
-    fn default() -> Self {
-        RuntimeOptions { lazy_unions: false }
-    }
+/// Check if a constant string matched at the beginning of the input ends on a grapheme cluster boundary
+/// (always true if the related [option](`RuntimeOptions::grapheme_boundaries`) is disabled)
+#[cfg(feature = "grapheme-boundaries")]
+fn ends_on_grapheme_boundary(ctx: &RuntimeContext, input: &str, len: usize) -> bool {
+    !ctx.options.grapheme_boundaries
+        || unicode_segmentation::GraphemeCursor::new(len, input.len(), true)
+            .is_boundary(input, 0)
+            .unwrap_or(true)
+}
+
+#[cfg(not(feature = "grapheme-boundaries"))]
+fn ends_on_grapheme_boundary(_: &RuntimeContext, _: &str, _: usize) -> bool {
+    true
+}
+
+/// Check if a constant string matched at the beginning of the input isn't directly followed by a word character
+/// (always true if the string isn't one of the grammar's [keywords](`PegSyntaxTree::keywords`))
+fn ends_on_keyword_boundary(ctx: &RuntimeContext, input: &str, string: &str) -> bool {
+    !ctx.grammar.keywords().contains(&string)
+        || !builtin::is_word_char(input[string.len()..].chars().next())
 }
 
 /// External rules handler
@@ -516,3 +618,85 @@ impl<'a> RuntimeCursor<'a> {
         cloned
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_peg;
+
+    /// Match a subject against a grammar with the provided options
+    fn run_with<'a, 'b: 'a>(
+        grammar: &'b PegSyntaxTree<'b>,
+        subject: &'a str,
+        options: RuntimeOptions,
+    ) -> Result<MatchedRule<'a>, RuntimeError<'a>> {
+        execute(&RuntimeContext {
+            grammar,
+            subject,
+            external_rules: None,
+            options,
+        })
+    }
+
+    /// Match a subject against a grammar with the default options
+    fn run<'a, 'b: 'a>(
+        grammar: &'b PegSyntaxTree<'b>,
+        subject: &'a str,
+    ) -> Result<MatchedRule<'a>, RuntimeError<'a>> {
+        run_with(grammar, subject, RuntimeOptions::new())
+    }
+
+    #[test]
+    fn cuts_commit_to_union_members() {
+        let grammar = parse_peg(
+            "main = \"if\" ^ \" \" \"(\" ident \")\" | ident \" \" ident\nident = @(B_ALPHABETIC+)",
+        )
+        .unwrap();
+
+        assert!(run(&grammar, "if (x)").is_ok());
+        assert!(run(&grammar, "is x").is_ok());
+
+        // Without the cut, `if x` would be matched by the second member
+        let err = run(&grammar, "if x").unwrap_err();
+        assert!(matches!(
+            err.content(),
+            RuntimeErrorContent::CstStringNotMatching("(")
+        ));
+
+        // Failures before the cut still try the other members
+        let err = run(&grammar, "i").unwrap_err();
+        assert!(matches!(
+            err.content(),
+            RuntimeErrorContent::NoMatchInUnion(_)
+        ));
+    }
+
+    #[test]
+    fn cuts_only_commit_in_their_rule() {
+        let grammar = parse_peg(
+            "main = stmt | call\nstmt = \"if\" ^ \" \" \"(\" ident \")\" | \"while\" ^ \" \" ident\ncall = ident \" \" ident\nident = @(B_ALPHABETIC+)",
+        )
+        .unwrap();
+
+        // `stmt` commits to its first member, but `main` still tries its own members
+        assert!(run(&grammar, "if x").is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "grapheme-boundaries")]
+    fn grapheme_boundaries() {
+        let grammar = parse_peg("main = \"e\" B_ANY*").unwrap();
+        let graphemes = RuntimeOptions {
+            grapheme_boundaries: true,
+            ..RuntimeOptions::new()
+        };
+
+        // `"e"` matches the beginning of the `e\u{301}` grapheme cluster, unless the option is enabled
+        assert!(run(&grammar, "e\u{301}").is_ok());
+        assert!(run_with(&grammar, "e\u{301}", graphemes).is_err());
+
+        assert!(run_with(&grammar, "e", graphemes).is_ok());
+        assert!(run_with(&grammar, "e\u{301}e", graphemes).is_err());
+        assert!(run_with(&grammar, "ee\u{301}", graphemes).is_ok());
+    }
+}
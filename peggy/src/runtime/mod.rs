@@ -10,4 +10,4 @@ mod executor;
 pub use builtin::*;
 pub use data::*;
 pub use errors::*;
-pub use executor::*;
+pub use executor::{execute, ExternalRulesHandler, RuntimeContext, RuntimeCursor, RuntimeOptions};
@@ -0,0 +1,278 @@
+use super::{get_enum_variant, InternalState};
+use crate::grammar::data::*;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+/// Generate the `dump` function, which pretty-prints matched data as an indented tree for debugging purposes
+///
+/// Each line is either a rule (with its span), a constant string or a builtin rule's character.
+/// Silent patterns don't appear in the tree.
+///
+/// With the [tree printer](`super::RustGenOptions::tree_printer`), matched rules' types also get a `print_tree` method,
+/// which prints the same tree with the input matched by each rule.
+pub fn gen_dump(state: &InternalState, pst: &PegSyntaxTree) -> TokenStream {
+    // Silent main rules don't produce any data
+    if state.rule_types[GRAMMAR_ENTRYPOINT_RULE].is_none() {
+        return quote! {
+            /// Pretty-print matched data as an indented tree, for debugging purposes
+            ///
+            /// As the grammar's main rule is silent, the tree is always empty.
+            pub fn dump(_: &SuccessData) -> String {
+                String::new()
+            }
+        };
+    }
+
+    let global_lifetime_req = if state.rules_with_lifetime.is_empty() {
+        quote! {}
+    } else {
+        quote! { <'a> }
+    };
+
+    let rules = pst
+        .ordered_rules()
+        .into_iter()
+        .filter(|rule| matches!(state.rule_types.get(rule.name()), Some(Some(_))))
+        .map(|rule| {
+            let name = rule.name();
+            let ident = state.rule_ident(name);
+
+            let lifetime_req = if state.rules_with_lifetime.contains(name) {
+                quote! { <'a> }
+            } else {
+                quote! {}
+            };
+
+            let print_tree = if state.tree_printer {
+                Some(quote! {
+                    impl #lifetime_req matched::#ident #lifetime_req {
+                        /// Pretty-print the matched data as an indented tree, with the input matched by each rule, for debugging purposes
+                        ///
+                        /// The provided input must be the one the data was matched from.
+                        pub fn print_tree(&self, input: &str) -> String {
+                            let mut out = DumpOutput { lines: String::new(), input: Some(input) };
+                            self.dump(0, &mut out);
+                            out.lines
+                        }
+                    }
+                })
+            } else {
+                None
+            };
+
+            quote! {
+                impl #lifetime_req Dump for matched::#ident #lifetime_req {
+                    fn dump(&self, depth: usize, out: &mut DumpOutput) {
+                        match (out.input, self.matched.inline()) {
+                            (Some(input), inline) => {
+                                dump_line(depth, out, format_args!("{} @{}..{} {:?}", #name, self.span.start, self.span.end, self.span.slice(input)));
+
+                                // Inline data is already shown by the matched input
+                                if inline.is_none() {
+                                    self.matched.dump(depth + 1, out);
+                                }
+                            }
+                            (None, Some(inline)) => dump_line(depth, out, format_args!("{} {} @{}..{}", #name, inline, self.span.start, self.span.end)),
+                            (None, None) => {
+                                dump_line(depth, out, format_args!("{} @{}..{}", #name, self.span.start, self.span.end));
+                                self.matched.dump(depth + 1, out);
+                            }
+                        }
+                    }
+                }
+
+                #print_tree
+            }
+        });
+
+    let mut builtin_rules: Vec<_> = state.builtin_rule_types.iter().collect();
+    builtin_rules.sort();
+
+    let builtin_rules = builtin_rules.into_iter().map(|name| {
+        let ident = format_ident!("{}", name);
+
+        quote! {
+            impl Dump for matched::#ident {
+                fn dump(&self, depth: usize, out: &mut DumpOutput) {
+                    dump_line(depth, out, format_args!("{} {:?} @{}..{}", #name, self.matched, self.span.start, self.span.end));
+                }
+            }
+        }
+    });
+
+    let mut strings: Vec<_> = state.cst_string_types.iter().collect();
+    strings.sort_by_key(|(string, _)| **string);
+
+    let strings = strings.into_iter().map(|(string, typename)| {
+        let literal = format!("{:?}", string);
+
+        quote! {
+            impl Dump for strings::#typename {
+                fn dump(&self, depth: usize, out: &mut DumpOutput) {
+                    dump_line(depth, out, format_args!("{}", #literal));
+                }
+
+                fn inline(&self) -> Option<String> {
+                    Some(#literal.to_string())
+                }
+            }
+        }
+    });
+
+    let unions = state.used_unions.iter().map(|&i| {
+        let variants: Vec<_> = (0..i)
+            .map(|i| format_ident!("{}", get_enum_variant(i)))
+            .collect();
+
+        let ident = format_ident!("Sw{}", i);
+
+        quote! {
+            impl<#(#variants: Dump),*> Dump for unions::#ident<#(#variants),*> {
+                fn dump(&self, depth: usize, out: &mut DumpOutput) {
+                    match self {
+                        #(Self::#variants(v) => v.dump(depth, out)),*
+                    }
+                }
+
+                fn inline(&self) -> Option<String> {
+                    match self {
+                        #(Self::#variants(v) => v.inline()),*
+                    }
+                }
+            }
+        }
+    });
+
+    // Suites' data are stored in tuples, which can't be larger than the suites themselves
+    let tuples = (2..=max_suite_len(pst)).map(|len| {
+        let types: Vec<_> = (0..len).map(|i| format_ident!("T{}", i)).collect();
+        let values: Vec<_> = (0..len).map(|i| format_ident!("v{}", i)).collect();
+
+        quote! {
+            impl<#(#types: Dump),*> Dump for (#(#types,)*) {
+                fn dump(&self, depth: usize, out: &mut DumpOutput) {
+                    let (#(#values,)*) = self;
+                    #(#values.dump(depth, out);)*
+                }
+            }
+        }
+    });
+
+    let wrapper = state.recursion_wrapper.gen_path();
+
+    quote! {
+        /// Pretty-print matched data as an indented tree, for debugging purposes
+        ///
+        /// Each line is either a rule with its span (e.g. `expr @3..17`), a constant string or a builtin rule's character.
+        /// Silent patterns don't appear in the tree.
+        pub fn dump #global_lifetime_req (data: &SuccessData #global_lifetime_req) -> String {
+            let mut out = DumpOutput { lines: String::new(), input: None };
+            data.dump(0, &mut out);
+            out.lines
+        }
+
+        /// Output of [`dump`]
+        struct DumpOutput<'i> {
+            /// Lines written so far
+            lines: String,
+            /// Input the data was matched from, to show the input matched by each rule
+            input: Option<&'i str>,
+        }
+
+        /// Write a line of [`dump`]'s output
+        fn dump_line(depth: usize, out: &mut DumpOutput, line: std::fmt::Arguments) {
+            use std::fmt::Write;
+            let _ = writeln!(out.lines, "{:indent$}{}", "", line, indent = depth * 2);
+        }
+
+        /// Matched data which can be pretty-printed by [`dump`]
+        trait Dump {
+            /// Write the data's lines at the provided depth
+            fn dump(&self, depth: usize, out: &mut DumpOutput);
+
+            /// Get the data's representation, if it can be written on its parent rule's line
+            fn inline(&self) -> Option<String> {
+                None
+            }
+        }
+
+        impl Dump for () {
+            fn dump(&self, _: usize, _: &mut DumpOutput) {}
+        }
+
+        // Optional constant strings
+        impl Dump for bool {
+            fn dump(&self, depth: usize, out: &mut DumpOutput) {
+                if *self {
+                    dump_line(depth, out, format_args!("(matched)"));
+                }
+            }
+        }
+
+        // Repeated constant strings
+        impl Dump for usize {
+            fn dump(&self, depth: usize, out: &mut DumpOutput) {
+                if *self > 0 {
+                    dump_line(depth, out, format_args!("(matched {} times)", self));
+                }
+            }
+        }
+
+        // Atomic patterns and patterns matching up to a terminator
+        impl<'a> Dump for &'a str {
+            fn dump(&self, depth: usize, out: &mut DumpOutput) {
+                dump_line(depth, out, format_args!("{:?}", self));
+            }
+
+            fn inline(&self) -> Option<String> {
+                Some(format!("{:?}", self))
+            }
+        }
+
+        impl<T: Dump> Dump for Option<T> {
+            fn dump(&self, depth: usize, out: &mut DumpOutput) {
+                if let Some(data) = self {
+                    data.dump(depth, out);
+                }
+            }
+        }
+
+        impl<T: Dump> Dump for Vec<T> {
+            fn dump(&self, depth: usize, out: &mut DumpOutput) {
+                for data in self {
+                    data.dump(depth, out);
+                }
+            }
+        }
+
+        // Recursive rules
+        impl<T: Dump> Dump for #wrapper<T> {
+            fn dump(&self, depth: usize, out: &mut DumpOutput) {
+                (**self).dump(depth, out);
+            }
+
+            fn inline(&self) -> Option<String> {
+                (**self).inline()
+            }
+        }
+
+        #(#tuples)*
+        #(#rules)*
+        #(#builtin_rules)*
+        #(#strings)*
+        #(#unions)*
+    }
+}
+
+/// Get the number of members of the grammar's largest suite
+fn max_suite_len(pst: &PegSyntaxTree) -> usize {
+    let mut max = 0;
+
+    pst.walk_rules(&mut |_, pattern| {
+        if let RulePatternValue::Suite(patterns) = pattern.value() {
+            max = max.max(patterns.len());
+        }
+    });
+
+    max
+}
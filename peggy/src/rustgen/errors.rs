@@ -0,0 +1,117 @@
+use crate::grammar::ParserLoc;
+use std::fmt;
+
+/// Error raised when a syntax tree can't be turned into a parser
+///
+/// Trees produced by [`parse_peg`](`crate::grammar::parse_peg`) are always valid, but trees which skipped validation
+/// (e.g. from [`parse_peg_nocheck`](`crate::grammar::parse_peg_nocheck`)) may not be, as well as some
+/// [generation options](`super::RustGenOptions`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenError {
+    /// The tree has no `main` rule
+    MissingMainRule,
+
+    /// A rule references another one which isn't declared
+    UnknownRule { rule: String, referenced: String },
+
+    /// A rule references a builtin rule which doesn't exist
+    UnknownBuiltinRule { rule: String, referenced: String },
+
+    /// A rule contains a suite without any pattern
+    EmptySuite { rule: String },
+
+    /// A rule contains a union without any member
+    EmptyUnion { rule: String },
+
+    /// A rule is renamed to something which is not a valid identifier (see [`super::RustGenOptions::rename`])
+    InvalidRename { rule: String, ident: String },
+
+    /// Two rules would be generated with the same identifier (see [`super::RustGenOptions::rename`])
+    DuplicateIdent {
+        first: String,
+        second: String,
+        ident: String,
+    },
+
+    /// A rule would be generated with an identifier the generated code already uses (e.g. `Some`), which can be avoided
+    /// by [renaming](`super::RustGenOptions::rename`) it
+    ReservedIdent {
+        rule: String,
+        ident: String,
+        loc: ParserLoc,
+    },
+
+    /// The custom recursion wrapper is not a valid path (see [`super::RecursionWrapper::Custom`])
+    InvalidRecursionWrapper(String),
+
+    /// A rule references a builtin rule other than `B_ANY` while the [token input](`super::RustGenOptions::token_input`)
+    /// option is enabled, which would match the characters encoding the tokens
+    BuiltinRuleInTokenInput { rule: String, referenced: String },
+
+    /// The grammar has more constant strings than the characters available to encode the kinds of tokens
+    /// (see [`super::RustGenOptions::token_input`])
+    TooManyTokenKinds { count: usize, max: usize },
+
+    /// The [streaming](`super::RustGenOptions::streaming`) option is enabled but the main rule is not a repeated
+    /// reference to a capturing rule
+    InvalidStreamingMainRule,
+}
+
+impl fmt::Display for GenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingMainRule => write!(f, "The grammar has no 'main' rule"),
+            Self::UnknownRule { rule, referenced } => write!(
+                f,
+                "Rule '{}' references unknown rule '{}'",
+                rule, referenced
+            ),
+            Self::UnknownBuiltinRule { rule, referenced } => write!(
+                f,
+                "Rule '{}' references unknown builtin rule '{}'",
+                rule, referenced
+            ),
+            Self::EmptySuite { rule } => write!(f, "Rule '{}' contains an empty suite", rule),
+            Self::EmptyUnion { rule } => write!(f, "Rule '{}' contains an empty union", rule),
+            Self::InvalidRename { rule, ident } => write!(
+                f,
+                "Rule '{}' can't be renamed to '{}', which is not a valid identifier",
+                rule, ident
+            ),
+            Self::DuplicateIdent {
+                first,
+                second,
+                ident,
+            } => write!(
+                f,
+                "Rules '{}' and '{}' would both be generated as '{}', please rename one of them",
+                first, second, ident
+            ),
+            Self::ReservedIdent { rule, ident, loc } => write!(
+                f,
+                "Rule '{}' (line {}, column {}) would be generated as '{}', which is reserved by the generated code, please rename it",
+                rule,
+                loc.line() + 1,
+                loc.col() + 1,
+                ident
+            ),
+            Self::InvalidRecursionWrapper(path) => {
+                write!(f, "Invalid recursion wrapper path: {}", path)
+            }
+            Self::BuiltinRuleInTokenInput { rule, referenced } => write!(
+                f,
+                "Rule '{}' references builtin rule '{}', but only 'B_ANY' can be used with the 'token_input' option",
+                rule, referenced
+            ),
+            Self::TooManyTokenKinds { count, max } => write!(
+                f,
+                "The grammar has {} constant strings, but at most {} kinds of tokens can be used with the 'token_input' option",
+                count, max
+            ),
+            Self::InvalidStreamingMainRule => write!(
+                f,
+                "The 'streaming' option requires the main rule to be a repeated reference to a capturing rule (e.g. 'main = token*')"
+            ),
+        }
+    }
+}
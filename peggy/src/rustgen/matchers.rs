@@ -1,25 +1,32 @@
 use quote::{quote, format_ident};
-use quote::__private::TokenStream;
-use super::{InternalState, make_safe_ident, get_enum_variant};
+use proc_macro2::TokenStream;
+use super::{InternalState, get_enum_variant, uses_ident};
+use super::model::{GenNode, Shape};
+use super::types::gen_suite_data;
 use crate::grammar::data::*;
 use crate::grammar::utils::*;
+use crate::grammar::repetition_min_length;
 
+/// Generate the matcher of a rule from its lowered pattern, the rule's data being discarded if it isn't capturing
+/// (see [`super::model::GenModel::is_capturing`])
 pub fn gen_rule_matcher<'a>(
     state: &mut InternalState<'a>,
     name: &'a str,
-    rule: &'a Rule,
+    node: &GenNode<'a>,
+    capturing: bool,
 ) -> TokenStream {
-    let ident = make_safe_ident(name);
+    let ident = state.rule_ident(name);
 
-    let pattern_matcher = gen_pattern_matcher(state, name, rule.pattern());
+    let pattern_matcher = gen_pattern_matcher(state, name, node);
 
-    let body = if let Some(PatternMode::Silent) = state.non_capturing_rules.get(name) {
-        quote! { #pattern_matcher }
+    let body = if !capturing {
+        // Rules made of silent patterns may still produce data (e.g. a union of silent patterns), which is discarded
+        quote! { #pattern_matcher.map(|(_, consumed, end_err)| ((), consumed, end_err)) }
     } else {
-        quote! { #pattern_matcher.and_then(|(matched, consumed, end_err)| Ok((super::matched::#ident { matched, at: offset }, consumed, end_err))) }
+        quote! { #pattern_matcher.and_then(|(matched, consumed, end_err)| Ok((super::matched::#ident { matched, span: super::Span { start: offset, end: offset + consumed } }, consumed, end_err))) }
     };
 
-    let ret_type = if let Some(PatternMode::Silent) = state.non_capturing_rules.get(name) {
+    let ret_type = if !capturing {
         quote! { () }
     } else if state.rules_with_lifetime.contains(name) {
         quote! { super::matched::#ident<'a> }
@@ -27,13 +34,37 @@ pub fn gen_rule_matcher<'a>(
         quote! { super::matched::#ident }
     };
 
+    let trace = if state.cargo_features {
+        Some(quote! {
+            #[cfg(feature = "trace")]
+            tracing::trace!(rule = #name, offset, "Matching rule");
+        })
+    } else {
+        None
+    };
+
+    // Only declare the variables the body actually uses, to avoid generating unused variables
+    let base_input_decl = if uses_ident(&body, "base_input_for_str") {
+        Some(quote! {
+            let base_input_for_str = input;
+            let base_offset_for_str = offset;
+        })
+    } else {
+        None
+    };
+
+    let rule_name_decl = if uses_ident(&body, "rule_name") {
+        Some(quote! { let rule_name = #name; })
+    } else {
+        None
+    };
+
     let body_with_eventual_debugger = if let Some(mod_name) = &state.debugger {
         quote! {
             super::super::#mod_name::entering_rule(#name, input, offset);
 
-            let base_input_for_str = input;
-            let base_offset_for_str = offset;
-            let rule_name = #name;
+            #base_input_decl
+            #rule_name_decl
             let result = #body;
 
             super::super::#mod_name::leaving_rule(#name, input, offset, result.clone().err());
@@ -42,51 +73,131 @@ pub fn gen_rule_matcher<'a>(
         }
     } else {
         quote! {
-            let base_input_for_str = input;
-            let base_offset_for_str = offset;
-            let rule_name = #name;
+            #base_input_decl
+            #rule_name_decl
             #body
         }
     };
 
+    let trivia_param = if state.collect_trivia {
+        // Rules that can't collect any trivia still take the parameter to keep a uniform signature
+        let trivia_ident = if uses_ident(&body, "trivia") {
+            format_ident!("trivia")
+        } else {
+            format_ident!("_trivia")
+        };
+
+        Some(quote! { , #trivia_ident: &mut Vec<super::Trivia> })
+    } else {
+        None
+    };
+
+    let doc = format!(
+        "Match the `{}` rule at the beginning of `input` (see the [module's documentation](self))",
+        name
+    );
+
     quote! {
-        pub fn #ident <'a> (source: &'a str, input: &'a str, offset: usize) -> Result<(#ret_type, usize, Option<super::PegError<'a>>), super::PegError<'a>> {
+        #[doc = #doc]
+        pub fn #ident <'a> (source: &'a str, input: &'a str, offset: usize #trivia_param) -> Result<(#ret_type, usize, Option<super::PegError<'a>>), super::PegError<'a>> {
+            #trace
             #body_with_eventual_debugger
         }
     }
 }
 
+/// Generate the matcher of a lowered pattern, which produces data of the pattern's shape
 pub fn gen_pattern_matcher<'a>(
     state: &mut InternalState<'a>,
     visiting: &'a str,
-    pattern: &'a Pattern,
+    node: &GenNode<'a>,
 ) -> TokenStream {
-    let matcher = gen_pattern_value_matcher(state, visiting, pattern.value());
+    let pattern = node.pattern;
+
+    let cut_scope = state.cut_scope;
+    state.cut_scope = cut_scope && pattern.forwards_cuts();
+    let matcher = gen_pattern_value_matcher(state, visiting, node);
+    state.cut_scope = cut_scope;
 
     let matcher = match pattern.mode() {
+            Some(PatternMode::Silent) if state.collect_trivia => quote! {{
+                let trivia_start = trivia.len();
+                let result = #matcher;
+
+                // Only keep the outermost silent match
+                trivia.truncate(trivia_start);
+
+                result.map(|(_, consumed, end_err)| {
+                    if consumed > 0 {
+                        super::push_trivia(trivia, super::Trivia { span: super::Span { start: offset, end: offset + consumed }, kind: #visiting });
+                    }
+
+                    ((), consumed, end_err)
+                })
+            }},
             Some(PatternMode::Silent) => quote! { #matcher.map(|(_, consumed, end_err)| ((), consumed, end_err)) },
-            Some(PatternMode::Peek) => quote! { #matcher.map(|(_, _, end_err)| ((), 0, end_err)) },
-            Some(PatternMode::Negative) => quote! {{
+            Some(PatternMode::Peek) => {
+                let matcher = gen_trivia_rollback(state, matcher, true);
+                quote! { #matcher.map(|(_, _, end_err)| ((), 0, end_err)) }
+            },
+            Some(PatternMode::Negative) => {
+                let matcher = gen_trivia_rollback(state, matcher, true);
+
+                quote! {{
                 let result = #matcher;
                 match result {
                     Ok((_, consumed, _)) => Err(super::PegErrorContent::MatchedInNegativePattern(&base_input_for_str[(offset - base_offset_for_str)..(offset - base_offset_for_str + consumed)]).at(source, offset, rule_name)),
                     Err(_) => Ok(((), 0, Option::<super::PegError>::None))
                 }
-            }},
+            }}},
+            Some(PatternMode::Atomic) if state.checked_slicing => quote! { #matcher.and_then(|(_, consumed, end_err)| {
+                match base_input_for_str.get((offset - base_offset_for_str)..(offset - base_offset_for_str + consumed)) {
+                    Some(matched) => Ok((matched, consumed, end_err)),
+                    None => Err(super::PegErrorContent::InternalConsumedOverflow(consumed).at(source, offset, rule_name))
+                }
+            }) },
             Some(PatternMode::Atomic) => quote! { #matcher.map(|(_, consumed, end_err)| {
                 (&base_input_for_str[(offset - base_offset_for_str)..(offset - base_offset_for_str + consumed)], consumed, end_err)
             }) },
             None => quote! { #matcher }
     };
 
+    // Trivia collected by failed attempts must be discarded
+    let matcher = match pattern.repetition() {
+        None => matcher,
+        Some(_) => gen_trivia_rollback(state, matcher, false),
+    };
+
+    let move_input = gen_move_input(state);
+
     match pattern.repetition() {
         None => quote! { #matcher },
         Some(rep) => match rep {
             PatternRepetition::Any | PatternRepetition::OneOrMore => {
-                let (storage, push_strategy, ret_val) = if pattern.is_dataless() {
-                    (None, None, quote! { () })
+                let (storage, piece_data, push_strategy, ret_val) = if !node.is_capturing() {
+                    (None, format_ident!("_"), None, quote! { () })
+                } else if node.shape == Shape::Count {
+                    // Markers are only counted
+                    (Some(quote! { let mut out = 0; }), format_ident!("_"), Some(quote! { out += 1; }), quote! { out })
                 } else {
-                    (Some(quote! { let mut out = vec![]; }), Some(quote! { out.push(piece_data); }), quote! { out })
+                    let push = match &state.preallocation_min_lengths {
+                        Some(lengths) => {
+                            // Empty elements would loop forever anyway, and elements which can't match never get pushed
+                            let min_len = repetition_min_length(lengths, pattern).max(1);
+                            let limit = super::PREALLOCATION_LIMIT;
+
+                            quote! {
+                                if out.is_empty() {
+                                    out.reserve((input.len() / #min_len).clamp(1, #limit));
+                                }
+
+                                out.push(piece_data);
+                            }
+                        },
+                        None => quote! { out.push(piece_data); }
+                    };
+
+                    (Some(quote! { let mut out = vec![]; }), format_ident!("piece_data"), Some(push), quote! { out })
                 };
 
                 let (init_var, init_set, err_handling) = if rep == PatternRepetition::Any {
@@ -111,12 +222,12 @@ pub fn gen_pattern_matcher<'a>(
                             let result = #matcher;
 
                             match result {
-                                Ok((piece_data, piece_consumed, end_err)) => {
+                                Ok((#piece_data, piece_consumed, _)) => {
                                     #init_set
                                     #push_strategy
+                                    #move_input
                                     consumed += piece_consumed;
                                     offset += piece_consumed;
-                                    input = &input[piece_consumed..];
                                 },
 
                                 Err(err) => break #err_handling
@@ -125,6 +236,24 @@ pub fn gen_pattern_matcher<'a>(
                     }
                 }
             },
+            PatternRepetition::Optional if !node.is_capturing() => quote! {
+                {
+                    let result = #matcher;
+                    match result {
+                        Ok((_, consumed, end_err)) => Ok(((), consumed, end_err)),
+                        Err(err) => Ok(((), 0, Some(err)))
+                    }
+                }
+            },
+            PatternRepetition::Optional if node.shape == Shape::Flag => quote! {
+                {
+                    let result = #matcher;
+                    match result {
+                        Ok((_, consumed, end_err)) => Ok((true, consumed, end_err)),
+                        Err(err) => Ok((false, 0, Some(err)))
+                    }
+                }
+            },
             PatternRepetition::Optional => quote! {
                 {
                     let result = #matcher;
@@ -138,24 +267,49 @@ pub fn gen_pattern_matcher<'a>(
     }
 }
 
+/// Generate the matcher of a lowered pattern's value, ignoring its mode and repetition
 pub fn gen_pattern_value_matcher<'a>(
     state: &mut InternalState<'a>,
     visiting: &'a str,
-    value: &'a RulePatternValue,
+    node: &GenNode<'a>,
 ) -> TokenStream {
-    match value {
+    match node.pattern.value() {
         RulePatternValue::CstString(string) => {
             let str_type = match state.cst_string_types.get(string) {
                 Some(str_type) => quote! { super::strings::#str_type },
 
                 // Happens when the parent pattern is silent
-                None => quote! { () }
+                None => quote! { () },
             };
 
-            let str_len = string.len();
+            // Tokens are encoded as a single character (see `encode_tokens`)
+            let (encoded, str_len) =
+                match state.token_markers.as_ref().map(|markers| markers[string]) {
+                    Some(marker) => (quote! { #marker }, marker.len_utf8()),
+                    None => (quote! { #string }, string.len()),
+                };
+
+            // Ensure the string doesn't end in the middle of a grapheme cluster (e.g. before a combining character)
+            let boundary_check = if state.grapheme_boundaries {
+                Some(quote! {
+                    && unicode_segmentation::GraphemeCursor::new(#str_len, input.len(), true).is_boundary(input, 0).unwrap_or(true)
+                })
+            } else {
+                None
+            };
+
+            // Ensure keywords aren't directly followed by a word character (e.g. `if` in `iffy`)
+            let keyword_check = if state.keywords.contains(string) && state.token_markers.is_none()
+            {
+                Some(quote! {
+                    && !input[#str_len..].starts_with(|c: char| c.is_alphanumeric() || c == '_')
+                })
+            } else {
+                None
+            };
 
             quote! {
-                if input.starts_with(#string) {
+                if input.starts_with(#encoded) #boundary_check #keyword_check {
                     Ok((#str_type, #str_len, Option::<super::PegError>::None))
                 } else {
                     Err(super::PegErrorContent::ExpectedCstString(#string).at(source, offset, rule_name))
@@ -163,21 +317,33 @@ pub fn gen_pattern_value_matcher<'a>(
             }
         }
         RulePatternValue::Rule(name) => {
-            let matcher = if is_builtin_rule_name(name) {
+            let matcher = if is_zero_width_builtin_rule_name(name) {
+                gen_zero_width_builtin_matcher(name)
+            } else if is_indentation_rule_name(name) {
+                gen_indentation_marker_matcher(state, name)
+            } else if is_builtin_rule_name(name) {
                 state.used_builtin_rules.insert(name);
-                gen_builtin_matcher(name)
+                gen_builtin_matcher(name, state.builtin_rule_types.contains(name))
             } else {
-                let ident = make_safe_ident(name);
-                let ret_data = quote! { #ident (source, input, offset) };
+                let ident = state.rule_ident(name);
+                let trivia_arg = if state.collect_trivia {
+                    Some(quote! { , trivia })
+                } else {
+                    None
+                };
+
+                // The matcher is referenced through its module, as the matchers' variables (e.g. `input`) would shadow it
+                let ret_data = quote! { self::#ident (source, input, offset #trivia_arg) };
 
-                if state.recursive_paths[visiting].contains(name) {
-                    quote! { #ret_data.map(|(data, consumed, end_err)| (std::rc::Rc::new(data), consumed, end_err)) }
+                if let Shape::Rule { indirect: true, .. } = node.value {
+                    let wrapper = state.recursion_wrapper.gen_path();
+                    quote! { #ret_data.map(|(data, consumed, end_err)| (#wrapper::new(data), consumed, end_err)) }
                 } else {
                     ret_data
                 }
             };
 
-            if state.non_capturing_rules.contains_key(name) {
+            if state.graph.non_capturing_mode(name).is_some() {
                 quote! {
                     {
                         let non_capturing = #matcher;
@@ -188,35 +354,37 @@ pub fn gen_pattern_value_matcher<'a>(
                 matcher
             }
         }
-        RulePatternValue::Group(pattern) => {
-            gen_pattern_matcher(state, visiting, pattern.as_ref())
-        }
+        RulePatternValue::Group(_) => gen_pattern_matcher(state, visiting, &node.children[0]),
         RulePatternValue::Suite(patterns) => {
             let mut used = vec![];
 
-            let create_storage: Vec<_> = patterns
+            let create_storage: Vec<_> = node
+                .children
                 .iter()
                 .enumerate()
-                .map(|(i, pattern)| {
-                    let matcher = gen_pattern_matcher(state, visiting, pattern);
-                    
-                    let mut is_dataless = pattern.is_dataless();
-
-                    if !is_dataless {
-                        if let RulePatternValue::Rule(name) = pattern.value() {
-                            if let Some(PatternMode::Silent) = state.non_capturing_rules.get(name) {
-                                is_dataless = true;
-                            }
-                        }
-                    }
+                .map(|(i, piece)| {
+                    let matcher = gen_pattern_matcher(state, visiting, piece);
 
-                    let storage = if is_dataless {
+                    // Only store the data of the pieces the suite's type is made of
+                    let storage = if !piece.is_capturing() {
                         format_ident!("_")
                     } else {
                         used.push(format_ident!("p{}", i));
                         format_ident!("p{}", i)
                     };
 
+                    // The last piece doesn't need to move the input forward
+                    let move_forward = if i + 1 < patterns.len() {
+                        let move_input = gen_move_input(state);
+
+                        Some(quote! {
+                            #move_input
+                            offset += piece_consumed;
+                        })
+                    } else {
+                        None
+                    };
+
                     quote! {
                         let result = #matcher;
 
@@ -228,19 +396,14 @@ pub fn gen_pattern_value_matcher<'a>(
                         if let Some(end_err) = end_err {
                             last_end_err = Some(end_err);
                         }
-                        
-                        offset += piece_consumed;
+
                         consumed += piece_consumed;
-                        input = &input[piece_consumed..];
+                        #move_forward
                     }
                 })
                 .collect();
 
-            let ret_success_value = if used.len() == 1 {
-                quote! { #(#used)* }
-            } else {
-                quote! { (#(#used,)*) }
-            };
+            let ret_success_value = gen_suite_data(&used);
 
             quote! {
                 // TODO: Find a less "hacky" way to achieve this
@@ -260,73 +423,275 @@ pub fn gen_pattern_value_matcher<'a>(
         RulePatternValue::Union(patterns) => {
             let union_ident = format_ident!("Sw{}", patterns.len());
 
-            let tries: Vec<_> = patterns
+            // Errors are only collected in a vector if all members failed, to avoid allocating on success
+            let errors: Vec<_> = (0..patterns.len())
+                .map(|i| format_ident!("err_{}", i))
+                .collect();
+
+            let tries: Vec<_> = node.children
                 .iter()
+                .zip(&errors)
                 .enumerate()
-                .map(|(i, pattern)| {
-                    let matcher = gen_pattern_matcher(state, visiting, pattern);
-                    
+                .map(|(i, (member, err_ident))| {
+                    let cut_scope = std::mem::replace(&mut state.cut_scope, true);
+                    let matcher = gen_pattern_matcher(state, visiting, member);
+                    state.cut_scope = cut_scope;
+
                     let union_variant = format_ident!("{}", get_enum_variant(i));
 
+                    // Only the trivia collected by the selected candidate are kept
+                    let (trivia_start, keep_trivia, drop_trivia) = if state.collect_trivia {
+                        (
+                            Some(quote! { let trivia_start = trivia.len(); }),
+                            Some(quote! { candidate_trivia = trivia.split_off(trivia_start); }),
+                            Some(quote! { trivia.truncate(trivia_start); })
+                        )
+                    } else {
+                        (None, None, None)
+                    };
+
+                    // Failing after a cut makes the whole union fail with the member's error
+                    let on_err = if member.pattern.has_cut() {
+                        quote! {
+                            if committed {
+                                cut_err = Some(err);
+                                None
+                            } else {
+                                Some(err)
+                            }
+                        }
+                    } else {
+                        quote! { Some(err) }
+                    };
+
+                    // Members following a cut are skipped once it has been passed
+                    let guard = if node.children[..i].iter().any(|member| member.pattern.has_cut()) {
+                        Some(quote! { if committed { None } else })
+                    } else {
+                        None
+                    };
+
                     quote! {
-                        {
+                        let #err_ident = #guard {
+                            #trivia_start
                             let union_result = #matcher;
-                            
+
                             match union_result {
-                                Ok((data, consumed, end_err)) => match candidate {
-                                    Some((_, candidate_consumed, _)) => if consumed > candidate_consumed {
-                                        candidate = Some((super::unions::#union_ident::#union_variant(data), consumed, end_err));
-                                    },
-                                    None => candidate = Some((super::unions::#union_ident::#union_variant(data), consumed, end_err))
+                                Ok((data, consumed, end_err)) => {
+                                    match candidate {
+                                        Some((_, candidate_consumed, _)) => if consumed > candidate_consumed {
+                                            candidate = Some((super::unions::#union_ident::#union_variant(data), consumed, end_err));
+                                            #keep_trivia
+                                        } else {
+                                            #drop_trivia
+                                        },
+                                        None => {
+                                            candidate = Some((super::unions::#union_ident::#union_variant(data), consumed, end_err));
+                                            #keep_trivia
+                                        }
+                                    }
+
+                                    None
                                 },
 
-                                Err(err) => errors.push(std::rc::Rc::new(err))
+                                Err(err) => {
+                                    #drop_trivia
+                                    #on_err
+                                }
                             }
-                        }
+                        };
                     }
                 })
                 .collect();
 
-            if tries.len() > state.highest_union_used {
-                state.highest_union_used = tries.len();
-            }
+            // Unions with a single member are replaced by groups beforehand (see `PegSyntaxTree::simplify_unions`)
+            debug_assert!(tries.len() >= 2);
+            state.used_unions.insert(tries.len());
+
+            let errors_count = errors.len();
+
+            let (candidate_trivia, restore_trivia) = if state.collect_trivia {
+                (
+                    Some(quote! { let mut candidate_trivia = Vec::new(); }),
+                    Some(quote! { trivia.append(&mut candidate_trivia); }),
+                )
+            } else {
+                (None, None)
+            };
+
+            let result = quote! {
+                match candidate {
+                    None => {
+                        let mut errors = Vec::with_capacity(#errors_count);
+                        #(errors.extend(#errors);)*
+                        Err(super::PegErrorContent::NoMatchInUnion(errors).at(source, offset, rule_name))
+                    },
+                    Some((data, consumed, end_err)) => Ok((data, consumed, end_err))
+                }
+            };
+
+            let (cut_state, result) = if node.children.iter().any(|member| member.pattern.has_cut())
+            {
+                (
+                    Some(quote! {
+                        let mut committed = false;
+                        let mut cut_err = None;
+                    }),
+                    quote! {
+                        match cut_err {
+                            Some(err) => Err(err),
+                            None => #result
+                        }
+                    },
+                )
+            } else {
+                (None, result)
+            };
 
             quote! {
                 {
                     let mut candidate = None;
-                    let mut errors = vec![];
+                    #cut_state
+                    #candidate_trivia
                     #(#tries)*
+                    #restore_trivia
 
-                    match candidate {
-                        None => Err(super::PegErrorContent::NoMatchInUnion(errors).at(source, offset, rule_name)),
-                        Some((data, consumed, end_err)) => Ok((data, consumed, end_err))
-                    }
+                    #result
+                }
+            }
+        }
+        RulePatternValue::Cut => {
+            let commit = if state.cut_scope {
+                Some(quote! { committed = true; })
+            } else {
+                None
+            };
+
+            quote! {{
+                #commit
+                Ok(((), 0, Option::<super::PegError>::None))
+            }}
+        }
+        RulePatternValue::Until(_) => {
+            let not_found = quote! { Err(super::PegErrorContent::TerminatorNotFound.at(source, until_offset, rule_name)) };
+
+            match cst_string_terminator(node.children[0].pattern) {
+                // Constant strings can be looked for directly (unless they must end on a grapheme boundary)
+                Some(string) if !state.grapheme_boundaries => {
+                    let encoded = match state.token_markers.as_ref().map(|markers| markers[string])
+                    {
+                        Some(marker) => quote! { #marker },
+                        None => quote! { #string },
+                    };
+
+                    quote! {{
+                        let until_offset = offset;
+
+                        match input.find(#encoded) {
+                            Some(len) => Ok((&input[..len], len, Option::<super::PegError>::None)),
+                            None => #not_found
+                        }
+                    }}
+                }
+
+                // Otherwise, the terminator is tried at each character until it matches
+                _ => {
+                    // Cuts in the terminator don't commit to the enclosing union member
+                    let cut_scope = std::mem::replace(&mut state.cut_scope, false);
+                    let matcher = gen_pattern_matcher(state, visiting, &node.children[0]);
+                    state.cut_scope = cut_scope;
+                    let matcher = gen_trivia_rollback(state, matcher, true);
+
+                    quote! {{
+                        let until_input = input;
+                        let until_offset = offset;
+                        let mut len = 0;
+
+                        loop {
+                            let input = &until_input[len..];
+                            let offset = until_offset + len;
+
+                            let result = #matcher;
+
+                            if result.is_ok() {
+                                break Ok((&until_input[..len], len, Option::<super::PegError>::None));
+                            }
+
+                            match input.chars().next() {
+                                Some(c) => len += c.len_utf8(),
+                                None => break #not_found
+                            }
+                        }
+                    }}
                 }
             }
         }
     }
 }
 
-pub fn gen_builtin_matcher(name: &str) -> TokenStream {
-    let cond = match name {
-        "B_ANY" => quote! { true },
+/// Generate the code moving the input forward by the number of bytes a piece consumed (`piece_consumed`), inside of a loop
+///
+/// With [checked slicing](`super::RustGenOptions::checked_slicing`), the loop is broken with an error
+/// instead of panicking if the consumed length doesn't fit the input.
+fn gen_move_input(state: &InternalState) -> TokenStream {
+    if state.checked_slicing {
+        quote! {
+            input = match input.get(piece_consumed..) {
+                Some(input) => input,
+                None => break Err(super::PegErrorContent::InternalConsumedOverflow(piece_consumed).at(source, offset, rule_name))
+            };
+        }
+    } else {
+        quote! { input = &input[piece_consumed..]; }
+    }
+}
+
+/// Get the constant string a terminator is made of, if any (e.g. `"*/"` or `("*/")`)
+fn cst_string_terminator<'a>(terminator: &'a Pattern) -> Option<&'a str> {
+    if terminator.mode().is_some() || terminator.repetition().is_some() {
+        return None;
+    }
+
+    match terminator.value() {
+        RulePatternValue::CstString(string) => Some(string),
+        RulePatternValue::Group(inner) => cst_string_terminator(inner),
+        _ => None,
+    }
+}
+
+/// Discard the trivia collected by a matcher if it fails (or in all cases with `always`)
+///
+/// Does nothing if trivia are not collected
+fn gen_trivia_rollback(state: &InternalState, matcher: TokenStream, always: bool) -> TokenStream {
+    if !state.collect_trivia {
+        return matcher;
+    }
+
+    let rollback = if always {
+        quote! { trivia.truncate(trivia_start); }
+    } else {
+        quote! {
+            if result.is_err() {
+                trivia.truncate(trivia_start);
+            }
+        }
+    };
 
-        "B_NEWLINE_CR" => quote! { *nc == '\r' },
-        "B_NEWLINE_LF" => quote! { *nc == '\n' },
+    quote! {{
+        let trivia_start = trivia.len();
+        let result = #matcher;
+        #rollback
+        result
+    }}
+}
 
-        "B_DOUBLE_QUOTE" => quote! { *nc == '"' },
+pub fn gen_builtin_matcher(name: &str, captured: bool) -> TokenStream {
+    if let Some(cond) = gen_ascii_builtin_cond(name) {
+        return gen_ascii_builtin_matcher(name, cond, captured);
+    }
 
-        "B_ASCII" => quote! { nc.is_ascii() },
-        "B_ASCII_ALPHABETIC" => quote! { nc.is_ascii_alphabetic() },
-        "B_ASCII_ALPHANUMERIC" => quote! { nc.is_ascii_alphanumeric() },
-        "B_ASCII_CONTROL" => quote! { nc.is_ascii_control() },
-        "B_ASCII_DIGIT" => quote! { nc.is_ascii_digit() },
-        "B_ASCII_GRAPHIC" => quote! { nc.is_ascii_graphic() },
-        "B_ASCII_HEXDIGIT" => quote! { nc.is_ascii_hexdigit() },
-        "B_ASCII_LOWERCASE" => quote! { nc.is_ascii_lowercase() },
-        "B_ASCII_PUNCTUATION" => quote! { nc.is_ascii_punctuation() },
-        "B_ASCII_UPPERCASE" => quote! { nc.is_ascii_uppercase() },
-        "B_ASCII_WHITESPACE" => quote! { nc.is_ascii_whitespace() },
+    let cond = match name {
+        "B_ANY" => quote! {},
 
         "B_ALPHABETIC" => quote! { nc.is_alphabetic() },
         "B_ALPHANUMERIC" => quote! { nc.is_alphanumeric() },
@@ -335,20 +700,184 @@ pub fn gen_builtin_matcher(name: &str) -> TokenStream {
         "B_NUMERIC" => quote! { nc.is_numeric() },
         "B_UPPERCASE" => quote! { nc.is_uppercase() },
         "B_WHITESPACE" => quote! { nc.is_whitespace() },
-        
-        _ => unreachable!()
+
+        "B_LETTER" => gen_general_category_cond(&[
+            "UppercaseLetter",
+            "LowercaseLetter",
+            "TitlecaseLetter",
+            "ModifierLetter",
+            "OtherLetter",
+        ]),
+        "B_MARK" => gen_general_category_cond(&["NonspacingMark", "SpacingMark", "EnclosingMark"]),
+        "B_DECIMAL_NUMBER" => gen_general_category_cond(&["DecimalNumber"]),
+        "B_PUNCTUATION" => gen_general_category_cond(&[
+            "ConnectorPunctuation",
+            "DashPunctuation",
+            "OpenPunctuation",
+            "ClosePunctuation",
+            "InitialPunctuation",
+            "FinalPunctuation",
+            "OtherPunctuation",
+        ]),
+        "B_CONNECTOR_PUNCTUATION" => gen_general_category_cond(&["ConnectorPunctuation"]),
+        "B_SYMBOL" => gen_general_category_cond(&[
+            "MathSymbol",
+            "CurrencySymbol",
+            "ModifierSymbol",
+            "OtherSymbol",
+        ]),
+
+        // Unknown builtin rules are rejected before generation starts
+        _ => unreachable!("unknown builtin rule: {}", name)
     };
 
     let name_ident = format_ident!("{}", name);
 
+    // Avoid generating a filter with an unused parameter
+    let filtered = if name == "B_ANY" {
+        quote! { nc }
+    } else {
+        quote! { nc.filter(|nc| #cond) }
+    };
+
+    // Happens when the rule is only used in silent patterns
+    let data = if captured {
+        quote! { super::matched::#name_ident { matched: nc, span: super::Span { start: offset, end: offset + nc.len_utf8() } } }
+    } else {
+        quote! { () }
+    };
+
     quote! {
         {
             let nc = input.chars().next();
 
-            match nc.filter(|nc| #cond) {
-                Some(nc) => Ok((super::matched::#name_ident { matched: nc, at: offset }, 1, Option::<super::PegError>::None)),
+            match #filtered {
+                Some(nc) => Ok((#data, nc.len_utf8(), Option::<super::PegError>::None)),
                 None => Err(super::PegErrorContent::FailedToMatchBuiltinRule(#name, nc).at(source, offset, rule_name))
             }
         }
     }
 }
+
+/// Generate the condition the next byte (`nb`) must satisfy to be matched by a builtin rule, if the rule only matches
+/// ASCII characters
+///
+/// Such rules can be matched without decoding the next character, as non-ASCII characters are only made of bytes which
+/// aren't ASCII themselves, and so never satisfy the condition.
+fn gen_ascii_builtin_cond(name: &str) -> Option<TokenStream> {
+    Some(match name {
+        "B_NEWLINE_CR" => quote! { *nb == b'\r' },
+        "B_NEWLINE_LF" => quote! { *nb == b'\n' },
+
+        "B_DOUBLE_QUOTE" => quote! { *nb == b'"' },
+
+        "B_ASCII" => quote! { nb.is_ascii() },
+        "B_ASCII_ALPHABETIC" => quote! { nb.is_ascii_alphabetic() },
+        "B_ASCII_ALPHANUMERIC" => quote! { nb.is_ascii_alphanumeric() },
+        "B_ASCII_CONTROL" => quote! { nb.is_ascii_control() },
+        "B_ASCII_DIGIT" => quote! { nb.is_ascii_digit() },
+        "B_ASCII_GRAPHIC" => quote! { nb.is_ascii_graphic() },
+        "B_ASCII_HEXDIGIT" => quote! { nb.is_ascii_hexdigit() },
+        "B_ASCII_LOWERCASE" => quote! { nb.is_ascii_lowercase() },
+        "B_ASCII_PUNCTUATION" => quote! { nb.is_ascii_punctuation() },
+        "B_ASCII_UPPERCASE" => quote! { nb.is_ascii_uppercase() },
+        "B_ASCII_WHITESPACE" => quote! { nb.is_ascii_whitespace() },
+
+        "B_BIN_DIGIT" => quote! { matches!(*nb, b'0'..=b'1') },
+        "B_OCTAL_DIGIT" => quote! { matches!(*nb, b'0'..=b'7') },
+        "B_DEC_DIGIT" => quote! { nb.is_ascii_digit() },
+        "B_DIGIT_NONZERO" => quote! { matches!(*nb, b'1'..=b'9') },
+        "B_HEX_DIGIT" => quote! { nb.is_ascii_hexdigit() },
+
+        _ => return None,
+    })
+}
+
+/// Generate the matcher of a builtin rule only matching ASCII characters (see [`gen_ascii_builtin_cond`]),
+/// which only looks at the next byte of the input
+fn gen_ascii_builtin_matcher(name: &str, cond: TokenStream, captured: bool) -> TokenStream {
+    let name_ident = format_ident!("{}", name);
+
+    // Happens when the rule is only used in silent patterns
+    let (matched, data) = if captured {
+        (
+            quote! { nb },
+            quote! { super::matched::#name_ident { matched: nb as char, span: super::Span { start: offset, end: offset + 1 } } },
+        )
+    } else {
+        (quote! { _ }, quote! { () })
+    };
+
+    quote! {
+        match input.as_bytes().first().copied().filter(|nb| #cond) {
+            Some(#matched) => Ok((#data, 1, Option::<super::PegError>::None)),
+            None => Err(super::PegErrorContent::FailedToMatchBuiltinRule(#name, input.chars().next()).at(source, offset, rule_name))
+        }
+    }
+}
+
+/// Generate the matcher of a [zero-width builtin rule](`crate::grammar::utils::ZERO_WIDTH_BUILTIN_RULES`),
+/// which checks the matching position using the whole input and captures nothing
+pub fn gen_zero_width_builtin_matcher(name: &str) -> TokenStream {
+    let cond = match name {
+        "B_LINE_START" => quote! { offset == 0 || source.as_bytes()[offset - 1] == b'\n' },
+        "B_WORD_BOUNDARY" => quote! {{
+            let is_word_char = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+            is_word_char(source[..offset].chars().next_back()) != is_word_char(input.chars().next())
+        }},
+
+        _ => unreachable!(),
+    };
+
+    quote! {
+        if #cond {
+            Ok(((), 0, Option::<super::PegError>::None))
+        } else {
+            Err(super::PegErrorContent::FailedToMatchBuiltinRule(#name, input.chars().next()).at(source, offset, rule_name))
+        }
+    }
+}
+
+/// Generate the matcher of an [indentation external rule](`crate::grammar::utils::INDENTATION_EXTERNAL_RULES`),
+/// which consumes the marker inserted at its place by the generated `preprocess_indentation` function and captures nothing
+pub fn gen_indentation_marker_matcher(state: &InternalState, name: &str) -> TokenStream {
+    if !state.indentation {
+        let message = format!(
+            "The '{}' external rule requires the 'indentation' option",
+            name
+        );
+        return quote! { compile_error!(#message) };
+    }
+
+    let marker = match name {
+        "E_INDENT" => quote! { super::INDENT_MARKER },
+        "E_DEDENT" => quote! { super::DEDENT_MARKER },
+        "E_NEWLINE" => quote! { super::NEWLINE_MARKER },
+
+        _ => unreachable!(),
+    };
+
+    quote! {
+        if input.starts_with(#marker) {
+            Ok(((), #marker.len_utf8(), Option::<super::PegError>::None))
+        } else {
+            Err(super::PegErrorContent::FailedToMatchExternalRule(#name).at(source, offset, rule_name))
+        }
+    }
+}
+
+/// Generate a condition checking if the next character belongs to one of the provided Unicode general categories
+///
+/// The generated code relies on the `unicode-general-category` crate, which must be a dependency of the crate using the parser
+fn gen_general_category_cond(categories: &[&str]) -> TokenStream {
+    let categories = categories
+        .iter()
+        .map(|category| format_ident!("{}", category));
+
+    quote! {
+        matches!(
+            unicode_general_category::get_general_category(*nc),
+            #(unicode_general_category::GeneralCategory::#categories)|*
+        )
+    }
+}
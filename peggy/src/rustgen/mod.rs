@@ -1,15 +1,27 @@
+mod dump;
+mod errors;
 mod matchers;
-mod non_capturing_pat;
-mod recursive_rules;
+mod model;
 mod rules_lifetime_reqs;
+mod sample;
+mod standalone;
 mod types;
 
+pub use errors::GenError;
+pub use model::TypeShape;
+pub use standalone::{gen_rust_crate, CrateOptions};
+
+use crate::grammar::graph::GrammarGraph;
+use crate::grammar::utils::{
+    is_builtin_rule_name, is_external_rule_name, is_valid_builtin_rule_name,
+};
 use crate::grammar::*;
-use quote::__private::{Ident, TokenStream};
+use model::GenModel;
+use proc_macro2::{Ident, TokenStream, TokenTree};
 use quote::{format_ident, quote};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
-pub static RUST_RESERVED_KEYWORDS: &[&str] = &[
+pub(crate) static RUST_RESERVED_KEYWORDS: &[&str] = &[
     "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for",
     "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
     "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where",
@@ -17,16 +29,291 @@ pub static RUST_RESERVED_KEYWORDS: &[&str] = &[
     "override", "priv", "typeof", "unsized", "virtual", "yield", "try", "union", "static", "dyn",
 ];
 
-pub struct InternalState<'a> {
-    recursive_paths: HashMap<&'a str, HashSet<&'a str>>,
+/// Identifiers of the Rust prelude and of the `matched` module the generated code uses where the rules' types and
+/// matchers are declared, which rules can't be generated as
+///
+/// The matchers' variables (e.g. `input`) and the primitive types (e.g. `str`) aren't reserved, as the matchers are
+/// called through their module and the primitive types referenced through their full path when a rule shadows them
+/// (see [`InternalState::primitive`]).
+pub(crate) static RESERVED_GENERATED_IDENTS: &[&str] =
+    &["MatchedRule", "Option", "Vec", "Ok", "Err", "Some", "None"];
+
+/// Primitive types used by the generated types, which rules' types may shadow
+static GENERATED_PRIMITIVES: &[&str] = &["bool", "char", "str", "usize"];
+
+pub(crate) struct InternalState<'a> {
+    /// Type of each constant string of the grammar
+    cst_string_names: HashMap<&'a str, TokenStream>,
+    /// Types of the constant strings whose data is captured, which are generated in the `strings` module
     cst_string_types: HashMap<&'a str, TokenStream>,
-    cst_string_counters: HashMap<&'a str, usize>,
     used_builtin_rules: HashSet<&'a str>,
+    builtin_rule_types: HashSet<&'a str>,
     rule_types: HashMap<&'a str, Option<TokenStream>>,
-    non_capturing_rules: HashMap<&'a str, PatternMode>,
+    graph: GrammarGraph<'a>,
     rules_with_lifetime: HashSet<&'a str>,
-    highest_union_used: usize,
+    used_unions: BTreeSet<usize>,
     debugger: Option<Ident>,
+    cargo_features: bool,
+    grapheme_boundaries: bool,
+    collect_trivia: bool,
+    recursion_wrapper: RecursionWrapper,
+    checked_slicing: bool,
+    tree_printer: bool,
+    indentation: bool,
+    /// Character encoding each token kind, when matching tokens
+    token_markers: Option<HashMap<&'a str, char>>,
+    /// Constant strings which must not be followed by a word character
+    keywords: HashSet<&'a str>,
+    /// Rules' minimum lengths, when repetitions are preallocated
+    preallocation_min_lengths: Option<HashMap<&'a str, usize>>,
+    /// Depth of the smallest instance of each rule's data, when generating sample constructors
+    sample_depths: Option<HashMap<&'a str, Option<usize>>>,
+    /// Identifiers used for rules instead of their name
+    rename: HashMap<String, String>,
+    /// Whether cuts commit to the union member being generated (see [`RulePatternValue::Cut`])
+    cut_scope: bool,
+    /// Primitive types which rules are generated as (see [`InternalState::primitive`])
+    shadowed_primitives: HashSet<&'static str>,
+}
+
+impl<'a> InternalState<'a> {
+    /// Get the identifier of a rule in the generated code (see [`RustGenOptions::rename`])
+    pub(crate) fn rule_ident(&self, name: &str) -> Ident {
+        make_safe_ident(self.rename.get(name).map_or(name, String::as_str))
+    }
+
+    /// Get a primitive type, referenced through its full path if a rule's type shadows it
+    pub(crate) fn primitive(&self, name: &'static str) -> TokenStream {
+        let ident = format_ident!("{}", name);
+
+        if self.shadowed_primitives.contains(name) {
+            quote! { ::core::primitive::#ident }
+        } else {
+            quote! { #ident }
+        }
+    }
+}
+
+/// Builtin rules relying on the `unicode-general-category` crate in generated parsers
+pub static GENERAL_CATEGORY_BUILTIN_RULES: &[&str] = &[
+    "B_LETTER",
+    "B_MARK",
+    "B_DECIMAL_NUMBER",
+    "B_PUNCTUATION",
+    "B_CONNECTOR_PUNCTUATION",
+    "B_SYMBOL",
+];
+
+/// Generated parser, split in its root items and its submodules' content
+struct GeneratedModules {
+    root: TokenStream,
+    matched: TokenStream,
+    rules: TokenStream,
+    strings: TokenStream,
+    unions: TokenStream,
+    uses_general_category: bool,
+}
+
+/// Options for the [Rust generator](`gen_rust_token_stream_with`)
+#[derive(Debug, Clone)]
+pub struct RustGenOptions {
+    /// Name of a module containing `entering_rule` and `leaving_rule` functions, called each time a rule is matched.
+    /// Must be a sibling of the module the parser is generated in.
+    pub debugger: Option<String>,
+
+    /// Don't require the `main` rule to consume the whole input.
+    /// The generated `exec` function then returns the number of consumed bytes alongside the matched data.
+    pub partial_match: bool,
+
+    /// Only match constant strings if they end on a grapheme cluster boundary (e.g. `"e"` won't match the beginning of `"e\u{301}"`).
+    /// This makes matching constant strings slower, and the generated code relies on the `unicode-segmentation` crate,
+    /// which must be a dependency of the crate using the parser.
+    pub grapheme_boundaries: bool,
+
+    /// Collect the input matched by silent patterns (e.g. whitespaces and comments) as [trivia](https://en.wikipedia.org/wiki/Trivia),
+    /// which are returned by the generated `exec` function alongside the matched data.
+    /// This is useful to build lossless tools like formatters, but makes the parser a bit slower.
+    pub collect_trivia: bool,
+
+    /// Simplify the type of optional and repeated constant strings, which are zero-sized markers:
+    /// `Option<strings::Str_...>` becomes a `bool` and `Vec<strings::Str_...>` becomes a `usize` (the number of repetitions).
+    /// Enabled by default.
+    pub simplify_marker_types: bool,
+
+    /// Type used to store the data of recursive rules in matched data
+    pub recursion_wrapper: RecursionWrapper,
+
+    /// Change which patterns are silent without editing the grammar
+    pub silence_override: SilenceOverride,
+
+    /// Check the number of bytes consumed by matchers before slicing the input, so that an invalid length
+    /// (out of bounds or not on a character boundary) produces a `PegErrorContent::InternalConsumedOverflow` error instead of a panic.
+    /// This is meant to diagnose bugs during a grammar's development, and makes the parser a bit slower.
+    pub checked_slicing: bool,
+
+    /// Path of the grammar file, relative to the root of the crate the parser is put in (e.g. `src/grammar.peggy`).
+    /// When provided, a test failing if the parser wasn't generated from the file's current content is generated.
+    /// This is useful when the generated parser is versioned alongside the grammar.
+    pub grammar_path: Option<String>,
+
+    /// Generate a `print_tree` method on matched rules' types (including `SuccessData`), which pretty-prints the matched data
+    /// as an indented tree like the `dump` function, showing the input matched by each rule. This is useful to debug grammars.
+    pub tree_printer: bool,
+
+    /// Derive [`Default`] on the types of matched rules whose data is made only of optional and repeated (`*`) patterns,
+    /// e.g. `modifiers = "pub"? "static"? attribute*`. The default value has an empty span at the beginning of the input.
+    pub derive_default: bool,
+
+    /// Generate an `exec_streaming` function, which calls a callback with each element matched by the main rule's repetition
+    /// instead of collecting them in a `Vec`. This requires the main rule to be a repeated reference to a capturing rule
+    /// (e.g. `main = token*` or `main = token+`), and is useful to tokenize large inputs without keeping all tokens in memory.
+    pub streaming: bool,
+
+    /// Support indentation-based grammars (e.g. Python-like languages) through the `E_INDENT`, `E_DEDENT` and `E_NEWLINE`
+    /// external rules. This generates a `preprocess_indentation` function, which inserts a marker character matched by
+    /// these rules wherever the indentation level changes and at the end of each non-blank line, and checks that
+    /// indentation is consistent (e.g. doesn't mix tabs and spaces between levels). The preprocessed text is then given to `exec`.
+    pub indentation: bool,
+
+    /// Reserve the capacity of the `Vec` storing the data of a repetition (`*` and `+`) once its first element is matched,
+    /// which avoids reallocating it as it grows. The capacity is estimated by dividing the remaining input's length by
+    /// the minimum length of an element, up to [`PREALLOCATION_LIMIT`] elements. This makes parsing large repeated structures
+    /// faster, but may reserve more memory than required.
+    pub preallocate_repetitions: bool,
+
+    /// Match a sequence of tokens produced by a separate lexer instead of a string. The constant strings of the grammar
+    /// are the kinds of the tokens, which are compared to the `kind` of the provided tokens (see the generated `LexedToken` trait).
+    /// This generates an `encode_tokens` function, which encodes each token as a character matched by the constant string
+    /// of its kind. The encoded text is then given to `exec`, and the offsets in the matched data can be converted to token indexes.
+    ///
+    /// As the matched data is built from the encoded text, the text it captures (e.g. with `B_ANY` or atomic patterns) is made
+    /// of the characters encoding the tokens, not of the tokens' content. Tokens whose kind isn't a constant string of the grammar
+    /// are only matched by `B_ANY`, and the other builtin rules can't be used ([`GenError::BuiltinRuleInTokenInput`]).
+    pub token_input: bool,
+
+    /// Generate a `sample` constructor on matched rules' types, which builds a minimal instance of the rule's data
+    /// (empty optional and repeated patterns, first simplest member of unions, placeholder characters for builtin rules
+    /// and empty spans). This is meant to build fixtures in the tests of code using the matched data. Rules whose data
+    /// has no finite instance (e.g. `list = "(" list ")"`) don't get a constructor.
+    pub sample_constructors: bool,
+
+    /// Identifiers used for rules in the generated code (their type, matcher function and enum variants) instead of their
+    /// name in the grammar, e.g. `expr_stmt` to `ExprStatement`. The grammar's names are still used in error messages
+    /// and debugging output. Unknown rules are ignored. Renaming a rule to an invalid identifier makes the generation fail
+    /// with [`GenError::InvalidRename`], and renames making two rules use the same identifier with [`GenError::DuplicateIdent`].
+    pub rename: HashMap<String, String>,
+}
+
+/// Maximum number of elements reserved at once for a repetition's data (see [`RustGenOptions::preallocate_repetitions`])
+pub const PREALLOCATION_LIMIT: usize = 1024;
+
+impl RustGenOptions {
+    /// Create a new set of generation options
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Default for RustGenOptions {
+    fn default() -> Self {
+        Self {
+            debugger: None,
+            partial_match: false,
+            grapheme_boundaries: false,
+            collect_trivia: false,
+            simplify_marker_types: true,
+            recursion_wrapper: RecursionWrapper::default(),
+            silence_override: SilenceOverride::default(),
+            checked_slicing: false,
+            grammar_path: None,
+            tree_printer: false,
+            derive_default: false,
+            streaming: false,
+            indentation: false,
+            preallocate_repetitions: false,
+            token_input: false,
+            sample_constructors: false,
+            rename: HashMap::new(),
+        }
+    }
+}
+
+/// Type used to store the data of recursive rules, to break the cycles in the generated types
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum RecursionWrapper {
+    /// Use [`std::rc::Rc`], which makes cloning the matched data cheap (default)
+    #[default]
+    Rc,
+
+    /// Use [`Box`], which is cheaper to build and makes the matched data owned (and `Send`), but is deeply cloned
+    Box,
+
+    /// Use [`std::sync::Arc`], which makes cloning the matched data cheap and makes it `Send` and `Sync`,
+    /// but is a bit more expensive to build than [`std::rc::Rc`]
+    Arc,
+
+    /// Use a custom type, from its path (e.g. `my_crate::Shared`)
+    ///
+    /// The type must provide a `new` function wrapping the data, dereference to it, and implement the traits derived by
+    /// the generated types (`Debug`, `Clone`, `PartialEq` and `Eq`).
+    Custom(String),
+}
+
+/// Change which patterns are silent (`°`) in the generated parser
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum SilenceOverride {
+    /// Use the grammar as it is written
+    #[default]
+    AsWritten,
+
+    /// Ignore all silent markers, so everything is captured (useful to debug a grammar).
+    /// Rules that only contained silent patterns then get their own types.
+    CaptureEverything,
+
+    /// Additionally treat the provided rules as silent wherever they are referenced (except in atomic patterns).
+    /// Unknown rules are ignored.
+    SilenceRules(Vec<String>),
+}
+
+impl SilenceOverride {
+    /// Apply the override to a syntax tree, or return `None` if it doesn't change anything
+    fn apply<'a>(&self, pst: &PegSyntaxTree<'a>) -> Option<PegSyntaxTree<'a>> {
+        match self {
+            Self::AsWritten => None,
+            Self::CaptureEverything => {
+                Some(pst.map_modes(&|pattern| {
+                    pattern.mode().filter(|mode| *mode != PatternMode::Silent)
+                }))
+            }
+            Self::SilenceRules(names) => Some(pst.map_modes(&|pattern| match (
+                pattern.mode(),
+                pattern.value(),
+            ) {
+                (None, RulePatternValue::Rule(name))
+                    if names.iter().any(|silenced| silenced == name) =>
+                {
+                    Some(PatternMode::Silent)
+                }
+                (mode, _) => mode,
+            })),
+        }
+    }
+}
+
+impl RecursionWrapper {
+    /// Get the path of the wrapper type
+    ///
+    /// Custom paths are checked before generation starts (see [`GenError::InvalidRecursionWrapper`]).
+    fn gen_path(&self) -> TokenStream {
+        match self {
+            Self::Rc => quote! { std::rc::Rc },
+            Self::Box => quote! { std::boxed::Box },
+            Self::Arc => quote! { std::sync::Arc },
+            Self::Custom(path) => path
+                .parse()
+                .unwrap_or_else(|_| panic!("Invalid recursion wrapper path: {}", path)),
+        }
+    }
 }
 
 pub fn gen_rust_str(pst: &PegSyntaxTree, debugger: Option<&str>) -> String {
@@ -34,33 +321,379 @@ pub fn gen_rust_str(pst: &PegSyntaxTree, debugger: Option<&str>) -> String {
 }
 
 pub fn gen_rust_token_stream(pst: &PegSyntaxTree, debugger: Option<&str>) -> TokenStream {
+    gen_rust_token_stream_with(
+        pst,
+        &RustGenOptions {
+            debugger: debugger.map(str::to_string),
+            ..RustGenOptions::new()
+        },
+    )
+}
+
+/// Generate a parser's code, using the provided [options](`RustGenOptions`)
+pub fn gen_rust_str_with(pst: &PegSyntaxTree, options: &RustGenOptions) -> String {
+    gen_rust_token_stream_with(pst, options).to_string()
+}
+
+/// Compile a grammar's source to a parser's code, using the provided [options](`RustGenOptions`)
+///
+/// This bundles [`parse_peg`] and [`gen_rust_str_with`], which is useful in build scripts.
+/// Errors can be displayed with [`pretty_format_parser_err`].
+///
+/// The generated code is not formatted, it can be passed through `rustfmt` if it is meant to be read.
+pub fn compile_to_rust(src: &str, options: &RustGenOptions) -> Result<String, ParserError> {
+    let pst = parse_peg(src)?;
+    Ok(gen_rust_str_with(&pst, options))
+}
+
+/// Generate a parser's token stream, using the provided [options](`RustGenOptions`)
+///
+/// If the tree can't be turned into a parser (see [`try_gen_rust_token_stream_with`]), the generated code
+/// is a compilation error describing why.
+pub fn gen_rust_token_stream_with(pst: &PegSyntaxTree, options: &RustGenOptions) -> TokenStream {
+    try_gen_rust_token_stream_with(pst, options).unwrap_or_else(|err| {
+        let message = err.to_string();
+        quote! { compile_error!(#message); }
+    })
+}
+
+/// Generate a parser's code, using the provided [options](`RustGenOptions`), or fail if the tree can't be turned into a parser
+///
+/// See [`try_gen_rust_token_stream_with`].
+pub fn try_gen_rust_str_with(
+    pst: &PegSyntaxTree,
+    options: &RustGenOptions,
+) -> Result<String, GenError> {
+    try_gen_rust_token_stream_with(pst, options).map(|stream| stream.to_string())
+}
+
+/// Generate a parser's token stream, using the provided [options](`RustGenOptions`), or fail if the tree can't be turned into a parser
+///
+/// This never fails for trees returned by [`parse_peg`] unless the options are invalid (e.g. [renaming](`RustGenOptions::rename`)
+/// a rule to an invalid identifier), but trees which skipped validation (e.g. from [`parse_peg_nocheck`]) may reference
+/// unknown rules or contain empty suites and unions.
+pub fn try_gen_rust_token_stream_with(
+    pst: &PegSyntaxTree,
+    options: &RustGenOptions,
+) -> Result<TokenStream, GenError> {
+    let GeneratedModules {
+        root,
+        matched,
+        rules,
+        strings,
+        unions,
+        uses_general_category: _,
+    } = gen_rust_modules(pst, options, false)?;
+
+    let no_linting = no_linting();
+
+    Ok(quote! {
+        #root
+
+        #no_linting
+        pub mod matched {
+            #matched
+        }
+
+        #no_linting
+        pub mod rules {
+            #rules
+        }
+
+        #no_linting
+        pub mod strings {
+            #strings
+        }
+
+        #no_linting
+        pub mod unions {
+            #unions
+        }
+    })
+}
+
+/// Get the rule repeated by the main rule (e.g. `token` for `main = token*`) if it can be streamed,
+/// along with the repetition's model
+fn streamed_rule<'a>(
+    graph: &GrammarGraph,
+    pst: &PegSyntaxTree<'a>,
+) -> Option<(&'a str, PatternRepetition)> {
+    let pattern = pst.main_rule().pattern();
+
+    let repetition = match pattern.repetition() {
+        Some(rep @ (PatternRepetition::Any | PatternRepetition::OneOrMore)) => rep,
+        _ => return None,
+    };
+
+    if pattern.mode().is_some() {
+        return None;
+    }
+
+    match pattern.value() {
+        RulePatternValue::Rule(name)
+            if !is_builtin_rule_name(name)
+                && !is_external_rule_name(name)
+                && graph.non_capturing_mode(name).is_none() =>
+        {
+            Some((name, repetition))
+        }
+        _ => None,
+    }
+}
+
+/// Generate the `exec_streaming` function, which matches the main rule's repetition of the provided rule
+fn gen_exec_streaming(
+    state: &InternalState,
+    name: &str,
+    repetition: PatternRepetition,
+    options: &RustGenOptions,
+) -> TokenStream {
+    let ident = state.rule_ident(name);
+
+    let (lifetime_req, lifetime_name, error_lifetime) = if state.rules_with_lifetime.is_empty() {
+        (quote! {}, quote! {}, quote! { '_ })
+    } else {
+        (quote! { <'a> }, quote! { 'a }, quote! { 'a })
+    };
+
+    let token_type = if state.rules_with_lifetime.contains(name) {
+        quote! { matched::#ident<'a> }
+    } else {
+        quote! { matched::#ident }
+    };
+
+    let (init_trivia, trivia_arg, trivia_type, trivia_ret) = if options.collect_trivia {
+        (
+            Some(quote! { let mut trivia = vec![]; }),
+            Some(quote! { , &mut trivia }),
+            Some(quote! { , Vec<Trivia> }),
+            Some(quote! { , trivia }),
+        )
+    } else {
+        (None, None, None, None)
+    };
+
+    let ret_type = if options.collect_trivia {
+        quote! { (usize #trivia_type) }
+    } else {
+        quote! { usize }
+    };
+
+    // Same as the repetition's matcher: a '+' repetition fails if no element matched
+    let require_one = if repetition == PatternRepetition::OneOrMore {
+        Some(quote! {
+            if !matched_one {
+                return Err(end_err);
+            }
+        })
+    } else {
+        None
+    };
+
+    let (init_matched_one, set_matched_one) = if repetition == PatternRepetition::OneOrMore {
+        (
+            Some(quote! { let mut matched_one = false; }),
+            Some(quote! { matched_one = true; }),
+        )
+    } else {
+        (None, None)
+    };
+
+    let ret_val = if options.collect_trivia {
+        quote! { (consumed #trivia_ret) }
+    } else {
+        quote! { consumed }
+    };
+
+    let end = if options.partial_match {
+        quote! { Ok(#ret_val) }
+    } else {
+        quote! {
+            if input.len() > consumed {
+                Err(end_err)
+            } else {
+                Ok(#ret_val)
+            }
+        }
+    };
+
+    let doc = format!(
+        "Match the input like [`exec`], but call `on_token` with each `{}` matched by the main rule instead of collecting them\n\nReturns the number of consumed bytes.",
+        name
+    );
+
+    quote! {
+        #[doc = #doc]
+        pub fn exec_streaming #lifetime_req (input: & #lifetime_name str, mut on_token: impl FnMut(#token_type)) -> Result<#ret_type, PegError<#error_lifetime>> {
+            #init_trivia
+            #init_matched_one
+            let mut consumed = 0;
+
+            let end_err = loop {
+                match rules::#ident(input, &input[consumed..], consumed #trivia_arg) {
+                    Ok((token, token_consumed, _)) => {
+                        on_token(token);
+                        consumed += token_consumed;
+                        #set_matched_one
+                    }
+
+                    Err(err) => break err,
+                }
+            };
+
+            #require_one
+            #end
+        }
+    }
+}
+
+/// Attributes disabling lints in generated modules
+fn no_linting() -> TokenStream {
+    quote! {
+        #[allow(non_camel_case_types)]
+        #[allow(non_snake_case)]
+    }
+}
+
+/// Get the shape of the data each rule captures in the parser generated with the provided [options](`RustGenOptions`),
+/// which is `None` for rules which don't capture anything
+///
+/// Shapes are computed like the generated types, so tools building on top of generated parsers don't need to read
+/// their code to know the structure of each rule's data. Fails like [`try_gen_rust_token_stream_with`].
+pub fn rule_type_shapes<'a>(
+    pst: &PegSyntaxTree<'a>,
+    options: &RustGenOptions,
+) -> Result<HashMap<&'a str, Option<TypeShape>>, GenError> {
+    let prepared = prepare_tree(pst, options)?;
+    let prepared_pst = prepared.as_ref().unwrap_or(pst);
+
+    let graph = GrammarGraph::new(prepared_pst);
+    let model = GenModel::lower(prepared_pst, &graph, options.simplify_marker_types);
+
+    Ok(pst
+        .rules()
+        .keys()
+        .map(|name| {
+            let shape = if model.is_capturing(name) {
+                model.rule(name).shape.to_type_shape()
+            } else {
+                None
+            };
+
+            (*name, shape)
+        })
+        .collect())
+}
+
+/// Apply the [silence override](`RustGenOptions::silence_override`) to a tree, ensure it can be turned into a parser,
+/// and replace its single-member unions by groups
+///
+/// Returns `None` if the tree is unchanged.
+fn prepare_tree<'a>(
+    pst: &PegSyntaxTree<'a>,
+    options: &RustGenOptions,
+) -> Result<Option<PegSyntaxTree<'a>>, GenError> {
+    let overridden = options.silence_override.apply(pst);
+
+    check_tree(overridden.as_ref().unwrap_or(pst), options)?;
+
+    // Unions with a single member would get a one-variant union type, which is useless
+    let simplified = overridden.as_ref().unwrap_or(pst).simplify_unions();
+
+    Ok(simplified.or(overridden))
+}
+
+/// Generate a parser's content
+///
+/// With `cargo_features`, the generated code is meant to be put in a [standalone crate](`gen_rust_crate`)
+/// and contains items gated behind its Cargo features.
+fn gen_rust_modules(
+    pst: &PegSyntaxTree,
+    options: &RustGenOptions,
+    cargo_features: bool,
+) -> Result<GeneratedModules, GenError> {
+    let prepared = prepare_tree(pst, options)?;
+    let pst = prepared.as_ref().unwrap_or(pst);
+
+    let graph = GrammarGraph::new(pst);
+    let model = GenModel::lower(pst, &graph, options.simplify_marker_types);
+
+    let streamed = if options.streaming {
+        Some(streamed_rule(&graph, pst).ok_or(GenError::InvalidStreamingMainRule)?)
+    } else {
+        None
+    };
+
     let mut state = InternalState {
-        recursive_paths: recursive_rules::find(pst),
+        cst_string_names: types::gen_cst_string_names(pst),
         cst_string_types: HashMap::new(),
-        cst_string_counters: HashMap::new(),
         used_builtin_rules: HashSet::new(),
+        builtin_rule_types: HashSet::new(),
         rule_types: HashMap::new(),
-        non_capturing_rules: non_capturing_pat::list_rules(pst),
-        rules_with_lifetime: rules_lifetime_reqs::build_lifetime_reqs(pst),
-        highest_union_used: 0,
-        debugger: debugger.map(|mod_name| format_ident!("{}", mod_name)),
+        rules_with_lifetime: rules_lifetime_reqs::build_lifetime_reqs(pst, &graph),
+        graph,
+        used_unions: BTreeSet::new(),
+        debugger: options
+            .debugger
+            .as_ref()
+            .map(|mod_name| format_ident!("{}", mod_name)),
+        cargo_features,
+        grapheme_boundaries: options.grapheme_boundaries,
+        collect_trivia: options.collect_trivia,
+        recursion_wrapper: options.recursion_wrapper.clone(),
+        checked_slicing: options.checked_slicing,
+        tree_printer: options.tree_printer,
+        indentation: options.indentation,
+        token_markers: if options.token_input {
+            Some(gen_token_markers(pst))
+        } else {
+            None
+        },
+        keywords: pst.keywords().iter().copied().collect(),
+        preallocation_min_lengths: if options.preallocate_repetitions {
+            Some(rules_min_length(pst.rules()))
+        } else {
+            None
+        },
+        sample_depths: None,
+        rename: options.rename.clone(),
+        cut_scope: false,
+        shadowed_primitives: pst
+            .rules()
+            .keys()
+            .map(|name| options.rename.get(*name).map_or(*name, String::as_str))
+            .filter_map(|ident| GENERATED_PRIMITIVES.iter().copied().find(|p| *p == ident))
+            .collect(),
     };
 
-    for name in pst.rules().keys() {
-        if !state.recursive_paths.contains_key(name) {
-            state.recursive_paths.insert(name, HashSet::new());
-        }
+    if options.sample_constructors {
+        state.sample_depths = Some(sample::build_sample_depths(&state, &model, pst));
     }
 
-    let mut rule_types: Vec<_> = pst
-        .rules()
+    let serde_derive = if cargo_features {
+        quote! { #[cfg_attr(feature = "serde", derive(serde::Serialize))] }
+    } else {
+        quote! {}
+    };
+
+    // Rules are visited in declaration order to make the generation deterministic
+    let ordered_rules = pst.ordered_rules();
+
+    let mut rule_types: Vec<_> = ordered_rules
         .iter()
-        .filter_map(|(name, content)| {
-            let ident = make_safe_ident(name);
+        .filter_map(|content| {
+            let name = content.name();
+            let ident = state.rule_ident(name);
+
+            let node = model.rule(name);
 
-            let rule_type = types::gen_rule_type(&mut state, name, content);
+            let rule_type = if model.is_capturing(name) {
+                types::gen_shape_type(&mut state, &node.shape)
+            } else {
+                None
+            };
 
-            state.rule_types.insert(*name, rule_type.clone());
+            state.rule_types.insert(name, rule_type.clone());
 
             let rule_type = rule_type?;
 
@@ -70,11 +703,36 @@ pub fn gen_rust_token_stream(pst: &PegSyntaxTree, debugger: Option<&str>) -> Tok
                 quote! {}
             };
 
+            let default_derive = if options.derive_default && node.is_defaultable() {
+                Some(quote! { #[derive(Default)] })
+            } else {
+                None
+            };
+
+            let sample_doc = match &state.sample_depths {
+                Some(depths) if depths[name].is_none() => Some(quote! {
+                    /// This rule's data has no finite instance, as each of its alternatives eventually contains the rule itself,
+                    /// so it has no `sample` constructor.
+                }),
+                _ => None,
+            };
+
             Some(quote! {
+                #sample_doc
                 #[derive(Debug, Clone, PartialEq, Eq)]
+                #default_derive
+                #serde_derive
                 pub struct #ident #lifetime_req {
                     pub matched: #rule_type,
-                    pub at: usize
+                    /// Bytes range the rule matched in the input (see [`super::char_index`] to get a characters count)
+                    pub span: super::Span
+                }
+
+                impl #lifetime_req #ident #lifetime_req {
+                    /// Get the bytes range the rule matched in the input
+                    pub fn span(&self) -> super::Span {
+                        self.span
+                    }
                 }
             })
         })
@@ -85,9 +743,9 @@ pub fn gen_rust_token_stream(pst: &PegSyntaxTree, debugger: Option<&str>) -> Tok
     let mut rule_types_enum_variants: Vec<_> = pst
         .rules()
         .iter()
-        .filter(|(name, _)| state.non_capturing_rules.get(*name) != Some(&PatternMode::Silent))
+        .filter(|(name, _)| model.is_capturing(name))
         .map(|(name, _)| {
-            let variant = make_safe_ident(name);
+            let variant = state.rule_ident(name);
 
             let lifetime_req = if state.rules_with_lifetime.contains(name) {
                 quote! { <'a> }
@@ -101,13 +759,67 @@ pub fn gen_rust_token_stream(pst: &PegSyntaxTree, debugger: Option<&str>) -> Tok
 
     rule_types_enum_variants.sort_by_key(|t| t.to_string());
 
+    let mut rule_types_enum_spans: Vec<_> = pst
+        .rules()
+        .keys()
+        .filter(|name| model.is_capturing(name))
+        .map(|name| {
+            let variant = state.rule_ident(name);
+            quote! { Self::#variant(data) => data.span }
+        })
+        .collect();
+
+    rule_types_enum_spans.sort_by_key(|t| t.to_string());
+
+    let mut rule_types_enum_accessors: Vec<_> = pst
+        .rules()
+        .keys()
+        .filter(|name| model.is_capturing(name))
+        .map(|name| {
+            let variant = state.rule_ident(name);
+
+            // Built from the identifier's text, as raw identifiers (e.g. `r#type`) can't be prefixed
+            let accessor = format_ident!(
+                "as_{}",
+                options.rename.get(*name).map_or(*name, String::as_str)
+            );
+
+            let lifetime_req = if state.rules_with_lifetime.contains(name) {
+                quote! { <'a> }
+            } else {
+                quote! {}
+            };
+
+            let doc = format!(
+                "Get the data of the `{}` rule, if it is the one which matched",
+                name
+            );
+
+            quote! {
+                #[doc = #doc]
+                pub fn #accessor(&self) -> Option<&super::matched::#variant #lifetime_req> {
+                    match self {
+                        Self::#variant(data) => Some(data),
+                        #[allow(unreachable_patterns)]
+                        _ => None
+                    }
+                }
+            }
+        })
+        .collect();
+
+    rule_types_enum_accessors.sort_by_key(|t| t.to_string());
+
     let mut cst_string_types_expanded: Vec<_> = state
         .cst_string_types
         .iter()
         .map(|(string, typename)| {
+            let doc = gen_cst_string_doc(string);
+
             quote! {
-                #[doc = #string]
+                #[doc = #doc]
                 #[derive(Debug, Clone, PartialEq, Eq)]
+                #serde_derive
                 // Original string: #string
                 pub struct #typename;
             }
@@ -116,24 +828,38 @@ pub fn gen_rust_token_stream(pst: &PegSyntaxTree, debugger: Option<&str>) -> Tok
 
     cst_string_types_expanded.sort_by_key(|t| t.to_string());
 
-    let mut rules: Vec<_> = pst
-        .rules()
+    let mut rules: Vec<_> = ordered_rules
         .iter()
-        .map(|(name, content)| matchers::gen_rule_matcher(&mut state, name, content))
+        .map(|content| {
+            let name = content.name();
+            matchers::gen_rule_matcher(&mut state, name, model.rule(name), model.is_capturing(name))
+        })
         .collect();
 
     rules.sort_by_key(|t| t.to_string());
 
+    let char_type = state.primitive("char");
+
+    // Builtin rules only used in silent patterns don't need a type
     let mut builtin_rules: Vec<_> = state
-        .used_builtin_rules
+        .builtin_rule_types
         .iter()
         .map(|name| {
             let ident = format_ident!("{}", name);
             quote! {
                 #[derive(Debug, Clone, PartialEq, Eq)]
+                #serde_derive
                 pub struct #ident {
-                    pub matched: char,
-                    pub at: usize
+                    pub matched: #char_type,
+                    /// Bytes range of the matched character in the input (see [`super::char_index`] to get a characters count)
+                    pub span: super::Span
+                }
+
+                impl #ident {
+                    /// Get the bytes range of the matched character in the input
+                    pub fn span(&self) -> super::Span {
+                        self.span
+                    }
                 }
             }
         })
@@ -141,7 +867,7 @@ pub fn gen_rust_token_stream(pst: &PegSyntaxTree, debugger: Option<&str>) -> Tok
 
     builtin_rules.sort_by_key(|t| t.to_string());
 
-    let unions = (2..=state.highest_union_used).map(|i| {
+    let unions = state.used_unions.iter().map(|&i| {
         let variants: Vec<_> = (0..i)
             .map(|i| format_ident!("{}", get_enum_variant(i)))
             .collect();
@@ -152,19 +878,26 @@ pub fn gen_rust_token_stream(pst: &PegSyntaxTree, debugger: Option<&str>) -> Tok
 
         let ident = format_ident!("Sw{}", i);
 
+        let doc = format!("Data of a union of {} members, whose variant indicates which member matched\n\nVariants of non-capturing members (e.g. silent ones) hold `()`.", i);
+
         quote! {
+            #[doc = #doc]
             #[derive(Debug, Clone, PartialEq, Eq)]
+            #serde_derive
             pub enum #ident<#(#variants),*> {
                 #(#variants (#variants),)*
             }
 
             impl<#(#variants,)*> #ident<#(#variants,)*> {
+                // Unions have one mapper per member
+                #[allow(clippy::too_many_arguments)]
                 pub fn variants<Mapped>(self, #(#mappers: impl FnOnce(#variants) -> Mapped),*) -> Mapped {
                     match self {
                         #(Self::#variants(v) => #mappers(v)),*
                     }
                 }
 
+                #[allow(clippy::too_many_arguments)]
                 pub fn variants_ref<Mapped>(&self, #(#mappers: impl FnOnce(&#variants) -> Mapped),*) -> Mapped {
                     match &self {
                         #(Self::#variants(v) => #mappers(v)),*
@@ -174,13 +907,16 @@ pub fn gen_rust_token_stream(pst: &PegSyntaxTree, debugger: Option<&str>) -> Tok
         }
     });
 
-    let no_linting = quote! {
-        #[allow(clippy::all)]
-        #[allow(non_camel_case_types)]
-        #[allow(non_snake_case)]
-    };
+    let main_rule = state.rule_ident(GRAMMAR_ENTRYPOINT_RULE);
 
-    let main_rule = format_ident!("{}", GRAMMAR_ENTRYPOINT_RULE);
+    // Silent main rules don't have a type
+    let success_type = if !model.is_capturing(GRAMMAR_ENTRYPOINT_RULE) {
+        quote! { () }
+    } else if state.rules_with_lifetime.contains(GRAMMAR_ENTRYPOINT_RULE) {
+        quote! { matched::#main_rule<'a> }
+    } else {
+        quote! { matched::#main_rule }
+    };
 
     let err_formatter_impl_ts = err_formatter_impl();
 
@@ -190,42 +926,360 @@ pub fn gen_rust_token_stream(pst: &PegSyntaxTree, debugger: Option<&str>) -> Tok
         (quote! { <'a> }, quote! { 'a })
     };
 
-    quote! {
-        pub fn exec #global_lifetime_req (input: & #global_lifetime_name str) -> Result<SuccessData #global_lifetime_req, PegError> {
-            rules::#main_rule(input, input, 0)
-                .and_then(|(typed_matched, consumed, end_err)| {
-                    if input.len() > consumed {
-                        Err(end_err.unwrap_or_else(|| PegErrorContent::ExpectedEndOfInput.at(input, consumed, #GRAMMAR_ENTRYPOINT_RULE)))
-                    } else {
-                        Ok(typed_matched)
-                    }
-                })
-        }
-
-        pub type SuccessData #global_lifetime_req = matched::#main_rule #global_lifetime_req;
+    let uses_general_category = state
+        .used_builtin_rules
+        .iter()
+        .any(|name| GENERAL_CATEGORY_BUILTIN_RULES.contains(name));
 
-        #[derive(Debug, Clone)]
-        pub struct PegError<'a> {
-            pub source: &'a str,
-            pub offset: usize,
-            pub content: PegErrorContent<'a>,
-            pub rule: &'static str,
-        }
+    let error_lifetime = if state.rules_with_lifetime.is_empty() {
+        quote! { '_ }
+    } else {
+        quote! { 'a }
+    };
 
-        impl<'a> PegError<'a> {
-            fn in_rule(mut self, rule: &'static str) -> Self {
-                self.rule = rule;
-                self
-            }
+    let (init_trivia, trivia_arg, trivia_type, trivia_ret) = if options.collect_trivia {
+        (
+            Some(quote! { let mut trivia = vec![]; }),
+            Some(quote! { , &mut trivia }),
+            Some(quote! { , Vec<Trivia> }),
+            Some(quote! { , trivia }),
+        )
+    } else {
+        (None, None, None, None)
+    };
 
-            pub fn deepest(&self) -> &PegError<'a> {
-                match &self.content {
-                    PegErrorContent::ExpectedCstString(_)
-                        | PegErrorContent::FailedToMatchBuiltinRule(_, _)
-                        | PegErrorContent::MatchedInNegativePattern(_)
-                        | PegErrorContent::ExpectedEndOfInput => self,
+    let exec_ret_type = if options.partial_match {
+        quote! { (SuccessData #global_lifetime_req, usize #trivia_type) }
+    } else if options.collect_trivia {
+        quote! { (SuccessData #global_lifetime_req #trivia_type) }
+    } else {
+        quote! { SuccessData #global_lifetime_req }
+    };
 
-                    PegErrorContent::NoMatchInUnion(errors) => {
+    let exec = if options.partial_match {
+        quote! {
+            /// Match the beginning of the input, returning the number of consumed bytes alongside the matched data
+            pub fn exec #global_lifetime_req (input: & #global_lifetime_name str) -> Result<#exec_ret_type, PegError<#error_lifetime>> {
+                #init_trivia
+                rules::#main_rule(input, input, 0 #trivia_arg).map(|(typed_matched, consumed, _)| (typed_matched, consumed #trivia_ret))
+            }
+        }
+    } else if options.collect_trivia {
+        quote! {
+            /// Match the input, returning the collected trivia alongside the matched data
+            pub fn exec #global_lifetime_req (input: & #global_lifetime_name str) -> Result<#exec_ret_type, PegError<#error_lifetime>> {
+                #init_trivia
+                rules::#main_rule(input, input, 0 #trivia_arg)
+                    .and_then(|(typed_matched, consumed, end_err)| {
+                        if input.len() > consumed {
+                            Err(end_err.unwrap_or_else(|| PegErrorContent::ExpectedEndOfInput.at(input, consumed, #GRAMMAR_ENTRYPOINT_RULE)))
+                        } else {
+                            Ok((typed_matched #trivia_ret))
+                        }
+                    })
+            }
+        }
+    } else {
+        quote! {
+            /// Match the whole input, returning the matched data
+            pub fn exec #global_lifetime_req (input: & #global_lifetime_name str) -> Result<#exec_ret_type, PegError<#error_lifetime>> {
+                rules::#main_rule(input, input, 0)
+                    .and_then(|(typed_matched, consumed, end_err)| {
+                        if input.len() > consumed {
+                            Err(end_err.unwrap_or_else(|| PegErrorContent::ExpectedEndOfInput.at(input, consumed, #GRAMMAR_ENTRYPOINT_RULE)))
+                        } else {
+                            Ok(typed_matched)
+                        }
+                    })
+            }
+        }
+    };
+
+    let exec_streaming =
+        streamed.map(|(name, repetition)| gen_exec_streaming(&state, name, repetition, options));
+
+    // Private helpers are only generated if they are used, to avoid dead code
+    let uses_helper =
+        |name: &str| uses_ident(&exec, name) || rules.iter().any(|rule| uses_ident(rule, name));
+
+    let push_trivia = if uses_helper("push_trivia") {
+        Some(quote! {
+            /// Add a trivia to the collected ones, merging it with the previous one if they are contiguous and of the same kind
+            fn push_trivia(trivia: &mut Vec<Trivia>, new: Trivia) {
+                if let Some(last) = trivia.last_mut() {
+                    if last.kind == new.kind && last.span.end == new.span.start {
+                        last.span.end = new.span.end;
+                        return;
+                    }
+                }
+
+                trivia.push(new);
+            }
+        })
+    } else {
+        None
+    };
+
+    let in_rule = if uses_helper("in_rule") {
+        Some(quote! {
+            fn in_rule(mut self, rule: &'static str) -> Self {
+                self.rule = rule;
+                self
+            }
+        })
+    } else {
+        None
+    };
+
+    let error_at = if uses_helper("at") {
+        Some(quote! {
+            impl<'a> PegErrorContent<'a> {
+                fn at(self, source: &'a str, offset: usize, rule: &'static str) -> PegError<'a> {
+                    PegError { source, offset, rule, content: self, context: SourceContext::default() }
+                }
+            }
+        })
+    } else {
+        None
+    };
+
+    let trivia = if options.collect_trivia {
+        Some(quote! {
+            /// Input matched by a silent pattern (e.g. whitespaces or comments)
+            #[derive(Debug, Clone, PartialEq, Eq)]
+            #serde_derive
+            pub struct Trivia {
+                /// Bytes range of the trivia in the input
+                pub span: Span,
+                /// Name of the rule the silent pattern is declared in
+                pub kind: &'static str,
+            }
+
+            #push_trivia
+        })
+    } else {
+        None
+    };
+
+    let dump = dump::gen_dump(&state, pst);
+    let samples = if options.sample_constructors {
+        Some(sample::gen_samples(&state, &model, pst))
+    } else {
+        None
+    };
+
+    let terminals = gen_terminals(pst);
+    let tokenizer = gen_tokenizer(&state, pst, &serde_derive);
+    let indentation = if options.indentation {
+        Some(gen_indentation_preprocessor())
+    } else {
+        None
+    };
+    let token_input = if options.token_input {
+        Some(gen_token_input())
+    } else {
+        None
+    };
+
+    let grammar_hash = grammar_hash(pst.source());
+    let generator_version = env!("CARGO_PKG_VERSION");
+    let grammar_hash_doc = format!(
+        "Hash of the grammar this parser was generated from, by Peggy v{} (see `peggy::verify_generated`)",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    let up_to_date_test = options.grammar_path.as_ref().map(|path| {
+        quote! {
+            #[cfg(test)]
+            #[test]
+            fn grammar_is_up_to_date() {
+                let grammar = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/", #path))
+                    .expect("Failed to read the grammar file");
+
+                // Same hash as `peggy::grammar_hash` (64-bit FNV-1a, ignoring line endings)
+                let mut hash: u64 = 0xcbf29ce484222325;
+
+                for line in grammar.lines() {
+                    for byte in line.bytes().chain(std::iter::once(b'\n')) {
+                        hash ^= u64::from(byte);
+                        hash = hash.wrapping_mul(0x100000001b3);
+                    }
+                }
+
+                assert_eq!(
+                    format!("{:016x}", hash),
+                    GRAMMAR_HASH,
+                    "The parser is outdated, it must be generated again from {}",
+                    #path
+                );
+            }
+        }
+    });
+
+    let root = quote! {
+        #[doc = #grammar_hash_doc]
+        pub const GRAMMAR_HASH: &str = #grammar_hash;
+
+        /// Version of Peggy this parser was generated by
+        pub const GENERATOR_VERSION: &str = #generator_version;
+
+        #up_to_date_test
+
+        #terminals
+
+        #exec
+
+        /// Match the input like [`exec`], the input being a part of a larger source (e.g. an expression embedded in a template)
+        ///
+        /// Errors are reported with the coordinates of the larger source (see [`SourceContext`]).
+        pub fn exec_in_context #global_lifetime_req (input: & #global_lifetime_name str, context: SourceContext) -> Result<#exec_ret_type, PegError<#error_lifetime>> {
+            exec(input).map_err(|err| err.with_context(context))
+        }
+
+        #exec_streaming
+
+        #tokenizer
+
+        #indentation
+        #token_input
+
+        #trivia
+
+        /// Data matched by the main rule, which is `()` if the rule is silent
+        pub type SuccessData #global_lifetime_req = #success_type;
+
+        #dump
+
+        #samples
+
+        /// Get the number of characters preceding a byte offset in the input
+        ///
+        /// All offsets in the parser (matched data's `span`, errors' `offset`, consumed lengths) are byte offsets.
+        /// Characters are only counted if they entirely precede the offset, so an offset in the middle of a character
+        /// doesn't count it, and an offset past the end of the input gives its number of characters.
+        pub fn char_index(input: &str, byte_offset: usize) -> usize {
+            input
+                .char_indices()
+                .take_while(|(i, c)| i + c.len_utf8() <= byte_offset)
+                .count()
+        }
+
+        /// Range of bytes in the input
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+        #serde_derive
+        pub struct Span {
+            /// Start offset (inclusive)
+            pub start: usize,
+            /// End offset (exclusive)
+            pub end: usize,
+        }
+
+        impl Span {
+            /// Get the span's length, in bytes
+            pub fn len(&self) -> usize {
+                self.end - self.start
+            }
+
+            /// Check if the span is empty
+            pub fn is_empty(&self) -> bool {
+                self.start == self.end
+            }
+
+            /// Get the input's slice covered by the span
+            pub fn slice<'a>(&self, input: &'a str) -> &'a str {
+                &input[self.start..self.end]
+            }
+        }
+
+        /// Position of the input in a larger source, when it is only a part of it (see [`exec_in_context`])
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+        pub struct SourceContext {
+            /// Byte offset of the input in the larger source
+            pub base_offset: usize,
+            /// Line of the input's first character in the larger source (starting at 0)
+            pub base_line: usize,
+            /// Column of the input's first character in the larger source, in characters (starting at 0)
+            pub base_col: usize,
+        }
+
+        impl SourceContext {
+            /// Convert a byte offset in the input to a byte offset in the larger source
+            pub fn offset(&self, offset: usize) -> usize {
+                self.base_offset + offset
+            }
+
+            /// Convert a span of the input to a span of the larger source
+            pub fn span(&self, span: Span) -> Span {
+                Span { start: self.offset(span.start), end: self.offset(span.end) }
+            }
+
+            /// Convert a line and a column (in characters) of the input to the ones of the larger source
+            ///
+            /// Only the first line of the input is shifted horizontally, as the other ones start at the beginning of a line.
+            pub fn line_col(&self, line: usize, col: usize) -> (usize, usize) {
+                if line == 0 {
+                    (self.base_line, self.base_col + col)
+                } else {
+                    (self.base_line + line, col)
+                }
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        pub struct PegError<'a> {
+            pub source: &'a str,
+            /// Byte offset of the error in the source (see [`char_index`] to get a characters count)
+            pub offset: usize,
+            pub content: PegErrorContent<'a>,
+            pub rule: &'static str,
+            /// Position of the source in a larger one, which is only set by [`exec_in_context`]
+            pub context: SourceContext,
+        }
+
+        impl<'a> PegError<'a> {
+            #in_rule
+
+            /// Set the position of the source in a larger one, including in the errors this one is made of
+            pub fn with_context(mut self, context: SourceContext) -> Self {
+                self.set_context(context);
+                self
+            }
+
+            fn set_context(&mut self, context: SourceContext) {
+                self.context = context;
+
+                if let PegErrorContent::NoMatchInUnion(errors) = &mut self.content {
+                    for err in errors {
+                        err.set_context(context);
+                    }
+                }
+            }
+
+            /// Get the byte offset of the error in the larger source (which is the source itself without context)
+            pub fn original_offset(&self) -> usize {
+                self.context.offset(self.offset)
+            }
+
+            /// Get the line and column (in characters) of the error in the larger source, both starting at 0
+            pub fn line_col(&self) -> (usize, usize) {
+                let line_start = self.source[..self.offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+                let line = self.source[..line_start].matches('\n').count();
+                let col = char_index(&self.source[line_start..], self.offset - line_start);
+
+                self.context.line_col(line, col)
+            }
+
+            pub fn deepest(&self) -> &PegError<'a> {
+                match &self.content {
+                    PegErrorContent::ExpectedCstString(_)
+                        | PegErrorContent::FailedToMatchBuiltinRule(_, _)
+                        | PegErrorContent::FailedToMatchExternalRule(_)
+                        | PegErrorContent::MatchedInNegativePattern(_)
+                        | PegErrorContent::TerminatorNotFound
+                        | PegErrorContent::InternalConsumedOverflow(_)
+                        | PegErrorContent::ExpectedEndOfInput => self,
+
+                    PegErrorContent::NoMatchInUnion(errors) => {
                         let mut deepest = errors[0].deepest();
 
                         for err in errors.iter().skip(1) {
@@ -246,43 +1300,82 @@ pub fn gen_rust_token_stream(pst: &PegSyntaxTree, debugger: Option<&str>) -> Tok
         pub enum PegErrorContent<'a> {
             ExpectedCstString(&'a str),
             FailedToMatchBuiltinRule(&'static str, Option<char>),
-            NoMatchInUnion(Vec<std::rc::Rc<PegError<'a>>>),
+            FailedToMatchExternalRule(&'static str),
+            NoMatchInUnion(Vec<PegError<'a>>),
             MatchedInNegativePattern(&'a str),
+            TerminatorNotFound,
+            /// A matcher consumed a number of bytes which doesn't fit the input (only with checked slicing)
+            InternalConsumedOverflow(usize),
             ExpectedEndOfInput
         }
 
-        impl<'a> PegErrorContent<'a> {
-            fn at(self, source: &'a str, offset: usize, rule: &'static str) -> PegError<'a> {
-                PegError { source, offset, rule, content: self }
-            }
-        }
+        #error_at
 
         #err_formatter_impl_ts
+    };
 
-        #no_linting
-        pub mod matched {
+    // An enum without variants can't be matched on by reference
+    let rule_types_enum_span_impl = if rule_types_enum_spans.is_empty() {
+        None
+    } else {
+        Some(quote! {
+            impl #global_lifetime_req MatchedRule #global_lifetime_req {
+                /// Get the bytes range the rule matched in the input
+                pub fn span(&self) -> super::Span {
+                    match self {
+                        #(#rule_types_enum_spans),*
+                    }
+                }
+
+                #(#rule_types_enum_accessors)*
+            }
+        })
+    };
+
+    Ok(GeneratedModules {
+        root,
+        matched: quote! {
             #[derive(Debug, Clone, PartialEq, Eq)]
+            #serde_derive
             pub enum MatchedRule #global_lifetime_req {
                 #(#rule_types_enum_variants),*
             }
 
+            #rule_types_enum_span_impl
+
             #(#rule_types)*
             #(#builtin_rules)*
-        }
+        },
+        rules: gen_rules_module(&rules, options.collect_trivia),
+        strings: quote! { #(#cst_string_types_expanded)* },
+        unions: quote! { #(#unions)* },
+        uses_general_category,
+    })
+}
 
-        #no_linting
-        pub mod rules {
-            #(#rules)*
-        }
+/// Generate the content of the module containing the rules' matchers, which are part of the generated parser's public API
+fn gen_rules_module(rules: &[TokenStream], collect_trivia: bool) -> TokenStream {
+    let trivia_doc = if collect_trivia {
+        Some(quote! {
+            #![doc = ""]
+            #![doc = " The trivia matched by silent patterns are pushed to the last argument."]
+        })
+    } else {
+        None
+    };
 
-        #no_linting
-        pub mod strings {
-            #(#cst_string_types_expanded)*
-        }
+    quote! {
+        //! Matchers for each rule of the grammar, which can be used to match a single rule (e.g. to compose parsers)
+        //!
+        //! Each matcher takes the whole input (`source`), the remaining input to match (`input`, which must be `&source[offset..]`)
+        //! and the byte `offset` of `input` in `source`, and matches the rule at the beginning of `input`.
+        //!
+        //! On success, it returns the rule's matched data (`()` for silent rules), the number of consumed bytes,
+        //! and the error which stopped the last repetition (if any), which explains why the rule didn't consume more input.
+        //! Unlike [`super::exec`], matchers don't require the whole input to be consumed.
+        #trivia_doc
 
-        pub mod unions {
-            #(#unions)*
-        }
+        #(#rules)*
     }
 }
 
@@ -290,45 +1383,33 @@ fn err_formatter_impl() -> TokenStream {
     quote! {
         impl<'a> std::fmt::Display for PegError<'a> {
             fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-                // Get all lines above the one the error is located on
-                let head_lines_count = self.source[..self.offset].lines().count();
+                // Get the byte offset the error's line starts at
+                let line_start = self.source[..self.offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
 
-                // Deduce from it from the error line's number
-                let line_index = if head_lines_count > 0 {
-                    head_lines_count - 1
-                } else {
-                    0
-                };
-
-                // Get the length of the lines above the one the error is located on
-                let head_lines_len = self
-                    .source
-                    .lines()
-                    .take(line_index)
-                    .fold(0, |acc, value| acc + value.len() + 1 /* TODO: Proper line jump counting */);
-
-                let line = if line_index < self.source.lines().count() {
-                    self.source.lines().nth(line_index).unwrap()
-                } else {
-                    ""
-                };
+                // Deduce from it the error line's number
+                let line_index = self.source[..line_start].matches('\n').count();
+
+                // Get the error's line, without its line jump
+                let line = self.source[line_start..].lines().next().unwrap_or("");
 
-                // Deduce from it the column of the error
-                let column = self.offset - head_lines_len;
+                // Get the column of the error, in characters
+                let column = char_index(&self.source[line_start..], self.offset - line_start);
+
+                // Get the error's coordinates in the larger source, if any
+                let (outer_line, outer_column) = self.context.line_col(line_index, column);
 
                 // Produce a padding
-                let padding =
-                    " ".repeat(line[..column].chars().count() + (line_index + 1).to_string().len() + 3);
+                let padding = " ".repeat(column + (outer_line + 1).to_string().len() + 3);
 
                 // Do the formatting
                 write!(
                     f,
                     "ERROR: While matching rule [{}] at line {}, column {}: \n\n{} | {}\n{}^{}",
                     self.rule,
-                    line_index + 1,
-                    column + 1,
-                    line_index + 1,
-                    self.source.lines().nth(line_index).unwrap(),
+                    outer_line + 1,
+                    outer_column + 1,
+                    outer_line + 1,
+                    line,
                     padding,
                     format!("{}", self.content)
                         .lines()
@@ -348,7 +1429,10 @@ fn err_formatter_impl() -> TokenStream {
                         write!(f, "Failed to match builtin rule [{}]: found character [{}]", rule, c)
                     }
                     PegErrorContent::FailedToMatchBuiltinRule(rule, None) => {
-                        write!(f, "Failed to match biultin rule [{}]: reached end of input", rule)
+                        write!(f, "Failed to match builtin rule [{}]: reached end of input", rule)
+                    }
+                    PegErrorContent::FailedToMatchExternalRule(rule) => {
+                        write!(f, "Failed to match external rule [{}]", rule)
                     }
                     PegErrorContent::NoMatchInUnion(matches) => write!(
                         f,
@@ -382,6 +1466,8 @@ fn err_formatter_impl() -> TokenStream {
                         "Matched content in negative pattern: {}",
                         neg.lines().next().unwrap_or("")
                     ),
+                    PegErrorContent::TerminatorNotFound => write!(f, "Reached the end of input without finding the terminator"),
+                    PegErrorContent::InternalConsumedOverflow(consumed) => write!(f, "Internal error: a matcher consumed {} bytes, which doesn't fit the remaining input", consumed),
                     PegErrorContent::ExpectedEndOfInput => write!(f, "Expected end of input"),
                 }
             }
@@ -389,7 +1475,500 @@ fn err_formatter_impl() -> TokenStream {
     }
 }
 
-pub fn get_enum_variant(mut i: usize) -> String {
+/// Check if an identifier is used in a token stream
+pub(crate) fn uses_ident(tokens: &TokenStream, ident: &str) -> bool {
+    tokens.clone().into_iter().any(|token| match token {
+        TokenTree::Ident(candidate) => candidate == ident,
+        TokenTree::Group(group) => uses_ident(&group.stream(), ident),
+        TokenTree::Punct(_) | TokenTree::Literal(_) => false,
+    })
+}
+
+/// Ensure a syntax tree can be turned into a parser with the provided options
+///
+/// Trees which went through validation always pass the tree checks, which makes generation infallible for them,
+/// but the other ones could otherwise make the generator panic or produce code that doesn't compile.
+fn check_tree(pst: &PegSyntaxTree, options: &RustGenOptions) -> Result<(), GenError> {
+    if !pst.rules().contains_key(GRAMMAR_ENTRYPOINT_RULE) {
+        return Err(GenError::MissingMainRule);
+    }
+
+    let mut err = None;
+
+    pst.walk_rules(&mut |rule, pattern| {
+        if err.is_some() {
+            return;
+        }
+
+        err = match pattern.value() {
+            RulePatternValue::Rule(name) if is_builtin_rule_name(name) => {
+                if is_valid_builtin_rule_name(name) {
+                    None
+                } else {
+                    Some(GenError::UnknownBuiltinRule {
+                        rule: rule.to_string(),
+                        referenced: name.to_string(),
+                    })
+                }
+            }
+            RulePatternValue::Rule(name) => {
+                if is_external_rule_name(name) || pst.rules().contains_key(name) {
+                    None
+                } else {
+                    Some(GenError::UnknownRule {
+                        rule: rule.to_string(),
+                        referenced: name.to_string(),
+                    })
+                }
+            }
+            RulePatternValue::Suite(patterns) if patterns.is_empty() => {
+                Some(GenError::EmptySuite {
+                    rule: rule.to_string(),
+                })
+            }
+            RulePatternValue::Union(patterns) if patterns.is_empty() => {
+                Some(GenError::EmptyUnion {
+                    rule: rule.to_string(),
+                })
+            }
+            _ => None,
+        };
+    });
+
+    if let Some(err) = err {
+        return Err(err);
+    }
+
+    if options.token_input {
+        check_token_input(pst)?;
+    }
+
+    if let RecursionWrapper::Custom(path) = &options.recursion_wrapper {
+        if path.parse::<TokenStream>().is_err() {
+            return Err(GenError::InvalidRecursionWrapper(path.clone()));
+        }
+    }
+
+    check_rule_idents(pst, &options.rename)
+}
+
+/// Ensure a grammar can match [tokens](`RustGenOptions::token_input`): builtin rules other than `B_ANY` would match
+/// the characters encoding the tokens instead of the tokens themselves, and each kind needs its own character
+fn check_token_input(pst: &PegSyntaxTree) -> Result<(), GenError> {
+    let mut err = None;
+
+    pst.walk_rules(&mut |rule, pattern| match pattern.value() {
+        RulePatternValue::Rule(name)
+            if err.is_none() && is_builtin_rule_name(name) && *name != "B_ANY" =>
+        {
+            err = Some(GenError::BuiltinRuleInTokenInput {
+                rule: rule.to_string(),
+                referenced: name.to_string(),
+            });
+        }
+        _ => {}
+    });
+
+    if let Some(err) = err {
+        return Err(err);
+    }
+
+    let count = list_terminals(pst).len();
+
+    if count > MAX_TOKEN_KINDS {
+        return Err(GenError::TooManyTokenKinds {
+            count,
+            max: MAX_TOKEN_KINDS,
+        });
+    }
+
+    Ok(())
+}
+
+/// Ensure rules get valid and distinct identifiers (see [`RustGenOptions::rename`]), which the generated code doesn't
+/// already use (see [`RESERVED_GENERATED_IDENTS`])
+fn check_rule_idents(
+    pst: &PegSyntaxTree,
+    rename: &HashMap<String, String>,
+) -> Result<(), GenError> {
+    // Rule using each identifier
+    let mut idents = HashMap::new();
+
+    for rule in pst.ordered_rules() {
+        let ident = match rename.get(rule.name()) {
+            Some(ident) => ident.as_str(),
+            None => rule.name(),
+        };
+
+        let mut chars = ident.chars();
+
+        let is_valid = matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+            && chars.all(|c| c.is_alphanumeric() || c == '_')
+            && !matches!(ident, "_" | "self" | "Self" | "super" | "crate");
+
+        if !is_valid {
+            return Err(GenError::InvalidRename {
+                rule: rule.name().to_string(),
+                ident: ident.to_string(),
+            });
+        }
+
+        if RESERVED_GENERATED_IDENTS.contains(&ident) {
+            return Err(GenError::ReservedIdent {
+                rule: rule.name().to_string(),
+                ident: ident.to_string(),
+                loc: rule.decl_loc(),
+            });
+        }
+
+        if let Some(other) = idents.insert(ident, rule.name()) {
+            return Err(GenError::DuplicateIdent {
+                first: other.to_string(),
+                second: rule.name().to_string(),
+                ident: ident.to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Generate the tokenizer matching the rules declared with the `@token` directive, if any
+fn gen_tokenizer(
+    state: &InternalState,
+    pst: &PegSyntaxTree,
+    serde_derive: &TokenStream,
+) -> Option<TokenStream> {
+    if pst.token_rules().is_empty() {
+        return None;
+    }
+
+    let variants: Vec<_> = pst
+        .token_rules()
+        .iter()
+        .map(|name| state.rule_ident(name))
+        .collect();
+
+    // Trivia collected by token rules aren't returned
+    let trivia_arg = if state.collect_trivia {
+        Some(quote! { , &mut vec![] })
+    } else {
+        None
+    };
+
+    let matchers = variants.iter().map(|ident| {
+        quote! {
+            if let Ok((_, consumed, _)) = rules::#ident(input, &input[offset..], offset #trivia_arg) {
+                if consumed > longest.map_or(0, |(_, len)| len) {
+                    longest = Some((TokenKind::#ident, consumed));
+                }
+            }
+        }
+    });
+
+    Some(quote! {
+        /// Kind of a [`Token`], which is the name of the rule it was matched by
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #serde_derive
+        #[allow(non_camel_case_types)]
+        pub enum TokenKind {
+            #(#variants,)*
+            /// Character no token rule matched
+            Error,
+        }
+
+        /// Token produced by [`tokenize`]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #serde_derive
+        pub struct Token {
+            pub kind: TokenKind,
+            /// Bytes range of the token in the input
+            pub span: Span,
+        }
+
+        /// Split the input into tokens, using the longest match of the token rules at each position
+        ///
+        /// This never fails: characters which don't start any token produce a [`TokenKind::Error`] token.
+        /// When multiple rules match the same length, the first declared one is used.
+        pub fn tokenize(input: &str) -> Vec<Token> {
+            let mut tokens = vec![];
+            let mut offset = 0;
+
+            while offset < input.len() {
+                let mut longest = None;
+
+                #(#matchers)*
+
+                let (kind, len) = longest.unwrap_or_else(|| {
+                    (TokenKind::Error, input[offset..].chars().next().unwrap().len_utf8())
+                });
+
+                tokens.push(Token { kind, span: Span { start: offset, end: offset + len } });
+                offset += len;
+            }
+
+            tokens
+        }
+    })
+}
+
+/// Generate the preprocessing inserting the markers matched by the `E_INDENT`, `E_DEDENT` and `E_NEWLINE` external rules
+fn gen_indentation_preprocessor() -> TokenStream {
+    quote! {
+        /// Marker matched by the `E_INDENT` external rule, inserted before the first line of a more indented block
+        pub const INDENT_MARKER: char = '\u{E000}';
+
+        /// Marker matched by the `E_DEDENT` external rule, inserted once per closed block before the first line following them
+        /// (or at the end of the input)
+        pub const DEDENT_MARKER: char = '\u{E001}';
+
+        /// Marker matched by the `E_NEWLINE` external rule, inserted at the end of each non-blank line
+        pub const NEWLINE_MARKER: char = '\u{E002}';
+
+        /// Error returned by [`preprocess_indentation`], with the byte offset it occurred at in the original input
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum IndentationError {
+            /// A line's indentation doesn't match any enclosing level, e.g. because tabs and spaces are mixed differently
+            InconsistentIndentation(usize),
+            /// The input contains one of the marker characters
+            ReservedCharacter(usize),
+        }
+
+        impl std::fmt::Display for IndentationError {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                match self {
+                    IndentationError::InconsistentIndentation(offset) => {
+                        write!(f, "Indentation doesn't match any enclosing level (at byte {})", offset)
+                    }
+                    IndentationError::ReservedCharacter(offset) => {
+                        write!(f, "Found a character reserved to indentation markers (at byte {})", offset)
+                    }
+                }
+            }
+        }
+
+        /// Input with indentation markers, produced by [`preprocess_indentation`]
+        ///
+        /// All offsets in the data matched in its text (e.g. spans) are relative to the text, not to the original input.
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct IndentedInput {
+            text: String,
+            /// Byte offsets of the markers in the text
+            markers: Vec<usize>,
+        }
+
+        impl IndentedInput {
+            /// Get the text to give to `exec`
+            pub fn text(&self) -> &str {
+                &self.text
+            }
+
+            /// Convert a byte offset in the text to a byte offset in the original input
+            pub fn original_offset(&self, offset: usize) -> usize {
+                offset - self.markers.partition_point(|marker| *marker < offset) * INDENT_MARKER.len_utf8()
+            }
+
+            fn push_marker(&mut self, marker: char) {
+                self.markers.push(self.text.len());
+                self.text.push(marker);
+            }
+        }
+
+        /// Insert the markers matched by the `E_INDENT`, `E_DEDENT` and `E_NEWLINE` external rules in an input
+        ///
+        /// Blank lines are ignored. A line is more indented than the previous level if its indentation (made of spaces and tabs)
+        /// starts with the previous level's one, so mixing tabs and spaces differently between levels is an error.
+        /// The blocks still opened at the end of the input are all closed there.
+        pub fn preprocess_indentation(input: &str) -> Result<IndentedInput, IndentationError> {
+            if let Some(offset) = input.find([INDENT_MARKER, DEDENT_MARKER, NEWLINE_MARKER]) {
+                return Err(IndentationError::ReservedCharacter(offset));
+            }
+
+            let mut indented = IndentedInput { text: String::with_capacity(input.len()), markers: vec![] };
+            let mut levels = vec![""];
+            let mut line_start = 0;
+
+            for line in input.split_inclusive('\n') {
+                let content = line.trim_start_matches([' ', '\t']);
+                let indent = &line[..line.len() - content.len()];
+
+                let line_end = line.strip_suffix('\n').map_or(line, |line| line.strip_suffix('\r').unwrap_or(line));
+
+                if line_end.trim().is_empty() {
+                    indented.text.push_str(line);
+                    line_start += line.len();
+                    continue;
+                }
+
+                indented.text.push_str(indent);
+
+                let current = *levels.last().unwrap();
+
+                if indent.len() > current.len() && indent.starts_with(current) {
+                    levels.push(indent);
+                    indented.push_marker(INDENT_MARKER);
+                } else {
+                    while levels.last().unwrap().len() > indent.len() {
+                        levels.pop();
+                        indented.push_marker(DEDENT_MARKER);
+                    }
+
+                    if *levels.last().unwrap() != indent {
+                        return Err(IndentationError::InconsistentIndentation(line_start));
+                    }
+                }
+
+                indented.text.push_str(&line_end[indent.len()..]);
+                indented.push_marker(NEWLINE_MARKER);
+                indented.text.push_str(&line[line_end.len()..]);
+
+                line_start += line.len();
+            }
+
+            for _ in 1..levels.len() {
+                indented.push_marker(DEDENT_MARKER);
+            }
+
+            Ok(indented)
+        }
+    }
+}
+
+/// Generate the encoding of token inputs, each token being encoded as a character matched by the constant string of its kind
+fn gen_token_input() -> TokenStream {
+    quote! {
+        /// Token produced by a separate lexer
+        pub trait LexedToken {
+            /// Get the token's kind, which is matched by the constant string with the same content (see [`TERMINALS`])
+            fn kind(&self) -> &str;
+        }
+
+        /// Character encoding the tokens of the first kind of [`TERMINALS`], the next kinds being encoded by the next characters
+        pub const FIRST_TOKEN_MARKER: char = #FIRST_TOKEN_MARKER;
+
+        /// Character encoding the tokens whose kind isn't a constant string of the grammar (only matched by `B_ANY`)
+        pub const UNKNOWN_TOKEN_MARKER: char = #UNKNOWN_TOKEN_MARKER;
+
+        /// Tokens encoded as characters, produced by [`encode_tokens`]
+        ///
+        /// All offsets in the data matched in its text (e.g. spans) are byte offsets in the text,
+        /// which can be converted to token indexes.
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct LexedInput {
+            text: String,
+        }
+
+        impl LexedInput {
+            /// Get the text to give to `exec`
+            pub fn text(&self) -> &str {
+                &self.text
+            }
+
+            /// Convert a byte offset in the text to the index of a token
+            pub fn token_index(&self, offset: usize) -> usize {
+                offset / FIRST_TOKEN_MARKER.len_utf8()
+            }
+
+            /// Get the indexes of the tokens covered by a span
+            pub fn token_range(&self, span: Span) -> std::ops::Range<usize> {
+                self.token_index(span.start)..self.token_index(span.end)
+            }
+        }
+
+        /// Encode tokens as characters, each one being matched by the constant string of its kind
+        pub fn encode_tokens<T: LexedToken>(tokens: &[T]) -> LexedInput {
+            let text = tokens
+                .iter()
+                .map(|token| match TERMINALS.binary_search(&token.kind()) {
+                    Ok(i) => char::from_u32(FIRST_TOKEN_MARKER as u32 + i as u32).unwrap(),
+                    Err(_) => UNKNOWN_TOKEN_MARKER,
+                })
+                .collect();
+
+            LexedInput { text }
+        }
+    }
+}
+
+/// Character encoding the tokens whose kind isn't a constant string of the grammar, the first one of the Supplementary
+/// Private Use Area-A (see [`RustGenOptions::token_input`])
+const UNKNOWN_TOKEN_MARKER: char = '\u{F0000}';
+
+/// Character encoding the first kind of tokens, the next ones being encoded by the next characters
+/// (see [`RustGenOptions::token_input`])
+const FIRST_TOKEN_MARKER: char = '\u{F0001}';
+
+/// Maximum number of token kinds, which are encoded by the characters of the Supplementary Private Use Area-A
+/// following [`FIRST_TOKEN_MARKER`] (up to U+FFFFD)
+const MAX_TOKEN_KINDS: usize = 0xFFFFD - FIRST_TOKEN_MARKER as usize + 1;
+
+/// Get the character encoding each token kind (see [`gen_token_input`])
+fn gen_token_markers<'a>(pst: &'a PegSyntaxTree) -> HashMap<&'a str, char> {
+    list_terminals(pst)
+        .into_iter()
+        .enumerate()
+        .map(|(i, string)| {
+            let marker = char::from_u32(FIRST_TOKEN_MARKER as u32 + i as u32).unwrap();
+            (string, marker)
+        })
+        .collect()
+}
+
+/// List the distinct constant strings of a grammar, sorted
+fn list_terminals<'a>(pst: &PegSyntaxTree<'a>) -> BTreeSet<&'a str> {
+    constant_strings(pst, false)
+        .into_iter()
+        .map(|(_, string, _)| string)
+        .collect()
+}
+
+/// Generate the tables listing the grammar's constant strings (e.g. its keywords and operators)
+fn gen_terminals(pst: &PegSyntaxTree) -> TokenStream {
+    let terminals = list_terminals(pst);
+    let mut terminals_by_rule: Vec<(&str, BTreeSet<&str>)> = vec![];
+
+    for (rule, string, _) in constant_strings(pst, true) {
+        match terminals_by_rule.last_mut() {
+            Some((last_rule, strings)) if *last_rule == rule => {
+                strings.insert(string);
+            }
+            _ => terminals_by_rule.push((rule, BTreeSet::from([string]))),
+        }
+    }
+
+    let terminals = terminals.iter();
+
+    let terminals_by_rule = terminals_by_rule.iter().map(|(rule, strings)| {
+        let strings = strings.iter();
+        quote! { (#rule, &[#(#strings),*]) }
+    });
+
+    quote! {
+        /// All the distinct constant strings of the grammar (e.g. keywords and operators), sorted
+        pub const TERMINALS: &[&str] = &[#(#terminals),*];
+
+        /// Distinct constant strings directly contained in each rule (in declaration order), sorted
+        ///
+        /// Rules which don't contain any constant string are not listed.
+        pub const TERMINALS_BY_RULE: &[(&str, &[&str])] = &[#(#terminals_by_rule),*];
+    }
+}
+
+/// Generate the documentation of a constant string's type
+///
+/// The string is escaped and put in a code span to prevent it from being interpreted as Markdown
+fn gen_cst_string_doc(string: &str) -> String {
+    let escaped = string.escape_debug().to_string();
+
+    // Code spans must be delimited by a longer run of backticks than any run they contain
+    let longest_backticks_run = escaped.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+
+    let delimiter = "`".repeat(longest_backticks_run + 1);
+
+    format!("Constant string: {} {} {}", delimiter, escaped, delimiter)
+}
+
+pub(crate) fn get_enum_variant(mut i: usize) -> String {
     if i == 0 {
         return "A".to_string();
     }
@@ -406,10 +1985,625 @@ pub fn get_enum_variant(mut i: usize) -> String {
     out.chars().rev().collect()
 }
 
-pub fn make_safe_ident(ident: &str) -> Ident {
+fn make_safe_ident(ident: &str) -> Ident {
     if RUST_RESERVED_KEYWORDS.contains(&ident) {
         format_ident!("r#{}", ident)
     } else {
         format_ident!("{}", ident)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generation_is_deterministic() {
+        let grammar = r#"
+value = object | array | number | °"null" | "true" | "false"
+object = "{" (pair ("," pair)*)? "}"
+pair = key ":" value
+key = ident | number
+array = "[" (value ("," value)*)? "]"
+number = @(B_ASCII_DIGIT+) ("." @(B_ASCII_DIGIT+))?
+ident = @(B_ASCII_ALPHABETIC+)
+sign = "+" | "-" | °"~"
+main = sign? value
+"#;
+
+        let pst = parse_peg(grammar).unwrap();
+        let first = gen_rust_str(&pst, None);
+
+        for _ in 0..5 {
+            // Each parse creates new hash maps, with different iteration orders
+            let pst = parse_peg(grammar).unwrap();
+            assert_eq!(gen_rust_str(&pst, None), first);
+        }
+    }
+
+    fn strings_module(grammar: &str) -> String {
+        let pst = parse_peg(grammar).unwrap();
+        gen_rust_modules(&pst, &RustGenOptions::new(), false)
+            .unwrap()
+            .strings
+            .to_string()
+    }
+
+    #[test]
+    fn strings_module_is_deterministic() {
+        // These strings all produce the `aB` type name, and are captured by rules in different orders
+        let grammars = [
+            "first = \"a b\"\nsecond = \"aB\" \"a  b\"\nmain = first second",
+            "second = \"a  b\" \"aB\"\nfirst = \"a b\"\nmain = first second",
+            "main = second first\nfirst = \"aB\" | \"a  b\"\nsecond = \"a b\"",
+        ];
+
+        let expected = strings_module(grammars[0]);
+        assert!(expected.contains("pub struct Str_a_B ;"));
+        assert!(expected.contains("pub struct Str_aB ;"));
+        assert!(expected.contains("pub struct Str1_aB ;"));
+
+        for grammar in &grammars {
+            for _ in 0..5 {
+                // Each parse creates new hash maps, with different iteration orders
+                assert_eq!(strings_module(grammar), expected, "in {:?}", grammar);
+            }
+        }
+    }
+
+    #[test]
+    fn cst_string_docs_are_escaped() {
+        assert_eq!(gen_cst_string_doc("if"), "Constant string: ` if `");
+        assert_eq!(gen_cst_string_doc("`"), "Constant string: `` ` ``");
+        assert_eq!(gen_cst_string_doc("a``b"), "Constant string: ``` a``b ```");
+        assert_eq!(gen_cst_string_doc("*→\\"), "Constant string: ` *→\\\\ `");
+    }
+
+    fn shapes(grammar: &str, options: &RustGenOptions) -> Vec<(String, Option<TypeShape>)> {
+        let pst = parse_peg(grammar).unwrap();
+        let mut shapes: Vec<_> = rule_type_shapes(&pst, options)
+            .unwrap()
+            .into_iter()
+            .map(|(name, shape)| (name.to_string(), shape))
+            .collect();
+        shapes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        shapes
+    }
+
+    const MARKERS_GRAMMAR: &str = r#"
+mutability = "mut"?
+stars = "*"*
+pointer = "&" mutability °"x"? stars
+main = pointer
+"#;
+
+    #[test]
+    fn simplified_marker_types() {
+        let marker = |string: &str| TypeShape::StrMarker(string.to_string());
+        let rule = |name: &str| TypeShape::RuleRef {
+            name: name.to_string(),
+            boxed: false,
+        };
+
+        let options = RustGenOptions::new();
+        assert!(options.simplify_marker_types);
+
+        assert_eq!(
+            shapes(MARKERS_GRAMMAR, &options),
+            vec![
+                ("main".to_string(), Some(rule("pointer"))),
+                ("mutability".to_string(), Some(TypeShape::Flag)),
+                (
+                    "pointer".to_string(),
+                    Some(TypeShape::Tuple(vec![
+                        marker("&"),
+                        rule("mutability"),
+                        rule("stars")
+                    ]))
+                ),
+                ("stars".to_string(), Some(TypeShape::Count)),
+            ]
+        );
+
+        let options = RustGenOptions {
+            simplify_marker_types: false,
+            ..RustGenOptions::new()
+        };
+
+        assert_eq!(
+            shapes(MARKERS_GRAMMAR, &options),
+            vec![
+                ("main".to_string(), Some(rule("pointer"))),
+                (
+                    "mutability".to_string(),
+                    Some(TypeShape::Option(Box::new(marker("mut"))))
+                ),
+                (
+                    "pointer".to_string(),
+                    Some(TypeShape::Tuple(vec![
+                        marker("&"),
+                        rule("mutability"),
+                        rule("stars")
+                    ]))
+                ),
+                (
+                    "stars".to_string(),
+                    Some(TypeShape::Vec(Box::new(marker("*"))))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_suites_and_unions() {
+        let mut pst = parse_peg_nocheck(r#"main = "a" | "b""#).unwrap();
+        pst.set_rule_value("main", RulePatternValue::Union(vec![]));
+
+        assert_eq!(
+            try_gen_rust_str_with(&pst, &RustGenOptions::new()).unwrap_err(),
+            GenError::EmptyUnion {
+                rule: "main".to_string()
+            }
+        );
+
+        let mut pst = parse_peg_nocheck(r#"main = "a" "b""#).unwrap();
+        pst.set_rule_value("main", RulePatternValue::Suite(vec![]));
+
+        assert_eq!(
+            try_gen_rust_str_with(&pst, &RustGenOptions::new()).unwrap_err(),
+            GenError::EmptySuite {
+                rule: "main".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_trees() {
+        let gen_err =
+            |pst: &PegSyntaxTree| try_gen_rust_str_with(pst, &RustGenOptions::new()).unwrap_err();
+
+        let mut pst = parse_peg_nocheck("main = a\na = \"a\"").unwrap();
+        pst.set_rule_value("a", RulePatternValue::Rule("b"));
+
+        assert_eq!(
+            gen_err(&pst),
+            GenError::UnknownRule {
+                rule: "a".to_string(),
+                referenced: "b".to_string()
+            }
+        );
+
+        pst.set_rule_value("a", RulePatternValue::Rule("B_NOT_A_BUILTIN"));
+
+        assert_eq!(
+            gen_err(&pst),
+            GenError::UnknownBuiltinRule {
+                rule: "a".to_string(),
+                referenced: "B_NOT_A_BUILTIN".to_string()
+            }
+        );
+
+        // The infallible generator produces a compilation error instead
+        let code = gen_rust_str_with(&pst, &RustGenOptions::new());
+        assert_eq!(
+            code,
+            quote! { compile_error!("Rule 'a' references unknown builtin rule 'B_NOT_A_BUILTIN'"); }.to_string()
+        );
+
+        // Empty unions are found in the rules the main one uses
+        let mut pst = parse_peg_nocheck("main = \"a\" (b | \"c\")\nb = \"b\" | \"c\"").unwrap();
+        pst.set_rule_value("b", RulePatternValue::Union(vec![]));
+
+        assert_eq!(
+            gen_err(&pst),
+            GenError::EmptyUnion {
+                rule: "b".to_string()
+            }
+        );
+
+        let mut pst = parse_peg_nocheck("main = a\na = \"a\"").unwrap();
+        pst.remove_rule("main");
+
+        assert_eq!(gen_err(&pst), GenError::MissingMainRule);
+    }
+
+    #[test]
+    fn renamed_rule_idents() {
+        let pst = parse_peg("main = a b\na = \"x\"\nb = \"y\"").unwrap();
+
+        let gen = |renames: &[(&str, &str)]| {
+            let mut options = RustGenOptions::new();
+
+            for (rule, ident) in renames {
+                options.rename.insert(rule.to_string(), ident.to_string());
+            }
+
+            try_gen_rust_str_with(&pst, &options)
+        };
+
+        // Keywords are turned into raw identifiers
+        let code = gen(&[("a", "First"), ("b", "type")]).unwrap();
+        assert!(code.contains("pub struct First"));
+        assert!(code.contains("pub struct r#type"));
+
+        // Unknown rules are ignored
+        gen(&[("c", "b")]).unwrap();
+
+        // Renaming a rule to the name of another one
+        assert_eq!(
+            gen(&[("a", "b")]),
+            Err(GenError::DuplicateIdent {
+                first: "a".to_string(),
+                second: "b".to_string(),
+                ident: "b".to_string(),
+            })
+        );
+
+        // Renaming two rules to the same identifier
+        assert_eq!(
+            gen(&[("a", "Value"), ("b", "Value")]),
+            Err(GenError::DuplicateIdent {
+                first: "a".to_string(),
+                second: "b".to_string(),
+                ident: "Value".to_string(),
+            })
+        );
+
+        // Swapping names is fine
+        gen(&[("a", "b"), ("b", "a")]).unwrap();
+
+        for ident in ["", "1a", "a-b", "a b", "r#type", "_", "self", "crate"] {
+            assert_eq!(
+                gen(&[("b", ident)]),
+                Err(GenError::InvalidRename {
+                    rule: "b".to_string(),
+                    ident: ident.to_string(),
+                }),
+                "with {:?}",
+                ident
+            );
+        }
+    }
+
+    #[test]
+    fn reserved_rule_idents() {
+        let gen = |grammar: &str, options: &RustGenOptions| {
+            try_gen_rust_str_with(&parse_peg(grammar).unwrap(), options)
+        };
+
+        for name in ["Some", "None", "Ok", "Err", "Option", "Vec", "MatchedRule"] {
+            // Rules are reported at their declaration, even when only referenced elsewhere
+            let grammar = format!("main = {0} \"x\" {0}?\n{0} = \"a\"", name);
+
+            assert_eq!(
+                gen(&grammar, &RustGenOptions::new()),
+                Err(GenError::ReservedIdent {
+                    rule: name.to_string(),
+                    ident: name.to_string(),
+                    loc: ParserLoc::new(1, 0),
+                })
+            );
+
+            // Renaming the rule avoids the conflict
+            let mut options = RustGenOptions::new();
+            options
+                .rename
+                .insert(name.to_string(), format!("{}_rule", name));
+
+            assert!(gen(&grammar, &options).is_ok(), "with {:?}", name);
+        }
+
+        // The matchers' variables and the primitive types are referenced so that rules don't shadow them
+        for name in [
+            "input", "offset", "result", "err_0", "p12", "Result", "str", "char", "usize", "bool",
+        ] {
+            let grammar = format!("main = {0}\n{0} = \"a\"", name);
+            assert!(
+                gen(&grammar, &RustGenOptions::new()).is_ok(),
+                "with {:?}",
+                name
+            );
+        }
+
+        // Rules can't be renamed to a reserved identifier either
+        let mut options = RustGenOptions::new();
+        options
+            .rename
+            .insert("value".to_string(), "Some".to_string());
+
+        assert_eq!(
+            gen("main = value\nvalue = \"a\"", &options),
+            Err(GenError::ReservedIdent {
+                rule: "value".to_string(),
+                ident: "Some".to_string(),
+                loc: ParserLoc::new(1, 0),
+            })
+        );
+    }
+
+    #[test]
+    fn single_member_unions() {
+        let grammar = "main = a\na = (\"x\" \"y\")";
+        let grouped = parse_peg(grammar).unwrap();
+
+        let member = match grouped.rules()["a"].pattern().value() {
+            RulePatternValue::Group(inner) => Pattern::clone(inner),
+            other => panic!("expected a group, got {:?}", other),
+        };
+
+        let mut pst = parse_peg(grammar).unwrap();
+        pst.set_rule_value("a", RulePatternValue::Union(vec![member]));
+
+        // The union is generated like a group of its member
+        assert_eq!(
+            try_gen_rust_str_with(&pst, &RustGenOptions::new()).unwrap(),
+            gen_rust_str(&grouped, None)
+        );
+    }
+
+    #[test]
+    fn custom_recursion_wrappers() {
+        let pst = parse_peg("item = \"(\" item? \")\"\nmain = item").unwrap();
+
+        let mut options = RustGenOptions::new();
+        options.recursion_wrapper = RecursionWrapper::Custom("crate::Shared".to_string());
+
+        let code = try_gen_rust_str_with(&pst, &options).unwrap();
+        assert!(code.contains("crate :: Shared <"));
+        assert!(!code.contains("Rc <"));
+
+        options.recursion_wrapper = RecursionWrapper::Custom("crate::(Shared".to_string());
+
+        assert_eq!(
+            try_gen_rust_str_with(&pst, &options).unwrap_err(),
+            GenError::InvalidRecursionWrapper("crate::(Shared".to_string())
+        );
+    }
+
+    #[test]
+    fn streaming_main_rule_shape() {
+        let options = RustGenOptions {
+            streaming: true,
+            ..RustGenOptions::new()
+        };
+
+        for grammar in &[
+            "token = B_ASCII_DIGIT+\nmain = token*",
+            "token = B_ASCII_DIGIT+\nmain = token+",
+        ] {
+            let code = try_gen_rust_str_with(&parse_peg(grammar).unwrap(), &options).unwrap();
+            assert!(code.contains("pub fn exec_streaming"));
+        }
+
+        for grammar in &[
+            "token = B_ASCII_DIGIT+\nmain = token",
+            "token = B_ASCII_DIGIT+\nmain = token?",
+            "token = B_ASCII_DIGIT+\nmain = °token*",
+            "token = B_ASCII_DIGIT+\nmain = token* \"!\"",
+            "digit = °B_ASCII_DIGIT\nmain = digit*",
+            "main = B_ASCII_DIGIT*",
+        ] {
+            assert_eq!(
+                try_gen_rust_str_with(&parse_peg(grammar).unwrap(), &options),
+                Err(GenError::InvalidStreamingMainRule),
+                "{}",
+                grammar
+            );
+        }
+    }
+
+    #[test]
+    fn samples_of_rules_without_finite_instance() {
+        let pst = parse_peg("list = \"(\" list \")\"\nmain = list | \"x\"").unwrap();
+
+        let options = RustGenOptions {
+            sample_constructors: true,
+            ..RustGenOptions::new()
+        };
+
+        let code = gen_rust_str_with(&pst, &options);
+
+        // Only `main` gets a constructor, using its second member
+        assert_eq!(code.matches("pub fn sample ()").count(), 1);
+        assert!(code.contains("impl matched :: main"));
+        assert!(!code.contains("impl matched :: list"));
+        assert!(code.contains("Sw2 :: B"));
+
+        // The lack of constructor is explained on the rule's type
+        let doc = code.find("has no finite instance").unwrap();
+        let next_struct = doc + code[doc..].find("pub struct").unwrap();
+        assert!(code[next_struct..].starts_with("pub struct list "));
+        assert_eq!(code.matches("has no finite instance").count(), 1);
+    }
+
+    #[test]
+    fn token_input_builtin_rules() {
+        let options = RustGenOptions {
+            token_input: true,
+            ..RustGenOptions::new()
+        };
+
+        let pst = parse_peg("other = B_ANY\nmain = \"ident\" other").unwrap();
+        try_gen_rust_str_with(&pst, &options).unwrap();
+
+        let pst = parse_peg("digit = B_ASCII_DIGIT\nmain = \"ident\" digit").unwrap();
+
+        assert_eq!(
+            try_gen_rust_str_with(&pst, &options),
+            Err(GenError::BuiltinRuleInTokenInput {
+                rule: "digit".to_string(),
+                referenced: "B_ASCII_DIGIT".to_string()
+            })
+        );
+
+        // Builtin rules are only rejected when matching tokens
+        try_gen_rust_str_with(&pst, &RustGenOptions::new()).unwrap();
+    }
+
+    /// Get a grammar whose `main` rule is a union of the provided number of distinct constant strings
+    fn terminals_grammar(count: usize) -> String {
+        let members: Vec<_> = (0..count).map(|i| format!("\"{}\"", i)).collect();
+        format!("main = {}", members.join(" | "))
+    }
+
+    #[test]
+    fn token_kinds_limit() {
+        let pst = parse_peg("main = \"b\" \"a\"").unwrap();
+        let markers = gen_token_markers(&pst);
+        assert_eq!(markers["a"], FIRST_TOKEN_MARKER);
+        assert_eq!(markers["b"], '\u{F0002}');
+
+        // Each kind gets a character of the private use area, distinct from the unknown tokens' one
+        let grammar = terminals_grammar(MAX_TOKEN_KINDS);
+        let pst = parse_peg_nocheck(&grammar).unwrap();
+        let markers = gen_token_markers(&pst);
+        assert_eq!(markers.len(), MAX_TOKEN_KINDS);
+        assert_eq!(markers.values().max(), Some(&'\u{FFFFD}'));
+        assert!(!markers
+            .values()
+            .any(|&marker| marker == UNKNOWN_TOKEN_MARKER));
+
+        let grammar = terminals_grammar(MAX_TOKEN_KINDS + 1);
+        let pst = parse_peg_nocheck(&grammar).unwrap();
+
+        let options = RustGenOptions {
+            token_input: true,
+            ..RustGenOptions::new()
+        };
+
+        assert_eq!(
+            try_gen_rust_str_with(&pst, &options),
+            Err(GenError::TooManyTokenKinds {
+                count: MAX_TOKEN_KINDS + 1,
+                max: MAX_TOKEN_KINDS
+            })
+        );
+    }
+
+    #[test]
+    fn builtin_rule_types() {
+        let code = gen_rust_str_with(
+            &parse_peg("main = °B_ANY*").unwrap(),
+            &RustGenOptions::new(),
+        );
+        assert!(!code.contains("pub struct B_ANY"));
+
+        // Builtin rules captured anywhere get a type, even if they are silent elsewhere
+        let pst = parse_peg("a = B_ASCII_DIGIT\nmain = a °B_ANY °B_ASCII_DIGIT").unwrap();
+        let code = gen_rust_str_with(&pst, &RustGenOptions::new());
+        assert!(code.contains("pub struct B_ASCII_DIGIT"));
+        assert!(!code.contains("pub struct B_ANY"));
+    }
+
+    #[test]
+    fn derive_default_only_on_optional_rules() {
+        let pst = parse_peg(
+            "attribute = °\"#\" @(B_ASCII_ALPHABETIC+)\nmodifiers = \"pub \"? attribute*\nmain = modifiers °\"fn\"",
+        )
+        .unwrap();
+
+        let mut options = RustGenOptions::new();
+        let code = gen_rust_str_with(&pst, &options);
+        assert!(!code.contains("derive (Default)"));
+
+        // Only `modifiers` is made of optional and repeated patterns
+        options.derive_default = true;
+        let code = gen_rust_str_with(&pst, &options);
+        assert_eq!(code.matches("# [derive (Default)]").count(), 1);
+        assert!(code.contains("# [derive (Default)] pub struct modifiers"));
+    }
+
+    const SILENCE_GRAMMAR: &str = r#"
+ws = °" "+
+comma = °","
+ident = @(B_ASCII_ALPHABETIC+)
+list = ident (comma ws? ident)*
+main = list
+"#;
+
+    #[test]
+    fn silence_override_as_written() {
+        let rule = |name: &str| TypeShape::RuleRef {
+            name: name.to_string(),
+            boxed: false,
+        };
+
+        let options = RustGenOptions::new();
+        assert_eq!(options.silence_override, SilenceOverride::AsWritten);
+
+        assert_eq!(
+            shapes(SILENCE_GRAMMAR, &options),
+            vec![
+                ("comma".to_string(), None),
+                ("ident".to_string(), Some(TypeShape::Str)),
+                (
+                    "list".to_string(),
+                    Some(TypeShape::Tuple(vec![
+                        rule("ident"),
+                        TypeShape::Vec(Box::new(rule("ident")))
+                    ]))
+                ),
+                ("main".to_string(), Some(rule("list"))),
+                ("ws".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn silence_override_capture_everything() {
+        let rule = |name: &str| TypeShape::RuleRef {
+            name: name.to_string(),
+            boxed: false,
+        };
+
+        let options = RustGenOptions {
+            silence_override: SilenceOverride::CaptureEverything,
+            ..RustGenOptions::new()
+        };
+
+        // Rules which were only made of silent patterns now have their own types
+        assert_eq!(
+            shapes(SILENCE_GRAMMAR, &options),
+            vec![
+                (
+                    "comma".to_string(),
+                    Some(TypeShape::StrMarker(",".to_string()))
+                ),
+                ("ident".to_string(), Some(TypeShape::Str)),
+                (
+                    "list".to_string(),
+                    Some(TypeShape::Tuple(vec![
+                        rule("ident"),
+                        TypeShape::Vec(Box::new(TypeShape::Tuple(vec![
+                            rule("comma"),
+                            TypeShape::Option(Box::new(rule("ws"))),
+                            rule("ident")
+                        ])))
+                    ]))
+                ),
+                ("main".to_string(), Some(rule("list"))),
+                ("ws".to_string(), Some(TypeShape::Count)),
+            ]
+        );
+    }
+
+    #[test]
+    fn silence_override_silence_rules() {
+        let options = RustGenOptions {
+            silence_override: SilenceOverride::SilenceRules(vec![
+                "ident".to_string(),
+                "unknown".to_string(),
+            ]),
+            ..RustGenOptions::new()
+        };
+
+        // The rule itself still captures data, but its references don't
+        let shapes = shapes(SILENCE_GRAMMAR, &options);
+        assert_eq!(shapes[1], ("ident".to_string(), Some(TypeShape::Str)));
+        assert_eq!(shapes[2], ("list".to_string(), None));
+        assert_eq!(shapes[3], ("main".to_string(), None));
+
+        let pst = parse_peg(SILENCE_GRAMMAR).unwrap();
+        assert!(try_gen_rust_str_with(&pst, &options).is_ok());
+    }
+}
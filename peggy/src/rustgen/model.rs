@@ -0,0 +1,318 @@
+use crate::grammar::data::*;
+use crate::grammar::graph::GrammarGraph;
+use crate::grammar::utils::*;
+use std::collections::HashMap;
+
+/// Shape of the data produced by a pattern in generated parsers
+///
+/// Types, matchers and samples are all rendered from shapes, so they always agree on the data a pattern produces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Shape<'a> {
+    /// No data at all (`()`), e.g. for silent patterns
+    Unit,
+
+    /// Matched input (`&'a str`), for atomic patterns and patterns matching up to a terminator
+    Str,
+
+    /// Zero-sized type of a constant string
+    CstString(&'a str),
+
+    /// Character matched by a builtin rule
+    Builtin(&'a str),
+
+    /// Data of a declared rule, `indirect` references (to a rule of the same recursive component) being wrapped in the
+    /// [recursion wrapper](`super::RustGenOptions::recursion_wrapper`)
+    Rule { name: &'a str, indirect: bool },
+
+    /// Number of times a repeated marker matched (`usize`)
+    Count,
+
+    /// Whether an optional marker matched (`bool`)
+    Flag,
+
+    /// Data of each match of a repeated pattern
+    Vec(Box<Shape<'a>>),
+
+    /// Data of an optional pattern
+    Option(Box<Shape<'a>>),
+
+    /// Data of the capturing pieces of a suite, which has at least two of them (a single piece isn't wrapped)
+    Tuple(Vec<Shape<'a>>),
+
+    /// Data of the member of a union which matched, non-capturing members holding [`Shape::Unit`]
+    Union(Vec<Shape<'a>>),
+}
+
+/// Shape of the data captured by a rule in generated parsers, as returned by [`rule_type_shapes`](`super::rule_type_shapes`)
+///
+/// Unlike the shapes used by the generator itself, non-capturing patterns are represented by `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum TypeShape {
+    /// Matched input (`&'a str`), for atomic patterns and patterns matching up to a terminator
+    Str,
+
+    /// Zero-sized type of a constant string (in the `strings` module)
+    StrMarker(String),
+
+    /// Character matched by a builtin rule (in the `matched` module)
+    BuiltinChar(String),
+
+    /// Data of a declared rule, `boxed` in the [recursion wrapper](`super::RustGenOptions::recursion_wrapper`) when
+    /// it references a rule of the same recursive component
+    RuleRef { name: String, boxed: bool },
+
+    /// Number of times a repeated marker matched (`usize`)
+    Count,
+
+    /// Whether an optional marker matched (`bool`)
+    Flag,
+
+    /// Data of each match of a repeated pattern
+    Vec(Box<TypeShape>),
+
+    /// Data of an optional pattern
+    Option(Box<TypeShape>),
+
+    /// Data of the capturing pieces of a suite
+    Tuple(Vec<TypeShape>),
+
+    /// Data of the member of a union which matched, non-capturing members holding `()`
+    Union(Vec<Option<TypeShape>>),
+}
+
+impl Shape<'_> {
+    /// Get the public counterpart of the shape, which is `None` for [`Shape::Unit`]
+    pub fn to_type_shape(&self) -> Option<TypeShape> {
+        let boxed = |shape: &Shape| Box::new(shape.to_type_shape().unwrap());
+
+        Some(match self {
+            Shape::Unit => return None,
+            Shape::Str => TypeShape::Str,
+            Shape::CstString(string) => TypeShape::StrMarker(string.to_string()),
+            Shape::Builtin(name) => TypeShape::BuiltinChar(name.to_string()),
+            Shape::Rule { name, indirect } => TypeShape::RuleRef {
+                name: name.to_string(),
+                boxed: *indirect,
+            },
+            Shape::Count => TypeShape::Count,
+            Shape::Flag => TypeShape::Flag,
+            // Repeated and optional patterns without any data are unit shapes themselves
+            Shape::Vec(data) => TypeShape::Vec(boxed(data)),
+            Shape::Option(data) => TypeShape::Option(boxed(data)),
+            // Suites only hold their capturing pieces
+            Shape::Tuple(pieces) => {
+                TypeShape::Tuple(pieces.iter().filter_map(Shape::to_type_shape).collect())
+            }
+            Shape::Union(members) => {
+                TypeShape::Union(members.iter().map(Shape::to_type_shape).collect())
+            }
+        })
+    }
+}
+
+/// Pattern lowered with the shape of its data
+pub struct GenNode<'a> {
+    /// The lowered pattern
+    pub pattern: &'a Pattern<'a>,
+
+    /// Shape of the pattern's value data, ignoring its mode and repetition
+    pub value: Shape<'a>,
+
+    /// Shape of the pattern's data
+    pub shape: Shape<'a>,
+
+    /// Nodes of the inner patterns: a group's pattern, a suite's pieces, a union's members or a terminator
+    pub children: Vec<GenNode<'a>>,
+}
+
+impl<'a> GenNode<'a> {
+    /// Check if the pattern captures any data
+    pub fn is_capturing(&self) -> bool {
+        self.shape != Shape::Unit
+    }
+
+    /// Check if the pattern's data implements [`Default`], which is the case for optional and repeated (`*`) patterns,
+    /// as well as groups and suites only capturing such patterns
+    pub fn is_defaultable(&self) -> bool {
+        match self.pattern.repetition() {
+            Some(PatternRepetition::Any) | Some(PatternRepetition::Optional) => true,
+            Some(PatternRepetition::OneOrMore) => false,
+            None if self.pattern.is_atomic() => false,
+            None => match self.pattern.value() {
+                RulePatternValue::Group(_) => self.children[0].is_defaultable(),
+                RulePatternValue::Suite(_) => self
+                    .children
+                    .iter()
+                    .filter(|child| child.is_capturing())
+                    .all(GenNode::is_defaultable),
+                RulePatternValue::CstString(_)
+                | RulePatternValue::Rule(_)
+                | RulePatternValue::Union(_)
+                | RulePatternValue::Until(_)
+                | RulePatternValue::Cut => false,
+            },
+        }
+    }
+}
+
+/// Grammar lowered for the Rust generator, with the shape of each pattern's data
+pub struct GenModel<'a> {
+    rules: HashMap<&'a str, GenNode<'a>>,
+
+    /// Whether each rule captures any data
+    capturing: HashMap<&'a str, bool>,
+}
+
+impl<'a> GenModel<'a> {
+    /// Lower all the rules of a grammar
+    ///
+    /// Rules whose patterns are all silent don't capture any data, nor do rules whose data would be empty
+    /// (e.g. `a = ~"b"` or `a = °"b" !"c"`).
+    pub fn lower(
+        pst: &'a PegSyntaxTree,
+        graph: &GrammarGraph<'a>,
+        simplify_marker_types: bool,
+    ) -> Self {
+        let capturing = graph.fixpoint(pst, true, |name, pattern, capturing| {
+            graph.non_capturing_mode(name) != Some(PatternMode::Silent)
+                && Lowering {
+                    graph,
+                    capturing,
+                    simplify_marker_types,
+                    visiting: name,
+                }
+                .lower(pattern)
+                .is_capturing()
+        });
+
+        let rules = pst
+            .rules()
+            .iter()
+            .map(|(name, rule)| {
+                let lowering = Lowering {
+                    graph,
+                    capturing: &capturing,
+                    simplify_marker_types,
+                    visiting: name,
+                };
+
+                (*name, lowering.lower(rule.pattern()))
+            })
+            .collect();
+
+        Self { rules, capturing }
+    }
+
+    /// Get the lowered pattern of a rule
+    pub fn rule(&self, name: &str) -> &GenNode<'a> {
+        &self.rules[name]
+    }
+
+    /// Check if a rule captures any data, in which case its data has the shape of its pattern
+    pub fn is_capturing(&self, name: &str) -> bool {
+        self.capturing[name]
+    }
+}
+
+/// Lowering of the patterns of a rule
+struct Lowering<'a, 'm> {
+    graph: &'m GrammarGraph<'a>,
+    capturing: &'m HashMap<&'a str, bool>,
+    simplify_marker_types: bool,
+    visiting: &'a str,
+}
+
+impl<'a, 'm> Lowering<'a, 'm> {
+    fn lower(&self, pattern: &'a Pattern<'a>) -> GenNode<'a> {
+        let children: Vec<_> = match pattern.value() {
+            RulePatternValue::Group(inner) | RulePatternValue::Until(inner) => {
+                vec![self.lower(inner)]
+            }
+            RulePatternValue::Suite(patterns) | RulePatternValue::Union(patterns) => {
+                patterns.iter().map(|pattern| self.lower(pattern)).collect()
+            }
+            RulePatternValue::CstString(_) | RulePatternValue::Rule(_) | RulePatternValue::Cut => {
+                vec![]
+            }
+        };
+
+        let value = match pattern.value() {
+            RulePatternValue::CstString(string) => Shape::CstString(string),
+            RulePatternValue::Rule(name)
+                if is_zero_width_builtin_rule_name(name) || is_indentation_rule_name(name) =>
+            {
+                Shape::Unit
+            }
+            RulePatternValue::Rule(name) if is_builtin_rule_name(name) => Shape::Builtin(name),
+            RulePatternValue::Rule(name) if self.capturing.get(name) == Some(&false) => Shape::Unit,
+            RulePatternValue::Rule(name) => Shape::Rule {
+                name,
+                indirect: self.graph.is_recursive_reference(self.visiting, name),
+            },
+            RulePatternValue::Group(_) => children[0].shape.clone(),
+            RulePatternValue::Suite(_) => {
+                let mut pieces: Vec<_> = children
+                    .iter()
+                    .filter(|child| child.is_capturing())
+                    .map(|child| child.shape.clone())
+                    .collect();
+
+                match pieces.len() {
+                    0 => Shape::Unit,
+                    1 => pieces.remove(0),
+                    _ => Shape::Tuple(pieces),
+                }
+            }
+            RulePatternValue::Union(_) => {
+                Shape::Union(children.iter().map(|child| child.shape.clone()).collect())
+            }
+            // The input preceding the terminator
+            RulePatternValue::Until(_) => Shape::Str,
+            RulePatternValue::Cut => Shape::Unit,
+        };
+
+        GenNode {
+            pattern,
+            shape: self.pattern_shape(pattern, &value),
+            value,
+            children,
+        }
+    }
+
+    /// Get the shape of a pattern's data from the shape of its value's data
+    fn pattern_shape(&self, pattern: &Pattern, value: &Shape<'a>) -> Shape<'a> {
+        if pattern.is_dataless() {
+            return Shape::Unit;
+        }
+
+        let data = if pattern.is_atomic() {
+            Shape::Str
+        } else {
+            value.clone()
+        };
+
+        match (pattern.repetition(), data) {
+            (_, Shape::Unit) => Shape::Unit,
+            (None, data) => data,
+
+            // Constant string markers are zero-sized, so repetitions of them are replaced by a count and options by a flag
+            (Some(PatternRepetition::Any), Shape::CstString(_))
+            | (Some(PatternRepetition::OneOrMore), Shape::CstString(_))
+                if self.simplify_marker_types =>
+            {
+                Shape::Count
+            }
+            (Some(PatternRepetition::Optional), Shape::CstString(_))
+                if self.simplify_marker_types =>
+            {
+                Shape::Flag
+            }
+
+            (Some(PatternRepetition::Any), data) | (Some(PatternRepetition::OneOrMore), data) => {
+                Shape::Vec(Box::new(data))
+            }
+            (Some(PatternRepetition::Optional), data) => Shape::Option(Box::new(data)),
+        }
+    }
+}
@@ -1,52 +1,22 @@
 use crate::grammar::data::*;
-use crate::grammar::utils::is_builtin_rule_name;
+use crate::grammar::graph::GrammarGraph;
+use crate::grammar::utils::{is_builtin_rule_name, is_external_rule_name};
 use std::collections::{HashMap, HashSet};
 
-pub fn build_lifetime_reqs<'a>(pst: &'a PegSyntaxTree) -> HashSet<&'a str> {
-    let mut lifetime_reqs = HashMap::new();
-    let mut visiting = HashSet::new();
-
-    for name in pst.rules().keys() {
-        check_rule(pst, &mut lifetime_reqs, &mut visiting, name);
-    }
-
-    lifetime_reqs
-        .iter()
-        .filter_map(|(rule, req_lifetime)| if *req_lifetime { Some(*rule) } else { None })
-        .collect::<HashSet<_>>()
-}
-
-pub fn check_rule<'a>(
+pub fn build_lifetime_reqs<'a>(
     pst: &'a PegSyntaxTree,
-    lifetime_reqs: &mut HashMap<&'a str, bool>,
-    visiting: &mut HashSet<&'a str>,
-    name: &'a str,
-) -> bool {
-    if let Some(req) = lifetime_reqs.get(&name) {
-        return *req;
-    }
-
-    if !visiting.insert(name) {
-        return false;
-    }
-
-    if is_builtin_rule_name(name) {
-        visiting.remove(name);
-        false
-    } else {
-        let ret = check_lifetime_req(pst, lifetime_reqs, visiting, pst.rules()[name].pattern());
-        visiting.remove(name);
-        lifetime_reqs.insert(name, ret);
-        ret
-    }
+    graph: &GrammarGraph<'a>,
+) -> HashSet<&'a str> {
+    graph
+        .fixpoint(pst, false, |_, pattern, lifetime_reqs| {
+            check_lifetime_req(pattern, lifetime_reqs)
+        })
+        .into_iter()
+        .filter_map(|(rule, req_lifetime)| if req_lifetime { Some(rule) } else { None })
+        .collect::<HashSet<_>>()
 }
 
-pub fn check_lifetime_req<'a>(
-    pst: &'a PegSyntaxTree,
-    lifetime_reqs: &mut HashMap<&'a str, bool>,
-    visiting: &mut HashSet<&'a str>,
-    pattern: &'a Pattern,
-) -> bool {
+pub fn check_lifetime_req(pattern: &Pattern, lifetime_reqs: &HashMap<&str, bool>) -> bool {
     match pattern.mode() {
         Some(mode) => match mode {
             PatternMode::Silent => false,
@@ -55,14 +25,47 @@ pub fn check_lifetime_req<'a>(
             PatternMode::Atomic => true,
         },
         None => match pattern.value() {
-            RulePatternValue::CstString(_) => false,
-            RulePatternValue::Rule(name) => check_rule(pst, lifetime_reqs, visiting, name),
-            RulePatternValue::Group(group) => {
-                check_lifetime_req(pst, lifetime_reqs, visiting, group)
+            RulePatternValue::CstString(_) | RulePatternValue::Cut => false,
+            RulePatternValue::Until(_) => true,
+            RulePatternValue::Rule(name)
+                if is_builtin_rule_name(name) || is_external_rule_name(name) =>
+            {
+                false
             }
+            RulePatternValue::Rule(name) => lifetime_reqs.get(name).copied().unwrap_or(false),
+            RulePatternValue::Group(group) => check_lifetime_req(group, lifetime_reqs),
             RulePatternValue::Suite(patterns) | RulePatternValue::Union(patterns) => patterns
                 .iter()
-                .any(|pattern| check_lifetime_req(pst, lifetime_reqs, visiting, pattern)),
+                .any(|pattern| check_lifetime_req(pattern, lifetime_reqs)),
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_peg;
+
+    #[test]
+    fn lifetimes_propagate_through_cycles() {
+        // Only `c` captures input slices, and the other rules of its cycle contain it
+        let grammar = r#"
+a = °"(" b* °")"
+b = °"[" c* °"]" | °"-"
+c = @"x" | a
+d = "d"
+main = a d
+"#;
+
+        // The result must not depend on the order rules are visited in
+        for _ in 0..10 {
+            let pst = parse_peg(grammar).unwrap();
+            let graph = GrammarGraph::new(&pst);
+
+            let mut reqs: Vec<_> = build_lifetime_reqs(&pst, &graph).into_iter().collect();
+            reqs.sort_unstable();
+
+            assert_eq!(reqs, ["a", "b", "c", "main"]);
+        }
+    }
+}
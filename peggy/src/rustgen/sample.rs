@@ -0,0 +1,188 @@
+use super::model::{GenModel, GenNode, Shape};
+use super::types::gen_suite_data;
+use super::{get_enum_variant, InternalState};
+use crate::grammar::data::*;
+use crate::grammar::utils::*;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use std::collections::HashMap;
+
+/// Compute the depth of the smallest instance of each rule's data, which is `None` if the rule has no finite instance
+/// (e.g. `list = "(" list ")"`)
+///
+/// Optional and repeated (`*`) patterns are left empty, unions use their shallowest member and each rule reference
+/// adds a level, so building a rule's sample only builds the samples of shallower rules, which ensures it terminates.
+pub fn build_sample_depths<'a>(
+    state: &InternalState<'a>,
+    model: &GenModel<'a>,
+    pst: &'a PegSyntaxTree,
+) -> HashMap<&'a str, Option<usize>> {
+    state.graph.fixpoint(pst, None, |name, _, depths| {
+        pattern_depth(model.rule(name), depths)
+    })
+}
+
+/// Compute the depth of the smallest instance of a lowered pattern's data (see [`build_sample_depths`])
+fn pattern_depth(node: &GenNode, depths: &HashMap<&str, Option<usize>>) -> Option<usize> {
+    if !node.is_capturing() || node.pattern.is_atomic() {
+        return Some(0);
+    }
+
+    match node.pattern.repetition() {
+        Some(PatternRepetition::Any) | Some(PatternRepetition::Optional) => return Some(0),
+        Some(PatternRepetition::OneOrMore) if node.shape == Shape::Count => return Some(0),
+        Some(PatternRepetition::OneOrMore) | None => {}
+    }
+
+    match node.pattern.value() {
+        RulePatternValue::CstString(_) | RulePatternValue::Until(_) | RulePatternValue::Cut => {
+            Some(0)
+        }
+        RulePatternValue::Rule(_) => match node.value {
+            Shape::Rule { name, .. } => depths.get(name).copied().flatten().map(|depth| depth + 1),
+            _ => Some(0),
+        },
+        RulePatternValue::Group(_) => pattern_depth(&node.children[0], depths),
+        RulePatternValue::Suite(_) => node
+            .children
+            .iter()
+            .filter(|piece| piece.is_capturing())
+            .try_fold(0, |max, piece| {
+                pattern_depth(piece, depths).map(|depth| max.max(depth))
+            }),
+        RulePatternValue::Union(_) => node
+            .children
+            .iter()
+            .filter_map(|member| pattern_depth(member, depths))
+            .min(),
+    }
+}
+
+/// Generate the `sample` constructors of matched rules' types (see [`super::RustGenOptions::sample_constructors`])
+pub fn gen_samples(state: &InternalState, model: &GenModel, pst: &PegSyntaxTree) -> TokenStream {
+    let depths = state.sample_depths.as_ref().unwrap();
+
+    let samples = pst
+        .ordered_rules()
+        .into_iter()
+        .filter(|rule| matches!(state.rule_types.get(rule.name()), Some(Some(_))))
+        .filter(|rule| depths[rule.name()].is_some())
+        .map(|rule| {
+            let name = rule.name();
+            let ident = state.rule_ident(name);
+
+            let lifetime_req = if state.rules_with_lifetime.contains(name) {
+                quote! { <'a> }
+            } else {
+                quote! {}
+            };
+
+            let sample = gen_pattern_sample(state, depths, model.rule(name));
+
+            quote! {
+                impl #lifetime_req matched::#ident #lifetime_req {
+                    /// Build a minimal instance of the rule's data, for testing purposes only
+                    ///
+                    /// Optional and repeated patterns are left empty (or hold a single element for `+` repetitions),
+                    /// unions use their simplest member, builtin rules hold a placeholder character (`'\0'`) and all spans
+                    /// are empty at the beginning of the input. The data doesn't correspond to any actual input.
+                    pub fn sample() -> Self {
+                        Self {
+                            matched: #sample,
+                            span: Span::default()
+                        }
+                    }
+                }
+            }
+        });
+
+    quote! { #(#samples)* }
+}
+
+/// Generate the minimal instance of a lowered pattern's data
+fn gen_pattern_sample(
+    state: &InternalState,
+    depths: &HashMap<&str, Option<usize>>,
+    node: &GenNode,
+) -> TokenStream {
+    match (&node.shape, node.pattern.repetition()) {
+        (Shape::Unit, _) => return quote! { () },
+        (Shape::Count, Some(PatternRepetition::OneOrMore)) => return quote! { 1 },
+        (Shape::Count, _) => return quote! { 0 },
+        (Shape::Flag, _) => return quote! { false },
+        (_, Some(PatternRepetition::Any)) => return quote! { vec![] },
+        (_, Some(PatternRepetition::Optional)) => return quote! { None },
+        (_, Some(PatternRepetition::OneOrMore)) | (_, None) => {}
+    }
+
+    let sample = if node.pattern.is_atomic() {
+        quote! { "" }
+    } else {
+        gen_value_sample(state, depths, node)
+    };
+
+    match node.pattern.repetition() {
+        Some(PatternRepetition::OneOrMore) => quote! { vec![#sample] },
+        _ => sample,
+    }
+}
+
+/// Generate the minimal instance of a capturing lowered pattern's value data
+fn gen_value_sample(
+    state: &InternalState,
+    depths: &HashMap<&str, Option<usize>>,
+    node: &GenNode,
+) -> TokenStream {
+    match node.pattern.value() {
+        RulePatternValue::CstString(string) => {
+            let ident = &state.cst_string_types[string];
+            quote! { strings::#ident }
+        }
+        RulePatternValue::Rule(name) if is_builtin_rule_name(name) => {
+            let ident = format_ident!("{}", name);
+            quote! { matched::#ident { matched: '\0', span: Span::default() } }
+        }
+        RulePatternValue::Rule(name) => {
+            let ident = state.rule_ident(name);
+
+            if let Shape::Rule { indirect: true, .. } = node.value {
+                let wrapper = state.recursion_wrapper.gen_path();
+                quote! { #wrapper::new(matched::#ident::sample()) }
+            } else {
+                quote! { matched::#ident::sample() }
+            }
+        }
+        RulePatternValue::Group(_) => gen_pattern_sample(state, depths, &node.children[0]),
+        RulePatternValue::Suite(_) => {
+            let pieces: Vec<_> = node
+                .children
+                .iter()
+                .filter(|piece| piece.is_capturing())
+                .map(|piece| gen_pattern_sample(state, depths, piece))
+                .collect();
+
+            gen_suite_data(&pieces)
+        }
+        RulePatternValue::Union(members) => {
+            // Use the first of the shallowest members
+            let (i, member) = node
+                .children
+                .iter()
+                .enumerate()
+                .filter_map(|(i, member)| Some((pattern_depth(member, depths)?, i, member)))
+                .min_by_key(|(depth, i, _)| (*depth, *i))
+                .map(|(_, i, member)| (i, member))
+                .unwrap();
+
+            let union_type = format_ident!("Sw{}", members.len());
+            let variant = format_ident!("{}", get_enum_variant(i));
+
+            // Non-capturing members hold `()`
+            let sample = gen_pattern_sample(state, depths, member);
+
+            quote! { unions::#union_type::#variant(#sample) }
+        }
+        RulePatternValue::Until(_) => quote! { "" },
+        RulePatternValue::Cut => quote! { () },
+    }
+}
@@ -0,0 +1,150 @@
+use super::{gen_rust_modules, no_linting, GeneratedModules, RustGenOptions};
+use crate::grammar::PegSyntaxTree;
+use quote::quote;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Options for the [standalone crate generator](`gen_rust_crate`)
+#[derive(Debug, Clone)]
+pub struct CrateOptions {
+    /// Name of the generated crate
+    pub name: String,
+
+    /// Version of the generated crate
+    pub version: String,
+
+    /// Additional dependencies, as pairs of crate names and TOML specifications (e.g. `("regex", "\"1.5\"")`)
+    pub dependencies: Vec<(String, String)>,
+
+    /// Options for the parser's generation (the debugger is not supported in standalone crates and will be ignored)
+    pub generator: RustGenOptions,
+}
+
+impl CrateOptions {
+    /// Create a new set of options for a crate with the provided name, using version `0.1.0` and no additional dependency
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: "0.1.0".to_string(),
+            dependencies: vec![],
+            generator: RustGenOptions::new(),
+        }
+    }
+}
+
+/// Generate a standalone crate containing a parser for the provided grammar
+///
+/// The crate is written in the provided directory, which is created if it doesn't exist yet.
+/// Existing files are overwritten.
+///
+/// If the tree can't be turned into a parser (see [`super::GenError`]), an [`io::ErrorKind::InvalidInput`] error
+/// is returned and nothing is written.
+///
+/// The following Cargo features are available in the generated crate:
+///
+/// * `serde`: derive `serde::Serialize` for all matched data types
+/// * `trace`: emit a `tracing` event each time a rule is matched
+pub fn gen_rust_crate(
+    pst: &PegSyntaxTree,
+    dir: impl AsRef<Path>,
+    options: &CrateOptions,
+) -> io::Result<()> {
+    let GeneratedModules {
+        root,
+        matched,
+        rules,
+        strings,
+        unions,
+        uses_general_category,
+    } = gen_rust_modules(
+        pst,
+        &RustGenOptions {
+            debugger: None,
+            ..options.generator.clone()
+        },
+        true,
+    )
+    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+
+    let no_linting = no_linting();
+
+    let lib = quote! {
+        #root
+
+        #no_linting
+        pub mod matched;
+
+        #no_linting
+        pub mod rules;
+
+        #no_linting
+        pub mod strings;
+
+        #no_linting
+        pub mod unions;
+    };
+
+    let dir = dir.as_ref();
+    let src = dir.join("src");
+
+    fs::create_dir_all(&src)?;
+
+    fs::write(
+        dir.join("Cargo.toml"),
+        gen_cargo_manifest(options, uses_general_category),
+    )?;
+
+    fs::write(
+        src.join("lib.rs"),
+        format!("//! Parser generated by Peggy\n\n{}\n", lib),
+    )?;
+
+    for (name, content) in [
+        ("matched", matched),
+        ("rules", rules),
+        ("strings", strings),
+        ("unions", unions),
+    ] {
+        fs::write(src.join(format!("{}.rs", name)), format!("{}\n", content))?;
+    }
+
+    Ok(())
+}
+
+/// Generate the Cargo manifest of a standalone crate
+fn gen_cargo_manifest(options: &CrateOptions, uses_general_category: bool) -> String {
+    let mut dependencies = vec![
+        (
+            "serde".to_string(),
+            "{ version = \"1.0\", features = [\"derive\", \"rc\"], optional = true }".to_string(),
+        ),
+        (
+            "tracing".to_string(),
+            "{ version = \"0.1\", optional = true }".to_string(),
+        ),
+    ];
+
+    if uses_general_category {
+        dependencies.push((
+            "unicode-general-category".to_string(),
+            "\"1.1.0\"".to_string(),
+        ));
+    }
+
+    if options.generator.grapheme_boundaries {
+        dependencies.push(("unicode-segmentation".to_string(), "\"1.7\"".to_string()));
+    }
+
+    dependencies.extend(options.dependencies.iter().cloned());
+
+    format!(
+        "[package]\nname = {:?}\nversion = {:?}\nedition = \"2018\"\n\n[features]\ndefault = []\ntrace = [\"tracing\"]\n\n[dependencies]\n{}",
+        options.name,
+        options.version,
+        dependencies
+            .iter()
+            .map(|(name, spec)| format!("{} = {}\n", name, spec))
+            .collect::<String>()
+    )
+}
@@ -1,120 +1,111 @@
-use super::{make_safe_ident, InternalState};
+use super::model::Shape;
+use super::InternalState;
 use crate::grammar::data::*;
-use crate::grammar::utils::*;
-use quote::__private::TokenStream;
-use quote::{format_ident, quote};
-use std::collections::HashMap;
-
-pub fn gen_rule_type<'a>(
-    state: &mut InternalState<'a>,
-    visiting: &'a str,
-    rule: &'a Rule,
-) -> Option<TokenStream> {
-    gen_pattern_type(state, visiting, rule.pattern())
-}
-
-pub fn gen_pattern_type<'a>(
-    state: &mut InternalState<'a>,
-    visiting: &'a str,
-    pattern: &'a Pattern,
-) -> Option<TokenStream> {
-    if pattern.is_dataless() {
-        return None;
-    }
-
-    let pattern_type = if pattern.is_atomic() {
-        quote! { &'a str }
-    } else {
-        gen_pattern_value_type(state, visiting, pattern.value())?
-    };
-
-    match pattern.repetition() {
-        None => Some(pattern_type),
-        Some(rep) => match rep {
-            PatternRepetition::Any | PatternRepetition::OneOrMore => {
-                Some(quote! { Vec<#pattern_type> })
-            }
-            PatternRepetition::Optional => Some(quote! { Option<#pattern_type> }),
-        },
-    }
-}
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use std::collections::{BTreeSet, HashMap};
+
+/// Generate the type of a pattern's data from its shape, which is `None` if it doesn't capture any data
+///
+/// The types of the encountered constant strings and builtin rules are registered to be generated afterwards.
+pub fn gen_shape_type<'a>(state: &mut InternalState<'a>, shape: &Shape<'a>) -> Option<TokenStream> {
+    match shape {
+        Shape::Unit => None,
+        Shape::Str => {
+            let str_type = state.primitive("str");
+            Some(quote! { &'a #str_type })
+        }
+        Shape::CstString(string) => {
+            let ident = state.cst_string_names[string].clone();
+            state.cst_string_types.insert(string, ident.clone());
+            Some(quote! { super::strings::#ident })
+        }
+        Shape::Builtin(name) => {
+            let ident = state.rule_ident(name);
+            state.builtin_rule_types.insert(name);
+            Some(quote! { super::matched::#ident })
+        }
+        Shape::Rule { name, indirect } => {
+            let ident = state.rule_ident(name);
 
-pub fn gen_pattern_value_type<'a>(
-    state: &mut InternalState<'a>,
-    visiting: &'a str,
-    value: &'a RulePatternValue,
-) -> Option<TokenStream> {
-    match value {
-        RulePatternValue::CstString(string) => {
-            Some(if let Some(ident) = state.cst_string_types.get(string) {
-                quote! { super::strings::#ident }
+            let lifetime_req = if state.rules_with_lifetime.contains(name) {
+                quote! { <'a> }
             } else {
-                let ident = format_str_type(&mut state.cst_string_counters, string);
-                state.cst_string_types.insert(string, ident.clone());
-                quote! { super::strings::#ident }
-            })
-        }
-        RulePatternValue::Rule(name) => {
-            let ident = make_safe_ident(name);
+                quote! {}
+            };
 
-            if is_builtin_rule_name(name) {
-                Some(quote! { super::matched::#ident })
-            } else if let Some(PatternMode::Silent) = state.non_capturing_rules.get(name) {
-                None
+            if *indirect {
+                let wrapper = state.recursion_wrapper.gen_path();
+                Some(quote! { #wrapper<super::matched::#ident #lifetime_req> })
             } else {
-                let lifetime_req = if state.rules_with_lifetime.contains(name) {
-                    quote! { <'a> }
-                } else {
-                    quote! {}
-                };
-
-                if state.recursive_paths[visiting].contains(name) {
-                    Some(quote! { std::rc::Rc<super::matched::#ident #lifetime_req> })
-                } else {
-                    Some(quote! { super::matched::#ident #lifetime_req })
-                }
+                Some(quote! { super::matched::#ident #lifetime_req })
             }
         }
-        RulePatternValue::Group(inner) => gen_pattern_type(state, visiting, inner.as_ref()),
-        RulePatternValue::Suite(patterns) => {
-            let types: Vec<_> = patterns
+        Shape::Count => Some(state.primitive("usize")),
+        Shape::Flag => Some(state.primitive("bool")),
+        Shape::Vec(data) => {
+            let data = gen_shape_type(state, data)?;
+            Some(quote! { Vec<#data> })
+        }
+        Shape::Option(data) => {
+            let data = gen_shape_type(state, data)?;
+            Some(quote! { Option<#data> })
+        }
+        Shape::Tuple(pieces) => {
+            let types: Vec<_> = pieces
                 .iter()
-                .map(|pattern| gen_pattern_type(state, visiting, pattern))
-                .filter_map(|pattern| pattern)
+                .filter_map(|piece| gen_shape_type(state, piece))
                 .collect();
 
-            if types.is_empty() {
-                None
-            } else if types.len() == 1 {
-                Some(quote! { #(#types)* })
-            } else {
-                Some(quote! { (#(#types),*) })
-            }
+            Some(gen_suite_data(&types))
         }
-        RulePatternValue::Union(patterns) => {
-            let types: Vec<_> = patterns
+        Shape::Union(members) => {
+            let types: Vec<_> = members
                 .iter()
-                .map(|pattern| {
-                    gen_pattern_type(state, visiting, pattern).unwrap_or_else(|| quote! { () })
-                })
+                .map(|member| gen_shape_type(state, member).unwrap_or_else(|| quote! { () }))
                 .collect();
 
-            let variants_len = patterns.len();
+            let union_type = format_ident!("Sw{}", members.len());
 
-            let union_type = format_ident!("Sw{}", variants_len);
-
-            if patterns.is_empty() {
-                None
-            } else {
-                Some(quote! { super::unions::#union_type<#(#types),*> })
-            }
+            Some(quote! { super::unions::#union_type<#(#types),*> })
         }
     }
 }
 
-pub fn format_str_type<'a>(
-    cst_string_counters: &mut HashMap<&'a str, usize>,
-    cst_string: &'a str,
+/// Generate a suite's data (either its type or its value) from the data of its capturing pieces
+///
+/// A single piece isn't wrapped in a tuple, so one-element tuples are never generated.
+pub fn gen_suite_data(pieces: &[impl ToTokens]) -> TokenStream {
+    match pieces {
+        [piece] => quote! { #piece },
+        _ => quote! { (#(#pieces),*) },
+    }
+}
+
+/// Name the types of all constant strings of a grammar
+///
+/// Strings are named in lexicographic order, so the counters differentiating strings which produce the same type name
+/// (see [`format_str_type`]) don't depend on the order rules are declared or visited in.
+pub fn gen_cst_string_names<'a>(pst: &PegSyntaxTree<'a>) -> HashMap<&'a str, TokenStream> {
+    let mut strings = BTreeSet::new();
+
+    pst.walk_rules(&mut |_, pattern| {
+        if let RulePatternValue::CstString(string) = pattern.value() {
+            strings.insert(*string);
+        }
+    });
+
+    let mut counters = HashMap::new();
+
+    strings
+        .into_iter()
+        .map(|string| (string, format_str_type(&mut counters, string)))
+        .collect()
+}
+
+pub fn format_str_type(
+    cst_string_counters: &mut HashMap<String, usize>,
+    cst_string: &str,
 ) -> TokenStream {
     let mut typename = String::new();
     let mut got_space = false;
@@ -185,14 +176,15 @@ pub fn format_str_type<'a>(
         } else if c == ';' {
             typename.push_str("SemiColon");
         } else {
-            typename.push_str(&format!("Char{}", c as u8));
+            typename.push_str(&format!("Char{}", c as u32));
         }
 
         typename.push_str("__");
     }
 
-    let counter = *cst_string_counters.get(cst_string).unwrap_or(&0);
-    cst_string_counters.insert(cst_string, counter + 1);
+    // Different strings may produce the same type name (e.g. "a b" and "aB"), so a counter is used to differentiate them
+    let counter = *cst_string_counters.get(&typename).unwrap_or(&0);
+    cst_string_counters.insert(typename.clone(), counter + 1);
 
     let ident = format_ident!(
         "Str{}_{}",
@@ -205,3 +197,46 @@ pub fn format_str_type<'a>(
     );
     quote! { #ident }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_peg;
+
+    fn names(grammar: &str) -> Vec<(String, String)> {
+        let pst = parse_peg(grammar).unwrap();
+        let mut names: Vec<_> = gen_cst_string_names(&pst)
+            .into_iter()
+            .map(|(string, ident)| (string.to_string(), ident.to_string()))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn cst_string_type_names() {
+        assert_eq!(
+            names(r#"main = "`" "→" "if" "a b" "+""#),
+            vec![
+                ("+".to_string(), "Str___Plus__".to_string()),
+                ("`".to_string(), "Str___Char96__".to_string()),
+                ("a b".to_string(), "Str_aB".to_string()),
+                ("if".to_string(), "Str_if".to_string()),
+                ("→".to_string(), "Str___Char8594__".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn colliding_cst_string_type_names() {
+        // All these strings produce the `aB` type name, and are numbered in lexicographic order
+        assert_eq!(
+            names(r#"main = "aB" "a  b" "a b""#),
+            vec![
+                ("a  b".to_string(), "Str_a_B".to_string()),
+                ("a b".to_string(), "Str_aB".to_string()),
+                ("aB".to_string(), "Str1_aB".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! Compile grammars embedded in a Markdown document, with locations relative to the document
+
+use peggy::prelude::*;
+
+const DOCUMENT: &str = include_str!("fixtures/embedded.md");
+
+/// Extract the indented code blocks of a Markdown document, with the line they start on
+fn indented_blocks(document: &str) -> Vec<(usize, String)> {
+    let mut blocks = vec![];
+    let mut current: Option<(usize, Vec<&str>)> = None;
+
+    for (l, line) in document.lines().enumerate() {
+        match (line.strip_prefix("    "), &mut current) {
+            (Some(content), Some((_, lines))) => lines.push(content),
+            (Some(content), None) => current = Some((l, vec![content])),
+            (None, Some((_, lines))) if line.is_empty() => lines.push(""),
+            (None, _) => {
+                if let Some((start, lines)) = current.take() {
+                    blocks.push((start, lines.join("\n").trim_end().to_string()));
+                }
+            }
+        }
+    }
+
+    if let Some((start, lines)) = current {
+        blocks.push((start, lines.join("\n").trim_end().to_string()));
+    }
+
+    blocks
+}
+
+fn origin(start: usize) -> SourceOrigin {
+    SourceOrigin::new(start, 0).with_indent(4)
+}
+
+#[test]
+fn rules_are_located_in_the_document() {
+    let (start, grammar) = &indented_blocks(DOCUMENT)[0];
+    assert_eq!(*start, 4);
+
+    let pst = parse_peg_with_origin(grammar, &origin(*start)).unwrap();
+
+    // The tree's source is still the grammar itself
+    assert_eq!(pst.source(), grammar);
+
+    let sum = &pst.rules()["sum"];
+    assert_eq!(sum.decl_loc().line(), 6);
+    assert_eq!(sum.decl_loc().col(), 4);
+    assert_eq!(
+        sum.pattern().span().slice(DOCUMENT),
+        Some(r#"number °"+" number"#)
+    );
+
+    let main = pst.main_rule();
+    assert_eq!(main.decl_loc().line(), 8);
+    assert_eq!(main.pattern().span().slice(DOCUMENT), Some("sum"));
+
+    for (name, rule) in pst.rules() {
+        let declaration = rule.span().slice(DOCUMENT).unwrap();
+        assert!(declaration.starts_with(&format!("{} = ", name)));
+    }
+}
+
+#[test]
+fn errors_are_located_in_the_document() {
+    let (start, grammar) = &indented_blocks(DOCUMENT)[1];
+    assert_eq!(*start, 12);
+
+    let err = parse_peg_with_origin(grammar, &origin(*start)).unwrap_err();
+
+    assert_eq!((err.line(), err.col(), err.length()), (12, 15, 1));
+    assert!(matches!(
+        err.content(),
+        ParserErrorContent::UnclosedGroup { started_at }
+            if started_at.line() == 12 && started_at.col() == 15
+    ));
+
+    let report = pretty_format_parser_err(DOCUMENT, err);
+    assert!(report.starts_with("ERROR: At line 13"), "{}", report);
+    assert!(report.contains(r#"main = "a" (b"#), "{}", report);
+}
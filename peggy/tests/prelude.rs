@@ -0,0 +1,59 @@
+//! Use Peggy only through its prelude, as most programs do
+
+use peggy::prelude::*;
+
+const GRAMMAR: &str = r#"
+number = @(B_ASCII_DIGIT+)
+sum = number °"+" number
+main = sum
+"#;
+
+#[test]
+fn parse_and_analyze() {
+    let pst: PegSyntaxTree = parse_peg(GRAMMAR).unwrap();
+    let main: &Rule = pst.main_rule();
+    assert!(matches!(
+        main.pattern().value(),
+        RulePatternValue::Rule("sum")
+    ));
+
+    assert_eq!(min_length(&pst, "sum"), 3);
+    let mut dependents: Vec<_> = dependents(&pst, "number").into_iter().collect();
+    dependents.sort_unstable();
+    assert_eq!(dependents, ["main", "sum"]);
+
+    let path: NodePath = node_at(&pst, 2, 0).unwrap();
+    assert_eq!(path.rule().name(), "sum");
+
+    let report: ValidationReport = validate(&pst);
+    assert!(report.errors().is_empty());
+
+    assert!(verify_generated(GRAMMAR, &grammar_hash(GRAMMAR)));
+}
+
+#[test]
+fn report_errors() {
+    let err: ParserError = parse_peg("main = 'a'").unwrap_err();
+    assert!(matches!(err.content(), ParserErrorContent::ExpectedPattern));
+    assert!(pretty_format_parser_err("main = 'a'", err).starts_with("ERROR: At line 1"));
+
+    let limits = ParseLimits {
+        max_rules: 1,
+        ..ParseLimits::default()
+    };
+    assert!(parse_peg_with_limits(GRAMMAR, &limits).is_err());
+}
+
+#[cfg(feature = "rustgen")]
+#[test]
+fn generate_rust_code() {
+    let mut options = RustGenOptions::new();
+    options.recursion_wrapper = RecursionWrapper::Box;
+
+    let code = compile_to_rust(GRAMMAR, &options).unwrap();
+    assert!(code.contains("pub fn exec"));
+
+    let pst = parse_peg(GRAMMAR).unwrap();
+    let shapes = rule_type_shapes(&pst, &options).unwrap();
+    assert!(shapes.contains_key("sum"));
+}
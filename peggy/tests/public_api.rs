@@ -0,0 +1,374 @@
+//! Compare the crate's public API with the expected one, stored in `public_api.txt`
+//!
+//! The public items are listed by walking the crate's sources from `lib.rs`, following public modules and re-exports.
+//! When a change of the public API is intended, the expected list can be updated by running this test with the
+//! `PEGGY_BLESS` environment variable set (e.g. `PEGGY_BLESS=1 cargo test -p peggy --test public_api`), and reviewing
+//! its diff.
+
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::{Attribute, Fields, ImplItem, Item, TraitItem, Type, UseTree, Visibility};
+
+/// Public methods of inherent implementations, by type name, with the `cfg` conditions they are declared under
+type Methods = HashMap<String, Vec<(String, Vec<String>)>>;
+
+/// Items, submodules' directory and `cfg` conditions of a module's submodules, by name
+type Submodules = HashMap<String, (Vec<Item>, PathBuf, Vec<String>)>;
+
+/// Get the conditions of the `cfg` attributes, or `None` if the item only exists in tests
+fn cfgs(attrs: &[Attribute], parent: &[String]) -> Option<Vec<String>> {
+    let mut cfgs = parent.to_vec();
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("cfg")) {
+        let cond = attr.meta.require_list().unwrap().tokens.to_string();
+
+        if cond == "test" {
+            return None;
+        }
+
+        cfgs.push(cond);
+    }
+
+    Some(cfgs)
+}
+
+fn is_pub(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+/// Parse a source file, returning its items and the directory of its submodules' files
+fn load(file: &Path) -> (Vec<Item>, PathBuf) {
+    let src = fs::read_to_string(file).unwrap();
+    let items = syn::parse_file(&src).unwrap().items;
+
+    let dir = match file.file_stem().unwrap().to_str().unwrap() {
+        "lib" | "mod" => file.parent().unwrap().to_path_buf(),
+        stem => file.parent().unwrap().join(stem),
+    };
+
+    (items, dir)
+}
+
+/// Get the file of a submodule declared without content
+fn module_file(dir: &Path, name: &str) -> PathBuf {
+    let file = dir.join(format!("{}.rs", name));
+
+    if file.exists() {
+        file
+    } else {
+        dir.join(name).join("mod.rs")
+    }
+}
+
+/// Collect the public methods of the inherent implementations declared in a file and its submodules
+fn collect_methods(items: &[Item], dir: &Path, cfg: &[String], methods: &mut Methods) {
+    for item in items {
+        match item {
+            Item::Mod(module) => {
+                let cfg = match cfgs(&module.attrs, cfg) {
+                    Some(cfg) => cfg,
+                    None => continue,
+                };
+
+                match &module.content {
+                    Some((_, items)) => collect_methods(items, dir, &cfg, methods),
+                    None => {
+                        let (items, dir) = load(&module_file(dir, &module.ident.to_string()));
+                        collect_methods(&items, &dir, &cfg, methods);
+                    }
+                }
+            }
+
+            Item::Impl(imp) if imp.trait_.is_none() => {
+                let cfg = match cfgs(&imp.attrs, cfg) {
+                    Some(cfg) => cfg,
+                    None => continue,
+                };
+
+                let ty = match &*imp.self_ty {
+                    Type::Path(ty) => ty.path.segments.last().unwrap().ident.to_string(),
+                    _ => continue,
+                };
+
+                for item in &imp.items {
+                    if let ImplItem::Fn(method) = item {
+                        if let (true, Some(cfg)) = (is_pub(&method.vis), cfgs(&method.attrs, &cfg))
+                        {
+                            methods
+                                .entry(ty.clone())
+                                .or_default()
+                                .push((method.sig.ident.to_string(), cfg));
+                        }
+                    }
+                }
+            }
+
+            _ => {}
+        }
+    }
+}
+
+/// Public API collector
+struct Collector {
+    methods: Methods,
+    items: BTreeSet<String>,
+}
+
+impl Collector {
+    fn record(&mut self, path: &str, kind: &str, cfg: &[String]) {
+        let mut line = format!("{} ({})", path, kind);
+
+        for cond in cfg {
+            line.push_str(&format!(" #[cfg({})]", cond));
+        }
+
+        self.items.insert(line);
+    }
+
+    /// Record the public items of a module, or only the one with the provided name
+    fn collect(
+        &mut self,
+        items: &[Item],
+        dir: &Path,
+        path: &str,
+        cfg: &[String],
+        only: Option<&str>,
+    ) {
+        // Submodules, which may be private but have re-exported items
+        let mut submodules = Submodules::new();
+
+        for item in items {
+            if let Item::Mod(module) = item {
+                if let Some(cfg) = cfgs(&module.attrs, cfg) {
+                    let (items, dir) = match &module.content {
+                        Some((_, items)) => (items.clone(), dir.join(module.ident.to_string())),
+                        None => load(&module_file(dir, &module.ident.to_string())),
+                    };
+
+                    submodules.insert(module.ident.to_string(), (items, dir, cfg));
+                }
+            }
+        }
+
+        for item in items {
+            let (attrs, vis, name, kind) = match item {
+                Item::Const(item) => (&item.attrs, &item.vis, &item.ident, "const"),
+                Item::Enum(item) => (&item.attrs, &item.vis, &item.ident, "enum"),
+                Item::Fn(item) => (&item.attrs, &item.vis, &item.sig.ident, "fn"),
+                Item::Mod(item) => (&item.attrs, &item.vis, &item.ident, "mod"),
+                Item::Static(item) => (&item.attrs, &item.vis, &item.ident, "static"),
+                Item::Struct(item) => (&item.attrs, &item.vis, &item.ident, "struct"),
+                Item::Trait(item) => (&item.attrs, &item.vis, &item.ident, "trait"),
+                Item::Type(item) => (&item.attrs, &item.vis, &item.ident, "type"),
+
+                Item::Use(item) if is_pub(&item.vis) => {
+                    if let Some(cfg) = cfgs(&item.attrs, cfg) {
+                        self.collect_use(&item.tree, &[], &submodules, path, &cfg, only);
+                    }
+
+                    continue;
+                }
+
+                _ => continue,
+            };
+
+            let name = name.to_string();
+
+            if !is_pub(vis) || only.is_some_and(|only| only != name) {
+                continue;
+            }
+
+            let cfg = match cfgs(attrs, cfg) {
+                Some(cfg) => cfg,
+                None => continue,
+            };
+
+            let item_path = format!("{}::{}", path, name);
+            self.record(&item_path, kind, &cfg);
+
+            match item {
+                Item::Mod(_) => {
+                    let (items, dir, cfg) = &submodules[&name];
+                    let (items, dir, cfg) = (items.clone(), dir.clone(), cfg.clone());
+                    self.collect(&items, &dir, &item_path, &cfg, None);
+                }
+
+                Item::Struct(item) => {
+                    for (i, field) in item.fields.iter().enumerate() {
+                        if !is_pub(&field.vis) {
+                            continue;
+                        }
+
+                        let field_name = match &field.ident {
+                            Some(ident) => ident.to_string(),
+                            None => i.to_string(),
+                        };
+
+                        if let Some(cfg) = cfgs(&field.attrs, &cfg) {
+                            self.record(&format!("{}::{}", item_path, field_name), "field", &cfg);
+                        }
+                    }
+                }
+
+                Item::Enum(item) => {
+                    for variant in &item.variants {
+                        if let Some(cfg) = cfgs(&variant.attrs, &cfg) {
+                            let kind = match variant.fields {
+                                Fields::Named(_) => "struct variant",
+                                Fields::Unnamed(_) => "tuple variant",
+                                Fields::Unit => "variant",
+                            };
+
+                            self.record(&format!("{}::{}", item_path, variant.ident), kind, &cfg);
+                        }
+                    }
+                }
+
+                Item::Trait(item) => {
+                    for trait_item in &item.items {
+                        if let TraitItem::Fn(method) = trait_item {
+                            if let Some(cfg) = cfgs(&method.attrs, &cfg) {
+                                self.record(
+                                    &format!("{}::{}", item_path, method.sig.ident),
+                                    "trait method",
+                                    &cfg,
+                                );
+                            }
+                        }
+                    }
+                }
+
+                _ => {}
+            }
+
+            if matches!(item, Item::Struct(_) | Item::Enum(_)) {
+                let methods = self.methods.get(&name).cloned().unwrap_or_default();
+
+                for (method, method_cfg) in methods {
+                    self.record(&format!("{}::{}", item_path, method), "method", &method_cfg);
+                }
+            }
+        }
+    }
+
+    /// Record the items re-exported by a `pub use` declaration
+    fn collect_use(
+        &mut self,
+        tree: &UseTree,
+        prefix: &[String],
+        submodules: &Submodules,
+        path: &str,
+        cfg: &[String],
+        only: Option<&str>,
+    ) {
+        // Re-exports from a submodule are resolved to the re-exported items
+        let submodule = match prefix {
+            [module] => submodules.get(module),
+            _ => None,
+        };
+
+        match tree {
+            UseTree::Path(tree) => {
+                let mut prefix = prefix.to_vec();
+                prefix.push(tree.ident.to_string());
+                self.collect_use(&tree.tree, &prefix, submodules, path, cfg, only);
+            }
+
+            UseTree::Group(group) => {
+                for tree in &group.items {
+                    self.collect_use(tree, prefix, submodules, path, cfg, only);
+                }
+            }
+
+            UseTree::Glob(_) => {
+                let (items, dir, _) =
+                    submodule.expect("glob re-exports must come from a submodule");
+                let (items, dir) = (items.clone(), dir.clone());
+                self.collect(&items, &dir, path, cfg, only);
+            }
+
+            UseTree::Name(name) => {
+                let name = name.ident.to_string();
+
+                if only.is_some_and(|only| only != name) {
+                    return;
+                }
+
+                match submodule {
+                    Some((items, dir, _)) => {
+                        let (items, dir) = (items.clone(), dir.clone());
+                        self.collect(&items, &dir, path, cfg, Some(&name));
+                    }
+                    None => self.record(
+                        &format!("{}::{}", path, name),
+                        &format!("re-export of {}::{}", prefix.join("::"), name),
+                        cfg,
+                    ),
+                }
+            }
+
+            UseTree::Rename(rename) => panic!("unsupported renamed re-export: {}", rename.rename),
+        }
+    }
+}
+
+/// List the crate's public items, one per line
+fn public_api() -> String {
+    let lib = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src")
+        .join("lib.rs");
+
+    let (items, dir) = load(&lib);
+
+    let mut methods = Methods::new();
+    collect_methods(&items, &dir, &[], &mut methods);
+
+    let mut collector = Collector {
+        methods,
+        items: BTreeSet::new(),
+    };
+
+    collector.collect(&items, &dir, "peggy", &[], None);
+
+    collector
+        .items
+        .into_iter()
+        .map(|item| item + "\n")
+        .collect()
+}
+
+#[test]
+fn public_api_snapshot() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("public_api.txt");
+
+    let api = public_api();
+
+    if env::var_os("PEGGY_BLESS").is_some() {
+        fs::write(&path, api).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "{} is missing, run the test with PEGGY_BLESS=1 to create it",
+            path.display()
+        )
+    });
+
+    let api: BTreeSet<_> = api.lines().collect();
+    let expected: BTreeSet<_> = expected.lines().collect();
+
+    let added: Vec<_> = api.difference(&expected).collect();
+    let removed: Vec<_> = expected.difference(&api).collect();
+
+    assert!(
+        added.is_empty() && removed.is_empty(),
+        "The public API changed (run the test with PEGGY_BLESS=1 if this is intended)\nAdded: {:#?}\nRemoved: {:#?}",
+        added,
+        removed
+    );
+}
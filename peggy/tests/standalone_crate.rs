@@ -0,0 +1,87 @@
+#![cfg(feature = "rustgen")]
+
+use peggy::grammar::parse_peg;
+use peggy::rustgen::{gen_rust_crate, CrateOptions};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+const GRAMMAR: &str = r#"
+value = object | array | number | °"null"
+object = °"{" (pair (°"," pair)*)? °"}"
+pair = key °":" value
+key = @(B_ALPHABETIC+)
+array = °"[" (value (°"," value)*)? °"]"
+number = @(B_ASCII_DIGIT+)
+main = value
+"#;
+
+/// Run a Cargo command in a generated crate
+fn cargo(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+        .args(args)
+        .current_dir(dir)
+        .env("CARGO_TARGET_DIR", dir.join("target"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn generated_crate_checks_with_all_features() {
+    let pst = parse_peg(GRAMMAR).unwrap();
+
+    let dir = env::temp_dir().join(format!("peggy_standalone_crate_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    gen_rust_crate(&pst, &dir, &CrateOptions::new("generated_parser")).unwrap();
+
+    assert!(dir.join("Cargo.toml").is_file());
+    assert!(dir.join("src").join("lib.rs").is_file());
+
+    let output = cargo(&dir, &["check", "--quiet", "--features", "serde,trace"]);
+    assert!(
+        output.status.success(),
+        "the generated crate doesn't compile:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn generated_crate_detects_outdated_grammars() {
+    let pst = parse_peg(GRAMMAR).unwrap();
+
+    let dir = env::temp_dir().join(format!("peggy_outdated_grammar_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    let mut options = CrateOptions::new("outdated_parser");
+    options.generator.grammar_path = Some("grammar.peggy".to_string());
+
+    gen_rust_crate(&pst, &dir, &options).unwrap();
+
+    // Line endings don't matter
+    fs::write(dir.join("grammar.peggy"), GRAMMAR.replace('\n', "\r\n")).unwrap();
+
+    let test_args = ["test", "--quiet", "--lib", "grammar_is_up_to_date"];
+    let output = cargo(&dir, &test_args);
+    assert!(
+        output.status.success(),
+        "the grammar should be up to date:\n{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    fs::write(
+        dir.join("grammar.peggy"),
+        GRAMMAR.replace("B_ASCII_DIGIT+", "B_ASCII_DIGIT*"),
+    )
+    .unwrap();
+
+    let output = cargo(&dir, &test_args);
+    assert!(!output.status.success(), "the grammar should be outdated");
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("The parser is outdated, it must be generated again from grammar.peggy"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}